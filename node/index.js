@@ -0,0 +1,63 @@
+// Node bindings for supply-solver, over the addon the crate builds with `--features node`:
+//
+//     cargo build --release --features node
+//     cp target/release/libsupply_solver.so node/supply_solver.node
+//
+//     const { loadRecipes } = require('./node');
+//     const plan = loadRecipes('satisfactory.yaml').solve('iron-plate', 1);
+//     plan.machines; // 7
+//     plan.raw;      // { 'iron-ore': '3/2' }
+//
+// Rates stay exact: pass a number or a string such as '1/3', and read them back as "numer/denom" strings.
+// On macOS link the addon with `-C link-arg=-undefined -C link-arg=dynamic_lookup`; Windows isn't supported.
+'use strict';
+
+const path = require('path');
+
+const addon = require(process.env.SUPPLY_SOLVER_ADDON || path.join(__dirname, 'supply_solver.node'));
+
+// rationals come out of plans as [numer, denom]
+function rational([numer, denom]) {
+  return denom === 1 ? String(numer) : `${numer}/${denom}`;
+}
+
+// a solved plan: the whole of it as `data`, in the saved plan format, and the parts a planner wants first
+class Plan {
+  constructor({ json, text, machines }) {
+    this.data = JSON.parse(json);
+    // the stage tree as `supply-solver solve` prints it
+    this.text = text;
+    this.machines = machines;
+  }
+
+  // units/second of each raw resource the plan draws
+  get raw() {
+    return Object.fromEntries(Object.entries(this.data.summary.raw).map(([widget, rate]) => [widget, rational(rate)]));
+  }
+}
+
+// a loaded game definition, freed once it is garbage collected
+class Cookbook {
+  #handle;
+
+  constructor(handle) {
+    this.#handle = handle;
+  }
+
+  static fromYaml(yaml) {
+    return new Cookbook(addon.fromYaml(yaml));
+  }
+
+  // plans `rate` units/second of `widget`; the options are those of a /solve request: purity, max_tier,
+  // unlocked, limits, exact_clock, share_surplus, network and game_version
+  solve(widget, rate, options = {}) {
+    return new Plan(addon.solve(this.#handle, JSON.stringify({ ...options, widget, rate })));
+  }
+}
+
+// the game definition at `file`, read as the command line reads it, compressed or compiled ones included
+function loadRecipes(file) {
+  return new Cookbook(addon.loadRecipes(file));
+}
+
+module.exports = { Cookbook, Plan, loadRecipes };
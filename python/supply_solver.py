@@ -0,0 +1,139 @@
+"""Python bindings for supply-solver, over the C interface the crate builds with `--features ffi`.
+
+    cargo build --release --features ffi
+    SUPPLY_SOLVER_LIB=target/release/libsupply_solver.so python3
+
+    >>> import supply_solver
+    >>> cookbook = supply_solver.load_recipes("satisfactory.yaml")
+    >>> plan = cookbook.solve("iron-plate", 1)
+    >>> plan.machines, plan.raw
+    (7, {'iron-ore': Fraction(3, 2)})
+
+Rates are exact: pass an int, a Fraction or a string such as "1/3", and read them back as Fractions.
+"""
+
+import ctypes
+import ctypes.util
+import json
+import os
+from fractions import Fraction
+
+__all__ = ["SolverError", "Cookbook", "Plan", "load_recipes"]
+
+
+class SolverError(Exception):
+    """A definition that failed to load or a request that failed to solve, with the solver's message."""
+
+
+def _library():
+    # SUPPLY_SOLVER_LIB, else the library next to this file, else wherever the system keeps it
+    path = os.environ.get("SUPPLY_SOLVER_LIB")
+    if path is None:
+        here = os.path.dirname(os.path.abspath(__file__))
+        names = ["libsupply_solver.so", "libsupply_solver.dylib", "supply_solver.dll"]
+        path = next((os.path.join(here, n) for n in names if os.path.exists(os.path.join(here, n))), None)
+    path = path or ctypes.util.find_library("supply_solver")
+    if path is None:
+        raise ImportError("libsupply_solver not found: build it with `cargo build --release --features ffi` and set SUPPLY_SOLVER_LIB")
+    lib = ctypes.CDLL(path)
+    handle, text = ctypes.c_void_p, ctypes.c_char_p
+    for name, args, result in [
+        ("supply_solver_last_error", [], text),
+        ("supply_solver_cookbook_from_yaml", [text], handle),
+        ("supply_solver_cookbook_load", [text], handle),
+        ("supply_solver_cookbook_free", [handle], None),
+        ("supply_solver_solve", [handle, text], handle),
+        ("supply_solver_plan_json", [handle], text),
+        ("supply_solver_plan_text", [handle], text),
+        ("supply_solver_plan_machines", [handle], ctypes.c_uint64),
+        ("supply_solver_plan_free", [handle], None),
+    ]:
+        function = getattr(lib, name)
+        function.argtypes, function.restype = args, result
+    return lib
+
+
+_lib = _library()
+
+
+def _checked(pointer):
+    if not pointer:
+        raise SolverError(_lib.supply_solver_last_error().decode())
+    return pointer
+
+
+def _rational(pair):
+    # rationals come out of plans as [numer, denom]
+    return Fraction(pair[0], pair[1])
+
+
+def _rate(rate):
+    if isinstance(rate, Fraction):
+        return "{}/{}".format(rate.numerator, rate.denominator)
+    if isinstance(rate, float):
+        raise TypeError("rates are exact, pass an int, a Fraction or a string such as '1/3'")
+    return rate
+
+
+class Plan:
+    """A solved plan: the whole of it as `data`, in the saved plan format, and the parts a sweep wants first."""
+
+    def __init__(self, data, text, machines):
+        self.data = data
+        # the stage tree as `supply-solver solve` prints it
+        self.text = text
+        self.machines = machines
+
+    @property
+    def raw(self):
+        """Units/second of each raw resource the plan draws."""
+        return {widget: _rational(rate) for widget, rate in self.data["summary"]["raw"].items()}
+
+    @property
+    def power(self):
+        """MW the plan's machines draw in all."""
+        return sum((_rational(mw) for mw in self.data["summary"]["power"].values()), Fraction(0))
+
+    def __repr__(self):
+        return "<Plan for {} of {}, {} machines>".format(_rational(self.data["rate"]), self.data["widget"], self.machines)
+
+
+class Cookbook:
+    """A loaded game definition, solved against any number of times."""
+
+    def __init__(self, handle):
+        self._handle = handle
+
+    @classmethod
+    def from_yaml(cls, yaml):
+        return cls(_checked(_lib.supply_solver_cookbook_from_yaml(yaml.encode())))
+
+    def solve(self, widget, rate, **options):
+        """Plans `rate` units/second of `widget`. The options are those of a /solve request: purity,
+        max_tier, unlocked, limits, exact_clock, share_surplus, network and game_version."""
+        request = dict(options, widget=widget, rate=_rate(rate))
+        plan = _checked(_lib.supply_solver_solve(self._handle, json.dumps(request).encode()))
+        try:
+            return Plan(json.loads(_lib.supply_solver_plan_json(plan)), _lib.supply_solver_plan_text(plan).decode(),
+                        _lib.supply_solver_plan_machines(plan))
+        finally:
+            _lib.supply_solver_plan_free(plan)
+
+    def close(self):
+        if self._handle:
+            _lib.supply_solver_cookbook_free(self._handle)
+            self._handle = None
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *_):
+        self.close()
+
+    def __del__(self):
+        self.close()
+
+
+def load_recipes(path):
+    """Reads the game definition at `path` as the command line does, compressed or compiled ones included."""
+    return Cookbook(_checked(_lib.supply_solver_cookbook_load(os.fspath(path).encode())))
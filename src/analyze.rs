@@ -0,0 +1,143 @@
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::error::Result;
+use crate::hypergraph::Hypergraph;
+use crate::{as_f64, solve, solve_within_limits, Constraints, PlanTree, Precision, Recipe};
+
+// machines per recipe, None when the builder limits can't fit the plan
+type Signature = Option<BTreeMap<String, u64>>;
+
+// a rate past which the plan changes shape
+pub struct Breakpoint {
+    // the last rate the previous plan covers
+    pub rate: Rational64,
+    pub changes: Vec<String>
+}
+
+fn count_machines(tree: &PlanTree, counts: &mut BTreeMap<String, u64>) {
+    *counts.entry(tree.recipe.name.clone()).or_insert(0) += tree.machine_count;
+    for child in tree.children() {
+        count_machines(child, counts);
+    }
+}
+
+fn signature(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Signature> {
+    let solution = if constraints.limits.is_empty() {
+        Some(solve(graph, extraction, widget, rate, constraints)?)
+    } else {
+        solve_within_limits(graph, extraction, widget, rate, constraints)?
+    };
+    Ok(solution.map(| plan | {
+        let mut counts = BTreeMap::new();
+        if let Some(tree) = plan.tree {
+            count_machines(&tree, &mut counts);
+        }
+        counts
+    }))
+}
+
+fn machines(count: u64) -> String {
+    if count == 1 { "1 machine".to_owned() } else { format!("{} machines", count) }
+}
+
+fn changes(before: &Signature, after: &Signature) -> Vec<String> {
+    match (before, after) {
+        (Some(_), None) => vec!["builder limits run out".to_owned()],
+        (None, Some(_)) => vec!["plan fits the builder limits again".to_owned()],
+        (None, None) => vec![],
+        (Some(before), Some(after)) => {
+            let mut changes = Vec::new();
+            for (recipe, count) in after.iter() {
+                match before.get(recipe) {
+                    Some(old) if old != count => changes.push(format!("{recipe}: {old} -> {count}", recipe=recipe, old=old, count=machines(*count))),
+                    Some(_) => {},
+                    None => changes.push(format!("switches to {recipe} ({count})", recipe=recipe, count=machines(*count)))
+                }
+            }
+            for recipe in before.keys().filter(| r | !after.contains_key(*r)) {
+                changes.push(format!("drops {recipe}", recipe=recipe));
+            }
+            changes
+        }
+    }
+}
+
+// samples the rate range and bisects every interval the plan changes in down to a thousandth of the range
+pub fn analyze(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, from: Rational64, to: Rational64, steps: u64, constraints: &Constraints) -> Result<Vec<Breakpoint>> {
+    let step = (to - from) / Rational64::from_integer(steps as i64);
+    let precision = (to - from) / Rational64::from_integer(1000);
+    let mut breakpoints = Vec::new();
+    let mut rate = from;
+    let mut current = signature(graph, extraction, widget, rate, constraints)?;
+    for i in 1..=steps {
+        let next_rate = from + step * Rational64::from_integer(i as i64);
+        let next = signature(graph, extraction, widget, next_rate, constraints)?;
+        // several changes can fall within one sample interval
+        while next != current {
+            let (mut lo, mut hi) = (rate, next_rate);
+            let mut hi_signature = next.clone();
+            while hi - lo > precision {
+                let mid = (lo + hi) / Rational64::from_integer(2);
+                let mid_signature = signature(graph, extraction, widget, mid, constraints)?;
+                if mid_signature == current {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                    hi_signature = mid_signature;
+                }
+            }
+            breakpoints.push(Breakpoint { rate: lo, changes: changes(&current, &hi_signature) });
+            current = hi_signature;
+            rate = hi;
+        }
+        rate = next_rate;
+    }
+    Ok(breakpoints)
+}
+
+pub fn print_analysis(widget: &String, target: Rational64, from: Rational64, to: Rational64, breakpoints: &[Breakpoint], precision: Precision) {
+    println!("Plan for {widget} between {from}/s and {to}/s", widget=widget, from=precision.fixed(as_f64(&from), 3), to=precision.fixed(as_f64(&to), 3));
+    if breakpoints.is_empty() {
+        println!("  no changes");
+    }
+    for breakpoint in breakpoints {
+        println!("  beyond {rate}/s: {changes}", rate=precision.fixed(as_f64(&breakpoint.rate), 3), changes=breakpoint.changes.join(", "));
+    }
+    println!();
+    let below = breakpoints.iter().rev().find(| b | b.rate < target);
+    let above = breakpoints.iter().find(| b | b.rate >= target);
+    let headroom = | rate: Rational64 | format!("{:+.*}", precision.places(1), as_f64(&((rate - target) / target)) * 100.0);
+    match above {
+        Some(b) => println!("Headroom: the plan for {target}/s holds up to {rate}/s ({headroom}%)", target=precision.fixed(as_f64(&target), 3), rate=precision.fixed(as_f64(&b.rate), 3), headroom=headroom(b.rate)),
+        None => println!("Headroom: the plan for {target}/s holds up to at least {to}/s", target=precision.fixed(as_f64(&target), 3), to=precision.fixed(as_f64(&to), 3))
+    }
+    if let Some(b) = below {
+        println!("It is oversized below {rate}/s ({headroom}%)", rate=precision.fixed(as_f64(&b.rate), 3), headroom=headroom(b.rate));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cookbook;
+
+    #[test]
+    fn analyze_test() {
+        let one = Rational64::from_integer(1);
+        let graph = Cookbook::builder().machine("Assembler", None).item("a").item("b")
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", one).product("a", one))
+            .build().graph().unwrap();
+        let (from, to) = (Rational64::new(1, 2), Rational64::new(5, 2));
+        let breakpoints = analyze(&graph, &[], &"a".to_owned(), from, to, 4, &Constraints::default()).unwrap();
+        // every whole unit a second takes one more machine, found to within a thousandth of the range
+        assert_eq!(breakpoints.len(), 2);
+        for (breakpoint, rate) in breakpoints.iter().zip([1, 2]) {
+            assert!(breakpoint.rate <= Rational64::from_integer(rate) && Rational64::from_integer(rate) - breakpoint.rate < (to - from) / 1000);
+        }
+        assert_eq!(breakpoints[0].changes, ["A: 1 -> 2 machines"]);
+        let limited = Constraints { limits: BTreeMap::from([("Assembler".to_owned(), 2)]), ..Default::default() };
+        let breakpoints = analyze(&graph, &[], &"a".to_owned(), from, to, 4, &limited).unwrap();
+        assert_eq!(breakpoints.last().unwrap().changes, ["builder limits run out"]);
+    }
+}
@@ -0,0 +1,511 @@
+use num::Rational64;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::tree::NTree;
+use crate::{buys, decimal, deserialize_rational_map, is_raw, load_yaml, producers, Constraints, Cookbook, Recipe};
+
+// widgets already on hand, as `widget: quantity`
+#[derive(Debug, Default, Deserialize)]
+pub struct Stock(#[serde(deserialize_with="deserialize_rational_map")] pub BTreeMap<String, Rational64>);
+
+impl Stock {
+    pub fn parse(file_path: &PathBuf, graph: &Hypergraph<String, Recipe>) -> Result<Self> {
+        let stock: Stock = load_yaml(file_path)?;
+        for widget in stock.0.keys() {
+            is_raw(graph, widget)?;
+        }
+        Ok(stock)
+    }
+}
+
+fn take(from: &mut BTreeMap<String, Rational64>, taken: &mut BTreeMap<String, Rational64>, widget: &String, quantity: Rational64) -> Rational64 {
+    let zero = Rational64::from_integer(0);
+    match from.get_mut(widget).filter(| q | **q > zero) {
+        Some(on_hand) => {
+            let amount = quantity.min(*on_hand);
+            *on_hand -= amount;
+            *taken.entry(widget.clone()).or_insert(zero) += amount;
+            quantity - amount
+        },
+        None => quantity
+    }
+}
+
+// what a batch draws from outside the plan
+#[derive(Debug, Default)]
+pub struct Materials {
+    // raw resources to gather
+    pub raw: BTreeMap<String, Rational64>,
+    // inventory left to draw on
+    pub stock: BTreeMap<String, Rational64>,
+    pub from_stock: BTreeMap<String, Rational64>,
+    // units crafted beyond what their stage needed, byproducts included
+    pub surplus: BTreeMap<String, Rational64>,
+    pub shared: BTreeMap<String, Rational64>,
+    // bought instead of crafted
+    pub purchased: BTreeMap<String, Rational64>
+}
+
+impl Materials {
+    pub fn new(stock: Stock) -> Self {
+        Materials { stock: stock.0, ..Default::default() }
+    }
+
+    // takes as much of `quantity` as the inventory holds, then surplus when sharing it, returning what is still missing
+    fn draw(&mut self, widget: &String, quantity: Rational64, share_surplus: bool) -> Rational64 {
+        let quantity = take(&mut self.stock, &mut self.from_stock, widget, quantity);
+        if share_surplus {
+            take(&mut self.surplus, &mut self.shared, widget, quantity)
+        } else {
+            quantity
+        }
+    }
+}
+
+// one recipe's share of a batch
+#[derive(Debug)]
+pub struct Stage<'a> {
+    pub recipe: &'a Recipe,
+    pub widget: String,
+    // units of `widget` the parent needs
+    pub quantity: Rational64,
+    pub crafts: u64,
+    pub machines: u64,
+    // seconds per craft on one machine
+    pub craft_time: Rational64
+}
+
+impl Stage<'_> {
+    // units of `widget` out of every craft
+    pub fn per_craft(&self) -> Rational64 {
+        self.recipe.products.iter().filter(| p | p.widget == self.widget).map(| p | p.quantity).sum::<Rational64>() * self.recipe.productivity()
+    }
+}
+
+pub type BatchTree<'a> = NTree<Stage<'a>>;
+
+// picks the recipe overshooting `quantity` the least on its final craft
+fn least_waste_batch<'a>(recipes: Vec<&'a Recipe>, widget: &String, quantity: Rational64, constraints: &Constraints) -> Result<(&'a Recipe, u64)> {
+    let mut candidates = Vec::new();
+    for recipe in recipes.into_iter().filter(| r | constraints.allows(r)) {
+        let per_craft = recipe.products.iter().find(| p | &p.widget == widget).map(| p | p.quantity * recipe.productivity()).ok_or_else(|| Error::UnknownWidget(widget.clone()))?;
+        if per_craft <= Rational64::from_integer(0) {
+            return Err(Error::Arithmetic(format!("recipe `{name}` produces no `{widget}`", name=recipe.name, widget=widget)));
+        }
+        let crafts = (quantity / per_craft).ceil();
+        candidates.push((recipe, crafts, crafts * per_craft - quantity));
+    }
+    if constraints.prefer_machines && candidates.iter().any(| (r, _, _) | !r.is_manual()) {
+        candidates.retain(| (r, _, _) | !r.is_manual());
+    }
+    // producers come back unordered, so break ties on fewer crafts, then name
+    candidates.into_iter().min_by(| (a, a_crafts, a_waste), (b, b_crafts, b_waste) | a_waste.cmp(b_waste).then(a_crafts.cmp(b_crafts)).then(a.name.cmp(&b.name)))
+        .map(| (recipe, crafts, _) | (recipe, crafts.to_integer() as u64))
+        .ok_or_else(|| Error::NoRecipes(widget.clone()))
+}
+
+// expands `quantity` units of `widget`, giving every stage enough machines to keep up with the
+// final one, which runs on a single machine unless a `target` time in seconds is given;
+// inventory is used up first, stages it fully covers are left out; shared surplus counts as on hand from the start
+pub fn batch_tree<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, quantity: Rational64, target: Option<Rational64>, constraints: &Constraints, materials: &mut Materials) -> Result<Option<BatchTree<'a>>> {
+    let quantity = materials.draw(widget, quantity, constraints.share_surplus);
+    if quantity <= Rational64::from_integer(0) {
+        return Ok(None);
+    }
+    if buys(graph, extraction, widget, constraints)? {
+        *materials.purchased.entry(widget.clone()).or_insert_with(|| Rational64::from_integer(0)) += quantity;
+        return Ok(None);
+    }
+    if is_raw(graph, widget)? {
+        *materials.raw.entry(widget.clone()).or_insert_with(|| Rational64::from_integer(0)) += quantity;
+        return Ok(None);
+    }
+    let (recipe, crafts) = least_waste_batch(producers(graph, widget)?, widget, quantity, constraints)?;
+    let (_, clock) = constraints.clock_range(recipe);
+    let craft_time = recipe.duration / (recipe.speed() * clock);
+    let work = craft_time * Rational64::from_integer(crafts as i64);
+    let target = target.unwrap_or(work);
+    let machines = if target > Rational64::from_integer(0) {
+        ((work / target).ceil().to_integer() as u64).clamp(1, crafts.max(1))
+    } else {
+        crafts.max(1)
+    };
+    for product in recipe.products.iter() {
+        let mut made = product.quantity * recipe.productivity() * Rational64::from_integer(crafts as i64);
+        if &product.widget == widget {
+            made -= quantity;
+        }
+        if made > Rational64::from_integer(0) {
+            *materials.surplus.entry(product.widget.clone()).or_insert_with(|| Rational64::from_integer(0)) += made;
+        }
+    }
+    let mut tree = NTree::new(Stage { recipe, widget: widget.clone(), quantity, crafts, machines, craft_time });
+    for reagent in recipe.reagents.iter() {
+        let needed = reagent.quantity * Rational64::from_integer(crafts as i64);
+        if let Some(child) = batch_tree(graph, extraction, &reagent.widget, needed, Some(target), constraints, materials)? {
+            tree.insert(child);
+        }
+    }
+    Ok(Some(tree))
+}
+
+// when a stage's machine group runs, with crafts split into rounds of one craft per machine
+#[derive(Debug)]
+pub struct Timing {
+    pub start: Rational64,
+    pub finish: Rational64,
+    // end of every round, in seconds from the start of the batch
+    pub rounds: Vec<Rational64>,
+    // the input that held up the last round to wait on inputs, if any did
+    pub critical: Option<usize>
+}
+
+pub type Schedule = NTree<Timing>;
+
+// seconds until `stage` has made `units` of its widget
+fn available_at(stage: &Stage, timing: &Timing, units: Rational64) -> Rational64 {
+    if units <= Rational64::from_integer(0) {
+        return Rational64::from_integer(0);
+    }
+    let crafts = (units / stage.per_craft()).ceil().to_integer() as u64;
+    // a group of no machines still runs one at a time, as schedule() has it
+    let round = crafts.max(1).div_ceil(stage.machines.max(1)) as usize;
+    timing.rounds.get(round.min(timing.rounds.len()).saturating_sub(1)).copied().unwrap_or_else(|| Rational64::from_integer(0))
+}
+
+// lets every stage start as soon as it has the inputs for a round rather than the whole batch
+pub fn schedule(tree: &BatchTree) -> Schedule {
+    let children: Vec<Schedule> = tree.children().iter().map(schedule).collect();
+    let mut rounds = Vec::new();
+    let mut critical = None;
+    let mut machines_free = Rational64::from_integer(0);
+    let mut crafted = 0;
+    while crafted < tree.crafts.max(1) {
+        crafted = (crafted + tree.machines.max(1)).min(tree.crafts.max(1));
+        let mut start = machines_free;
+        for (i, (child, timing)) in tree.children().iter().zip(children.iter()).enumerate() {
+            let per_craft = tree.recipe.reagents.iter().filter(| r | r.widget == child.widget).map(| r | r.quantity).sum::<Rational64>();
+            // whatever the child doesn't make came out of stock or shared surplus, which are used first
+            let from_stock = per_craft * Rational64::from_integer(tree.crafts as i64) - child.quantity;
+            let needed = per_craft * Rational64::from_integer(crafted as i64) - from_stock;
+            let ready = available_at(child, timing, needed);
+            if ready > start {
+                start = ready;
+                critical = Some(i);
+            }
+        }
+        machines_free = start + tree.craft_time;
+        rounds.push(machines_free);
+    }
+    let start = rounds[0] - tree.craft_time;
+    let mut node = NTree::new(Timing { start, finish: machines_free, rounds, critical });
+    for child in children {
+        node.insert(child);
+    }
+    node
+}
+
+// stages from the final one down to the input that held the whole batch up
+pub fn critical_path<'a, 'b>(tree: &'a BatchTree<'b>, timing: &Schedule) -> Vec<&'a Stage<'b>> {
+    let mut path = vec![&**tree];
+    if let Some(i) = timing.critical {
+        path.extend(critical_path(&tree.children()[i], &timing.children()[i]));
+    }
+    path
+}
+
+// what every stage's output costs in total: machine time at the builders' operating cost plus its inputs,
+// those no stage makes being valued at their market price
+pub fn costs(tree: &BatchTree, prices: &BTreeMap<String, Rational64>) -> NTree<Rational64> {
+    let children: Vec<NTree<Rational64>> = tree.children().iter().map(| c | costs(c, prices)).collect();
+    let crafts = Rational64::from_integer(tree.crafts as i64);
+    let mut total = tree.recipe.operating_cost() * tree.craft_time * crafts + children.iter().map(| c | **c).sum::<Rational64>();
+    for reagent in tree.recipe.reagents.iter() {
+        let made = tree.children().iter().filter(| c | c.widget == reagent.widget).map(| c | c.quantity).sum::<Rational64>();
+        let unmade = reagent.quantity * crafts - made;
+        if let (Some(price), true) = (prices.get(&reagent.widget), unmade > Rational64::from_integer(0)) {
+            total += price * unmade;
+        }
+    }
+    let mut node = NTree::new(total);
+    for child in children {
+        node.insert(child);
+    }
+    node
+}
+
+// one builder group's working window
+pub struct Task<'a> {
+    pub stage: &'a Stage<'a>,
+    pub start: Rational64,
+    pub finish: Rational64
+}
+
+// every stage in plan order, final stage first
+pub fn timeline<'a>(tree: &'a BatchTree<'a>, timing: &Schedule) -> Vec<Task<'a>> {
+    let mut tasks = vec![Task { stage: tree, start: timing.start, finish: timing.finish }];
+    for (child, child_timing) in tree.children().iter().zip(timing.children().iter()) {
+        tasks.extend(timeline(child, child_timing));
+    }
+    tasks
+}
+
+// mermaid reads `:` and `#` as syntax inside task names
+fn mermaid_label(text: &str) -> String {
+    text.replace([':', '#', ';'], " ")
+}
+
+pub fn print_mermaid(tree: &BatchTree, widget: &str) {
+    let timing = schedule(tree);
+    let mut sections: BTreeMap<&str, Vec<Task>> = BTreeMap::new();
+    for task in timeline(tree, &timing) {
+        sections.entry(task.stage.recipe.builder_name()).or_default().push(task);
+    }
+    println!("gantt");
+    println!("    title {quantity} {widget}", quantity=decimal(&tree.quantity, 3), widget=mermaid_label(widget));
+    println!("    dateFormat X");
+    println!("    axisFormat %H:%M:%S");
+    for (builder, tasks) in sections.iter() {
+        println!("    section {builder}", builder=mermaid_label(builder));
+        for task in tasks {
+            println!("    {name} ({machines}x) :{start}, {finish}", name=mermaid_label(&task.stage.recipe.name), machines=task.stage.machines,
+                start=task.start.floor().to_integer(), finish=task.finish.ceil().to_integer());
+        }
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+// stage costs in the same plan order as the timeline
+fn flatten_costs(costs: &NTree<Rational64>, out: &mut Vec<Rational64>) {
+    out.push(**costs);
+    for child in costs.children() {
+        flatten_costs(child, out);
+    }
+}
+
+pub fn print_csv(tree: &BatchTree, prices: &BTreeMap<String, Rational64>) {
+    let timing = schedule(tree);
+    let mut stage_costs = Vec::new();
+    flatten_costs(&costs(tree, prices), &mut stage_costs);
+    println!("recipe,builder,machines,crafts,start,finish,cost");
+    for (task, cost) in timeline(tree, &timing).into_iter().zip(stage_costs) {
+        println!("{recipe},{builder},{machines},{crafts},{start},{finish},{cost}", recipe=csv_field(&task.stage.recipe.name), builder=csv_field(task.stage.recipe.builder_name()),
+            machines=task.stage.machines, crafts=task.stage.crafts, start=decimal(&task.start, 3), finish=decimal(&task.finish, 3), cost=decimal(&cost, 2));
+    }
+}
+
+pub fn format_duration(seconds: Rational64) -> String {
+    let total = seconds.ceil().to_integer();
+    let (hours, minutes, secs) = (total / 3600, total % 3600 / 60, total % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, secs)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn amount(cookbook: &Cookbook, widget: &String, quantity: &Rational64) -> String {
+    if cookbook.is_fluid(widget) {
+        format!("{} m³ {}", decimal(quantity, 3), widget)
+    } else {
+        format!("{} {}", decimal(quantity, 3), widget)
+    }
+}
+
+fn describe(stage: &Stage, timing: &Timing, cost: Option<&NTree<Rational64>>, cookbook: &Cookbook) -> String {
+    let mut line = format!("{crafts} crafts on {machines}x {builder} -> {name} ({amount}, {start} to {finish})",
+        crafts=stage.crafts, machines=stage.machines, builder=stage.recipe.builder_name(), name=stage.recipe.name,
+        amount=amount(cookbook, &stage.widget, &stage.quantity), start=format_duration(timing.start), finish=format_duration(timing.finish));
+    if let Some(cost) = cost {
+        line.push_str(&format!(" [{cost} per unit]", cost=decimal(&(**cost / stage.quantity), 2)));
+    }
+    line
+}
+
+pub fn print_materials(materials: &Materials, constraints: &Constraints, cookbook: &Cookbook) {
+    if !materials.purchased.is_empty() {
+        let cost = | (widget, quantity): (&String, &Rational64) | constraints.prices.get(widget).map_or(Rational64::from_integer(0), | p | p * quantity);
+        println!();
+        println!("Shopping list: {total}", total=decimal(&materials.purchased.iter().map(cost).sum::<Rational64>(), 2));
+        for (widget, quantity) in materials.purchased.iter() {
+            println!("  {amount} ({cost})", amount=amount(cookbook, widget, quantity), cost=decimal(&cost((widget, quantity)), 2));
+        }
+    }
+    if !materials.from_stock.is_empty() {
+        println!();
+        println!("From stock:");
+        for (widget, quantity) in materials.from_stock.iter() {
+            println!("  {}", amount(cookbook, widget, quantity));
+        }
+    }
+    if !materials.shared.is_empty() {
+        println!();
+        println!("Shared surplus:");
+        for (widget, quantity) in materials.shared.iter() {
+            println!("  {}", amount(cookbook, widget, quantity));
+        }
+    }
+    if !materials.raw.is_empty() {
+        println!();
+        println!("Raw materials:");
+        for (widget, quantity) in materials.raw.iter() {
+            println!("  {}", amount(cookbook, widget, quantity));
+        }
+    }
+    if materials.surplus.values().any(| q | *q > Rational64::from_integer(0)) {
+        println!();
+        println!("Left over:");
+        for (widget, quantity) in materials.surplus.iter().filter(| (_, q) | **q > Rational64::from_integer(0)) {
+            println!("  {}", amount(cookbook, widget, quantity));
+        }
+    }
+}
+
+// `prices` rolls stage costs into the output
+pub fn print_batch(tree: &BatchTree, prices: Option<&BTreeMap<String, Rational64>>, materials: &Materials, constraints: &Constraints, cookbook: &Cookbook) {
+    let timing = schedule(tree);
+    let costs = prices.map(| p | costs(tree, p));
+    print!("{}", tree.render(| stage, path | describe(stage, timing.at(path).expect("timing per stage"), costs.as_ref().and_then(| c | c.at(path)), cookbook)));
+    print_materials(materials, constraints, cookbook);
+    if let Some(costs) = &costs {
+        println!();
+        println!("Cost: {total} in total, {unit} per {widget}", total=decimal(costs, 2), unit=decimal(&(**costs / tree.quantity), 2), widget=tree.widget);
+    }
+    println!();
+    let path: Vec<&str> = critical_path(tree, &timing).iter().rev().map(| stage | stage.recipe.name.as_str()).collect();
+    println!("Critical path: {path}", path=path.join(" -> "));
+    println!("Completion time: {time}", time=format_duration(timing.finish));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(n: i64) -> Rational64 {
+        Rational64::from_integer(n)
+    }
+
+    fn stage<'a>(recipe: &'a Recipe, crafts: u64, machines: u64) -> BatchTree<'a> {
+        let widget = recipe.products[0].widget.clone();
+        NTree::new(Stage { recipe, widget, quantity: seconds(crafts as i64), crafts, machines, craft_time: recipe.duration })
+    }
+
+    #[test]
+    fn schedule_test() {
+        // three plates made at once feed a frame machine one craft at a time
+        let frame = Recipe::new("Frame", seconds(2)).reagent("plate", seconds(1)).product("frame", seconds(1));
+        let plate = Recipe::new("Plate", seconds(1)).product("plate", seconds(1));
+        let mut tree = stage(&frame, 3, 1);
+        tree.insert(stage(&plate, 3, 3));
+        let timing = schedule(&tree);
+        assert_eq!(timing.children()[0].rounds, [seconds(1)]);
+        assert_eq!(timing.rounds, [seconds(3), seconds(5), seconds(7)]);
+        assert_eq!((timing.start, timing.finish, timing.critical), (seconds(1), seconds(7), Some(0)));
+    }
+
+    #[test]
+    fn critical_path_test() {
+        // the slow rods hold the frame up, not the plates
+        let frame = Recipe::new("Frame", seconds(1)).reagent("plate", seconds(1)).reagent("rod", seconds(1)).product("frame", seconds(1));
+        let plate = Recipe::new("Plate", seconds(1)).product("plate", seconds(1));
+        let rod = Recipe::new("Rod", seconds(4)).product("rod", seconds(1));
+        let mut tree = stage(&frame, 2, 1);
+        tree.insert(stage(&plate, 2, 1));
+        tree.insert(stage(&rod, 2, 1));
+        let timing = schedule(&tree);
+        let path: Vec<&str> = critical_path(&tree, &timing).iter().map(| s | s.recipe.name.as_str()).collect();
+        assert_eq!(path, ["Frame", "Rod"]);
+        assert_eq!(timing.finish, seconds(9));
+    }
+
+    #[test]
+    fn no_machines_test() {
+        // a group of no machines runs as one instead of never finishing
+        let frame = Recipe::new("Frame", seconds(1)).reagent("plate", seconds(1)).product("frame", seconds(1));
+        let plate = Recipe::new("Plate", seconds(1)).product("plate", seconds(1));
+        let mut tree = stage(&frame, 2, 0);
+        tree.insert(stage(&plate, 2, 0));
+        assert_eq!(schedule(&tree).finish, seconds(3));
+        let empty = Timing { start: seconds(0), finish: seconds(0), rounds: Vec::new(), critical: None };
+        assert_eq!(available_at(&tree.children()[0], &empty, seconds(1)), seconds(0));
+    }
+
+    #[test]
+    fn costs_test() {
+        // ore no stage makes is valued at its price, the plates a stage makes aren't
+        let frame = Recipe::new("Frame", seconds(1)).reagent("plate", seconds(2)).product("frame", seconds(1));
+        let plate = Recipe::new("Plate", seconds(1)).reagent("ore", seconds(3)).product("plate", seconds(1));
+        let mut tree = stage(&frame, 2, 1);
+        tree.insert(stage(&plate, 4, 1));
+        let prices = BTreeMap::from([("ore".to_owned(), seconds(2)), ("plate".to_owned(), seconds(100))]);
+        let costs = costs(&tree, &prices);
+        assert_eq!((*costs, *costs.children()[0]), (seconds(24), seconds(24)));
+    }
+
+    // frames made two at a time from three plates, plates one at a time from ore
+    fn frame_book() -> Cookbook {
+        Cookbook::builder().machine("Assembler", None).item("frame").item("plate").item("ore")
+            .recipe(Recipe::new("Frame", seconds(4)).made_by("Assembler").reagent("plate", seconds(3)).product("frame", seconds(2)))
+            .recipe(Recipe::new("Plate", seconds(1)).made_by("Assembler").reagent("ore", seconds(1)).product("plate", seconds(1)))
+            .build()
+    }
+
+    #[test]
+    fn batch_tree_test() {
+        let graph = frame_book().graph().unwrap();
+        let mut materials = Materials::default();
+        let tree = batch_tree(&graph, &[], &"frame".to_owned(), seconds(5), Some(seconds(4)), &Constraints::default(), &mut materials).unwrap().unwrap();
+        // three crafts for five frames, one each on three machines to be done in four seconds
+        assert_eq!((tree.crafts, tree.machines), (3, 3));
+        let plates = &tree.children()[0];
+        assert_eq!((plates.quantity, plates.crafts, plates.machines), (seconds(9), 9, 3));
+        assert_eq!(materials.raw, BTreeMap::from([("ore".to_owned(), seconds(9))]));
+        assert_eq!(materials.surplus, BTreeMap::from([("frame".to_owned(), seconds(1))]));
+        // left to itself the last stage runs on one machine
+        let tree = batch_tree(&graph, &[], &"frame".to_owned(), seconds(5), None, &Constraints::default(), &mut Materials::default()).unwrap().unwrap();
+        assert_eq!((tree.machines, tree.children()[0].machines), (1, 1));
+    }
+
+    #[test]
+    fn timeline_test() {
+        let frame = Recipe::new("Frame", seconds(2)).reagent("plate", seconds(1)).product("frame", seconds(1));
+        let plate = Recipe::new("Plate", seconds(1)).product("plate", seconds(1));
+        let mut tree = stage(&frame, 3, 1);
+        tree.insert(stage(&plate, 3, 3));
+        let windows: Vec<_> = timeline(&tree, &schedule(&tree)).iter().map(| t | (t.stage.recipe.name.as_str(), t.start, t.finish)).collect();
+        assert_eq!(windows, [("Frame", seconds(1), seconds(7)), ("Plate", seconds(0), seconds(1))]);
+        assert_eq!(mermaid_label("Alt: Iron #2; cast"), "Alt  Iron  2  cast");
+        assert_eq!((csv_field("Plate"), csv_field("Rod, \"cast\"")), ("Plate".to_owned(), "\"Rod, \"\"cast\"\"\"".to_owned()));
+    }
+
+    #[test]
+    fn stock_test() {
+        let graph = frame_book().graph().unwrap();
+        let frames = | on_hand: &[(&str, i64)] | {
+            let mut materials = Materials::new(Stock(on_hand.iter().map(| (w, q) | (w.to_string(), seconds(*q))).collect()));
+            let tree = batch_tree(&graph, &[], &"frame".to_owned(), seconds(4), None, &Constraints::default(), &mut materials).unwrap();
+            (tree.map(| t | t.children().first().map(| c | c.crafts)), materials)
+        };
+        // four plates on hand leave two of the six to make
+        let (plates, materials) = frames(&[("plate", 4)]);
+        assert_eq!(plates, Some(Some(2)));
+        assert_eq!((materials.from_stock["plate"], materials.stock["plate"], materials.raw["ore"]), (seconds(4), seconds(0), seconds(2)));
+        // all the plates on hand leave out their stage, all the frames the whole batch
+        assert_eq!(frames(&[("plate", 10)]).0, Some(None));
+        let (tree, materials) = frames(&[("frame", 5)]);
+        assert_eq!((tree, materials.stock["frame"]), (None, seconds(1)));
+    }
+}
@@ -0,0 +1,84 @@
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::{child_flows, Cookbook, PlanTree, Precision};
+
+// everything the plan's stages making one widget add up to
+#[derive(Debug)]
+pub struct BillLine {
+    pub machines: BTreeMap<String, u64>,
+    // units/second the consuming stages draw, and what the rounded-up machines make
+    pub requested: Rational64,
+    pub made: Rational64
+}
+
+#[derive(Debug, Default)]
+pub struct Bill {
+    pub widgets: BTreeMap<String, BillLine>,
+    // units/second of inputs no stage in the plan makes
+    pub inputs: BTreeMap<String, Rational64>
+}
+
+fn collect(tree: &PlanTree, widget: &str, bill: &mut Bill) {
+    let zero = Rational64::from_integer(0);
+    let line = bill.widgets.entry(widget.to_owned()).or_insert_with(|| BillLine { machines: BTreeMap::new(), requested: zero, made: zero });
+    *line.machines.entry(tree.recipe.builder_name().to_owned()).or_insert(0) += tree.machine_count;
+    line.requested += tree.requested_rate;
+    line.made += tree.actual_rate;
+    let flows = child_flows(tree);
+    for reagent in tree.recipe.reagents.iter().filter(| r | !flows.iter().any(| (fed, _) | fed.widget == r.widget)) {
+        *bill.inputs.entry(reagent.widget.clone()).or_insert(zero) += tree.draws(reagent);
+    }
+    for (child, (reagent, _)) in tree.children().iter().zip(flows) {
+        collect(child, &reagent.widget, bill);
+    }
+}
+
+// per-widget totals over the whole plan, stages making the same widget in several branches adding up
+pub fn bill_of_materials(tree: &PlanTree, widget: &str) -> Bill {
+    let mut bill = Bill::default();
+    collect(tree, widget, &mut bill);
+    bill
+}
+
+pub fn print_bill(bill: &Bill, cookbook: &Cookbook, precision: Precision) {
+    println!();
+    println!("Bill of materials:");
+    for (widget, line) in bill.widgets.iter() {
+        let machines: Vec<String> = line.machines.iter().map(| (builder, count) | format!("{count}x {builder}", count=count, builder=builder)).collect();
+        println!("  {widget}: {made} from {machines} ({requested} needed)", widget=widget, made=cookbook.format_rate(widget, &line.made, precision),
+            machines=machines.join(", "), requested=cookbook.format_rate(widget, &line.requested, precision));
+    }
+    if !bill.inputs.is_empty() {
+        println!();
+        println!("Inputs:");
+        for (widget, rate) in bill.inputs.iter() {
+            println!("  {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints, Recipe};
+
+    #[test]
+    fn bill_test() {
+        // `b` is made in both branches under `a`, once for `a` itself and once for the `c` it also takes
+        let one = Rational64::from_integer(1);
+        let graph = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("c").item("ore")
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", one).reagent("c", one).product("a", one))
+            .recipe(Recipe::new("B", one).made_by("Assembler").reagent("ore", one).product("b", Rational64::from_integer(2)))
+            .recipe(Recipe::new("C", one).made_by("Assembler").reagent("b", one).product("c", one))
+            .build().graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), one, &Constraints::default()).unwrap().tree.unwrap();
+        let bill = bill_of_materials(&tree, "a");
+        assert_eq!(bill.widgets.keys().map(String::as_str).collect::<Vec<_>>(), ["a", "b", "c"]);
+        let b = &bill.widgets["b"];
+        // half a machine in each branch, rounded up in both
+        assert_eq!((&b.machines, b.requested, b.made), (&BTreeMap::from([("Assembler".to_owned(), 2)]), Rational64::from_integer(2), Rational64::from_integer(4)));
+        // and the ore is what those whole machines draw
+        assert_eq!(bill.inputs, BTreeMap::from([("ore".to_owned(), Rational64::from_integer(2))]));
+    }
+}
@@ -0,0 +1,154 @@
+use num::Rational64;
+use serde_json::{json, Value};
+
+use crate::{as_f64, Cookbook, PlanTree, Precision};
+
+// Factorio 1.1, which 2.0 still imports, migrating the chests' names
+const VERSION: u64 = (1 << 48) | (1 << 32);
+const REQUESTER: &str = "logistic-chest-requester";
+const PROVIDER: &str = "logistic-chest-passive-provider";
+const INSERTER: &str = "fast-inserter";
+
+// Adler-32, the checksum ending a zlib stream
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// a zlib stream of stored deflate blocks; Factorio doesn't mind that nothing is compressed
+fn zlib(bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = bytes.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        out.push(blocks.peek().is_none() as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(bytes).to_be_bytes());
+    out
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, | n, (i, b) | n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char } else { '=' });
+        }
+    }
+    out
+}
+
+// the string to paste into the game, and how much of the plan it covers
+pub struct Blueprint {
+    pub string: String,
+    pub machines: usize,
+    // stages left out: extraction, hand-crafting and those moving fluids, which chests can't
+    pub skipped: Vec<String>
+}
+
+struct Layout<'c> {
+    cookbook: &'c Cookbook,
+    entities: Vec<Value>,
+    // the top of the next stage's row
+    y: f64,
+    skipped: Vec<String>,
+    machines: usize
+}
+
+impl Layout<'_> {
+    // a 1x1 entity at the tile with top left corner (x, y)
+    fn entity(&mut self, name: &str, x: f64, y: f64, extra: Value) {
+        let mut entity = json!({ "entity_number": self.entities.len() + 1, "name": name, "position": { "x": x + 0.5, "y": y + 0.5 } });
+        if let (Value::Object(entity), Value::Object(extra)) = (&mut entity, extra) {
+            entity.extend(extra);
+        }
+        self.entities.push(entity);
+    }
+
+    // a row of the stage's machines, each fed from a requester chest above and emptied into a provider below
+    fn stage(&mut self, tree: &PlanTree) {
+        let recipe = tree.recipe;
+        let builder = recipe.builder_name().to_owned();
+        let moves_fluid = recipe.reagents.iter().chain(recipe.products.iter()).any(| r | self.cookbook.is_fluid(&r.widget));
+        if recipe.builder.is_none() || recipe.manual || moves_fluid || self.cookbook.extractors.iter().any(| e | e.name == builder) {
+            if !self.skipped.contains(&recipe.name) {
+                self.skipped.push(recipe.name.clone());
+            }
+        } else {
+            // rounded up to whole tiles, Factorio's 3x3 assemblers without a footprint in the definition
+            let size = | side: Option<Rational64> | side.map_or(3.0, | s | as_f64(&s).ceil().max(1.0));
+            let (width, length) = recipe.machine.as_ref().map_or((3.0, 3.0), | m | (size(m.width), size(m.length)));
+            // two crafts' worth of every input
+            let requests: Vec<Value> = recipe.reagents.iter().enumerate()
+                .map(| (i, r) | json!({ "index": i + 1, "name": r.widget, "count": (as_f64(&r.quantity) * 2.0).ceil().max(1.0) as u64 })).collect();
+            for m in 0..tree.machine_count {
+                let (x, y) = (m as f64 * (width + 1.0), self.y);
+                let middle = x + (width / 2.0).floor();
+                self.entity(REQUESTER, middle, y, json!({ "request_filters": requests }));
+                // inserters pick up from the north and drop to the south without a direction
+                self.entity(INSERTER, middle, y + 1.0, json!({}));
+                let number = self.entities.len() + 1;
+                self.entities.push(json!({ "entity_number": number, "name": builder, "position": { "x": x + width / 2.0, "y": y + 2.0 + length / 2.0 },
+                    "recipe": recipe.name }));
+                self.entity(INSERTER, middle, y + 2.0 + length, json!({}));
+                self.entity(PROVIDER, middle, y + 3.0 + length, json!({}));
+                self.machines += 1;
+            }
+            self.y += length + 5.0;
+        }
+        for child in tree.children() {
+            self.stage(child);
+        }
+    }
+}
+
+/// A rough Factorio blueprint string for the plan, a starting point to stamp down and rework: a row
+/// of machines per stage, the goal's at the top, each machine set to its recipe between a requester
+/// chest asking for its inputs and a passive provider taking its output, the logistic network
+/// standing in for belts. Builder and recipe names are used as Factorio's entity and recipe names, as
+/// in definitions exported from the game. There are no power poles, and stages extracting, crafting
+/// by hand or moving fluids are left to be built by hand.
+pub fn blueprint(tree: &PlanTree, widget: &String, rate: Rational64, cookbook: &Cookbook, precision: Precision) -> Blueprint {
+    let mut layout = Layout { cookbook, entities: Vec::new(), y: 0.0, skipped: Vec::new(), machines: 0 };
+    layout.stage(tree);
+    let signal = if cookbook.is_fluid(widget) { "fluid" } else { "item" };
+    let value = json!({ "blueprint": {
+        "item": "blueprint",
+        "label": format!("{rate} of {widget}", rate=cookbook.format_rate(widget, &rate, precision), widget=widget),
+        "icons": [{ "index": 1, "signal": { "type": signal, "name": widget } }],
+        "entities": layout.entities,
+        "version": VERSION
+    } });
+    let json = serde_json::to_vec(&value).expect("blueprints serialize to JSON");
+    Blueprint { string: format!("0{}", base64(&zlib(&json))), machines: layout.machines, skipped: layout.skipped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_test() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man is"), "TWFuIGlz");
+        // a final stored block of the whole input, its length and the complement, then the checksum
+        let stream = zlib(b"abc");
+        assert_eq!(stream, [0x78, 0x01, 1, 3, 0, 0xfc, 0xff, b'a', b'b', b'c', 0x02, 0x4d, 0x01, 0x27]);
+    }
+}
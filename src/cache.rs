@@ -0,0 +1,109 @@
+use num::Rational64;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::saved::SavedPlan;
+use crate::{index, Constraints, Cookbook};
+
+// $SUPPLY_SOLVER_CACHE, empty to cache nothing, or supply-solver/plans under $XDG_CACHE_HOME or ~/.cache
+pub fn locate() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("SUPPLY_SOLVER_CACHE") {
+        return if path.is_empty() { None } else { Some(PathBuf::from(path)) };
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME").filter(| d | !d.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(| home | Path::new(&home).join(".cache")))?;
+    Some(base.join("supply-solver").join("plans"))
+}
+
+// bumped whenever what goes into a key changes, so entries made before miss rather than being read as other plans
+const KEY_FORMAT: u32 = 2;
+
+// everything in `constraints` that changes a plan, sets in a fixed order; taken apart field by field
+// so a new constraint can't be left out of the key
+pub fn constraints_key(constraints: &Constraints) -> String {
+    let Constraints { max_tier, prefer_machines, min_clock, max_clock, exact_clock, excluded, unlocked, categories, excluded_categories, alternate_penalty, game_version, limits, share_surplus, prices, network,
+        max_depth, cost, downstream_waste, minimize_power, rounding, sinks } = constraints;
+    let sorted = | set: &HashSet<String> | set.iter().cloned().collect::<BTreeSet<_>>();
+    format!("v{format} {recipes:?} {clocks:?} {planning:?} {amounts:?}", format=KEY_FORMAT,
+        recipes=(max_tier, prefer_machines, sorted(excluded), sorted(unlocked), categories.as_ref().map(sorted), sorted(excluded_categories), alternate_penalty, game_version.as_ref().map(ToString::to_string)),
+        clocks=(min_clock, max_clock, exact_clock, rounding),
+        planning=(share_surplus, network, max_depth, cost, downstream_waste, minimize_power),
+        amounts=(limits, prices, sinks))
+}
+
+// 64-bit FNV-1a, the same from one build and platform to the next as the std hasher isn't
+// promised to be; each part is preceded by its length so parts can't run into each other
+fn hashed(dir: &Path, prefix: &str, parts: &[&[u8]]) -> PathBuf {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in (part.len() as u64).to_le_bytes().iter().chain(part.iter()) {
+            hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+    dir.join(format!("{prefix}{hash:016x}", prefix=prefix, hash=hash))
+}
+
+/// The file a plan is cached in, named for a hash of the game definition's contents, the goal, the
+/// purity of resource nodes and the constraints.
+pub fn entry(dir: &Path, definition: &[u8], widget: &str, rate: Rational64, purity: &str, constraints: &Constraints) -> PathBuf {
+    let (rate, key) = (rate.to_string(), constraints_key(constraints));
+    hashed(dir, "", &[definition, widget.as_bytes(), rate.as_bytes(), purity.as_bytes(), key.as_bytes()]).with_extension("msgpack")
+}
+
+/// Where the latest plan for a goal from the game definition at `game_def` is kept, whatever the
+/// definition said then, as `.msgpack` with the definition it was solved from beside it as `.yaml`,
+/// (or compiled index), for solving it again after an edit without starting over.
+pub fn goal_entry(dir: &Path, game_def: &Path, widget: &str, rate: Rational64, purity: &str, constraints: &Constraints) -> PathBuf {
+    let game_def = game_def.canonicalize().unwrap_or_else(| _ | game_def.to_owned());
+    let (game_def, rate, key) = (game_def.to_string_lossy(), rate.to_string(), constraints_key(constraints));
+    hashed(dir, "goal-", &[game_def.as_bytes(), widget.as_bytes(), rate.as_bytes(), purity.as_bytes(), key.as_bytes()])
+}
+
+// a missing, unreadable or outdated entry is a miss
+pub fn load(entry: &Path) -> Option<SavedPlan> {
+    SavedPlan::load(&entry.to_owned()).ok()
+}
+
+// a cache that can't be written to just doesn't speed up later runs
+pub fn store(entry: &Path, plan: &SavedPlan) {
+    if let Some(dir) = entry.parent() {
+        if std::fs::create_dir_all(dir).is_ok() {
+            let _ = plan.save(&entry.to_owned());
+        }
+    }
+}
+
+pub fn load_previous(goal_entry: &Path) -> Option<(Cookbook, SavedPlan)> {
+    let definition = std::fs::read(goal_entry.with_extension("yaml")).ok()?;
+    let cookbook = match definition.starts_with(index::MAGIC) {
+        true => index::load(&definition).ok()?,
+        false => Cookbook::from_yaml(std::str::from_utf8(&definition).ok()?).ok()?
+    };
+    Some((cookbook, load(&goal_entry.with_extension("msgpack"))?))
+}
+
+pub fn store_previous(goal_entry: &Path, definition: &[u8], plan: &SavedPlan) {
+    store(&goal_entry.with_extension("msgpack"), plan);
+    let _ = std::fs::write(goal_entry.with_extension("yaml"), definition);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_test() {
+        let dir = Path::new("cache");
+        let rate = Rational64::from_integer(1);
+        let mut constraints = Constraints::default();
+        let plain = entry(dir, b"widgets: []", "plate", rate, "normal", &constraints);
+        assert_eq!(plain, entry(dir, b"widgets: []", "plate", rate, "normal", &constraints));
+        assert_ne!(plain, entry(dir, b"widgets: [ore]", "plate", rate, "normal", &constraints));
+        assert_ne!(plain, entry(dir, b"widgets: []", "plate", rate, "pure", &constraints));
+        constraints.unlocked.insert("Pure Plate".to_owned());
+        assert_ne!(plain, entry(dir, b"widgets: []", "plate", rate, "normal", &constraints));
+        // parts are kept apart, and the name doesn't change between builds
+        assert_ne!(entry(dir, b"ab", "c", rate, "normal", &constraints), entry(dir, b"a", "bc", rate, "normal", &constraints));
+        assert_eq!(hashed(dir, "", &[b"plate"]), dir.join("d75cfe78f5b6e2ac"));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+
+use crate::error::{Error, Result};
+use crate::{decimal, Cookbook, Recipe};
+
+// which widgets and recipes a listing shows
+pub struct Filter {
+    // part of the name, any case
+    pub pattern: Option<String>,
+    pub builder: Option<String>,
+    pub max_tier: Option<u32>
+}
+
+impl Filter {
+    fn matches(&self, name: &str) -> bool {
+        self.pattern.as_ref().is_none_or(| p | name.to_lowercase().contains(&p.to_lowercase()))
+    }
+
+    fn allows(&self, recipe: &Recipe) -> bool {
+        self.builder.as_ref().is_none_or(| b | recipe.builder_name().eq_ignore_ascii_case(b)) && self.max_tier.is_none_or(| tier | recipe.tier <= tier)
+    }
+}
+
+// widgets matching the pattern with how many of the allowed recipes make them; raw widgets
+// are listed unless a builder is asked for that doesn't extract them
+pub fn print_widgets(cookbook: &Cookbook, filter: &Filter) {
+    let mut shown = 0;
+    for widget in cookbook.widgets.iter().filter(| w | filter.matches(w.name())) {
+        let makers = cookbook.recipes.iter().filter(| r | r.products.iter().any(| p | &p.widget == widget.name())).collect::<Vec<_>>();
+        let allowed = makers.iter().filter(| r | filter.allows(r)).count();
+        let extracted_by = cookbook.extractors.iter().filter(| e | e.resources.contains(widget.name())).map(| e | e.name.as_str()).collect::<Vec<_>>();
+        let kind = if widget.is_fluid() { " fluid" } else { "" };
+        let line = if makers.is_empty() {
+            if filter.builder.as_ref().is_some_and(| b | !extracted_by.iter().any(| e | e.eq_ignore_ascii_case(b))) {
+                continue;
+            }
+            match extracted_by.is_empty() {
+                true => format!("raw{kind}", kind=kind),
+                false => format!("raw{kind}, extracted by {extractors}", kind=kind, extractors=extracted_by.join(", "))
+            }
+        } else if allowed == 0 {
+            continue;
+        } else {
+            format!("{count} recipe{s}{kind}", count=allowed, s=if allowed == 1 { "" } else { "s" }, kind=kind)
+        };
+        println!("{name}: {line}", name=widget.name(), line=line);
+        shown += 1;
+    }
+    println!("{shown} of {total} widgets", shown=shown, total=cookbook.widgets.len());
+}
+
+// allowed recipes making a widget that matches the pattern, or named like it
+pub fn print_recipes(cookbook: &Cookbook, filter: &Filter) {
+    let mut shown = 0;
+    for recipe in cookbook.recipes.iter().filter(| r | filter.allows(r) && (filter.matches(&r.name) || r.products.iter().any(| p | filter.matches(&p.widget)))) {
+        let mut about = vec![recipe.builder_name().to_owned()];
+        if recipe.tier > 0 {
+            about.push(format!("tier {tier}", tier=recipe.tier));
+        }
+        if recipe.alternate {
+            about.push("alternate".to_owned());
+        }
+        println!("{name} [{about}]: {formula} in {duration}s", name=recipe.name, about=about.join(", "), formula=recipe.formula(), duration=decimal(&recipe.duration, 2));
+        shown += 1;
+    }
+    println!("{shown} of {total} recipes", shown=shown, total=cookbook.recipes.len());
+}
+
+// recipes taking `widget` as a reagent and, when `transitive`, the recipes taking their products in turn,
+// each with the widget that leads to it, a step at a time; breadth first, so every recipe shows at its nearest
+pub fn uses<'c>(cookbook: &'c Cookbook, widget: &'c String, transitive: bool) -> Result<Vec<Vec<(&'c Recipe, &'c String)>>> {
+    if !cookbook.widgets.iter().any(| w | w.name() == widget) {
+        return Err(Error::UnknownWidget(widget.clone()));
+    }
+    let mut reached = BTreeSet::from([widget]);
+    let mut listed = BTreeSet::new();
+    let mut frontier = vec![widget];
+    let mut steps = Vec::new();
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        let mut uses: Vec<(&Recipe, &String)> = Vec::new();
+        for input in frontier.iter() {
+            for recipe in cookbook.recipes.iter().filter(| r | r.reagents.iter().any(| g | &g.widget == *input)) {
+                if listed.insert(&recipe.name) {
+                    uses.push((recipe, *input));
+                }
+            }
+        }
+        if uses.is_empty() {
+            break;
+        }
+        uses.sort_by(| a, b | a.0.name.cmp(&b.0.name));
+        for (recipe, _) in uses.iter() {
+            next.extend(recipe.products.iter().map(| p | &p.widget).filter(| p | reached.insert(*p)));
+        }
+        steps.push(uses);
+        if !transitive {
+            break;
+        }
+        frontier = next;
+    }
+    Ok(steps)
+}
+
+pub fn print_uses(cookbook: &Cookbook, widget: &String, transitive: bool) -> Result<()> {
+    let steps = uses(cookbook, widget, transitive)?;
+    let mut downstream = BTreeSet::new();
+    for (depth, uses) in steps.iter().enumerate() {
+        println!("{heading}:", heading=if depth == 0 { format!("Recipes using {}", widget) } else { format!("{} steps away", depth + 1) });
+        for (recipe, input) in uses {
+            let via = if *input == widget { String::new() } else { format!(" via {}", input) };
+            println!("  {name} [{builder}]{via}: {formula}", name=recipe.name, builder=recipe.builder_name(), via=via, formula=recipe.formula());
+            downstream.extend(recipe.products.iter().map(| p | &p.widget).filter(| p | *p != widget));
+        }
+    }
+    if steps.is_empty() {
+        println!("Nothing uses {widget}", widget=widget);
+    } else if transitive {
+        println!("{count} widgets downstream: {widgets}", count=downstream.len(), widgets=downstream.iter().map(| w | w.as_str()).collect::<Vec<_>>().join(", "));
+    }
+    Ok(())
+}
+
+// a widget first made at a step, with the recipes making it
+pub type Made<'c> = (&'c String, Vec<&'c str>);
+
+// widgets `have` leads to using only recipes whose every reagent is on hand or made from it, a step at a
+// time, each with the recipes first making it
+pub fn craftable<'c>(cookbook: &'c Cookbook, have: &'c [String], filter: &Filter) -> Result<Vec<Vec<Made<'c>>>> {
+    if let Some(unknown) = have.iter().find(| h | !cookbook.widgets.iter().any(| w | w.name() == *h)) {
+        return Err(Error::UnknownWidget(unknown.clone()));
+    }
+    let mut available: BTreeSet<&String> = have.iter().collect();
+    let mut steps = Vec::new();
+    loop {
+        let mut made: Vec<Made> = Vec::new();
+        for recipe in cookbook.recipes.iter().filter(| r | filter.allows(r) && r.reagents.iter().all(| g | available.contains(&g.widget))) {
+            for product in recipe.products.iter().filter(| p | !available.contains(&p.widget)) {
+                match made.iter_mut().find(| (w, _) | *w == &product.widget) {
+                    Some((_, recipes)) => recipes.push(&recipe.name),
+                    None => made.push((&product.widget, vec![&recipe.name]))
+                }
+            }
+        }
+        if made.is_empty() {
+            break;
+        }
+        made.sort();
+        available.extend(made.iter().map(| (w, _) | *w));
+        steps.push(made);
+    }
+    Ok(steps)
+}
+
+pub fn print_craftable(cookbook: &Cookbook, have: &[String], filter: &Filter) -> Result<()> {
+    let steps = craftable(cookbook, have, filter)?;
+    for (step, made) in steps.iter().enumerate() {
+        println!("{heading}:", heading=if step == 0 { "Craftable now".to_owned() } else { format!("{} steps away", step + 1) });
+        for (widget, recipes) in made.iter() {
+            println!("  {widget} ({recipes})", widget=widget, recipes=recipes.join(", "));
+        }
+    }
+    if steps.is_empty() {
+        println!("Nothing can be made from {have} alone", have=have.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Rational64;
+
+    #[test]
+    fn filter_test() {
+        let one = Rational64::from_integer(1);
+        let recipe = Recipe::new("Iron Plate", one).made_by("Constructor").at_tier(2);
+        let filter = Filter { pattern: Some("PLATE".to_owned()), builder: Some("constructor".to_owned()), max_tier: Some(2) };
+        // names match any case, builders by name whatever their case
+        assert!(filter.matches("reinforced-iron-plate") && !filter.matches("iron-rod"));
+        assert!(filter.allows(&recipe));
+        assert!(!Filter { max_tier: Some(1), ..filter }.allows(&recipe));
+        assert!(!Filter { pattern: None, builder: Some("Assembler".to_owned()), max_tier: None }.allows(&recipe));
+        let everything = Filter { pattern: None, builder: None, max_tier: None };
+        assert!(everything.matches("anything") && everything.allows(&Recipe::new("By Hand", one)));
+    }
+
+    #[test]
+    fn uses_test() {
+        let one = Rational64::from_integer(1);
+        let step = | name: &str, from: &str, to: &str | Recipe::new(name, one).made_by("Assembler").reagent(from, one).product(to, one);
+        let cookbook = Cookbook::builder().machine("Assembler", None).item("ore").item("ingot").item("plate").item("frame")
+            .recipe(step("Ingot", "ore", "ingot")).recipe(step("Plate", "ingot", "plate")).recipe(step("Cast Plate", "ore", "plate")).recipe(step("Frame", "plate", "frame"))
+            .build();
+        let names = | steps: Vec<Vec<(&Recipe, &String)>> | steps.iter().map(| uses | uses.iter().map(| (r, via) | format!("{} via {}", r.name, via)).collect::<Vec<_>>()).collect::<Vec<_>>();
+        let ore = "ore".to_owned();
+        assert_eq!(names(uses(&cookbook, &ore, false).unwrap()), [["Cast Plate via ore", "Ingot via ore"]]);
+        // Plate is a step further through the ingot, and Frame shows once, at its nearest
+        assert_eq!(names(uses(&cookbook, &ore, true).unwrap()), [vec!["Cast Plate via ore", "Ingot via ore"], vec!["Frame via plate", "Plate via ingot"]]);
+        assert!(uses(&cookbook, &"frame".to_owned(), true).unwrap().is_empty());
+        assert!(matches!(uses(&cookbook, &"nothing".to_owned(), false), Err(Error::UnknownWidget(_))));
+    }
+
+    #[test]
+    fn craftable_test() {
+        let one = Rational64::from_integer(1);
+        let cookbook = Cookbook::builder().machine("Assembler", None).machine("Smelter", None).item("ore").item("coal").item("ingot").item("steel").item("plate")
+            .recipe(Recipe::new("Ingot", one).made_by("Smelter").reagent("ore", one).product("ingot", one))
+            .recipe(Recipe::new("Steel", one).made_by("Smelter").reagent("ingot", one).reagent("coal", one).product("steel", one))
+            .recipe(Recipe::new("Plate", one).made_by("Assembler").reagent("ingot", one).product("plate", one))
+            .recipe(Recipe::new("Cast Plate", one).made_by("Smelter").reagent("ore", one).product("plate", one))
+            .build();
+        let everything = Filter { pattern: None, builder: None, max_tier: None };
+        let have = ["ore".to_owned()];
+        // steel needs coal as well, and plates made now aren't listed again a step later
+        assert_eq!(craftable(&cookbook, &have, &everything).unwrap(), [vec![(&"ingot".to_owned(), vec!["Ingot"]), (&"plate".to_owned(), vec!["Cast Plate"])]]);
+        let smelted = Filter { builder: Some("smelter".to_owned()), ..everything };
+        let both = ["ore".to_owned(), "coal".to_owned()];
+        assert_eq!(craftable(&cookbook, &both, &smelted).unwrap().iter().map(| made | made.len()).collect::<Vec<_>>(), [2, 1]);
+        assert!(craftable(&cookbook, &["plate".to_owned()], &smelted).unwrap().is_empty());
+        assert!(matches!(craftable(&cookbook, &["nothing".to_owned()], &smelted), Err(Error::UnknownWidget(w)) if w == "nothing"));
+    }
+}
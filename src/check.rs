@@ -0,0 +1,167 @@
+use num::traits::{CheckedMul, CheckedSub};
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::saved::{SavedNode, SavedPlan};
+use crate::validate::Problem;
+use crate::{decimal, Reagent};
+
+// the product of rates, None when it overflows as hand-edited numbers can
+fn times(factors: &[Rational64]) -> Option<Rational64> {
+    factors.iter().try_fold(Rational64::from_integer(1), | acc, f | acc.checked_mul(f))
+}
+
+struct Checker<'p> {
+    plan: &'p SavedPlan,
+    problems: Vec<Problem>,
+    machines: BTreeMap<String, u64>,
+    // units/second of each widget across the plan, made by any stage and taken by them
+    made: BTreeMap<&'p String, Rational64>,
+    // what rounding to the nearest count may leave the stages making each widget short of
+    slack: BTreeMap<&'p String, Rational64>,
+    taken: BTreeMap<&'p String, Rational64>,
+    stages: usize
+}
+
+impl<'p> Checker<'p> {
+    fn problem(&mut self, path: &[&str], message: String) {
+        self.problems.push(Problem { location: path.join(" > "), message });
+    }
+
+    // inputs a stage may go without a child for, or short of, as the summary says they came from elsewhere
+    fn covered_elsewhere(&self, widget: &String) -> bool {
+        let summary = &self.plan.summary;
+        summary.purchases.contains_key(widget) || summary.supplied.contains_key(widget) || summary.shared.contains_key(widget)
+    }
+
+    fn node(&mut self, node: &'p SavedNode, widget: &'p String, path: &mut Vec<&'p str>) {
+        path.push(&node.recipe.name);
+        self.stages += 1;
+        *self.machines.entry(node.recipe.builder_name().to_owned()).or_insert(0) += node.count;
+        for product in node.recipe.products.iter() {
+            if let Some(made) = node.recipe.rate(&product.widget).ok().and_then(| rate | times(&[rate, node.machines, node.clock])) {
+                *self.made.entry(&product.widget).or_insert_with(|| Rational64::from_integer(0)) += made;
+            }
+        }
+        if Rational64::from_integer(node.count as i64) != node.machines.ceil() {
+            self.problem(path, format!("{count} machines don't round {machines} up", count=node.count, machines=decimal(&node.machines, 3)));
+        }
+        match node.recipe.rate(widget) {
+            Err(_) => self.problem(path, format!("{recipe} doesn't make {widget}", recipe=node.recipe.name, widget=widget)),
+            Ok(rate) => match times(&[rate, node.machines, node.clock]) {
+                None => self.problem(path, "its rates overflow 64-bit rationals".to_owned()),
+                Some(made) => {
+                    if made != node.actual_rate {
+                        self.problem(path, format!("{machines} machines at {clock}% make {made}/s of {widget}, not the {actual}/s stated", machines=decimal(&node.machines, 3),
+                            clock=decimal(&(node.clock * 100), 1), made=decimal(&made, 3), widget=widget, actual=decimal(&node.actual_rate, 3)));
+                    }
+                    // rounding to the nearest count may leave up to half a machine's output unmade
+                    let slack = times(&[rate, node.clock, Rational64::new(1, 2)]).unwrap_or_else(|| Rational64::from_integer(0));
+                    *self.slack.entry(widget).or_insert_with(|| Rational64::from_integer(0)) += slack;
+                    if made.checked_sub(&node.requested_rate).is_some_and(| over | over < -slack) {
+                        self.problem(path, format!("makes {made}/s of {widget}, short of the {requested}/s asked of it", made=decimal(&made, 3), widget=widget,
+                            requested=decimal(&node.requested_rate, 3)));
+                    }
+                }
+            }
+        }
+        if node.actual_rate.checked_sub(&node.requested_rate) != Some(node.surplus) {
+            self.problem(path, format!("a surplus of {surplus}/s doesn't match {actual}/s made for {requested}/s asked", surplus=decimal(&node.surplus, 3),
+                actual=decimal(&node.actual_rate, 3), requested=decimal(&node.requested_rate, 3)));
+        }
+        // children supply the reagents they make, in the order the recipe takes them
+        let mut supplied: BTreeMap<&String, Rational64> = BTreeMap::new();
+        let mut children = Vec::new();
+        for child in node.children.iter() {
+            match node.recipe.reagents.iter().find(| r | child.recipe.products.iter().any(| p | p.widget == r.widget)) {
+                Some(reagent) => {
+                    *supplied.entry(&reagent.widget).or_insert_with(|| Rational64::from_integer(0)) += child.requested_rate;
+                    children.push((child, reagent));
+                },
+                None => self.problem(path, format!("{child} feeds it nothing it takes", child=child.recipe.name))
+            }
+        }
+        for reagent in node.recipe.reagents.iter() {
+            self.reagent(node, reagent, supplied.get(&reagent.widget).copied(), path);
+        }
+        for (child, reagent) in children {
+            self.node(child, &reagent.widget, path);
+        }
+        path.pop();
+    }
+
+    // a stage takes only what the output asked of it needs, machines beyond that idling, and no more than its
+    // machines can; a reagent without stages of its own below may come from a stage elsewhere in the plan,
+    // as in network plans
+    fn reagent(&mut self, node: &SavedNode, reagent: &'p Reagent, supplied: Option<Rational64>, path: &[&str]) {
+        let one = Rational64::from_integer(1);
+        let utilization = if node.actual_rate > Rational64::from_integer(0) { (node.requested_rate / node.actual_rate).min(one) } else { Rational64::from_integer(0) };
+        let taken = match node.recipe.consumption(reagent).ok().and_then(| each | times(&[each, node.machines, node.clock, utilization])) {
+            Some(taken) => taken,
+            None => return self.problem(path, format!("what it takes of {widget} overflows 64-bit rationals", widget=reagent.widget))
+        };
+        *self.taken.entry(&reagent.widget).or_insert_with(|| Rational64::from_integer(0)) += taken;
+        if let Some(supplied) = supplied.filter(| s | *s < taken && !self.covered_elsewhere(&reagent.widget)) {
+            self.problem(path, format!("takes {taken}/s of {widget}, its stages supply {supplied}/s", taken=decimal(&taken, 3), widget=reagent.widget,
+                supplied=decimal(&supplied, 3)));
+        }
+    }
+}
+
+/// Every stage of a saved plan whose numbers don't add up: machine counts that don't cover the machines
+/// running, rates the recipe can't make at the stated machines and clock or short of what was asked,
+/// and inputs its stages don't supply; then widgets the plan as a whole takes more of than it makes,
+/// and machine totals not matching the stages. Inputs the summary says were bought, supplied from
+/// outside or shared from surplus aren't held against it. Returns the problems and how many stages
+/// were checked.
+pub fn check(plan: &SavedPlan) -> (Vec<Problem>, usize) {
+    let mut checker = Checker { plan, problems: Vec::new(), machines: BTreeMap::new(), made: BTreeMap::new(), slack: BTreeMap::new(), taken: BTreeMap::new(), stages: 0 };
+    if let Some(tree) = &plan.tree {
+        checker.node(tree, &plan.widget, &mut Vec::new());
+        // what the goal's stage was sized for, which builder limits may have cut below the rate asked; its own
+        // shortfall, if any, is already told
+        *checker.taken.entry(&plan.widget).or_insert_with(|| Rational64::from_integer(0)) += tree.requested_rate.min(tree.actual_rate);
+    }
+    let zero = Rational64::from_integer(0);
+    let short: Vec<(&String, Rational64, Rational64)> = checker.taken.iter()
+        .map(| (widget, taken) | (*widget, *taken, checker.made.get(widget).copied().unwrap_or(zero)))
+        .filter(| (widget, taken, made) | *made + checker.slack.get(widget).copied().unwrap_or(zero) < *taken && !checker.covered_elsewhere(widget)).collect();
+    for (widget, taken, made) in short {
+        checker.problems.push(Problem { location: "plan".to_owned(), message: format!("{taken}/s of {widget} is taken, its stages make {made}/s",
+            taken=decimal(&taken, 3), widget=widget, made=decimal(&made, 3)) });
+    }
+    let mut builders: Vec<&String> = checker.machines.keys().chain(plan.summary.machines.keys()).collect();
+    builders.sort();
+    builders.dedup();
+    for builder in builders {
+        let (counted, stated) = (checker.machines.get(builder).copied().unwrap_or(0), plan.summary.machines.get(builder).copied().unwrap_or(0));
+        if counted != stated {
+            checker.problems.push(Problem { location: "summary".to_owned(), message: format!("{stated} {builder} stated, the stages have {counted}",
+                stated=stated, builder=builder, counted=counted) });
+        }
+    }
+    (checker.problems, checker.stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_recipes, solve, Constraints};
+
+    #[test]
+    fn check_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let plan = solve(&graph, &extraction, &"modular-frame".to_owned(), Rational64::new(3, 2), &Constraints::default()).unwrap();
+        let mut saved = SavedPlan::new(&cookbook, &plan.widget, plan.rate, plan.tree.as_ref(), plan.summary.clone(), &BTreeMap::new());
+        let (problems, stages) = check(&saved);
+        assert!(problems.is_empty() && stages > 1);
+        let tree = saved.tree.as_mut().unwrap();
+        tree.count += 1;
+        tree.children[0].requested_rate /= 2;
+        let locations: Vec<String> = check(&saved).0.into_iter().map(| p | p.location).collect();
+        // the count no longer rounds the machines up, the halved input falls short and no longer matches its surplus
+        assert_eq!(locations, ["Modular Frame", "Modular Frame", "Modular Frame > Reinforced Iron Plate", "summary"]);
+    }
+}
@@ -0,0 +1,64 @@
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::{child_flows, Constraints, Cookbook, PlanTree, Precision};
+
+// a capacity the plan draws on, and how far the whole plan could scale before it runs out
+pub struct Chokepoint {
+    pub description: String,
+    pub scale: Rational64
+}
+
+fn links(tree: &PlanTree, cookbook: &Cookbook, chokepoints: &mut Vec<Chokepoint>, precision: Precision) {
+    for (child, (reagent, flow)) in tree.children().iter().zip(child_flows(tree)) {
+        // one line of the fastest tier per link, as the tree prints them
+        if let Some(fastest) = cookbook.transport(&reagent.widget).iter().max_by_key(| t | t.rate) {
+            if flow > Rational64::from_integer(0) {
+                chokepoints.push(Chokepoint {
+                    description: format!("{rate} of {widget} into {recipe} on a {tier}", rate=cookbook.format_rate(&reagent.widget, &flow, precision), widget=reagent.widget,
+                        recipe=tree.recipe.name, tier=fastest.name),
+                    scale: fastest.rate / flow
+                });
+            }
+        }
+        links(child, cookbook, chokepoints, precision);
+    }
+}
+
+// every builder limit and logistics link of the plan, tightest first; the first ones are the cut that
+// caps the plan's throughput when it is scaled up as a whole
+pub fn chokepoints(tree: &PlanTree, cookbook: &Cookbook, constraints: &Constraints, precision: Precision) -> Vec<Chokepoint> {
+    let mut chokepoints = Vec::new();
+    let mut machines = BTreeMap::new();
+    for node in tree.iter_dfs() {
+        *machines.entry(node.recipe.builder_name().to_owned()).or_insert(0) += node.machine_count;
+    }
+    for (builder, used) in machines.iter() {
+        if let Some(limit) = constraints.limits.get(builder) {
+            chokepoints.push(Chokepoint {
+                description: format!("{used} {builder} of the {limit} allowed", used=used, builder=builder, limit=limit),
+                scale: Rational64::new(*limit as i64, *used as i64)
+            });
+        }
+    }
+    links(tree, cookbook, &mut chokepoints, precision);
+    chokepoints.sort_by_key(| c | c.scale);
+    chokepoints
+}
+
+pub fn print_chokepoints(chokepoints: &[Chokepoint], widget: &String, rate: &Rational64, cookbook: &Cookbook, precision: Precision) {
+    println!();
+    println!("Chokepoints:");
+    let tightest = match chokepoints.first() {
+        Some(c) => c.scale,
+        None => {
+            println!("  no builder limits or logistics tiers bound the plan");
+            return;
+        }
+    };
+    println!("  throughput cap: {cap} of {widget} ({share}% of the target)", cap=cookbook.format_rate(widget, &(rate * tightest), precision), widget=widget, share=precision.percent(&tightest));
+    for chokepoint in chokepoints.iter().take(5) {
+        let mark = if chokepoint.scale == tightest { " [cut]" } else { "" };
+        println!("  {description}: caps {widget} at {cap}{mark}", description=chokepoint.description, widget=widget, cap=cookbook.format_rate(widget, &(rate * chokepoint.scale), precision), mark=mark);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+// subcommands planning a goal, which take every default
+const GOAL_COMMANDS: [&str; 5] = ["solve", "simulate", "analyze", "explore", "unlocks"];
+
+// defaults and the flags they stand for, with the subcommands besides the goal ones that take them
+const FLAGS: [(&str, &[&str]); 11] = [
+    ("purity", &["repl"]),
+    ("max_tier", &["repl"]),
+    ("unlock", &["repl"]),
+    ("exclude", &[]),
+    ("prefer_machines", &[]),
+    ("exact_clock", &[]),
+    ("share_surplus", &[]),
+    ("buy", &[]),
+    ("min_clock", &[]),
+    ("max_clock", &[]),
+    ("cost", &[])
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Text(String),
+    Number(String),
+    Flag(bool),
+    List(Vec<Value>)
+}
+
+/// Defaults read from `config.toml`: flags added to every invocation that doesn't give them,
+/// directories to look for game definitions in and names standing for game definition paths.
+///
+/// ```toml
+/// purity = "pure"
+/// unlock = ["Pure Iron Ingot", "Steel Rod"]
+/// buy = true
+/// data_path = ["~/games"]
+///
+/// [datasets]
+/// sf = "~/games/satisfactory.yaml"
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    defaults: BTreeMap<String, Value>,
+    data_path: Vec<PathBuf>,
+    datasets: BTreeMap<String, PathBuf>
+}
+
+// $SUPPLY_SOLVER_CONFIG, empty to use none, or supply-solver/config.toml under $XDG_CONFIG_HOME or ~/.config
+pub fn locate() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("SUPPLY_SOLVER_CONFIG") {
+        return if path.is_empty() { None } else { Some(PathBuf::from(path)) };
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME").filter(| d | !d.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(| home | Path::new(&home).join(".config")))?;
+    Some(base.join("supply-solver").join("config.toml"))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path)
+    }
+}
+
+// a quoted string, handling the common escapes, and what follows it
+fn text(line: &str) -> Option<(String, &str)> {
+    let mut chars = line.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &line[i + 2..])),
+            '\\' => value.push(match chars.next()?.1 {
+                'n' => '\n',
+                't' => '\t',
+                other => other
+            }),
+            c => value.push(c)
+        }
+    }
+    None
+}
+
+// one value and what follows it on the line
+fn value(line: &str) -> Option<(Value, &str)> {
+    let line = line.trim_start();
+    if line.starts_with('"') {
+        return text(line).map(| (t, rest) | (Value::Text(t), rest));
+    }
+    if let Some(mut rest) = line.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Some((Value::List(items), after));
+            }
+            let (item, after) = value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+    let end = line.find(| c: char | c == ',' || c == ']' || c == '#' || c.is_whitespace()).unwrap_or(line.len());
+    let (word, rest) = line.split_at(end);
+    match word {
+        "true" => Some((Value::Flag(true), rest)),
+        "false" => Some((Value::Flag(false), rest)),
+        _ if word.parse::<f64>().is_ok() => Some((Value::Number(word.to_owned()), rest)),
+        _ => None
+    }
+}
+
+impl Config {
+    // no file at `path` is no defaults
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents).map_err(| e | Error::Usage(format!("bad config file {}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(source) => Err(Error::Io { path: path.to_owned(), source })
+        }
+    }
+
+    // the flat subset of TOML the defaults need: top-level keys and a [datasets] table of strings
+    pub fn parse(contents: &str) -> std::result::Result<Self, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (n, line) in contents.lines().enumerate() {
+            let at = | message: String | format!("line {n}: {message}", n=n + 1, message=message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(| l | l.strip_suffix(']')) {
+                section = name.trim().to_owned();
+                if section != "datasets" {
+                    return Err(at(format!("unknown table [{}]", section)));
+                }
+                continue;
+            }
+            let (key, rest) = line.split_once('=').ok_or_else(|| at("expected key = value".to_owned()))?;
+            let key = key.trim().trim_matches('"').to_owned();
+            let (parsed, rest) = value(rest).ok_or_else(|| at(format!("bad value for {}", key)))?;
+            let rest = rest.trim();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(at(format!("unexpected `{}` after the value", rest)));
+            }
+            if section == "datasets" {
+                match parsed {
+                    Value::Text(path) => config.datasets.insert(key, expand_home(&path)),
+                    _ => return Err(at(format!("dataset {} should be a path in quotes", key)))
+                };
+                continue;
+            }
+            if key == "data_path" {
+                match parsed {
+                    Value::List(items) if items.iter().all(| i | matches!(i, Value::Text(_))) => config.data_path.extend(items.into_iter().filter_map(| i | match i {
+                        Value::Text(dir) => Some(expand_home(&dir)),
+                        _ => None
+                    })),
+                    _ => return Err(at("data_path should be a list of directories in quotes".to_owned()))
+                }
+                continue;
+            }
+            let well_typed = match (key.as_str(), &parsed) {
+                ("purity" | "cost", Value::Text(_)) => true,
+                ("max_tier" | "min_clock" | "max_clock", Value::Number(_)) => true,
+                ("unlock" | "exclude", Value::List(items)) => items.iter().all(| i | matches!(i, Value::Text(_))),
+                ("prefer_machines" | "exact_clock" | "share_surplus" | "buy", Value::Flag(_)) => true,
+                (known, _) if FLAGS.iter().any(| (k, _) | *k == known) => false,
+                _ => return Err(at(format!("unknown setting {}", key)))
+            };
+            if !well_typed {
+                return Err(at(format!("{} has the wrong type of value", key)));
+            }
+            config.defaults.insert(key, parsed);
+        }
+        Ok(config)
+    }
+
+    // directories to look for game definitions in
+    pub fn data_path(&self) -> &[PathBuf] {
+        &self.data_path
+    }
+
+    // game definition paths by the names standing for them
+    pub fn datasets(&self) -> &BTreeMap<String, PathBuf> {
+        &self.datasets
+    }
+
+    // `args` with the defaults the command line leaves out added at the end, where repeatable
+    // flags can't swallow positionals
+    pub fn apply(&self, mut args: Vec<OsString>) -> Vec<OsString> {
+        // the subcommand is the first word that isn't a flag or the directory of a --data-path before it
+        let mut words = args.iter().skip(1).filter_map(| a | a.to_str());
+        let mut command = None;
+        while let Some(word) = words.next() {
+            if word == "--data-path" {
+                words.next();
+            } else if !word.starts_with('-') {
+                command = Some(word.to_owned());
+                break;
+            }
+        }
+        let command = match command {
+            Some(command) => command,
+            None => return args
+        };
+        let given = | flag: &str | args.iter().filter_map(| a | a.to_str()).any(| a | a == flag || a.starts_with(&format!("{}=", flag)));
+        let mut added: Vec<OsString> = Vec::new();
+        for (key, others) in FLAGS.iter() {
+            let value = match self.defaults.get(*key) {
+                Some(value) if GOAL_COMMANDS.contains(&command.as_str()) || others.contains(&command.as_str()) => value,
+                _ => continue
+            };
+            let flag = format!("--{}", key.replace('_', "-"));
+            match value {
+                // lists add to whatever the command line gives
+                Value::List(items) => for item in items {
+                    if let Value::Text(item) = item {
+                        added.push(format!("{}={}", flag, item).into());
+                    }
+                },
+                _ if given(&flag) => {},
+                Value::Flag(true) => added.push(flag.into()),
+                Value::Flag(false) => {},
+                Value::Text(v) | Value::Number(v) => added.push(format!("{}={}", flag, v).into())
+            }
+        }
+        args.extend(added);
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter().map(| a | a.into_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn apply_test() {
+        let config = Config::parse("# defaults\npurity = \"pure\"\nunlock = [\"Pure Iron Ingot\", \"Steel Rod\"]\nbuy = true\nexact_clock = false\n\n[datasets]\nsf = \"/games/sf.yaml\"\n").unwrap();
+        let args = | line: &str | line.split(' ').map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(strings(config.apply(args("supply-solver solve sf stator 2 --purity=impure"))),
+            vec!["supply-solver", "solve", "sf", "stator", "2", "--purity=impure", "--unlock=Pure Iron Ingot", "--unlock=Steel Rod", "--buy"]);
+        assert_eq!(strings(config.apply(args("supply-solver --data-path repl dot sf"))), vec!["supply-solver", "--data-path", "repl", "dot", "sf"]);
+        assert_eq!(config.datasets().get("sf"), Some(&PathBuf::from("/games/sf.yaml")));
+        assert!(Config::parse("colour = \"red\"").unwrap_err().contains("line 1"));
+        assert!(Config::parse("buy = \"yes\"").is_err());
+    }
+}
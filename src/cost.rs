@@ -0,0 +1,262 @@
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub};
+use num::Rational64;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+// what a stage would be like with a candidate recipe, for a cost expression to weigh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    // machines placed, rounded up
+    Machines,
+    // MW drawn by them
+    Power,
+    // idle machine-equivalents left by rounding up
+    Waste,
+    // units/second of reagents drawn
+    Inputs,
+    // units/second of reagents nothing crafts
+    Raw,
+    Tier,
+    // 1 for alternate recipes, 0 otherwise
+    Alternate,
+    // 1 for hand-crafted recipes, 0 otherwise
+    Manual,
+    // points/second a sink pays for the byproducts and the machines' output beyond the demand, with --sink
+    Sink,
+    // m² the machines cover, nothing for builders without dimensions
+    Footprint
+}
+
+const METRICS: [(&str, Metric); 10] = [
+    ("machines", Metric::Machines),
+    ("power", Metric::Power),
+    ("waste", Metric::Waste),
+    ("inputs", Metric::Inputs),
+    ("raw", Metric::Raw),
+    ("tier", Metric::Tier),
+    ("alternate", Metric::Alternate),
+    ("manual", Metric::Manual),
+    ("sink", Metric::Sink),
+    ("footprint", Metric::Footprint)
+];
+
+/// An arithmetic expression over [`Metric`]s such as `machines*2 + power*0.1 + raw`, scoring the
+/// recipes a stage could use so the lowest scoring one is picked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CostExpr {
+    Number(Rational64),
+    Metric(Metric),
+    Negate(Box<CostExpr>),
+    Add(Box<CostExpr>, Box<CostExpr>),
+    Subtract(Box<CostExpr>, Box<CostExpr>),
+    Multiply(Box<CostExpr>, Box<CostExpr>),
+    Divide(Box<CostExpr>, Box<CostExpr>)
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    at: usize,
+    // sub-costs a score file defined on earlier lines
+    defined: &'t [(String, CostExpr)]
+}
+
+impl<'t> Parser<'t> {
+    fn fail(&self, expected: &str) -> Error {
+        Error::Usage(format!("bad cost expression `{text}`: expected {expected} at column {column}", text=self.text, expected=expected, column=self.at + 1))
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.at..];
+        self.at += rest.len() - rest.trim_start().len();
+        self.text[self.at..].chars().next()
+    }
+
+    fn take(&mut self, pattern: impl Fn(char) -> bool) -> &'t str {
+        let rest = &self.text[self.at..];
+        let length = rest.find(| c | !pattern(c)).unwrap_or(rest.len());
+        self.at += length;
+        &rest[..length]
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<CostExpr, Error> {
+        let mut expr = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.at += 1;
+            let right = Box::new(self.product()?);
+            expr = if op == '+' { CostExpr::Add(Box::new(expr), right) } else { CostExpr::Subtract(Box::new(expr), right) };
+        }
+        Ok(expr)
+    }
+
+    // product := factor (('*' | '/') factor)*
+    fn product(&mut self) -> Result<CostExpr, Error> {
+        let mut expr = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.at += 1;
+            let right = Box::new(self.factor()?);
+            expr = if op == '*' { CostExpr::Multiply(Box::new(expr), right) } else { CostExpr::Divide(Box::new(expr), right) };
+        }
+        Ok(expr)
+    }
+
+    // factor := number | metric | '-' factor | '(' sum ')'
+    fn factor(&mut self) -> Result<CostExpr, Error> {
+        match self.peek() {
+            Some('-') => {
+                self.at += 1;
+                Ok(CostExpr::Negate(Box::new(self.factor()?)))
+            },
+            Some('(') => {
+                self.at += 1;
+                let expr = self.sum()?;
+                if self.peek() != Some(')') {
+                    return Err(self.fail("`)`"));
+                }
+                self.at += 1;
+                Ok(expr)
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.at;
+                let number = self.take(| c | c.is_ascii_digit() || c == '.');
+                crate::parse_decimal(number).map(CostExpr::Number).ok_or_else(|| {
+                    self.at = start;
+                    self.fail("a number")
+                })
+            },
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.at;
+                let name = self.take(| c | c.is_ascii_alphanumeric() || c == '_');
+                if let Some((_, expr)) = self.defined.iter().rev().find(| (n, _) | n == name) {
+                    return Ok(expr.clone());
+                }
+                match METRICS.iter().find(| (n, _) | *n == name) {
+                    Some((_, metric)) => Ok(CostExpr::Metric(*metric)),
+                    None => {
+                        self.at = start;
+                        let names: Vec<&str> = METRICS.iter().map(| (n, _) | *n).chain(self.defined.iter().map(| (n, _) | n.as_str())).collect();
+                        Err(self.fail(&format!("one of {}", names.join(", "))))
+                    }
+                }
+            },
+            _ => Err(self.fail("a number, a metric or `(`"))
+        }
+    }
+}
+
+impl FromStr for CostExpr {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        parse(text, &[])
+    }
+}
+
+fn parse(text: &str, defined: &[(String, CostExpr)]) -> Result<CostExpr, Error> {
+    let mut parser = Parser { text, at: 0, defined };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(parser.fail("an operator"))
+    }
+}
+
+impl CostExpr {
+    /// A score file: `name = expression` lines defining sub-costs that later lines can use, then the
+    /// expression scoring each recipe on the last line; `#` starts a comment, e.g.
+    ///
+    /// ```text
+    /// # machines weigh more when they slow the build down
+    /// build = machines * (1 + alternate)
+    /// build * 2 + power / 10 + raw
+    /// ```
+    pub fn from_script(text: &str) -> Result<Self, Error> {
+        let mut defined: Vec<(String, CostExpr)> = Vec::new();
+        let mut score = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at_line = | e: Error | Error::Usage(format!("line {number}: {e}", number=number + 1, e=e));
+            if score.is_some() {
+                return Err(at_line(Error::Usage("nothing may follow the score expression".to_owned())));
+            }
+            match line.split_once('=') {
+                Some((name, expr)) => {
+                    let name = name.trim();
+                    if name.is_empty() || !name.chars().all(| c | c.is_ascii_alphanumeric() || c == '_') || name.starts_with(| c: char | c.is_ascii_digit()) {
+                        return Err(at_line(Error::Usage(format!("`{name}` is not a name for a sub-cost", name=name))));
+                    }
+                    if METRICS.iter().any(| (n, _) | *n == name) {
+                        return Err(at_line(Error::Usage(format!("`{name}` is a metric already", name=name))));
+                    }
+                    let expr = parse(expr.trim(), &defined).map_err(at_line)?;
+                    defined.push((name.to_owned(), expr));
+                },
+                None => score = Some(parse(line, &defined).map_err(at_line)?)
+            }
+        }
+        score.ok_or_else(|| Error::Usage("the score file has no expression to score recipes by".to_owned()))
+    }
+
+    pub fn uses(&self, metric: Metric) -> bool {
+        match self {
+            CostExpr::Number(_) => false,
+            CostExpr::Metric(m) => *m == metric,
+            CostExpr::Negate(e) => e.uses(metric),
+            CostExpr::Add(a, b) | CostExpr::Subtract(a, b) | CostExpr::Multiply(a, b) | CostExpr::Divide(a, b) => a.uses(metric) || b.uses(metric)
+        }
+    }
+
+    // None when it divides by zero, overflows 64-bit rationals or a metric can't be worked out
+    pub fn eval(&self, metric: &dyn Fn(Metric) -> Option<Rational64>) -> Option<Rational64> {
+        match self {
+            CostExpr::Number(n) => Some(*n),
+            CostExpr::Metric(m) => metric(*m),
+            CostExpr::Negate(e) => Rational64::from_integer(0).checked_sub(&e.eval(metric)?),
+            CostExpr::Add(a, b) => a.eval(metric)?.checked_add(&b.eval(metric)?),
+            CostExpr::Subtract(a, b) => a.eval(metric)?.checked_sub(&b.eval(metric)?),
+            CostExpr::Multiply(a, b) => a.eval(metric)?.checked_mul(&b.eval(metric)?),
+            CostExpr::Divide(a, b) => a.eval(metric)?.checked_div(&b.eval(metric)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_test() {
+        let expr: CostExpr = "machines*2 + power*0.5 - (raw - 1) / 4".parse().unwrap();
+        let metric = | m: Metric | Some(Rational64::from_integer(match m {
+            Metric::Machines => 3,
+            Metric::Power => 10,
+            Metric::Raw => 9,
+            _ => 0
+        }));
+        assert_eq!(expr.eval(&metric), Some(Rational64::from_integer(9)));
+        assert_eq!("1 / waste".parse::<CostExpr>().unwrap().eval(&metric), None);
+        assert_eq!("machines * 4611686018427387904".parse::<CostExpr>().unwrap().eval(&metric), None);
+        assert!("machines * ore".parse::<CostExpr>().unwrap_err().to_string().contains("column 12"));
+        assert!("machines power".parse::<CostExpr>().is_err());
+    }
+
+    #[test]
+    fn script_test() {
+        let metric = | m: Metric | Some(Rational64::from_integer(match m {
+            Metric::Machines => 3,
+            Metric::Alternate => 1,
+            Metric::Power => 20,
+            _ => 0
+        }));
+        let script = "# alternates cost double the machines\nbuild = machines * (1 + alternate)\n\nbuild * 2 + power / 10  # and a little power\n";
+        assert_eq!(CostExpr::from_script(script).unwrap().eval(&metric), Some(Rational64::from_integer(14)));
+        assert!(CostExpr::from_script("power = 1\npower").unwrap_err().to_string().starts_with("line 1:"));
+        assert!(CostExpr::from_script("machines\nraw").unwrap_err().to_string().starts_with("line 2:"));
+        assert!(CostExpr::from_script("a = build\na").unwrap_err().to_string().contains("column 1"));
+        assert!(CostExpr::from_script("# nothing\n").is_err());
+    }
+}
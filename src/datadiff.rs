@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use crate::saved::{SavedNode, SavedPlan};
+use crate::{decimal, Cookbook, Recipe, Widget};
+
+// how one widget, recipe or builder differs between two versions of a game definition
+pub enum Change {
+    Added(String),
+    Removed(String),
+    // what changed, as `field: before -> after`
+    Changed(String, Vec<String>)
+}
+
+impl Change {
+    pub fn name(&self) -> &String {
+        match self {
+            Change::Added(name) | Change::Removed(name) | Change::Changed(name, _) => name
+        }
+    }
+}
+
+pub struct DataDiff {
+    pub widgets: Vec<Change>,
+    pub recipes: Vec<Change>,
+    pub builders: Vec<Change>
+}
+
+// `field: before -> after` when the two differ
+fn field(changes: &mut Vec<String>, name: &str, before: String, after: String) {
+    if before != after {
+        changes.push(format!("{name}: {before} -> {after}", name=name, before=before, after=after));
+    }
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map_or_else(|| "none".to_owned(), T::to_string)
+}
+
+fn widget_changes(old: &Widget, new: &Widget) -> Vec<String> {
+    let details = | w: &Widget | match w {
+        Widget::Item(_) => (false, None, None),
+        Widget::Detailed { fluid, price, sink, .. } => (*fluid, *price, *sink)
+    };
+    let ((old_fluid, old_price, old_sink), (new_fluid, new_price, new_sink)) = (details(old), details(new));
+    let mut changes = Vec::new();
+    field(&mut changes, "fluid", old_fluid.to_string(), new_fluid.to_string());
+    field(&mut changes, "price", optional(&old_price.map(| p | decimal(&p, 3))), optional(&new_price.map(| p | decimal(&p, 3))));
+    field(&mut changes, "sink", optional(&old_sink.map(| s | decimal(&s, 3))), optional(&new_sink.map(| s | decimal(&s, 3))));
+    changes
+}
+
+fn recipe_changes(old: &Recipe, new: &Recipe) -> Vec<String> {
+    let mut changes = Vec::new();
+    field(&mut changes, "builder", old.builder_name().to_owned(), new.builder_name().to_owned());
+    field(&mut changes, "manual", old.manual.to_string(), new.manual.to_string());
+    field(&mut changes, "duration", format!("{}s", decimal(&old.duration, 3)), format!("{}s", decimal(&new.duration, 3)));
+    field(&mut changes, "formula", old.formula(), new.formula());
+    field(&mut changes, "tier", old.tier.to_string(), new.tier.to_string());
+    field(&mut changes, "alternate", old.alternate.to_string(), new.alternate.to_string());
+    field(&mut changes, "category", optional(&old.category), optional(&new.category));
+    field(&mut changes, "power", optional(&old.power.map(| v | decimal(&v, 3))), optional(&new.power.map(| v | decimal(&v, 3))));
+    field(&mut changes, "speed", optional(&old.speed.map(| v | decimal(&v, 3))), optional(&new.speed.map(| v | decimal(&v, 3))));
+    field(&mut changes, "productivity", optional(&old.productivity.map(| v | decimal(&v, 3))), optional(&new.productivity.map(| v | decimal(&v, 3))));
+    field(&mut changes, "duration spread", optional(&old.duration_spread), optional(&new.duration_spread));
+    field(&mut changes, "yield spread", optional(&old.yield_spread), optional(&new.yield_spread));
+    field(&mut changes, "since", optional(&old.since), optional(&new.since));
+    field(&mut changes, "deprecated", optional(&old.deprecated), optional(&new.deprecated));
+    changes
+}
+
+// items of both versions paired up by name, in the new one's order with the removed after
+fn compare<'c, T>(old: &'c [T], new: &'c [T], name: impl Fn(&T) -> &String, changes: impl Fn(&T, &T) -> Vec<String>) -> Vec<Change> {
+    let before: BTreeMap<&String, &T> = old.iter().map(| t | (name(t), t)).collect();
+    let after: BTreeSet<&String> = new.iter().map(&name).collect();
+    let mut diff = Vec::new();
+    for item in new {
+        match before.get(name(item)) {
+            None => diff.push(Change::Added(name(item).clone())),
+            Some(previous) => {
+                let changed = changes(previous, item);
+                if !changed.is_empty() {
+                    diff.push(Change::Changed(name(item).clone(), changed));
+                }
+            }
+        }
+    }
+    diff.extend(old.iter().map(&name).filter(| n | !after.contains(n)).map(| n | Change::Removed(n.clone())));
+    diff
+}
+
+// builders differ in anything at all, their fields being many and all bearing on plans
+fn debug_changes<T: Debug>(old: &T, new: &T) -> Vec<String> {
+    let (before, after) = (format!("{:?}", old), format!("{:?}", new));
+    if before == after { Vec::new() } else { vec!["definition changed".to_owned()] }
+}
+
+/// The widgets, recipes and builders added to, removed from or changed in `new` since `old`, each
+/// change to a recipe told field by field: builder, duration, reagents and products, tier and the rest.
+pub fn diff(old: &Cookbook, new: &Cookbook) -> DataDiff {
+    DataDiff {
+        widgets: compare(&old.widgets, &new.widgets, Widget::name, widget_changes),
+        recipes: compare(&old.recipes, &new.recipes, | r | &r.name, recipe_changes),
+        builders: compare(&old.builders, &new.builders, | b | &b.name, debug_changes)
+    }
+}
+
+impl DataDiff {
+    pub fn is_empty(&self) -> bool {
+        self.widgets.is_empty() && self.recipes.is_empty() && self.builders.is_empty()
+    }
+
+    /// Why a saved plan made with the old definition needs solving again, nothing when it doesn't: its
+    /// stages' recipes or their builders changed or went away, or a recipe was added for a widget the plan
+    /// makes, which the solver might pick now.
+    pub fn affects(&self, plan: &SavedPlan, new: &Cookbook) -> Vec<String> {
+        fn stages<'p>(node: &'p SavedNode, out: &mut Vec<&'p Recipe>) {
+            out.push(&node.recipe);
+            for child in node.children.iter() {
+                stages(child, out);
+            }
+        }
+        let mut recipes = Vec::new();
+        if let Some(tree) = &plan.tree {
+            stages(tree, &mut recipes);
+        }
+        let made: BTreeSet<&String> = recipes.iter().flat_map(| r | r.products.iter().map(| p | &p.widget)).collect();
+        let mut reasons = Vec::new();
+        for change in self.recipes.iter() {
+            let used = recipes.iter().any(| r | &r.name == change.name());
+            match change {
+                Change::Removed(name) if used => reasons.push(format!("recipe {} was removed", name)),
+                Change::Changed(name, _) if used => reasons.push(format!("recipe {} changed", name)),
+                Change::Added(name) => {
+                    let makes = new.recipes.iter().find(| r | &r.name == name).into_iter().flat_map(| r | r.products.iter()).find(| p | made.contains(&p.widget));
+                    if let Some(product) = makes {
+                        reasons.push(format!("recipe {} now also makes {}", name, product.widget));
+                    }
+                },
+                _ => {}
+            }
+        }
+        for change in self.builders.iter() {
+            if !matches!(change, Change::Added(_)) && recipes.iter().any(| r | r.builder_name() == change.name()) {
+                reasons.push(format!("builder {} changed", change.name()));
+            }
+        }
+        reasons.dedup();
+        reasons
+    }
+}
+
+fn print_changes(kind: &str, changes: &[Change]) {
+    if changes.is_empty() {
+        return;
+    }
+    println!("{kind}:", kind=kind);
+    for change in changes {
+        match change {
+            Change::Added(name) => println!("  + {}", name),
+            Change::Removed(name) => println!("  - {}", name),
+            Change::Changed(name, fields) => {
+                println!("  ~ {}", name);
+                for field in fields {
+                    println!("      {}", field);
+                }
+            }
+        }
+    }
+}
+
+pub fn print_diff(diff: &DataDiff, plans: &[(PathBuf, Vec<String>)]) {
+    if diff.is_empty() {
+        println!("No changes");
+    }
+    print_changes("Widgets", &diff.widgets);
+    print_changes("Recipes", &diff.recipes);
+    print_changes("Builders", &diff.builders);
+    if plans.is_empty() {
+        return;
+    }
+    println!();
+    println!("Saved plans:");
+    for (path, reasons) in plans {
+        match reasons.is_empty() {
+            true => println!("  {}: up to date", path.display()),
+            false => println!("  {}: re-solve, {}", path.display(), reasons.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Rational64;
+
+    #[test]
+    fn diff_test() {
+        let one = Rational64::from_integer(1);
+        let old = Cookbook::builder().machine("Assembler", None).item("ore").item("plate").item("rod")
+            .recipe(Recipe::new("Plate", one).made_by("Assembler").reagent("ore", one).product("plate", one))
+            .recipe(Recipe::new("Rod", one).made_by("Assembler").reagent("ore", one).product("rod", one))
+            .build();
+        let new = Cookbook::builder().machine("Assembler", None).item("ore").item("plate").item("beam")
+            .recipe(Recipe::new("Plate", Rational64::from_integer(2)).made_by("Assembler").reagent("ore", Rational64::from_integer(2)).product("plate", one))
+            .recipe(Recipe::new("Beam", one).made_by("Assembler").reagent("plate", one).product("beam", one))
+            .build();
+        let diff = diff(&old, &new);
+        let names = | changes: &[Change] | changes.iter().map(| c | match c {
+            Change::Added(n) => format!("+{}", n),
+            Change::Removed(n) => format!("-{}", n),
+            Change::Changed(n, fields) => format!("~{} {}", n, fields.join("; "))
+        }).collect::<Vec<_>>();
+        assert_eq!(names(&diff.widgets), ["+beam", "-rod"]);
+        assert_eq!(names(&diff.recipes), ["~Plate duration: 1s -> 2s; formula: 1 ore -> 1 plate -> 2 ore -> 1 plate", "+Beam", "-Rod"]);
+        assert!(diff.builders.is_empty());
+    }
+}
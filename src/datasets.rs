@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+// one place game definitions are looked for, with what put it on the search path
+pub struct Layer {
+    pub source: String,
+    pub dirs: Vec<PathBuf>,
+    // names standing for game definition paths
+    pub names: BTreeMap<String, PathBuf>
+}
+
+/// Where a game definition given by name is looked for, in order: the working directory, `--data-path`
+/// directories, `$SUPPLY_SOLVER_DATA_PATH`, the config file's `data_path` and `[datasets]`, then the
+/// `supply-solver` directory under each XDG data directory.
+pub struct SearchPath {
+    pub layers: Vec<Layer>
+}
+
+fn layer(source: &str, dirs: Vec<PathBuf>) -> Layer {
+    Layer { source: source.to_owned(), dirs, names: BTreeMap::new() }
+}
+
+// $XDG_DATA_HOME or ~/.local/share, then $XDG_DATA_DIRS or /usr/local/share and /usr/share
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("XDG_DATA_HOME").filter(| d | !d.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(| home | Path::new(&home).join(".local").join("share")));
+    let shared = std::env::var_os("XDG_DATA_DIRS").filter(| d | !d.is_empty()).unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    home.into_iter().chain(std::env::split_paths(&shared)).map(| dir | dir.join("supply-solver")).collect()
+}
+
+pub fn search_path(flags: &[PathBuf], config: &Config, config_path: Option<&Path>) -> SearchPath {
+    let env = std::env::var_os("SUPPLY_SOLVER_DATA_PATH").map(| dirs | std::env::split_paths(&dirs).collect()).unwrap_or_default();
+    let mut configured = layer(&match config_path {
+        Some(path) => format!("config {}", path.display()),
+        None => "config".to_owned()
+    }, config.data_path().to_vec());
+    configured.names = config.datasets().clone();
+    SearchPath {
+        layers: vec![
+            layer("working directory", vec![PathBuf::from(".")]),
+            layer("--data-path", flags.to_vec()),
+            layer("SUPPLY_SOLVER_DATA_PATH", env),
+            configured,
+            layer("XDG data directories", xdg_data_dirs())
+        ]
+    }
+}
+
+// the game definitions in `dir` by name, the file name without its .yaml or .yml
+fn definitions(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = std::fs::read_dir(dir).into_iter().flatten().flatten().map(| entry | entry.path())
+        .filter(| path | path.is_file() && matches!(path.extension().and_then(| e | e.to_str()), Some("yaml" | "yml")))
+        .filter_map(| path | Some((path.file_stem()?.to_str()?.to_owned(), path.clone())))
+        .collect();
+    found.sort();
+    found
+}
+
+impl SearchPath {
+    // a path that exists is used as given, anything else is looked up as a dataset name, a file
+    // or a file without its .yaml or .yml along the search path; unknown names are left for loading to report
+    pub fn resolve(&self, game_def: &Path) -> PathBuf {
+        if game_def.exists() {
+            return game_def.to_owned();
+        }
+        for layer in self.layers.iter() {
+            if let Some(path) = game_def.to_str().and_then(| name | layer.names.get(name)) {
+                return path.clone();
+            }
+            for dir in layer.dirs.iter() {
+                let candidates = [game_def.to_owned(), game_def.with_extension("yaml"), game_def.with_extension("yml")];
+                if let Some(found) = candidates.iter().map(| c | dir.join(c)).find(| c | c.is_file()) {
+                    return found;
+                }
+            }
+        }
+        game_def.to_owned()
+    }
+}
+
+pub fn print_search_path(search_path: &SearchPath) {
+    for layer in search_path.layers.iter() {
+        println!("{source}:", source=layer.source);
+        if layer.dirs.is_empty() && layer.names.is_empty() {
+            println!("  nothing");
+        }
+        for (name, path) in layer.names.iter() {
+            println!("  {name} = {path}", name=name, path=path.display());
+        }
+        for dir in layer.dirs.iter() {
+            if !dir.is_dir() {
+                println!("  {dir} (missing)", dir=dir.display());
+                continue;
+            }
+            println!("  {dir}", dir=dir.display());
+            let found = definitions(dir);
+            if found.is_empty() {
+                println!("    no game definitions");
+            }
+            for (name, path) in found {
+                println!("    {name} ({file})", name=name, file=path.file_name().and_then(| f | f.to_str()).unwrap_or_default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_test() {
+        let dir = std::env::temp_dir().join(format!("supply-solver-datasets-{}", std::process::id()));
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        for (at, file) in [(&first, "sf.yml"), (&second, "sf.yaml"), (&second, "other.yaml"), (&second, "notes.txt")] {
+            std::fs::create_dir_all(at).unwrap();
+            std::fs::write(at.join(file), "").unwrap();
+        }
+        let mut named = layer("config", Vec::new());
+        named.names.insert("mods".to_owned(), PathBuf::from("/games/mods.yaml"));
+        let search_path = SearchPath { layers: vec![layer("--data-path", vec![dir.join("missing"), first.clone()]), named, layer("XDG data directories", vec![second.clone()])] };
+        // the first layer with a match wins, with or without the extension
+        assert_eq!(search_path.resolve(Path::new("sf")), first.join("sf.yml"));
+        assert_eq!(search_path.resolve(Path::new("other")), second.join("other.yaml"));
+        assert_eq!(search_path.resolve(Path::new("mods")), PathBuf::from("/games/mods.yaml"));
+        // an existing path is used as given, an unknown name left for loading to report
+        assert_eq!(search_path.resolve(&second.join("notes.txt")), second.join("notes.txt"));
+        assert_eq!(search_path.resolve(Path::new("nothing")), PathBuf::from("nothing"));
+        assert_eq!(definitions(&second), [("other".to_owned(), second.join("other.yaml")), ("sf".to_owned(), second.join("sf.yaml"))]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not read {path}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("could not parse {path}: {source}")]
+    Parse { path: PathBuf, source: serde_yaml::Error },
+    #[error("could not parse the game definition: {0}")]
+    Definition(serde_yaml::Error),
+    #[error("could not listen on {address}: {source}")]
+    Listen { address: String, source: std::io::Error },
+    #[error("bad compiled game definition {path}: {message}")]
+    IndexFormat { path: PathBuf, message: String },
+    #[error("bad template {path}: {message}")]
+    Template { path: PathBuf, message: String },
+    #[error("bad plan file {path}: {source}")]
+    PlanFormat { path: PathBuf, source: serde_json::Error },
+    #[error("unknown widget `{0}`")]
+    UnknownWidget(String),
+    #[error("no available recipe produces `{0}`")]
+    NoRecipes(String),
+    #[error("unknown resource node purity `{0}`")]
+    UnknownPurity(String),
+    #[error("unknown generator `{0}`")]
+    UnknownGenerator(String),
+    #[error("`{0}` generators cannot cover the power drawn by their own fuel chain")]
+    PowerDiverges(String),
+    #[error("the machines making what builders use up need ever more of it")]
+    UpkeepDiverges,
+    #[error("builder limits leave no capacity to produce `{0}`")]
+    LimitsExceeded(String),
+    #[error("could not copy to the clipboard: {0}")]
+    Clipboard(String),
+    #[error("{0}")]
+    Usage(String),
+    #[error("arithmetic failure: {0}")]
+    Arithmetic(String)
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,322 @@
+use num::Rational64;
+use std::collections::{BTreeMap, HashSet};
+use std::io::BufRead;
+
+use crate::error::{Error, Result};
+use crate::{as_f64, child_flows, describe, summary_text, Constraints, Cookbook, Plan, PlanTree, Precision};
+
+const HELP: &str = "  N              expand or collapse line N
+  open           expand every stage
+  close          collapse everything below the goal
+  /TEXT          expand to and mark the stages whose line contains TEXT
+  recipes N      list the recipes that could make line N's widget
+  alt RECIPE     unlock or lock an alternate recipe
+  exclude N|RECIPE  forbid or allow line N's recipe or a recipe by name
+  pin N|RECIPE   make line N's recipe, or a recipe, the only one used for its widget, or unpin it
+  rate R         re-solve for R, e.g. 45/min, 2.5/s or 1/3
+  + / -          nudge the rate up or down by a tenth
+  summary        print the plan's full summary
+  quit           leave";
+
+// which stages are expanded and which matched the last search, by path
+struct View {
+    expanded: HashSet<Vec<usize>>,
+    matches: HashSet<Vec<usize>>
+}
+
+// the visible stages in display order, each with its path
+fn visible(tree: &PlanTree, view: &View, path: &mut Vec<usize>, lines: &mut Vec<Vec<usize>>) {
+    lines.push(path.clone());
+    if view.expanded.contains(path) {
+        for (i, child) in tree.children().iter().enumerate() {
+            path.push(i);
+            visible(child, view, path, lines);
+            path.pop();
+        }
+    }
+}
+
+fn show(plan: &Plan, view: &View, edits: &Edits, cookbook: &Cookbook, precision: Precision) -> Vec<Vec<usize>> {
+    let tree = plan.tree.as_ref().expect("the goal is crafted");
+    let mut lines = Vec::new();
+    visible(tree, view, &mut Vec::new(), &mut lines);
+    println!();
+    for (n, path) in lines.iter().enumerate() {
+        let node = tree.at(path).expect("visible paths are in the tree");
+        let marker = match (node.children().is_empty(), view.expanded.contains(path)) {
+            (true, _) => ' ',
+            (false, true) => '▾',
+            (false, false) => '▸'
+        };
+        let found = if view.matches.contains(path) { "  <" } else { "" };
+        println!("{n:>4} {indent}{marker} {stage}{found}", n=n + 1, indent="  ".repeat(path.len()), marker=marker, stage=describe(node, precision), found=found);
+    }
+    let power = plan.summary.total_power().map_or_else(| _ | "?".to_owned(), | power | precision.fixed(as_f64(&power), 1));
+    let raw: Vec<String> = plan.raw_inputs().iter().map(| (widget, rate) | format!("{widget} {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision))).collect();
+    println!("---- {machines} machines, {power} MW{raw}", machines=plan.total_machines(), power=power,
+        raw=if raw.is_empty() { String::new() } else { format!(", raw: {}", raw.join(", ")) });
+    let mut changes: Vec<String> = Vec::new();
+    changes.extend(edits.constraints.unlocked.iter().map(| r | format!("+{}", r)));
+    changes.extend(edits.constraints.excluded.iter().map(| r | format!("-{}", r)));
+    changes.extend(edits.pins.iter().map(| (widget, r) | format!("{} only by {}", widget, r)));
+    if !changes.is_empty() {
+        changes.sort();
+        println!("---- {}", changes.join(", "));
+    }
+    lines
+}
+
+// the recipe choices and rate the plan is currently solved with
+#[derive(Clone)]
+struct Edits {
+    rate: Rational64,
+    constraints: Constraints,
+    // widget to the one recipe allowed to make it
+    pins: BTreeMap<String, String>
+}
+
+impl Edits {
+    // the constraints with every recipe competing with a pin excluded
+    fn applied(&self, cookbook: &Cookbook) -> Constraints {
+        let mut constraints = self.constraints.clone();
+        for (widget, pinned) in self.pins.iter() {
+            constraints.unlocked.insert(pinned.clone());
+            constraints.excluded.extend(cookbook.recipes.iter()
+                .filter(| r | &r.name != pinned && r.products.iter().any(| p | &p.widget == widget))
+                .map(| r | r.name.clone()));
+        }
+        constraints
+    }
+}
+
+// the widget the stage at `path` makes for its parent
+fn stage_widget(plan: &Plan, path: &[usize]) -> String {
+    let mut widget = plan.widget.clone();
+    let mut node = plan.tree.as_ref().expect("the goal is crafted");
+    for &i in path {
+        widget = child_flows(node)[i].0.widget.clone();
+        node = &node.children()[i];
+    }
+    widget
+}
+
+// line N's widget and recipe, or a recipe by name with its first product
+fn target(argument: &str, plan: &Plan, lines: &[Vec<usize>], cookbook: &Cookbook) -> Result<(String, String)> {
+    if let Ok(n) = argument.parse::<usize>() {
+        let path = lines.get(n.wrapping_sub(1)).ok_or_else(|| Error::Usage(format!("no line {n}, the view shows {count}", n=n, count=lines.len())))?;
+        let node = plan.tree.as_ref().and_then(| t | t.at(path)).expect("visible paths are in the tree");
+        return Ok((stage_widget(plan, path), node.recipe.name.clone()));
+    }
+    match cookbook.recipes.iter().find(| r | r.name == argument) {
+        Some(recipe) => Ok((recipe.products.first().map(| p | p.widget.clone()).unwrap_or_default(), recipe.name.clone())),
+        None => Err(Error::Usage(format!("no line or recipe `{}`", argument)))
+    }
+}
+
+fn toggle(set: &mut HashSet<String>, name: String) {
+    if !set.remove(&name) {
+        set.insert(name);
+    }
+}
+
+// applies a what-if command to `edits`, Ok(false) when it was not one
+fn edit(command: &str, edits: &mut Edits, plan: &Plan, lines: &[Vec<usize>], cookbook: &Cookbook) -> Result<bool> {
+    let (word, argument) = command.split_once(' ').map_or((command, ""), | (w, a) | (w, a.trim()));
+    match word {
+        "+" => edits.rate *= Rational64::new(11, 10),
+        "-" => edits.rate *= Rational64::new(9, 10),
+        "rate" => {
+            edits.rate = crate::parse_rate(argument)?;
+        },
+        "alt" => match cookbook.recipes.iter().find(| r | r.name == argument) {
+            Some(recipe) if recipe.alternate => toggle(&mut edits.constraints.unlocked, recipe.name.clone()),
+            Some(_) => return Err(Error::Usage(format!("{} is not an alternate recipe", argument))),
+            None => return Err(Error::Usage(format!("no recipe `{}`", argument)))
+        },
+        "exclude" => {
+            let (_, recipe) = target(argument, plan, lines, cookbook)?;
+            toggle(&mut edits.constraints.excluded, recipe);
+        },
+        "pin" => {
+            let (widget, recipe) = target(argument, plan, lines, cookbook)?;
+            if edits.pins.get(&widget) == Some(&recipe) {
+                edits.pins.remove(&widget);
+            } else {
+                edits.pins.insert(widget, recipe);
+            }
+        },
+        _ => return Ok(false)
+    }
+    Ok(true)
+}
+
+fn print_recipes(argument: &str, plan: &Plan, lines: &[Vec<usize>], edits: &Edits, cookbook: &Cookbook) -> Result<()> {
+    let n = argument.parse::<usize>().map_err(| _ | Error::Usage(format!("recipes takes a line number, not `{}`", argument)))?;
+    let path = lines.get(n.wrapping_sub(1)).ok_or_else(|| Error::Usage(format!("no line {n}, the view shows {count}", n=n, count=lines.len())))?;
+    let widget = stage_widget(plan, path);
+    let used = &plan.tree.as_ref().and_then(| t | t.at(path)).expect("visible paths are in the tree").recipe.name;
+    let constraints = edits.applied(cookbook);
+    println!("{widget} can be made by:", widget=widget);
+    for recipe in cookbook.recipes.iter().filter(| r | r.products.iter().any(| p | p.widget == widget)) {
+        let state = if &recipe.name == used {
+            "in use"
+        } else if edits.pins.get(&widget) == Some(&recipe.name) {
+            "pinned"
+        } else if constraints.allows(recipe) {
+            "allowed"
+        } else if constraints.excluded.contains(&recipe.name) {
+            "excluded"
+        } else if recipe.alternate && !constraints.unlocked.contains(&recipe.name) {
+            "locked alternate"
+        } else {
+            "above the tier limit"
+        };
+        println!("  {name} ({state})", name=recipe.name, state=state);
+    }
+    Ok(())
+}
+
+// browses the plan's tree from commands read off `input`, a line at a time, until it ends or says quit;
+// what-if edits re-solve through `solve` and replace the plan, or are undone when it fails
+pub fn explore<'a>(mut plan: Plan<'a>, constraints: &Constraints, cookbook: &Cookbook, precision: Precision, input: impl BufRead, solve: impl Fn(Rational64, &Constraints) -> Result<Plan<'a>>) {
+    if plan.tree.is_none() {
+        println!("{widget} is a raw resource", widget=plan.widget);
+        return;
+    }
+    let mut edits = Edits { rate: plan.rate, constraints: constraints.clone(), pins: BTreeMap::new() };
+    let mut view = View { expanded: HashSet::from([Vec::new()]), matches: HashSet::new() };
+    let mut lines = show(&plan, &view, &edits, cookbook, precision);
+    println!("(type help for commands)");
+    for command in input.lines().map_while(| line | line.ok()) {
+        let command = command.trim();
+        let tree = plan.tree.as_ref().expect("the goal is crafted");
+        match command {
+            "" => continue,
+            "quit" | "q" => return,
+            "help" | "?" => {
+                println!("{}", HELP);
+                continue;
+            },
+            "summary" => {
+                match summary_text(&plan.summary, &edits.applied(cookbook), cookbook, precision) {
+                    Ok(text) => print!("{}", text),
+                    Err(e) => println!("{}", e)
+                }
+                continue;
+            },
+            "open" => view.expanded = tree.find_all(| _ | true).into_iter().collect(),
+            "close" => view.expanded = HashSet::from([Vec::new()]),
+            _ if command.starts_with("recipes") => {
+                if let Err(e) = print_recipes(command["recipes".len()..].trim(), &plan, &lines, &edits, cookbook) {
+                    println!("{}", e);
+                }
+                continue;
+            },
+            _ => {
+                let before = edits.clone();
+                match edit(command, &mut edits, &plan, &lines, cookbook) {
+                    Ok(true) => match solve(edits.rate, &edits.applied(cookbook)) {
+                        Ok(solved) => {
+                            println!("re-solved: {machines} machines, was {before}", machines=solved.total_machines(), before=plan.total_machines());
+                            plan = solved;
+                            view.matches.clear();
+                        },
+                        Err(e) => {
+                            println!("{}, change undone", e);
+                            edits = before;
+                            continue;
+                        }
+                    },
+                    Ok(false) => match (command.strip_prefix('/'), command.parse::<usize>()) {
+                        (Some(text), _) => {
+                            let needle = text.to_lowercase();
+                            view.matches = tree.find_all(| node | describe(node, precision).to_lowercase().contains(&needle)).into_iter().collect();
+                            for path in view.matches.iter() {
+                                view.expanded.extend((0..path.len()).map(| depth | path[..depth].to_vec()));
+                            }
+                            println!("{count} stages match", count=view.matches.len());
+                        },
+                        (None, Ok(n)) if n >= 1 && n <= lines.len() => {
+                            let path = &lines[n - 1];
+                            if !view.expanded.remove(path) {
+                                view.expanded.insert(path.clone());
+                            }
+                        },
+                        (None, Ok(n)) => {
+                            println!("no line {n}, the view shows {count}", n=n, count=lines.len());
+                            continue;
+                        },
+                        _ => {
+                            println!("unknown command `{}`, type help for commands", command);
+                            continue;
+                        }
+                    },
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                }
+            }
+        }
+        lines = show(&plan, &view, &edits, cookbook, precision);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Recipe};
+
+    fn chain() -> Cookbook {
+        let one = Rational64::from_integer(1);
+        let step = | name: &str, from: &str, to: &str | Recipe::new(name, one).made_by("Assembler").reagent(from, one).product(to, one);
+        Cookbook::builder().machine("Assembler", None).item("a").item("b").item("c").item("ore")
+            .recipe(step("A", "b", "a")).recipe(step("B", "c", "b")).recipe(step("C", "ore", "c")).recipe(step("Alt A", "c", "a").as_alternate())
+            .build()
+    }
+
+    #[test]
+    fn visible_test() {
+        let cookbook = chain();
+        let graph = cookbook.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(1), &Constraints::default()).unwrap();
+        let tree = plan.tree.as_ref().unwrap();
+        let lines = | view: &View | {
+            let mut lines = Vec::new();
+            visible(tree, view, &mut Vec::new(), &mut lines);
+            lines
+        };
+        // the goal starts expanded, a stage below a collapsed one isn't listed
+        let mut view = View { expanded: HashSet::from([Vec::new()]), matches: HashSet::new() };
+        assert_eq!(lines(&view), [vec![], vec![0]]);
+        view.expanded.insert(vec![0]);
+        assert_eq!(lines(&view), [vec![], vec![0], vec![0, 0]]);
+        view.expanded.remove(&Vec::new());
+        assert_eq!(lines(&view), [Vec::<usize>::new()]);
+        assert_eq!(stage_widget(&plan, &[0, 0]), "c");
+    }
+
+    #[test]
+    fn edit_test() {
+        let cookbook = chain();
+        let graph = cookbook.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(1), &Constraints::default()).unwrap();
+        let lines = vec![vec![], vec![0]];
+        let mut edits = Edits { rate: plan.rate, constraints: Constraints::default(), pins: BTreeMap::new() };
+        assert!(edit("rate 2", &mut edits, &plan, &lines, &cookbook).unwrap());
+        assert!(edit("+", &mut edits, &plan, &lines, &cookbook).unwrap());
+        assert_eq!(edits.rate, Rational64::new(11, 5));
+        // pinning the alternate unlocks it and excludes what competes with it, pinning it again undoes that
+        assert!(edit("pin Alt A", &mut edits, &plan, &lines, &cookbook).unwrap());
+        let applied = edits.applied(&cookbook);
+        assert!(applied.unlocked.contains("Alt A") && applied.excluded.contains("A"));
+        edit("pin Alt A", &mut edits, &plan, &lines, &cookbook).unwrap();
+        assert!(edits.pins.is_empty());
+        // line numbers name the recipe on that line
+        edit("exclude 2", &mut edits, &plan, &lines, &cookbook).unwrap();
+        assert!(edits.constraints.excluded.contains("B"));
+        assert!(matches!(edit("alt A", &mut edits, &plan, &lines, &cookbook), Err(Error::Usage(_))));
+        assert!(matches!(edit("exclude 3", &mut edits, &plan, &lines, &cookbook), Err(Error::Usage(_))));
+        assert!(!edit("open", &mut edits, &plan, &lines, &cookbook).unwrap());
+    }
+}
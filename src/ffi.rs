@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::error::{Error, Result};
+use crate::{load_recipes, Cookbook, Format, Precision, SolveRequest};
+
+// a solved plan, kept as text since the tree borrows a graph the handle does not own
+pub struct SolvedPlan {
+    pub(crate) json: CString,
+    pub(crate) text: CString,
+    pub(crate) machines: u64
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail<T>(error: Error) -> *mut T {
+    let message = CString::new(error.to_string().replace('\0', " ")).expect("nul bytes are replaced");
+    LAST_ERROR.with(| last | *last.borrow_mut() = Some(message));
+    ptr::null_mut()
+}
+
+fn c_string(text: String) -> Result<CString> {
+    CString::new(text).map_err(| e | Error::Usage(format!("output holds a nul byte: {}", e)))
+}
+
+unsafe fn read(text: *const c_char) -> Result<String> {
+    if text.is_null() {
+        return Err(Error::Usage("null string".to_owned()));
+    }
+    CStr::from_ptr(text).to_str().map(str::to_owned).map_err(| e | Error::Usage(format!("string is not UTF-8: {}", e)))
+}
+
+pub(crate) fn solve_request(cookbook: &Cookbook, request: &str) -> Result<SolvedPlan> {
+    let request: SolveRequest = serde_json::from_str(request).map_err(| e | Error::Usage(format!("bad request: {}", e)))?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&request.purity)?;
+    let plan = request.solve(&graph, &extraction)?;
+    Ok(SolvedPlan {
+        json: c_string(plan.render(Format::Json, cookbook, Precision::default()).0)?,
+        text: c_string(plan.render(Format::Text, cookbook, Precision::default()).0)?,
+        machines: plan.total_machines()
+    })
+}
+
+/// The message of the last call on this thread that returned null, or null when there was none.
+/// The string stays valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn supply_solver_last_error() -> *const c_char {
+    LAST_ERROR.with(| last | last.borrow().as_ref().map_or(ptr::null(), | e | e.as_ptr()))
+}
+
+/// Parses a YAML game definition into a cookbook handle, null on failure.
+///
+/// # Safety
+/// `yaml` must be a nul-terminated string. The handle is freed with `supply_solver_cookbook_free`.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_cookbook_from_yaml(yaml: *const c_char) -> *mut Cookbook {
+    match read(yaml).and_then(| text | Cookbook::from_yaml(&text)) {
+        Ok(cookbook) => Box::into_raw(Box::new(cookbook)),
+        Err(e) => fail(e)
+    }
+}
+
+/// Loads the game definition at `path` as `load_recipes` does, compressed or a compiled index included,
+/// into a cookbook handle, null on failure.
+///
+/// # Safety
+/// `path` must be a nul-terminated string. The handle is freed with `supply_solver_cookbook_free`.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_cookbook_load(path: *const c_char) -> *mut Cookbook {
+    match read(path).and_then(| path | load_recipes(&path.into())) {
+        Ok(cookbook) => Box::into_raw(Box::new(cookbook)),
+        Err(e) => fail(e)
+    }
+}
+
+/// # Safety
+/// `cookbook` must come from `supply_solver_cookbook_from_yaml` or `supply_solver_cookbook_load` and not be used afterwards; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_cookbook_free(cookbook: *mut Cookbook) {
+    if !cookbook.is_null() {
+        drop(Box::from_raw(cookbook));
+    }
+}
+
+/// Solves a JSON request (see `SolveRequest`) such as `{"widget": "stator", "rate": 2}` into a plan
+/// handle, null on failure.
+///
+/// # Safety
+/// `cookbook` must be a live cookbook handle and `request` a nul-terminated string. The plan is
+/// freed with `supply_solver_plan_free`.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_solve(cookbook: *const Cookbook, request: *const c_char) -> *mut SolvedPlan {
+    let cookbook = match cookbook.as_ref() {
+        Some(cookbook) => cookbook,
+        None => return fail(Error::Usage("null cookbook".to_owned()))
+    };
+    match read(request).and_then(| request | solve_request(cookbook, &request)) {
+        Ok(plan) => Box::into_raw(Box::new(plan)),
+        Err(e) => fail(e)
+    }
+}
+
+/// The plan in the saved plan format, valid as long as the plan.
+///
+/// # Safety
+/// `plan` must be a live plan handle.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_plan_json(plan: *const SolvedPlan) -> *const c_char {
+    plan.as_ref().map_or(ptr::null(), | p | p.json.as_ptr())
+}
+
+/// The plan's stage tree as `solve` prints it, valid as long as the plan.
+///
+/// # Safety
+/// `plan` must be a live plan handle.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_plan_text(plan: *const SolvedPlan) -> *const c_char {
+    plan.as_ref().map_or(ptr::null(), | p | p.text.as_ptr())
+}
+
+/// # Safety
+/// `plan` must be a live plan handle.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_plan_machines(plan: *const SolvedPlan) -> u64 {
+    plan.as_ref().map_or(0, | p | p.machines)
+}
+
+/// # Safety
+/// `plan` must come from `supply_solver_solve` and not be used afterwards; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn supply_solver_plan_free(plan: *mut SolvedPlan) {
+    if !plan.is_null() {
+        drop(Box::from_raw(plan));
+    }
+}
@@ -1,74 +1,357 @@
-use indexmap::{IndexSet, IndexMap};
-use std::collections::{HashSet, BTreeSet};
+use indexmap::IndexMap;
+use num::Zero;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, BTreeMap, HashSet, BTreeSet, VecDeque};
+use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::Neg;
+use thiserror::Error;
 
 pub type EdgeIndex = usize;
 pub type NodeIndex = usize;
 
-struct Hyperedge<E> {
-    src: BTreeSet<NodeIndex>,
-    dst: BTreeSet<NodeIndex>,
-    weight: E
+// how a graph stores its indices internally; the API always speaks usize, a narrower type
+// only saves memory on big graphs and panics once the graph outgrows it
+pub trait IndexType: Copy + Ord + Hash + Debug {
+    fn new(index: usize) -> Self;
+    fn index(self) -> usize;
 }
 
-impl<E> PartialEq for Hyperedge<E> {
-    fn eq(&self, rhs: &Self) -> bool {
-        self.src == rhs.src && self.dst == rhs.dst
+impl IndexType for usize {
+    fn new(index: usize) -> Self {
+        index
+    }
+
+    fn index(self) -> usize {
+        self
     }
 }
-impl<E> Eq for Hyperedge<E> {}
 
-impl<E> Hash for Hyperedge<E> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.src.hash(state);
-        self.dst.hash(state);
+impl IndexType for u32 {
+    fn new(index: usize) -> Self {
+        u32::try_from(index).expect("graph outgrew its u32 indices")
+    }
+
+    fn index(self) -> usize {
+        self as usize
     }
 }
 
-struct Hypernode {
-    neighbors: HashSet<NodeIndex>,
-    neighbor_of: HashSet<NodeIndex>
+impl IndexType for u16 {
+    fn new(index: usize) -> Self {
+        u16::try_from(index).expect("graph outgrew its u16 indices")
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
 }
 
-impl Hypernode {
-    pub fn new() -> Self {
-        Hypernode { neighbors: HashSet::new(), neighbor_of: HashSet::new() }
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HypergraphError {
+    #[error("node does not exist")]
+    NodeNotFound,
+    // an edge endpoint not yet inserted as a node, with its position among the edge's sources or destinations
+    #[error("{0:?} {1} of the edge is not a node")]
+    MissingEndpoint(Endpoint, usize),
+    #[error("edge {0} does not exist")]
+    EdgeNotFound(EdgeIndex),
+    // the index is the existing edge's with the same endpoints
+    #[error("edge {0} already joins the same nodes")]
+    DuplicateEdge(EdgeIndex),
+    // nodes along one cycle, each feeding the next and the last feeding the first
+    #[error("nodes {0:?} form a cycle")]
+    Cycle(Vec<NodeIndex>)
+}
+
+// an edge's endpoints, the weight lives beside it
+#[derive(Clone, PartialEq, Eq)]
+struct Hyperedge<Ix> {
+    src: BTreeSet<Ix>,
+    dst: BTreeSet<Ix>
+}
+
+struct Hypernode<D, Ix> {
+    neighbors: HashSet<Ix>,
+    neighbor_of: HashSet<Ix>,
+    data: D
+}
+
+impl<D, Ix> Hypernode<D, Ix> {
+    pub fn new(data: D) -> Self {
+        Hypernode { neighbors: HashSet::new(), neighbor_of: HashSet::new(), data }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Source,
+    Destination
+}
+
+// which way a traversal follows edges: from sources to destinations, e.g. what a widget goes into,
+// or back from destinations to sources, e.g. what a widget is made from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward
+}
+
+// what a visitor hook wants next: go on, skip whatever lies beyond this node or edge, or end the traversal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Prune,
+    Stop
+}
+
+// hooks a traversal calls as it goes; every node and edge is visited at most once, and each one
+// discovered or examined is finished once everything reached through it is, unless the traversal stops
+pub trait Visitor<N, E> {
+    fn discover_node(&mut self, _n: NodeIndex, _node: &N) -> Control {
+        Control::Continue
     }
+
+    fn finish_node(&mut self, _n: NodeIndex, _node: &N) {}
+
+    fn examine_edge(&mut self, _e: EdgeIndex, _weight: &E) -> Control {
+        Control::Continue
+    }
+
+    fn finish_edge(&mut self, _e: EdgeIndex, _weight: &E) {}
+}
+
+// a sparse nodes x edges matrix, entries sorted by edge and then node
+#[derive(Debug, PartialEq)]
+pub struct Incidence<C> {
+    pub rows: usize,
+    pub columns: usize,
+    pub entries: Vec<(NodeIndex, EdgeIndex, C)>
+}
+
+// the serialized form: nodes in index order, edges by node index and in index order
+#[derive(Serialize)]
+struct StoredRef<'a, N, E, D> {
+    nodes: Vec<StoredNode<&'a N, &'a D>>,
+    edges: Vec<StoredEdge<&'a E>>
+}
+
+#[derive(Deserialize)]
+struct Stored<N, E, D> {
+    nodes: Vec<StoredNode<N, D>>,
+    edges: Vec<StoredEdge<E>>
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredNode<N, D> {
+    node: N,
+    data: D
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEdge<E> {
+    src: Vec<NodeIndex>,
+    dst: Vec<NodeIndex>,
+    weight: E
 }
 
-pub struct Hypergraph<N, E>
-where N: Hash + Eq {
-    nodes: IndexMap<N, Hypernode>,
-    edges: IndexSet<Hyperedge<E>>
+#[derive(Debug, PartialEq)]
+pub struct Statistics {
+    pub nodes: usize,
+    pub edges: usize,
+    // nodes no edge leads to, no edge leaves, and neither
+    pub sources: Vec<NodeIndex>,
+    pub sinks: Vec<NodeIndex>,
+    pub isolated: Vec<NodeIndex>,
+    pub max_in_degree: usize,
+    pub max_out_degree: usize,
+    // edges by number of sources and destinations
+    pub cardinalities: BTreeMap<(usize, usize), usize>
 }
 
-impl<N, E> Hypergraph<N, E>
-where N: Hash + Eq {
+// bookkeeping for Tarjan's strongly connected components
+struct Tarjan {
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<NodeIndex>,
+    next: usize,
+    components: Vec<Vec<NodeIndex>>
+}
+
+// nodes of type N carry a payload of type D, edges a weight of type E, indices are stored as Ix
+pub struct Hypergraph<N, E, D = (), Ix = usize>
+where N: Hash + Eq, Ix: IndexType {
+    nodes: IndexMap<N, Hypernode<D, Ix>>,
+    // edges with the same endpoints may sit side by side, e.g. alternate recipes
+    edges: Vec<(Hyperedge<Ix>, E)>
+}
+
+impl<N, E, D, Ix> Default for Hypergraph<N, E, D, Ix>
+where N: Hash + Eq, Ix: IndexType {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N, E, D, Ix> Hypergraph<N, E, D, Ix>
+where N: Hash + Eq, Ix: IndexType {
     pub fn new() -> Self {
-        Self { nodes: IndexMap::new(), edges: IndexSet::new() }
+        Self { nodes: IndexMap::new(), edges: Vec::new() }
+    }
+
+    // room for this many nodes and edges before reallocating, for bulk construction
+    pub fn with_capacity(nodes: usize, edges: usize) -> Self {
+        Self { nodes: IndexMap::with_capacity(nodes), edges: Vec::with_capacity(edges) }
+    }
+
+    pub fn reserve(&mut self, nodes: usize, edges: usize) {
+        self.nodes.reserve(nodes);
+        self.edges.reserve(edges);
+    }
+
+    pub fn insert_node(&mut self, node: N) -> NodeIndex
+    where D: Default {
+        self.insert_node_with(node, D::default())
     }
 
-    pub fn insert_node(&mut self, node: N) -> NodeIndex {
-        let (index, _) = self.nodes.insert_full(node, Hypernode::new());
+    pub fn insert_node_with(&mut self, node: N, data: D) -> NodeIndex {
+        let (index, _) = self.nodes.insert_full(node, Hypernode::new(data));
         index
     }
 
-    pub fn insert_edge(&mut self, sources: &[N], destinations: &[N], weight: E) -> EdgeIndex {
-        let mapping = | node: &N | -> usize { self.nodes.get_index_of(node).unwrap() };
-        let (index, _) = self.edges.insert_full(
-            Hyperedge::<E> {
-                src: sources.iter().map(mapping).collect(),
-                dst: destinations.iter().map(mapping).collect(),
-                weight
+    // every endpoint has to be a node already
+    pub fn insert_edge(&mut self, sources: &[N], destinations: &[N], weight: E) -> Result<EdgeIndex, HypergraphError> {
+        let mapping = | nodes: &[N], endpoint: Endpoint | nodes.iter().enumerate()
+            .map(| (i, node) | self.nodes.get_index_of(node).map(Ix::new).ok_or(HypergraphError::MissingEndpoint(endpoint, i)))
+            .collect::<Result<BTreeSet<Ix>, _>>();
+        let edge = Hyperedge { src: mapping(sources, Endpoint::Source)?, dst: mapping(destinations, Endpoint::Destination)? };
+        let index = self.edges.len();
+        for src in edge.src.iter() {
+            self.nodes[src.index()].neighbors.insert(Ix::new(index));
+        }
+        for dst in edge.dst.iter() {
+            self.nodes[dst.index()].neighbor_of.insert(Ix::new(index));
+        }
+        self.edges.push((edge, weight));
+        Ok(index)
+    }
+
+    // inserts every node in turn, returning their indices; a node already present keeps its place and payload
+    pub fn add_nodes<I>(&mut self, nodes: I) -> Vec<NodeIndex>
+    where D: Default, I: IntoIterator<Item=N> {
+        let nodes = nodes.into_iter();
+        self.nodes.reserve(nodes.size_hint().0);
+        nodes.map(| node | match self.nodes.get_index_of(&node) {
+            Some(index) => index,
+            None => self.insert_node(node)
+        }).collect()
+    }
+
+    // inserts (sources, destinations, weight) triples in turn, returning their indices; stops at the first
+    // edge with a missing endpoint, keeping the edges before it
+    pub fn add_edges<I, S, T>(&mut self, edges: I) -> Result<Vec<EdgeIndex>, HypergraphError>
+    where I: IntoIterator<Item=(S, T, E)>, S: AsRef<[N]>, T: AsRef<[N]> {
+        let edges = edges.into_iter();
+        self.edges.reserve(edges.size_hint().0);
+        edges.map(| (sources, destinations, weight) | self.insert_edge(sources.as_ref(), destinations.as_ref(), weight)).collect()
+    }
+
+    // like insert_edge, but refuses an edge whose endpoints match an existing one
+    pub fn insert_unique_edge(&mut self, sources: &[N], destinations: &[N], weight: E) -> Result<EdgeIndex, HypergraphError> {
+        let (sources_ref, destinations_ref): (Vec<&N>, Vec<&N>) = (sources.iter().collect(), destinations.iter().collect());
+        if let Some(existing) = self.find_edge(&sources_ref, &destinations_ref) {
+            return Err(HypergraphError::DuplicateEdge(existing));
+        }
+        self.insert_edge(sources, destinations, weight)
+    }
+
+    // the last edge takes over the removed one's index
+    pub fn remove_edge(&mut self, e: &EdgeIndex) -> Result<E, HypergraphError> {
+        if *e >= self.edges.len() {
+            return Err(HypergraphError::EdgeNotFound(*e));
+        }
+        let (_, weight) = self.edges.swap_remove(*e);
+        let (removed, moved) = (Ix::new(*e), Ix::new(self.edges.len()));
+        for (_, node) in self.nodes.iter_mut() {
+            node.neighbors.remove(&removed);
+            node.neighbor_of.remove(&removed);
+        }
+        if moved != removed {
+            let (src, dst) = self.edges.get(*e).map(| (m, _) | (m.src.clone(), m.dst.clone())).expect("moved edge exists");
+            for n in src {
+                let node = &mut self.nodes[n.index()];
+                node.neighbors.remove(&moved);
+                node.neighbors.insert(removed);
+            }
+            for n in dst {
+                let node = &mut self.nodes[n.index()];
+                node.neighbor_of.remove(&moved);
+                node.neighbor_of.insert(removed);
             }
-        );
-        for src in sources {
-            self.nodes.get_mut(src).unwrap().neighbors.insert(index);
         }
-        for dst in destinations {
-            self.nodes.get_mut(dst).unwrap().neighbor_of.insert(index);
+        Ok(weight)
+    }
+
+    // drops the node along with every edge touching it, returning their weights;
+    // the last node takes over the removed one's index
+    pub fn remove_node<Q>(&mut self, node: &Q) -> Result<Vec<E>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let index = self.nodes.get_index_of(node).ok_or(HypergraphError::NodeNotFound)?;
+        let mut touching: Vec<EdgeIndex> = self.nodes[index].neighbors.union(&self.nodes[index].neighbor_of).map(| e | e.index()).collect();
+        // highest first, so the edges swapped into removed slots are never ones still to remove
+        touching.sort_unstable_by(| a, b | b.cmp(a));
+        let mut weights = Vec::with_capacity(touching.len());
+        for e in touching {
+            weights.push(self.remove_edge(&e).expect("edge from the node's index sets exists"));
         }
-        index
+        self.nodes.swap_remove_index(index);
+        let moved = self.nodes.len();
+        if moved != index {
+            let remap = | n: Ix | if n.index() == moved { Ix::new(index) } else { n };
+            self.edges = std::mem::take(&mut self.edges).into_iter().map(| (edge, weight) | (Hyperedge {
+                src: edge.src.into_iter().map(remap).collect(),
+                dst: edge.dst.into_iter().map(remap).collect()
+            }, weight)).collect();
+        }
+        weights.reverse();
+        Ok(weights)
+    }
+
+    // merges `nodes` into the first of them, which keeps its payload, rewriting the edges touching the others;
+    // node indices are renumbered, edges keep theirs
+    pub fn contract<Q>(&mut self, nodes: &[&Q]) -> Result<NodeIndex, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let merged = nodes.iter().map(| n | self.node_index(*n)).collect::<Result<BTreeSet<_>, _>>()?;
+        let survivor = self.node_index(*nodes.first().ok_or(HypergraphError::NodeNotFound)?)?;
+
+        let mut mapping = vec![0; self.order()];
+        let mut kept = IndexMap::new();
+        for (i, (node, data)) in std::mem::take(&mut self.nodes).into_iter().enumerate() {
+            if i == survivor || !merged.contains(&i) {
+                mapping[i] = kept.len();
+                kept.insert(node, Hypernode::new(data.data));
+            }
+        }
+        for i in merged.iter() {
+            mapping[*i] = mapping[survivor];
+        }
+        self.nodes = kept;
+
+        for (index, (edge, _)) in self.edges.iter_mut().enumerate() {
+            let remap = | set: &BTreeSet<Ix> | set.iter().map(| n | Ix::new(mapping[n.index()])).collect();
+            *edge = Hyperedge { src: remap(&edge.src), dst: remap(&edge.dst) };
+            for n in edge.src.iter() {
+                self.nodes[n.index()].neighbors.insert(Ix::new(index));
+            }
+            for n in edge.dst.iter() {
+                self.nodes[n.index()].neighbor_of.insert(Ix::new(index));
+            }
+        }
+        Ok(mapping[survivor])
     }
 
     pub fn order(&self) -> usize {
@@ -79,20 +362,559 @@ where N: Hash + Eq {
         self.edges.len()
     }
 
-    pub fn neighbors(&self, node: &N) -> Result<Vec<&EdgeIndex>, &str> {
-        self.nodes.get(node).ok_or_else(|| "Node does not exist").map(| n | n.neighbors.iter().collect())
+    // nodes are looked up by anything they borrow as, e.g. `&str` for `String` nodes
+    pub fn neighbors<Q>(&self, node: &Q) -> Result<Vec<EdgeIndex>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get(node).ok_or(HypergraphError::NodeNotFound).map(| n | n.neighbors.iter().map(| e | e.index()).collect())
+    }
+
+    pub fn neighbor_of<Q>(&self, node: &Q) -> Result<Vec<EdgeIndex>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get(node).ok_or(HypergraphError::NodeNotFound).map(| n | n.neighbor_of.iter().map(| e | e.index()).collect())
+    }
+
+    pub fn get_node(&self, n: &NodeIndex) -> Result<&N, HypergraphError> {
+        self.nodes.get_index(*n).ok_or(HypergraphError::NodeNotFound).map(| (k, _) | k)
+    }
+
+    pub fn node_index<Q>(&self, node: &Q) -> Result<NodeIndex, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get_index_of(node).ok_or(HypergraphError::NodeNotFound)
+    }
+
+    pub fn contains_node<Q>(&self, node: &Q) -> bool
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.contains_key(node)
+    }
+
+    // the first edge joining exactly these sources to exactly these destinations
+    pub fn find_edge<Q>(&self, sources: &[&Q], destinations: &[&Q]) -> Option<EdgeIndex>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let indices = | nodes: &[&Q] | nodes.iter().map(| n | self.nodes.get_index_of(*n).map(Ix::new)).collect::<Option<BTreeSet<Ix>>>();
+        let (src, dst) = (indices(sources)?, indices(destinations)?);
+        self.edges.iter().position(| (edge, _) | edge.src == src && edge.dst == dst)
+    }
+
+    // every edge with `source` among its sources and `destination` among its destinations, in index order
+    pub fn edges_between<Q>(&self, source: &Q, destination: &Q) -> Result<Vec<EdgeIndex>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let src = self.nodes.get(source).ok_or(HypergraphError::NodeNotFound)?;
+        let dst = self.nodes.get(destination).ok_or(HypergraphError::NodeNotFound)?;
+        let mut edges: Vec<EdgeIndex> = src.neighbors.intersection(&dst.neighbor_of).map(| e | e.index()).collect();
+        edges.sort_unstable();
+        Ok(edges)
+    }
+
+    pub fn get_weight(&self, e: &EdgeIndex) -> Result<&E, HypergraphError> {
+        self.edges.get(*e).ok_or(HypergraphError::EdgeNotFound(*e)).map(| (_, weight) | weight)
+    }
+
+    pub fn get_weight_mut(&mut self, e: &EdgeIndex) -> Result<&mut E, HypergraphError> {
+        self.edges.get_mut(*e).ok_or(HypergraphError::EdgeNotFound(*e)).map(| (_, weight) | weight)
+    }
+
+    pub fn get_data<Q>(&self, node: &Q) -> Result<&D, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get(node).ok_or(HypergraphError::NodeNotFound).map(| n | &n.data)
+    }
+
+    pub fn get_data_mut<Q>(&mut self, node: &Q) -> Result<&mut D, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get_mut(node).ok_or(HypergraphError::NodeNotFound).map(| n | &mut n.data)
+    }
+
+    // edges leading to the node
+    pub fn in_degree<Q>(&self, node: &Q) -> Result<usize, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get(node).ok_or(HypergraphError::NodeNotFound).map(| n | n.neighbor_of.len())
+    }
+
+    // edges leaving the node
+    pub fn out_degree<Q>(&self, node: &Q) -> Result<usize, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        self.nodes.get(node).ok_or(HypergraphError::NodeNotFound).map(| n | n.neighbors.len())
+    }
+
+    // sources and destinations of the edge
+    pub fn cardinality(&self, e: &EdgeIndex) -> Result<(usize, usize), HypergraphError> {
+        self.edges.get(*e).ok_or(HypergraphError::EdgeNotFound(*e)).map(| (edge, _) | (edge.src.len(), edge.dst.len()))
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        let nodes: Vec<&Hypernode<D, Ix>> = self.nodes.values().collect();
+        let matching = | keep: &dyn Fn(&Hypernode<D, Ix>) -> bool | (0..nodes.len()).filter(| n | keep(nodes[*n])).collect::<Vec<NodeIndex>>();
+        let mut cardinalities = BTreeMap::new();
+        for (edge, _) in self.edges.iter() {
+            *cardinalities.entry((edge.src.len(), edge.dst.len())).or_insert(0) += 1;
+        }
+        Statistics {
+            nodes: self.order(),
+            edges: self.size(),
+            sources: matching(&| n | n.neighbor_of.is_empty() && !n.neighbors.is_empty()),
+            sinks: matching(&| n | n.neighbors.is_empty() && !n.neighbor_of.is_empty()),
+            isolated: matching(&| n | n.neighbors.is_empty() && n.neighbor_of.is_empty()),
+            max_in_degree: nodes.iter().map(| n | n.neighbor_of.len()).max().unwrap_or(0),
+            max_out_degree: nodes.iter().map(| n | n.neighbors.len()).max().unwrap_or(0),
+            cardinalities
+        }
+    }
+
+    // nodes some edge leads to from `n`
+    fn successors(&self, n: NodeIndex) -> BTreeSet<NodeIndex> {
+        self.nodes[n].neighbors.iter().flat_map(| e | self.edges.get(e.index()).expect("indexed edge exists").0.dst.iter().map(| n | n.index())).collect()
+    }
+
+    fn predecessors(&self, n: NodeIndex) -> BTreeSet<NodeIndex> {
+        self.nodes[n].neighbor_of.iter().flat_map(| e | self.edges.get(e.index()).expect("indexed edge exists").0.src.iter().map(| n | n.index())).collect()
+    }
+
+    // the edges a traversal leaves `n` by, in index order
+    fn next_edges(&self, n: NodeIndex, direction: Direction) -> Vec<EdgeIndex> {
+        let node = &self.nodes[n];
+        let mut edges: Vec<EdgeIndex> = match direction {
+            Direction::Forward => node.neighbors.iter(),
+            Direction::Backward => node.neighbor_of.iter()
+        }.map(| e | e.index()).collect();
+        edges.sort_unstable();
+        edges
+    }
+
+    // the nodes a traversal reaches over `e`
+    fn far_ends(&self, e: EdgeIndex, direction: Direction) -> Vec<NodeIndex> {
+        let edge = &self.edges.get(e).expect("edge exists").0;
+        match direction {
+            Direction::Forward => &edge.dst,
+            Direction::Backward => &edge.src
+        }.iter().map(| n | n.index()).collect()
+    }
+
+    // false once the visitor stops the traversal
+    fn visit_depth<V: Visitor<N, E>>(&self, n: NodeIndex, direction: Direction, visitor: &mut V, seen: &mut [bool], examined: &mut [bool]) -> bool {
+        let node = self.nodes.get_index(n).expect("node exists").0;
+        seen[n] = true;
+        match visitor.discover_node(n, node) {
+            Control::Stop => return false,
+            Control::Prune => (),
+            Control::Continue => for e in self.next_edges(n, direction) {
+                if examined[e] {
+                    continue;
+                }
+                examined[e] = true;
+                let weight = &self.edges.get(e).expect("edge exists").1;
+                match visitor.examine_edge(e, weight) {
+                    Control::Stop => return false,
+                    Control::Prune => (),
+                    Control::Continue => for next in self.far_ends(e, direction) {
+                        if !seen[next] && !self.visit_depth(next, direction, visitor, seen, examined) {
+                            return false;
+                        }
+                    }
+                }
+                visitor.finish_edge(e, weight);
+            }
+        }
+        visitor.finish_node(n, node);
+        true
+    }
+
+    // walks depth first from each of `start` in turn, lowest edge index first
+    pub fn depth_first<Q, V>(&self, start: &[&Q], direction: Direction, visitor: &mut V) -> Result<(), HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized, V: Visitor<N, E> {
+        let start = start.iter().map(| n | self.node_index(*n)).collect::<Result<Vec<_>, _>>()?;
+        let mut seen = vec![false; self.order()];
+        let mut examined = vec![false; self.size()];
+        for n in start {
+            if !seen[n] && !self.visit_depth(n, direction, visitor, &mut seen, &mut examined) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // false once the visitor stops the traversal
+    fn discover<V: Visitor<N, E>>(&self, n: NodeIndex, visitor: &mut V, seen: &mut [bool], queue: &mut VecDeque<NodeIndex>) -> bool {
+        let node = self.nodes.get_index(n).expect("node exists").0;
+        seen[n] = true;
+        match visitor.discover_node(n, node) {
+            Control::Stop => return false,
+            Control::Prune => visitor.finish_node(n, node),
+            Control::Continue => queue.push_back(n)
+        }
+        true
+    }
+
+    // walks breadth first from all of `start` at once, a node finishing when the edges leaving it have been examined
+    pub fn breadth_first<Q, V>(&self, start: &[&Q], direction: Direction, visitor: &mut V) -> Result<(), HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized, V: Visitor<N, E> {
+        let start = start.iter().map(| n | self.node_index(*n)).collect::<Result<Vec<_>, _>>()?;
+        let mut seen = vec![false; self.order()];
+        let mut examined = vec![false; self.size()];
+        let mut queue = VecDeque::new();
+        for n in start {
+            if !seen[n] && !self.discover(n, visitor, &mut seen, &mut queue) {
+                return Ok(());
+            }
+        }
+        while let Some(n) = queue.pop_front() {
+            for e in self.next_edges(n, direction) {
+                if examined[e] {
+                    continue;
+                }
+                examined[e] = true;
+                let weight = &self.edges.get(e).expect("edge exists").1;
+                match visitor.examine_edge(e, weight) {
+                    Control::Stop => return Ok(()),
+                    Control::Prune => (),
+                    Control::Continue => for next in self.far_ends(e, direction) {
+                        if !seen[next] && !self.discover(next, visitor, &mut seen, &mut queue) {
+                            return Ok(());
+                        }
+                    }
+                }
+                visitor.finish_edge(e, weight);
+            }
+            visitor.finish_node(n, self.nodes.get_index(n).expect("node exists").0);
+        }
+        Ok(())
+    }
+
+    // every node after all nodes feeding it, lowest index first among the ready ones
+    pub fn topological_order(&self) -> Result<Vec<NodeIndex>, HypergraphError> {
+        let mut incoming: Vec<usize> = (0..self.order()).map(| n | self.predecessors(n).len()).collect();
+        let mut ready: BTreeSet<NodeIndex> = (0..self.order()).filter(| n | incoming[*n] == 0).collect();
+        let mut order = Vec::with_capacity(self.order());
+        while let Some(n) = ready.pop_first() {
+            order.push(n);
+            for next in self.successors(n) {
+                incoming[next] -= 1;
+                if incoming[next] == 0 {
+                    ready.insert(next);
+                }
+            }
+        }
+        if order.len() == self.order() {
+            return Ok(order);
+        }
+        // walk back from a node left over until the walk repeats itself
+        let left: HashSet<NodeIndex> = (0..self.order()).filter(| n | incoming[*n] > 0).collect();
+        let mut walk = vec![*left.iter().min().expect("some node is left")];
+        loop {
+            let last = *walk.last().unwrap();
+            let previous = self.predecessors(last).into_iter().find(| p | left.contains(p)).expect("left over nodes have a left over predecessor");
+            if let Some(start) = walk.iter().position(| n | *n == previous) {
+                let mut cycle = walk.split_off(start);
+                cycle.reverse();
+                let lowest = cycle.iter().enumerate().min_by_key(| (_, n) | **n).map_or(0, | (i, _) | i);
+                cycle.rotate_left(lowest);
+                return Err(HypergraphError::Cycle(cycle));
+            }
+            walk.push(previous);
+        }
+    }
+
+    fn strong_connect(&self, n: NodeIndex, state: &mut Tarjan) {
+        state.index[n] = Some(state.next);
+        state.lowlink[n] = state.next;
+        state.next += 1;
+        state.stack.push(n);
+        state.on_stack[n] = true;
+        for next in self.successors(n) {
+            match state.index[next] {
+                None => {
+                    self.strong_connect(next, state);
+                    state.lowlink[n] = state.lowlink[n].min(state.lowlink[next]);
+                },
+                Some(index) if state.on_stack[next] => state.lowlink[n] = state.lowlink[n].min(index),
+                Some(_) => ()
+            }
+        }
+        if Some(state.lowlink[n]) == state.index[n] {
+            let mut component = Vec::new();
+            loop {
+                let member = state.stack.pop().expect("the root is still on the stack");
+                state.on_stack[member] = false;
+                component.push(member);
+                if member == n {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            state.components.push(component);
+        }
+    }
+
+    // groups of nodes that all reach each other, feeding components first and each sorted by index;
+    // a component is a loop when it has several nodes or an edge from its node back to itself
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeIndex>> {
+        let mut state = Tarjan {
+            index: vec![None; self.order()],
+            lowlink: vec![0; self.order()],
+            on_stack: vec![false; self.order()],
+            stack: Vec::new(),
+            next: 0,
+            components: Vec::new()
+        };
+        for n in 0..self.order() {
+            if state.index[n].is_none() {
+                self.strong_connect(n, &mut state);
+            }
+        }
+        // Tarjan finishes a component only after everything it feeds
+        state.components.reverse();
+        state.components
+    }
+
+    // the components that are loops, e.g. recycling chains in recipe data
+    pub fn loops(&self) -> Vec<Vec<NodeIndex>> {
+        self.strongly_connected_components().into_iter().filter(| c | c.len() > 1 || self.successors(c[0]).contains(&c[0])).collect()
+    }
+
+    // nodes made from `start` alone: an edge only fires once all its sources are reached,
+    // edges without sources firing from the outset
+    pub fn reachable_from<Q>(&self, start: &[&Q]) -> Result<BTreeSet<NodeIndex>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let mut reached = start.iter().map(| n | self.node_index(*n)).collect::<Result<BTreeSet<_>, _>>()?;
+        let mut fired = vec![false; self.size()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (e, (edge, _)) in self.edges.iter().enumerate() {
+                if !fired[e] && edge.src.iter().all(| n | reached.contains(&n.index())) {
+                    fired[e] = true;
+                    changed = true;
+                    reached.extend(edge.dst.iter().map(| n | n.index()));
+                }
+            }
+        }
+        Ok(reached)
+    }
+
+    // nodes any edge leading to `target` draws on, transitively, not counting `target` unless it is in a loop
+    pub fn required_by<Q>(&self, target: &Q) -> Result<BTreeSet<NodeIndex>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized {
+        let mut required = BTreeSet::new();
+        let mut pending = vec![self.node_index(target)?];
+        while let Some(n) = pending.pop() {
+            for previous in self.predecessors(n) {
+                if required.insert(previous) {
+                    pending.push(previous);
+                }
+            }
+        }
+        Ok(required)
+    }
+
+    fn collect_path(&self, n: NodeIndex, via: &[Option<EdgeIndex>], path: &mut Vec<EdgeIndex>) {
+        if let Some(e) = via[n] {
+            if !path.contains(&e) {
+                for src in self.edges.get(e).expect("edge exists").0.src.iter() {
+                    self.collect_path(src.index(), via, path);
+                }
+                path.push(e);
+            }
+        }
+    }
+
+    // cheapest hyperpath from `start` to `target`, a path's cost being the sum of `cost` over its edges
+    // with shared sub-paths counted once per use; returns the cost and the edges in firing order,
+    // None when `start` can't reach `target`
+    pub fn shortest_hyperpath<Q, C, F>(&self, start: &[&Q], target: &Q, cost: F) -> Result<Option<(C, Vec<EdgeIndex>)>, HypergraphError>
+    where N: Borrow<Q>, Q: Hash + Eq + ?Sized, C: Copy + Ord + Zero, F: Fn(&E) -> C {
+        let target = self.node_index(target)?;
+        let mut best: Vec<Option<C>> = vec![None; self.order()];
+        let mut via: Vec<Option<EdgeIndex>> = vec![None; self.order()];
+        let mut done = vec![false; self.order()];
+        // sources each edge still waits on
+        let mut waiting: Vec<usize> = self.edges.iter().map(| (e, _) | e.src.len()).collect();
+        let mut heap = BinaryHeap::new();
+        for n in start {
+            let n = self.node_index(*n)?;
+            best[n] = Some(C::zero());
+            heap.push(Reverse((C::zero(), n)));
+        }
+        let relax = | e: EdgeIndex, best: &mut Vec<Option<C>>, via: &mut Vec<Option<EdgeIndex>>, heap: &mut BinaryHeap<Reverse<(C, NodeIndex)>> | {
+            let (edge, weight) = self.edges.get(e).expect("edge exists");
+            let total = edge.src.iter().fold(cost(weight), | acc, src | acc + best[src.index()].expect("sources are done"));
+            for dst in edge.dst.iter().map(| n | n.index()) {
+                if best[dst].is_none_or(| b | total < b) {
+                    best[dst] = Some(total);
+                    via[dst] = Some(e);
+                    heap.push(Reverse((total, dst)));
+                }
+            }
+        };
+        for e in (0..self.size()).filter(| e | waiting[*e] == 0) {
+            relax(e, &mut best, &mut via, &mut heap);
+        }
+        while let Some(Reverse((c, n))) = heap.pop() {
+            if done[n] || best[n] != Some(c) {
+                continue;
+            }
+            done[n] = true;
+            if n == target {
+                let mut path = Vec::new();
+                self.collect_path(n, &via, &mut path);
+                return Ok(Some((c, path)));
+            }
+            for e in self.nodes[n].neighbors.iter().map(| e | e.index()) {
+                waiting[e] -= 1;
+                if waiting[e] == 0 {
+                    relax(e, &mut best, &mut via, &mut heap);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // a copy holding only the nodes matching `keep`, in their order, and the edges whose endpoints all survive
+    pub fn subgraph<F>(&self, keep: F) -> Self
+    where N: Clone, E: Clone, D: Clone, F: Fn(&N) -> bool {
+        self.filtered(keep, | _ | true)
+    }
+
+    fn filtered<F, G>(&self, keep_node: F, keep_edge: G) -> Self
+    where N: Clone, E: Clone, D: Clone, F: Fn(&N) -> bool, G: Fn(EdgeIndex) -> bool {
+        let mut graph = Self::new();
+        let mut mapping = vec![None; self.order()];
+        for (i, (node, data)) in self.nodes.iter().enumerate() {
+            if keep_node(node) {
+                mapping[i] = Some(graph.insert_node_with(node.clone(), data.data.clone()));
+            }
+        }
+        for (e, (edge, weight)) in self.edges.iter().enumerate() {
+            if !keep_edge(e) {
+                continue;
+            }
+            let nodes = | set: &BTreeSet<Ix> | set.iter().map(| n | mapping[n.index()].map(| _ | self.nodes.get_index(n.index()).expect("node exists").0.clone())).collect::<Option<Vec<N>>>();
+            if let (Some(sources), Some(destinations)) = (nodes(&edge.src), nodes(&edge.dst)) {
+                graph.insert_edge(&sources, &destinations, weight.clone()).expect("edges of a valid graph stay distinct");
+            }
+        }
+        graph
     }
 
-    pub fn neighbor_of(&self, node: &N) -> Result<Vec<&EdgeIndex>, &str> {
-        self.nodes.get(node).ok_or_else(|| "Node does not exist").map(| n | n.neighbor_of.iter().collect())
+    // whether `to` follows from `from` over the edges marked in `kept`, via any one source of each edge
+    fn reaches(&self, from: NodeIndex, to: NodeIndex, kept: &[bool]) -> bool {
+        let mut seen = vec![false; self.order()];
+        let mut pending = vec![from];
+        seen[from] = true;
+        while let Some(n) = pending.pop() {
+            for e in self.nodes[n].neighbors.iter().filter(| e | kept[e.index()]) {
+                for next in self.edges.get(e.index()).expect("indexed edge exists").0.dst.iter().map(| n | n.index()) {
+                    if next == to {
+                        return true;
+                    }
+                    if !seen[next] {
+                        seen[next] = true;
+                        pending.push(next);
+                    }
+                }
+            }
+        }
+        false
     }
 
-    pub fn get_node(&self, n: &NodeIndex) -> Result<&N, &str> {
-        self.nodes.get_index(n.clone()).ok_or_else(|| "Node does not exist").map(| (k, _) | k)
+    // a copy without the edges whose every source already leads to every destination through other
+    // edges, so each node still reaches what it did; later edges go first, parallel copies of an
+    // edge collapsing into the first, and edges without sources always stay
+    pub fn transitive_reduction(&self) -> Self
+    where N: Clone, E: Clone, D: Clone {
+        let mut kept = vec![true; self.size()];
+        for e in (0..self.size()).rev() {
+            let edge = &self.edges.get(e).expect("edge exists").0;
+            if edge.src.is_empty() {
+                continue;
+            }
+            kept[e] = false;
+            let implied = edge.src.iter().all(| src | edge.dst.iter().all(| dst | dst == src || self.reaches(src.index(), dst.index(), &kept)));
+            kept[e] = !implied;
+        }
+        self.filtered(| _ | true, | e | kept[e])
     }
 
-    pub fn get_weight(&self, e: &EdgeIndex) -> Result<&E, &str> {
-        self.edges.get_index(e.clone()).ok_or_else(|| "Edge does not exist").map(| e | &e.weight)
+    // signed node x edge coefficients, `coefficient` giving each endpoint's magnitude from the edge weight:
+    // negative for what an edge consumes, positive for what it makes, netted when a node is both
+    pub fn incidence_matrix<C, F>(&self, coefficient: F) -> Incidence<C>
+    where C: Copy + Zero + Neg<Output=C>, F: Fn(&E, &N, Endpoint) -> C {
+        let mut entries = Vec::new();
+        for (e, (edge, weight)) in self.edges.iter().enumerate() {
+            for n in edge.src.union(&edge.dst) {
+                let node = self.nodes.get_index(n.index()).expect("node exists").0;
+                let mut value = C::zero();
+                if edge.src.contains(n) {
+                    value = value + -coefficient(weight, node, Endpoint::Source);
+                }
+                if edge.dst.contains(n) {
+                    value = value + coefficient(weight, node, Endpoint::Destination);
+                }
+                if !value.is_zero() {
+                    entries.push((n.index(), e, value));
+                }
+            }
+        }
+        Incidence { rows: self.order(), columns: self.size(), entries }
+    }
+
+    // Graphviz source with nodes as boxes and each edge as a diamond joining its sources to its destinations
+    pub fn to_dot<F, G>(&self, node_label: F, edge_label: G) -> String
+    where F: Fn(&N) -> String, G: Fn(&E) -> String {
+        let quote = | label: String | label.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph {\n");
+        for (i, (node, _)) in self.nodes.iter().enumerate() {
+            dot.push_str(&format!("    n{i} [shape=box, label=\"{label}\"];\n", i=i, label=quote(node_label(node))));
+        }
+        for (e, (edge, weight)) in self.edges.iter().enumerate() {
+            dot.push_str(&format!("    e{e} [shape=diamond, label=\"{label}\"];\n", e=e, label=quote(edge_label(weight))));
+            for src in edge.src.iter() {
+                dot.push_str(&format!("    n{src} -> e{e};\n", src=src.index(), e=e));
+            }
+            for dst in edge.dst.iter() {
+                dot.push_str(&format!("    e{e} -> n{dst};\n", e=e, dst=dst.index()));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // every edge after the edges making its sources
+    pub fn topological_edges(&self) -> Result<Vec<EdgeIndex>, HypergraphError> {
+        let mut position = vec![0; self.order()];
+        for (i, n) in self.topological_order()?.into_iter().enumerate() {
+            position[n] = i + 1;
+        }
+        let mut edges: Vec<EdgeIndex> = (0..self.size()).collect();
+        edges.sort_by_key(| e | self.edges.get(*e).expect("edge exists").0.src.iter().map(| n | position[n.index()]).max().unwrap_or(0));
+        Ok(edges)
+    }
+}
+
+impl<N, E, D, Ix> Serialize for Hypergraph<N, E, D, Ix>
+where N: Hash + Eq + Serialize, E: Serialize, D: Serialize, Ix: IndexType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredRef {
+            nodes: self.nodes.iter().map(| (node, n) | StoredNode { node, data: &n.data }).collect(),
+            edges: self.edges.iter().map(| (edge, weight) | StoredEdge { src: edge.src.iter().map(| n | n.index()).collect(), dst: edge.dst.iter().map(| n | n.index()).collect(), weight }).collect()
+        }.serialize(serializer)
+    }
+}
+
+impl<'de, N, E, D, Ix> Deserialize<'de> for Hypergraph<N, E, D, Ix>
+where N: Hash + Eq + Clone + Deserialize<'de>, E: Deserialize<'de>, D: Deserialize<'de>, Ix: IndexType {
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let stored = Stored::<N, E, D>::deserialize(deserializer)?;
+        let mut graph = Self::with_capacity(stored.nodes.len(), stored.edges.len());
+        let mut names = Vec::with_capacity(stored.nodes.len());
+        for StoredNode { node, data } in stored.nodes {
+            names.push(node.clone());
+            if graph.insert_node_with(node, data) + 1 != names.len() {
+                return Err(serde::de::Error::custom("duplicate node"));
+            }
+        }
+        for StoredEdge { src, dst, weight } in stored.edges {
+            let nodes = | indices: &[NodeIndex] | indices.iter().map(| i | names.get(*i).cloned().ok_or_else(|| serde::de::Error::custom(format!("edge refers to missing node {i}", i=i)))).collect::<Result<Vec<N>, De::Error>>();
+            graph.insert_edge(&nodes(&src)?, &nodes(&dst)?, weight).map_err(serde::de::Error::custom)?;
+        }
+        Ok(graph)
     }
 }
 
@@ -102,13 +924,8 @@ mod tests {
 
     fn build_basic_graph() -> Hypergraph<u32, u32> {
         let mut graph = Hypergraph::<u32, u32>::new();
-        graph.insert_node(1);
-        graph.insert_node(2);
-        graph.insert_node(3);
-        graph.insert_node(4);
-        graph.insert_edge(&vec![1, 2], &vec![3, 4], 15);
-        graph.insert_edge(&vec![3], &vec![1], 30);
-        graph.insert_edge(&vec![4], &vec![2], 45);
+        graph.add_nodes(1..=4);
+        graph.add_edges(vec![(vec![1, 2], vec![3, 4], 15), (vec![3], vec![1], 30), (vec![4], vec![2], 45)]).unwrap();
         graph
     }
 
@@ -129,9 +946,340 @@ mod tests {
         let graph = build_basic_graph();
         let neighbors = graph.neighbors(&1u32);
         let neighbor_of = graph.neighbor_of(&1u32);
-        assert_eq!(neighbors, Ok(vec![&0usize]));
-        assert_eq!(neighbor_of, Ok(vec![&1usize]));
-        assert_eq!(graph.get_weight(neighbors.unwrap()[0]), Ok(&15));
-        assert_eq!(graph.get_weight(neighbor_of.unwrap()[0]), Ok(&30));
+        assert_eq!(neighbors, Ok(vec![0]));
+        assert_eq!(neighbor_of, Ok(vec![1]));
+        assert_eq!(graph.get_weight(&neighbors.unwrap()[0]), Ok(&15));
+        assert_eq!(graph.get_weight(&neighbor_of.unwrap()[0]), Ok(&30));
+    }
+
+    #[test]
+    fn remove_test() {
+        let mut graph = build_basic_graph();
+        assert_eq!(graph.remove_edge(&0), Ok(15));
+        assert_eq!(graph.size(), 2);
+        // the last edge moved into the freed index
+        assert_eq!(graph.neighbors(&4u32), Ok(vec![0]));
+        assert_eq!(graph.get_weight(&0), Ok(&45));
+        assert_eq!(graph.neighbors(&1u32), Ok(vec![]));
+
+        assert_eq!(graph.remove_node(&1u32), Ok(vec![30]));
+        assert_eq!(graph.order(), 3);
+        assert_eq!(graph.size(), 1);
+        assert!(graph.neighbors(&1u32).is_err());
+        assert_eq!(graph.neighbor_of(&2u32), Ok(vec![0]));
+        assert_eq!(graph.get_node(&0), Ok(&4));
+    }
+
+    #[test]
+    fn error_test() {
+        let mut graph = build_basic_graph();
+        assert_eq!(graph.insert_unique_edge(&[3], &[1], 60), Err(HypergraphError::DuplicateEdge(1)));
+        assert_eq!(graph.insert_edge(&[5], &[1], 60), Err(HypergraphError::MissingEndpoint(Endpoint::Source, 0)));
+        assert_eq!(graph.insert_edge(&[1], &[2, 6], 60), Err(HypergraphError::MissingEndpoint(Endpoint::Destination, 1)));
+        assert_eq!(graph.get_weight(&3), Err(HypergraphError::EdgeNotFound(3)));
+        assert_eq!(graph.size(), 3);
+    }
+
+    #[test]
+    fn edge_lookup_test() {
+        let graph = build_basic_graph();
+        assert_eq!(graph.find_edge(&[&2u32, &1], &[&4, &3]), Some(0));
+        assert_eq!(graph.find_edge(&[&1u32], &[&3]), None);
+        assert_eq!(graph.find_edge(&[&5u32], &[&3]), None);
+        assert_eq!(graph.edges_between(&1u32, &4), Ok(vec![0]));
+        assert_eq!(graph.edges_between(&3u32, &2), Ok(vec![]));
+        assert_eq!(graph.edges_between(&5u32, &2), Err(HypergraphError::NodeNotFound));
+    }
+
+    #[test]
+    fn topological_test() {
+        let mut graph = Hypergraph::<u32, u32>::new();
+        for n in 1..=4 {
+            graph.insert_node(n);
+        }
+        graph.insert_edge(&[3], &[1], 0).unwrap();
+        graph.insert_edge(&[1, 4], &[2], 1).unwrap();
+        graph.insert_edge(&[3], &[4], 2).unwrap();
+        assert_eq!(graph.topological_order(), Ok(vec![2, 0, 3, 1]));
+        assert_eq!(graph.topological_edges(), Ok(vec![0, 2, 1]));
+
+        // 1 -> 3 -> 1 and 2 -> 4 -> 2 in the basic graph
+        let cyclic = build_basic_graph();
+        assert_eq!(cyclic.topological_order(), Err(HypergraphError::Cycle(vec![0, 2])));
+    }
+
+    #[test]
+    fn component_test() {
+        let mut graph = build_basic_graph();
+        graph.insert_node(5);
+        graph.insert_node(6);
+        graph.insert_edge(&[3], &[5], 60).unwrap();
+        graph.insert_edge(&[6], &[6], 75).unwrap();
+        assert_eq!(graph.strongly_connected_components(), vec![vec![5], vec![0, 1, 2, 3], vec![4]]);
+        assert_eq!(graph.loops(), vec![vec![5], vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn reachability_test() {
+        let mut graph = Hypergraph::<u32, u32>::new();
+        for n in 1..=5 {
+            graph.insert_node(n);
+        }
+        graph.insert_edge(&[1], &[3], 0).unwrap();
+        graph.insert_edge(&[2, 3], &[4], 1).unwrap();
+        graph.insert_edge(&[], &[2], 2).unwrap();
+        graph.insert_edge(&[5], &[1], 3).unwrap();
+        assert_eq!(graph.reachable_from(&[&1u32]), Ok(BTreeSet::from([0, 1, 2, 3])));
+        assert_eq!(graph.reachable_from::<u32>(&[]), Ok(BTreeSet::from([1])));
+        assert_eq!(graph.required_by(&4u32), Ok(BTreeSet::from([0, 1, 2, 4])));
+        assert_eq!(graph.reachable_from(&[&6u32]), Err(HypergraphError::NodeNotFound));
+    }
+
+    #[test]
+    fn hyperpath_test() {
+        let mut graph = Hypergraph::<u32, u32>::new();
+        for n in 1..=5 {
+            graph.insert_node(n);
+        }
+        graph.insert_edge(&[1], &[2], 1).unwrap();
+        graph.insert_edge(&[1], &[3], 5).unwrap();
+        graph.insert_edge(&[2, 3], &[4], 1).unwrap();
+        graph.insert_edge(&[2], &[4], 10).unwrap();
+        assert_eq!(graph.shortest_hyperpath(&[&1u32], &4, | w | *w), Ok(Some((7, vec![0, 1, 2]))));
+        assert_eq!(graph.shortest_hyperpath(&[&1u32], &4, | w | if *w == 5 { 50 } else { *w }), Ok(Some((11, vec![0, 3]))));
+        assert_eq!(graph.shortest_hyperpath(&[&1u32], &5, | w | *w), Ok(None));
+    }
+
+    #[test]
+    fn subgraph_test() {
+        let graph = build_basic_graph();
+        let sub = graph.subgraph(| n | *n != 2);
+        assert_eq!(sub.order(), 3);
+        assert_eq!(sub.size(), 1);
+        assert_eq!(sub.get_node(&2), Ok(&4));
+        assert_eq!(sub.neighbors(&3u32), Ok(vec![0]));
+        assert_eq!(sub.get_weight(&0), Ok(&30));
+    }
+
+    #[test]
+    fn contract_test() {
+        let mut graph = build_basic_graph();
+        graph.insert_node(5);
+        graph.insert_edge(&[5], &[4], 60).unwrap();
+        graph.insert_edge(&[3], &[4], 75).unwrap();
+        // 3 -> 1 and 3 -> 4 become parallel edges once 1 and 4 are one node
+        assert_eq!(graph.contract(&[&1u32, &4]), Ok(0));
+        assert_eq!(graph.order(), 4);
+        assert_eq!(graph.size(), 5);
+        assert_eq!(graph.get_node(&3), Ok(&5));
+        assert_eq!(graph.find_edge(&[&1u32, &2], &[&3, &1]), Some(0));
+        assert_eq!(graph.find_edge(&[&5u32], &[&1]), Some(3));
+        assert_eq!(graph.edges_between(&3u32, &1), Ok(vec![1, 4]));
+        assert_eq!(graph.neighbor_of(&1u32).map(| mut e | { e.sort(); e }), Ok(vec![0, 1, 3, 4]));
+    }
+
+    #[test]
+    fn incidence_test() {
+        let mut graph = build_basic_graph();
+        graph.insert_edge(&[2], &[2], 0).unwrap();
+        let matrix = graph.incidence_matrix(| w, n, end | match end {
+            Endpoint::Source => *w as i64 + *n as i64,
+            Endpoint::Destination => *w as i64
+        });
+        assert_eq!(matrix.rows, 4);
+        assert_eq!(matrix.columns, 4);
+        assert_eq!(matrix.entries, vec![(0, 0, -16), (1, 0, -17), (2, 0, 15), (3, 0, 15), (0, 1, 30), (2, 1, -33), (1, 2, 45), (3, 2, -49), (1, 3, -2)]);
+    }
+
+    #[test]
+    fn serde_test() {
+        let graph = build_basic_graph();
+        let json = serde_json::to_string(&graph).unwrap();
+        let restored: Hypergraph<u32, u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.order(), 4);
+        assert_eq!(restored.size(), 3);
+        assert_eq!(restored.get_node(&3), Ok(&4));
+        assert_eq!(restored.find_edge(&[&1u32, &2], &[&3, &4]), Some(0));
+        assert_eq!(restored.get_weight(&2), Ok(&45));
+        assert_eq!(restored.neighbor_of(&1u32), Ok(vec![1]));
+
+        let broken = r#"{"nodes":[{"node":1,"data":null}],"edges":[{"src":[0],"dst":[1],"weight":5}]}"#;
+        assert!(serde_json::from_str::<Hypergraph<u32, u32>>(broken).is_err());
+    }
+
+    #[test]
+    fn dot_test() {
+        let mut graph = Hypergraph::<u32, &str>::new();
+        graph.insert_node(1);
+        graph.insert_node(2);
+        graph.insert_edge(&[1], &[2], "say \"hi\"").unwrap();
+        assert_eq!(graph.to_dot(| n | n.to_string(), | w | w.to_string()), "digraph {\n    n0 [shape=box, label=\"1\"];\n    n1 [shape=box, label=\"2\"];\n    \
+            e0 [shape=diamond, label=\"say \\\"hi\\\"\"];\n    n0 -> e0;\n    e0 -> n1;\n}\n");
+    }
+
+    #[test]
+    fn parallel_edge_test() {
+        let mut graph = build_basic_graph();
+        assert_eq!(graph.insert_edge(&[3], &[1], 60), Ok(3));
+        assert_eq!(graph.edges_between(&3u32, &1), Ok(vec![1, 3]));
+        assert_eq!(graph.remove_edge(&1), Ok(30));
+        assert_eq!(graph.edges_between(&3u32, &1), Ok(vec![1]));
+        assert_eq!(graph.get_weight(&1), Ok(&60));
+    }
+
+    #[test]
+    fn statistics_test() {
+        let mut graph = build_basic_graph();
+        graph.insert_node(5);
+        graph.insert_node(6);
+        graph.insert_edge(&[5], &[2], 60).unwrap();
+        assert_eq!(graph.in_degree(&2u32), Ok(2));
+        assert_eq!(graph.out_degree(&1u32), Ok(1));
+        assert_eq!(graph.cardinality(&0), Ok((2, 2)));
+        assert_eq!(graph.statistics(), Statistics {
+            nodes: 6,
+            edges: 4,
+            sources: vec![4],
+            sinks: vec![],
+            isolated: vec![5],
+            max_in_degree: 2,
+            max_out_degree: 1,
+            cardinalities: BTreeMap::from([((1, 1), 3), ((2, 2), 1)])
+        });
+    }
+
+    #[test]
+    fn mutation_test() {
+        let mut graph = Hypergraph::<u32, u32, Vec<u32>>::new();
+        graph.insert_node(1);
+        graph.insert_node_with(2, vec![7]);
+        let e = graph.insert_edge(&[1], &[2], 15).unwrap();
+        *graph.get_weight_mut(&e).unwrap() += 1;
+        graph.get_data_mut(&1).unwrap().push(3);
+        assert_eq!(graph.get_weight(&e), Ok(&16));
+        assert_eq!(graph.get_data(&1), Ok(&vec![3]));
+        assert_eq!(graph.get_data(&2), Ok(&vec![7]));
+        assert_eq!(graph.get_weight_mut(&1), Err(HypergraphError::EdgeNotFound(1)));
+    }
+
+    #[test]
+    fn transitive_reduction_test() {
+        let mut graph = Hypergraph::<u32, u32>::new();
+        for n in 1..=4 {
+            graph.insert_node(n);
+        }
+        graph.insert_edge(&[1], &[2], 10).unwrap();
+        graph.insert_edge(&[2], &[3], 20).unwrap();
+        // a shortcut the chain already covers, and a duplicate of the first edge
+        graph.insert_edge(&[1], &[3], 30).unwrap();
+        graph.insert_edge(&[1], &[2], 40).unwrap();
+        // 4 only comes from 1 and 3 together, so this stays
+        graph.insert_edge(&[1, 3], &[4], 50).unwrap();
+        let reduced = graph.transitive_reduction();
+        assert_eq!(reduced.order(), 4);
+        assert_eq!(reduced.size(), 3);
+        assert_eq!(reduced.find_edge(&[&1], &[&3]), None);
+        assert_eq!(reduced.edges_between(&1, &2).map(| e | e.len()), Ok(1));
+        assert_eq!(reduced.get_weight(&reduced.edges_between(&1, &2).unwrap()[0]), Ok(&10));
+        assert_eq!(reduced.reachable_from(&[&1]), graph.reachable_from(&[&1]));
+    }
+
+    #[test]
+    fn compact_index_test() {
+        let mut graph = Hypergraph::<u32, u32, (), u32>::with_capacity(4, 3);
+        for n in 1..=4 {
+            graph.insert_node(n);
+        }
+        graph.insert_edge(&[1, 2], &[3, 4], 15).unwrap();
+        graph.insert_edge(&[3], &[1], 30).unwrap();
+        graph.insert_edge(&[4], &[2], 45).unwrap();
+        assert_eq!(graph.neighbors(&1), Ok(vec![0]));
+        assert_eq!(graph.remove_edge(&0), Ok(15));
+        assert_eq!(graph.neighbors(&4), Ok(vec![0]));
+        assert_eq!(graph.topological_order(), Ok(vec![2, 0, 3, 1]));
+    }
+
+    // records the hooks as they fire, stopping once it discovers `stop_at` and pruning at `prune_at`
+    struct Recorder {
+        events: Vec<String>,
+        stop_at: Option<u32>,
+        prune_at: Option<u32>
+    }
+
+    impl Recorder {
+        fn new(stop_at: Option<u32>, prune_at: Option<u32>) -> Self {
+            Recorder { events: Vec::new(), stop_at, prune_at }
+        }
+    }
+
+    impl Visitor<u32, u32> for Recorder {
+        fn discover_node(&mut self, _n: NodeIndex, node: &u32) -> Control {
+            self.events.push(format!("n{}", node));
+            if Some(*node) == self.stop_at {
+                Control::Stop
+            } else if Some(*node) == self.prune_at {
+                Control::Prune
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn finish_node(&mut self, _n: NodeIndex, node: &u32) {
+            self.events.push(format!("/n{}", node));
+        }
+
+        fn examine_edge(&mut self, e: EdgeIndex, _weight: &u32) -> Control {
+            self.events.push(format!("e{}", e));
+            Control::Continue
+        }
+
+        fn finish_edge(&mut self, e: EdgeIndex, _weight: &u32) {
+            self.events.push(format!("/e{}", e));
+        }
+    }
+
+    #[test]
+    fn depth_first_test() {
+        let graph = build_basic_graph();
+        let mut recorder = Recorder::new(None, None);
+        graph.depth_first(&[&1], Direction::Forward, &mut recorder).unwrap();
+        assert_eq!(recorder.events.join(" "), "n1 e0 n3 e1 /e1 /n3 n4 e2 n2 /n2 /e2 /n4 /e0 /n1");
+        let mut recorder = Recorder::new(Some(4), None);
+        graph.depth_first(&[&1], Direction::Forward, &mut recorder).unwrap();
+        assert_eq!(recorder.events.join(" "), "n1 e0 n3 e1 /e1 /n3 n4");
+        assert!(graph.depth_first(&[&9], Direction::Forward, &mut recorder).is_err());
+    }
+
+    #[test]
+    fn breadth_first_test() {
+        let graph = build_basic_graph();
+        let mut recorder = Recorder::new(None, None);
+        graph.breadth_first(&[&2], Direction::Backward, &mut recorder).unwrap();
+        assert_eq!(recorder.events.join(" "), "n2 e2 n4 /e2 /n2 e0 n1 /e0 /n4 e1 n3 /e1 /n1 /n3");
+        let mut recorder = Recorder::new(None, Some(4));
+        graph.breadth_first(&[&2], Direction::Backward, &mut recorder).unwrap();
+        assert_eq!(recorder.events.join(" "), "n2 e2 n4 /n4 /e2 /n2");
+    }
+
+    #[test]
+    fn bulk_insert_test() {
+        let mut graph = Hypergraph::<u32, u32>::new();
+        assert_eq!(graph.add_nodes(vec![1, 2, 1]), vec![0, 1, 0]);
+        assert_eq!(graph.add_edges([([1], [2], 10), ([2], [1], 20)]), Ok(vec![0, 1]));
+        assert_eq!(graph.add_edges([([1], [2], 30), ([3], [1], 40)]), Err(HypergraphError::MissingEndpoint(Endpoint::Source, 0)));
+        assert_eq!(graph.size(), 3);
+    }
+
+    #[test]
+    fn borrowed_lookup_test() {
+        let mut graph = Hypergraph::<String, u32>::new();
+        graph.insert_node("ore".to_owned());
+        graph.insert_node("ingot".to_owned());
+        graph.insert_edge(&["ore".to_owned()], &["ingot".to_owned()], 1).unwrap();
+        assert!(graph.contains_node("ore"));
+        assert_eq!(graph.node_index("ingot"), Ok(1));
+        assert_eq!(graph.neighbor_of("ingot"), Ok(vec![0]));
+        assert_eq!(graph.remove_node("ore"), Ok(vec![1]));
+        assert!(!graph.contains_node("ore"));
     }
 }
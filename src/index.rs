@@ -0,0 +1,325 @@
+use indexmap::IndexSet;
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::simulate::Distribution;
+use crate::version::GameVersion;
+use crate::{checked_ratio, Builder, Cookbook, Extractor, Generator, Reagent, Recipe, Transport, Widget};
+
+// starts every compiled index; game definitions in YAML can't, as it isn't valid UTF-8
+pub const MAGIC: &[u8; 4] = b"SS\xffI";
+// bumped whenever an index stops reading back the same
+const FORMAT: u32 = 7;
+
+// every name is written once, in a table ahead of the body that refers to them by position
+#[derive(Default)]
+struct Writer {
+    strings: IndexSet<String>,
+    body: Vec<u8>
+}
+
+impl Writer {
+    fn u32(&mut self, n: u32) {
+        self.body.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn len(&mut self, n: usize) {
+        self.u32(n as u32);
+    }
+
+    fn flag(&mut self, set: bool) {
+        self.body.push(set as u8);
+    }
+
+    fn text(&mut self, text: &str) {
+        let (at, _) = self.strings.insert_full(text.to_owned());
+        self.len(at);
+    }
+
+    fn rational(&mut self, r: &Rational64) {
+        self.body.extend_from_slice(&r.numer().to_le_bytes());
+        self.body.extend_from_slice(&r.denom().to_le_bytes());
+    }
+
+    fn optional<T>(&mut self, value: Option<&T>, write: impl Fn(&mut Self, &T)) {
+        self.flag(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+
+    fn reagents(&mut self, reagents: &[Reagent]) {
+        self.len(reagents.len());
+        for reagent in reagents {
+            self.text(&reagent.widget);
+            self.rational(&reagent.quantity);
+        }
+    }
+
+    fn transports(&mut self, tiers: &[Transport]) {
+        self.len(tiers.len());
+        for tier in tiers {
+            self.text(&tier.name);
+            self.rational(&tier.rate);
+        }
+    }
+
+    fn builder(&mut self, builder: &Builder) {
+        self.text(&builder.name);
+        for value in [&builder.power, &builder.min_clock, &builder.max_clock] {
+            self.optional(value.as_ref(), Self::rational);
+        }
+        self.optional(builder.power_exponent.as_ref(), | w, e | w.body.extend_from_slice(&e.to_bits().to_le_bytes()));
+        for value in [&builder.speed, &builder.productivity, &builder.width, &builder.length, &builder.operating_cost] {
+            self.optional(value.as_ref(), Self::rational);
+        }
+        self.reagents(&builder.consumes);
+    }
+
+    fn recipe(&mut self, recipe: &Recipe) {
+        self.text(&recipe.name);
+        self.optional(recipe.builder.as_ref(), | w, b | w.text(b));
+        self.flag(recipe.manual);
+        self.rational(&recipe.duration);
+        self.reagents(&recipe.products);
+        self.reagents(&recipe.reagents);
+        self.u32(recipe.tier);
+        self.flag(recipe.alternate);
+        self.optional(recipe.category.as_ref(), | w, c | w.text(c));
+        for value in [&recipe.power, &recipe.speed, &recipe.productivity] {
+            self.optional(value.as_ref(), Self::rational);
+        }
+        for spread in [&recipe.duration_spread, &recipe.yield_spread] {
+            self.optional(spread.map(| s | s.to_string()).as_ref(), | w, s | w.text(s));
+        }
+        for version in [&recipe.since, &recipe.deprecated] {
+            self.optional(version.as_ref().map(| v | v.to_string()).as_ref(), | w, v | w.text(v));
+        }
+    }
+}
+
+/// A compact binary form of a game definition: names interned in one table, numbers as fixed-width
+/// integers. It loads without any text parsing, which dominates for large modded datasets; recipes
+/// get their builders resolved and the graph is built when loading, as for YAML. The graph's adjacency
+/// isn't stored: it is one pass over the recipes, and merged or namespaced cookbooks rebuild it anyway.
+pub fn compile(cookbook: &Cookbook) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.optional(cookbook.namespace.as_ref(), | w, n | w.text(n));
+    writer.len(cookbook.widgets.len());
+    for widget in cookbook.widgets.iter() {
+        writer.text(widget.name());
+        writer.optional(matches!(widget, Widget::Detailed { .. }).then_some(widget), | w, widget | {
+            w.flag(widget.is_fluid());
+            w.optional(widget.price().as_ref(), Writer::rational);
+            w.optional(widget.sink().as_ref(), Writer::rational);
+        });
+    }
+    writer.len(cookbook.builders.len());
+    for builder in cookbook.builders.iter() {
+        writer.builder(builder);
+    }
+    writer.transports(&cookbook.belts);
+    writer.transports(&cookbook.pipes);
+    writer.len(cookbook.extractors.len());
+    for extractor in cookbook.extractors.iter() {
+        writer.text(&extractor.name);
+        writer.len(extractor.resources.len());
+        for resource in extractor.resources.iter() {
+            writer.text(resource);
+        }
+        writer.rational(&extractor.rate);
+        writer.u32(extractor.tier);
+    }
+    writer.len(cookbook.purities.len());
+    for (purity, multiplier) in cookbook.purities.iter() {
+        writer.text(purity);
+        writer.rational(multiplier);
+    }
+    writer.len(cookbook.generators.len());
+    for generator in cookbook.generators.iter() {
+        writer.text(&generator.name);
+        writer.rational(&generator.power);
+        writer.reagents(&generator.fuel);
+    }
+    writer.len(cookbook.recipes.len());
+    for recipe in cookbook.recipes.iter() {
+        writer.recipe(recipe);
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT.to_le_bytes());
+    bytes.extend_from_slice(&(writer.strings.len() as u32).to_le_bytes());
+    for text in writer.strings.iter() {
+        bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(text.as_bytes());
+    }
+    bytes.extend_from_slice(&writer.body);
+    bytes
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    at: usize,
+    strings: Vec<&'b str>
+}
+
+// what went wrong reading an index, for the error naming the file
+type Read<T> = std::result::Result<T, String>;
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, n: usize) -> Read<&'b [u8]> {
+        let taken = self.bytes.get(self.at..self.at + n).ok_or_else(|| format!("truncated at byte {}", self.at))?;
+        self.at += n;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Read<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("took 4 bytes")))
+    }
+
+    fn i64(&mut self) -> Read<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().expect("took 8 bytes")))
+    }
+
+    fn len(&mut self) -> Read<usize> {
+        self.u32().map(| n | n as usize)
+    }
+
+    fn flag(&mut self) -> Read<bool> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(format!("bad flag {} at byte {}", other, self.at - 1))
+        }
+    }
+
+    fn text(&mut self) -> Read<String> {
+        let at = self.len()?;
+        self.strings.get(at).map(| s | (*s).to_owned()).ok_or_else(|| format!("string {} is past the table", at))
+    }
+
+    fn spread(&mut self) -> Read<Distribution> {
+        let text = self.text()?;
+        text.parse().map_err(| e | format!("bad spread `{}`: {}", text, e))
+    }
+
+    fn version(&mut self) -> Read<GameVersion> {
+        let text = self.text()?;
+        text.parse()
+    }
+
+    fn rational(&mut self) -> Read<Rational64> {
+        let (numer, denom) = (self.i64()?, self.i64()?);
+        checked_ratio(numer, denom).ok_or_else(|| format!("bad ratio {}/{} at byte {}", numer, denom, self.at - 16))
+    }
+
+    fn optional<T>(&mut self, read: impl Fn(&mut Self) -> Read<T>) -> Read<Option<T>> {
+        if self.flag()? { read(self).map(Some) } else { Ok(None) }
+    }
+
+    fn list<T>(&mut self, read: impl Fn(&mut Self) -> Read<T>) -> Read<Vec<T>> {
+        (0..self.len()?).map(| _ | read(self)).collect()
+    }
+
+    fn reagents(&mut self) -> Read<Vec<Reagent>> {
+        self.list(| r | Ok(Reagent { widget: r.text()?, quantity: r.rational()? }))
+    }
+
+    fn transports(&mut self) -> Read<Vec<Transport>> {
+        self.list(| r | Ok(Transport { name: r.text()?, rate: r.rational()? }))
+    }
+
+    fn builder(&mut self) -> Read<Builder> {
+        Ok(Builder {
+            name: self.text()?,
+            power: self.optional(Self::rational)?,
+            min_clock: self.optional(Self::rational)?,
+            max_clock: self.optional(Self::rational)?,
+            power_exponent: self.optional(| r | Ok(f64::from_bits(r.i64()? as u64)))?,
+            speed: self.optional(Self::rational)?,
+            productivity: self.optional(Self::rational)?,
+            width: self.optional(Self::rational)?,
+            length: self.optional(Self::rational)?,
+            operating_cost: self.optional(Self::rational)?,
+            consumes: self.reagents()?
+        })
+    }
+
+    fn recipe(&mut self) -> Read<Recipe> {
+        Ok(Recipe {
+            name: self.text()?,
+            builder: self.optional(Self::text)?,
+            manual: self.flag()?,
+            duration: self.rational()?,
+            products: self.reagents()?,
+            reagents: self.reagents()?,
+            tier: self.u32()?,
+            alternate: self.flag()?,
+            category: self.optional(Self::text)?,
+            power: self.optional(Self::rational)?,
+            speed: self.optional(Self::rational)?,
+            productivity: self.optional(Self::rational)?,
+            duration_spread: self.optional(Self::spread)?,
+            yield_spread: self.optional(Self::spread)?,
+            since: self.optional(Self::version)?,
+            deprecated: self.optional(Self::version)?,
+            machine: None
+        })
+    }
+}
+
+/// Reads back what [`compile`] wrote, the message saying where it stops making sense.
+pub fn load(bytes: &[u8]) -> Read<Cookbook> {
+    let mut reader = Reader { bytes, at: 0, strings: Vec::new() };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err("not a compiled game definition".to_owned());
+    }
+    let format = reader.u32()?;
+    if format != FORMAT {
+        return Err(format!("index format {found}, this build reads format {FORMAT}", found=format, FORMAT=FORMAT));
+    }
+    for _ in 0..reader.len()? {
+        let length = reader.len()?;
+        let text = std::str::from_utf8(reader.take(length)?).map_err(| e | format!("bad string at byte {}: {}", reader.at - length, e))?;
+        reader.strings.push(text);
+    }
+    let namespace = reader.optional(Reader::text)?;
+    let widgets = reader.list(| r | {
+        let name = r.text()?;
+        Ok(match r.optional(| r | Ok((r.flag()?, r.optional(Reader::rational)?, r.optional(Reader::rational)?)))? {
+            Some((fluid, price, sink)) => Widget::Detailed { name, fluid, price, sink },
+            None => Widget::Item(name)
+        })
+    })?;
+    let builders = reader.list(Reader::builder)?;
+    let (belts, pipes) = (reader.transports()?, reader.transports()?);
+    let extractors = reader.list(| r | Ok(Extractor { name: r.text()?, resources: r.list(Reader::text)?, rate: r.rational()?, tier: r.u32()? }))?;
+    let purities: BTreeMap<String, Rational64> = reader.list(| r | Ok((r.text()?, r.rational()?)))?.into_iter().collect();
+    let generators = reader.list(| r | Ok(Generator { name: r.text()?, power: r.rational()?, fuel: r.reagents()? }))?;
+    let recipes = reader.list(Reader::recipe)?;
+    if reader.at != bytes.len() {
+        return Err(format!("{} bytes left over", bytes.len() - reader.at));
+    }
+    Ok(Cookbook { namespace, widgets, builders, belts, pipes, extractors, purities, generators, recipes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let cookbook = crate::load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let bytes = compile(&cookbook);
+        let loaded = load(&bytes).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", cookbook));
+        assert!(load(&bytes[..bytes.len() - 1]).unwrap_err().contains("truncated"));
+        assert!(load(b"widgets: []").is_err());
+        // a corrupt ratio is an error like any other, not an overflow
+        for (numer, denom) in [(1i64, 0i64), (i64::MIN, -1)] {
+            let bytes: Vec<u8> = [numer.to_le_bytes(), denom.to_le_bytes()].concat();
+            let mut reader = Reader { bytes: &bytes, at: 0, strings: Vec::new() };
+            assert!(reader.rational().unwrap_err().starts_with("bad ratio"));
+        }
+    }
+}
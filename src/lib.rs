@@ -0,0 +1,3194 @@
+//! Plans factories for games like Satisfactory: load a game definition with [`load_recipes`], then [`solve`]
+//! for a widget at a rate to get the [`Plan`] of machines that makes it.
+
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Signed};
+use num::Rational64;
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+pub mod analyze;
+pub mod batch;
+pub mod bill;
+pub mod blueprint;
+pub mod cache;
+pub mod catalog;
+pub mod check;
+pub mod chokepoints;
+pub mod config;
+pub mod cost;
+pub mod datadiff;
+pub mod datasets;
+pub mod explore;
+pub mod index;
+pub mod msgpack;
+pub mod namespace;
+pub mod phases;
+pub mod network;
+pub mod optimize;
+pub mod players;
+pub mod ramp;
+pub mod repl;
+pub mod requests;
+pub mod saved;
+pub mod schema;
+pub mod serve;
+pub mod share;
+pub mod simulate;
+pub mod surplus;
+pub mod svg;
+pub mod template;
+pub mod unlocks;
+pub mod validate;
+pub mod version;
+pub mod sites;
+
+pub mod error;
+
+// a C interface over opaque cookbook and plan handles, for embedding in mods and other languages
+#[cfg(feature = "ffi")]
+pub mod ffi;
+// a Node-API addon over the same plans, for JavaScript planners; see node/index.js
+#[cfg(feature = "node")]
+pub mod node;
+use crate::error::{Error, Result};
+
+pub mod tree;
+use crate::tree::NTree;
+
+pub mod hypergraph;
+use crate::hypergraph::Hypergraph;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Reagent {
+    pub widget: String,
+    #[serde(deserialize_with="deserialize_rational")]
+    pub quantity: Rational64
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Recipe {
+    pub name: String,
+    // recipes without a builder are hand-crafted
+    #[serde(default)]
+    pub builder: Option<String>,
+    #[serde(default)]
+    pub manual: bool,
+    // in seconds, "1.5min" and the like in the game definition
+    #[serde(deserialize_with="deserialize_duration")]
+    pub duration: Rational64,
+    pub products: Vec<Reagent>,
+    pub reagents: Vec<Reagent>,
+    // unlock tier/milestone, recipes without one are always available
+    #[serde(default)]
+    pub tier: u32,
+    // alternate recipes stay locked until unlocked by name
+    #[serde(default)]
+    pub alternate: bool,
+    // the production method, such as smelting or refining, for plans to be restricted to some
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub category: Option<String>,
+    // in MW per machine, defaults to the builder's
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    pub power: Option<Rational64>,
+    // module bonuses as fractions, defaulting to the builder's
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    pub speed: Option<Rational64>,
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    pub productivity: Option<Rational64>,
+    // what each craft's time and yield are multiplied by in simulations, such as "normal(0.1)", fixed when unset
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub duration_spread: Option<simulate::Distribution>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub yield_spread: Option<simulate::Distribution>,
+    // the game version adding it and the one taking it out, for a file covering several versions
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub since: Option<version::GameVersion>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub deprecated: Option<version::GameVersion>,
+    // resolved from the builders section when the graph is built, saved plans carry it along
+    #[serde(default)]
+    pub machine: Option<Builder>
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Integer(i64),
+    Decimal(f64),
+    Text(String),
+    // [numer, denom], as saved plans write them
+    Ratio(i64, i64)
+}
+
+/// The exact value of a decimal such as `1.5`, `-0.1` or `2.5e-3`, None when it isn't one or doesn't
+/// fit a 64-bit rational.
+pub fn parse_decimal(text: &str) -> Option<Rational64> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], text[at + 1..].parse::<i32>().ok()?),
+        None => (text, 0)
+    };
+    let (negative, digits) = match mantissa.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa))
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() || !whole.chars().chain(fraction.chars()).all(| c | c.is_ascii_digit()) {
+        return None;
+    }
+    let mut numer: i64 = 0;
+    for digit in whole.chars().chain(fraction.chars()) {
+        numer = numer.checked_mul(10)?.checked_add(digit.to_digit(10)? as i64)?;
+    }
+    let scale = exponent - fraction.len() as i32;
+    let power = 10i64.checked_pow(scale.unsigned_abs())?;
+    let value = if scale >= 0 { Rational64::from_integer(numer.checked_mul(power)?) } else { Rational64::new(numer, power) };
+    Some(if negative { -value } else { value })
+}
+
+// a decimal or a "numer/denom" ratio
+// `numer`/`denom`, None for a zero denominator or a negative one whose sign can't move to the numerator
+pub fn checked_ratio(numer: i64, denom: i64) -> Option<Rational64> {
+    match denom {
+        0 => None,
+        d if d < 0 => Some(Rational64::new(numer.checked_neg()?, d.checked_neg()?)),
+        d => Some(Rational64::new(numer, d))
+    }
+}
+
+fn parse_number(text: &str) -> Option<Rational64> {
+    match text.split_once('/') {
+        Some((numer, denom)) => checked_ratio(numer.trim().parse::<i64>().ok()?, denom.trim().parse::<i64>().ok()?),
+        None => parse_decimal(text)
+    }
+}
+
+// seconds in a unit of time, as rates and durations spell them
+fn seconds(unit: &str) -> Option<i64> {
+    match unit {
+        "s" | "sec" | "second" => Some(1),
+        "m" | "min" | "minute" => Some(60),
+        "h" | "hr" | "hour" => Some(3600),
+        _ => None
+    }
+}
+
+/// A positive rate in units/second from a number per second such as `2.5` or `1/3`, or one per unit
+/// of time such as `45/min`, `2.5/s` or `600/h`.
+pub fn parse_rate(text: &str) -> Result<Rational64> {
+    let bad = || Error::Usage(format!("rate `{}` is not a positive number like 45/min, 2.5/s or 1/3", text));
+    let rate = match text.rsplit_once('/').and_then(| (amount, unit) | Some((amount, seconds(unit.trim())?))) {
+        Some((amount, seconds)) => parse_number(amount.trim()).map(| amount | amount / Rational64::from_integer(seconds)),
+        None => parse_number(text.trim())
+    };
+    rate.filter(| r | *r > Rational64::from_integer(0)).ok_or_else(bad)
+}
+
+// seconds from a number of them, or one suffixed with a unit such as `1.5min`, `90 s` or `1/3 h`
+fn parse_duration(text: &str) -> Option<Rational64> {
+    let text = text.trim();
+    let number = text.trim_end_matches(| c: char | c.is_ascii_alphabetic());
+    let unit = match &text[number.len()..] {
+        "" => 1,
+        unit => seconds(unit)?
+    };
+    parse_number(number.trim())?.checked_mul(&Rational64::from_integer(unit))
+}
+
+impl Number {
+    // accepts integers, decimals, "numer/denom" strings and [numer, denom] pairs, decimals exactly: YAML
+    // floats by the shortest decimal that reads back as the same float, which is what was written
+    fn into_rational<E: serde::de::Error>(self) -> std::result::Result<Rational64, E> {
+        let decimal = | text: &str | parse_decimal(text).ok_or_else(|| E::custom(format!("Bad number `{}`", text)));
+        match self {
+            Number::Ratio(_, 0) => Err(E::custom("Zero denominator")),
+            Number::Ratio(numer, denom) => checked_ratio(numer, denom).ok_or_else(|| E::custom(format!("Bad number `[{}, {}]`", numer, denom))),
+            Number::Integer(i) => Ok(Rational64::from_integer(i)),
+            Number::Decimal(f) if f.is_finite() => decimal(&f.to_string()),
+            Number::Decimal(f) => Err(E::custom(format!("Bad number `{}`", f))),
+            Number::Text(s) => match s.trim().split_once('/') {
+                Some(_) => parse_number(s.trim()).ok_or_else(|| E::custom(format!("Bad number `{}`", s.trim()))),
+                None => decimal(s.trim())
+            }
+        }
+    }
+}
+
+fn deserialize_rational<'de, D>(deserializer: D) -> std::result::Result<Rational64, D::Error> where D: Deserializer<'de> {
+    Number::deserialize(deserializer)?.into_rational()
+}
+
+// like deserialize_rational, strings also taking a unit of time
+fn deserialize_duration<'de, D>(deserializer: D) -> std::result::Result<Rational64, D::Error> where D: Deserializer<'de> {
+    match Number::deserialize(deserializer)? {
+        Number::Text(text) => parse_duration(&text).ok_or_else(|| serde::de::Error::custom(format!("Bad duration `{}`", text))),
+        number => number.into_rational()
+    }
+}
+
+fn deserialize_rational_map<'de, D>(deserializer: D) -> std::result::Result<BTreeMap<String, Rational64>, D::Error> where D: Deserializer<'de> {
+    BTreeMap::<String, Number>::deserialize(deserializer)?.into_iter().map(| (k, v) | Ok((k, v.into_rational()?))).collect()
+}
+
+fn deserialize_optional_duration<'de, D>(deserializer: D) -> std::result::Result<Option<Rational64>, D::Error> where D: Deserializer<'de> {
+    #[derive(Deserialize)]
+    struct Duration(#[serde(deserialize_with="deserialize_duration")] Rational64);
+    Ok(Option::<Duration>::deserialize(deserializer)?.map(| Duration(d) | d))
+}
+
+fn deserialize_optional_rational<'de, D>(deserializer: D) -> std::result::Result<Option<Rational64>, D::Error> where D: Deserializer<'de> {
+    Option::<Number>::deserialize(deserializer)?.map(Number::into_rational).transpose()
+}
+
+impl Recipe {
+    // units/second
+    pub fn rate(&self, widget: &String) -> Result<Rational64> {
+        let reagent = self.products.iter().find(| r | widget == &r.widget).ok_or_else(|| Error::UnknownWidget(widget.clone()))?;
+        if self.duration <= Rational64::from_integer(0) {
+            return Err(Error::Arithmetic(format!("recipe `{name}` has a non-positive duration", name=self.name)));
+        }
+        reagent.quantity.checked_mul(&self.speed()).and_then(| r | r.checked_mul(&self.productivity())).and_then(| r | r.checked_div(&self.duration))
+            .ok_or_else(|| Error::Arithmetic(format!("the rate of `{widget}` from recipe `{name}` overflows 64-bit rationals", widget=widget, name=self.name)))
+    }
+
+    // units/second of a reagent consumed by one machine at 100% clock
+    pub fn consumption(&self, reagent: &Reagent) -> Result<Rational64> {
+        self.drawn(reagent, Rational64::from_integer(1), Rational64::from_integer(1))
+    }
+
+    // units/second of a reagent `count` machines at `clock` draw, as planning sizes the stage making it
+    fn drawn(&self, reagent: &Reagent, count: Rational64, clock: Rational64) -> Result<Rational64> {
+        reagent.quantity.checked_mul(&self.speed()).and_then(| r | r.checked_div(&self.duration))
+            .and_then(| r | r.checked_mul(&count)).and_then(| r | r.checked_mul(&clock))
+            .ok_or_else(|| Error::Arithmetic(format!("the `{widget}` drawn by {count}x recipe `{name}` overflows 64-bit rationals", widget=reagent.widget, count=count, name=self.name)))
+    }
+
+    pub fn speed(&self) -> Rational64 {
+        Rational64::from_integer(1) + self.speed.or_else(|| self.machine.as_ref().and_then(| m | m.speed)).unwrap_or_else(|| Rational64::from_integer(0))
+    }
+
+    pub fn productivity(&self) -> Rational64 {
+        Rational64::from_integer(1) + self.productivity.or_else(|| self.machine.as_ref().and_then(| m | m.productivity)).unwrap_or_else(|| Rational64::from_integer(0))
+    }
+
+    // reagents and products per craft, e.g. "6 iron-plate + 12 screw -> 1 reinforced-iron-plate"
+    pub fn formula(&self) -> String {
+        let side = | reagents: &[Reagent] | reagents.iter().map(| r | format!("{} {}", r.quantity, r.widget)).collect::<Vec<_>>().join(" + ");
+        format!("{reagents} -> {products}", reagents=side(&self.reagents), products=side(&self.products))
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.manual || self.builder.is_none()
+    }
+
+    pub fn builder_name(&self) -> &str {
+        self.builder.as_deref().unwrap_or("Handcraft")
+    }
+
+    // MW per machine running at `clock`
+    pub fn power_at(&self, clock: Rational64) -> Option<Rational64> {
+        let base = self.power.or_else(|| self.machine.as_ref().and_then(| m | m.power))?;
+        match self.machine.as_ref().and_then(| m | m.power_exponent) {
+            // rounded to kW so sums of curve values stay representable
+            Some(exponent) => Some(Rational64::new((as_f64(&base) * as_f64(&clock).powf(exponent) * 1000.0).round() as i64, 1000)),
+            None => Some(base * clock)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Builder {
+    name: String,
+    // in MW per machine
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    power: Option<Rational64>,
+    // supported clock speeds, in percent
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    min_clock: Option<Rational64>,
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    max_clock: Option<Rational64>,
+    // power draw scales with clock^power_exponent, linearly when unset
+    #[serde(default)]
+    power_exponent: Option<f64>,
+    // module/beacon bonuses as fractions, e.g. 0.5 for +50%
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    speed: Option<Rational64>,
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    productivity: Option<Rational64>,
+    // footprint in meters
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    width: Option<Rational64>,
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    length: Option<Rational64>,
+    // per machine and second of running, in the currency of widget prices
+    #[serde(default, deserialize_with="deserialize_optional_rational")]
+    operating_cost: Option<Rational64>,
+    // units/second every machine built uses up whatever it crafts: fuel, maintenance parts
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    consumes: Vec<Reagent>
+}
+
+impl Builder {
+    pub fn area(&self) -> Option<Rational64> {
+        Some(self.width? * self.length?)
+    }
+}
+
+impl Recipe {
+    // per machine-second, free for hand-crafting and builders without one
+    pub fn operating_cost(&self) -> Rational64 {
+        self.machine.as_ref().and_then(| m | m.operating_cost).unwrap_or_else(|| Rational64::from_integer(0))
+    }
+}
+
+// a bare name declares a solid item
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Widget {
+    Item(String),
+    Detailed {
+        name: String,
+        // fluids are measured in m³ and moved through pipes
+        #[serde(default)]
+        fluid: bool,
+        // market price per unit, for buying instead of crafting
+        #[serde(default, deserialize_with="deserialize_optional_rational")]
+        price: Option<Rational64>,
+        // points per unit fed to a sink, for surplus worth something
+        #[serde(default, deserialize_with="deserialize_optional_rational")]
+        sink: Option<Rational64>
+    }
+}
+
+impl Widget {
+    pub fn name(&self) -> &String {
+        match self {
+            Widget::Item(name) => name,
+            Widget::Detailed { name, .. } => name
+        }
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        matches!(self, Widget::Detailed { fluid: true, .. })
+    }
+
+    pub fn price(&self) -> Option<Rational64> {
+        match self {
+            Widget::Item(_) => None,
+            Widget::Detailed { price, .. } => *price
+        }
+    }
+
+    pub fn sink(&self) -> Option<Rational64> {
+        match self {
+            Widget::Item(_) => None,
+            Widget::Detailed { sink, .. } => *sink
+        }
+    }
+}
+
+// a belt or pipe tier
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Transport {
+    pub name: String,
+    // units/second
+    #[serde(deserialize_with="deserialize_rational")]
+    pub rate: Rational64
+}
+
+// slowest tier carrying `flow` on its own, else the fastest and how many of them it takes;
+// tiers with no positive rate carry nothing and are passed over
+fn transport_for(tiers: &[Transport], flow: Rational64) -> Option<(&Transport, u64)> {
+    let usable = || tiers.iter().filter(| t | t.rate > Rational64::from_integer(0));
+    let fastest = usable().max_by_key(| t | t.rate)?;
+    match usable().filter(| t | t.rate >= flow).min_by_key(| t | t.rate) {
+        Some(tier) => Some((tier, 1)),
+        None => Some((fastest, (flow / fastest.rate).ceil().to_integer() as u64))
+    }
+}
+
+// produces raw resources at a rate scaled by the purity of the node it sits on
+#[derive(Clone, Debug, Deserialize)]
+pub struct Extractor {
+    // builder name, see the builders section for power and clocks
+    name: String,
+    resources: Vec<String>,
+    // units/second on a node with multiplier 1
+    #[serde(deserialize_with="deserialize_rational")]
+    rate: Rational64,
+    #[serde(default)]
+    tier: u32
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Generator {
+    pub name: String,
+    // MW produced per generator
+    #[serde(deserialize_with="deserialize_rational")]
+    pub power: Rational64,
+    // units/second burnt per generator
+    pub fuel: Vec<Reagent>
+}
+
+fn default_purities() -> BTreeMap<String, Rational64> {
+    vec![
+        ("impure".to_owned(), Rational64::new(1, 2)),
+        ("normal".to_owned(), Rational64::from_integer(1)),
+        ("pure".to_owned(), Rational64::from_integer(2))
+    ].into_iter().collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Cookbook {
+    // the `modname` qualifying its widgets as `modname:widget` where merged datasets collide
+    #[serde(default)]
+    namespace: Option<String>,
+    widgets: Vec<Widget>,
+    #[serde(default)]
+    builders: Vec<Builder>,
+    #[serde(default)]
+    belts: Vec<Transport>,
+    #[serde(default)]
+    pipes: Vec<Transport>,
+    #[serde(default)]
+    extractors: Vec<Extractor>,
+    // resource node multipliers by purity
+    #[serde(default="default_purities", deserialize_with="deserialize_rational_map")]
+    purities: BTreeMap<String, Rational64>,
+    #[serde(default)]
+    generators: Vec<Generator>,
+    recipes: Vec<Recipe>
+}
+
+// reads a game definition keeping only the recipes named, every other one being dropped as soon as
+// it's read so the rest never pile up; the other sections are read whole
+struct KeptCookbook<'k>(&'k BTreeSet<String>);
+
+impl<'de, 'k> DeserializeSeed<'de> for KeptCookbook<'k> {
+    type Value = Cookbook;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Cookbook, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'k> serde::de::Visitor<'de> for KeptCookbook<'k> {
+    type Value = Cookbook;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a game definition")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Cookbook, A::Error> where A: serde::de::MapAccess<'de> {
+        let mut sections = serde_yaml::Mapping::new();
+        let mut recipes = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "recipes" => recipes = Some(map.next_value_seed(KeptRecipes(self.0))?),
+                _ => { sections.insert(key.into(), map.next_value()?); }
+            }
+        }
+        sections.insert("recipes".into(), serde_yaml::Value::Sequence(Vec::new()));
+        let mut cookbook: Cookbook = serde_yaml::from_value(serde_yaml::Value::Mapping(sections)).map_err(serde::de::Error::custom)?;
+        cookbook.recipes = recipes.ok_or_else(|| serde::de::Error::missing_field("recipes"))?;
+        Ok(cookbook)
+    }
+}
+
+struct KeptRecipes<'k>(&'k BTreeSet<String>);
+
+impl<'de, 'k> DeserializeSeed<'de> for KeptRecipes<'k> {
+    type Value = Vec<Recipe>;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Vec<Recipe>, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'k> serde::de::Visitor<'de> for KeptRecipes<'k> {
+    type Value = Vec<Recipe>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a list of recipes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Vec<Recipe>, A::Error> where A: serde::de::SeqAccess<'de> {
+        let mut recipes = Vec::new();
+        while let Some(recipe) = seq.next_element::<Recipe>()? {
+            if self.0.contains(&recipe.name) {
+                recipes.push(recipe);
+            }
+        }
+        Ok(recipes)
+    }
+}
+
+// just enough of the recipes to tell which a widget can be made from
+#[derive(Deserialize)]
+struct Outline {
+    recipes: Vec<RecipeOutline>
+}
+
+#[derive(Deserialize)]
+struct RecipeOutline {
+    name: String,
+    products: Vec<WidgetName>,
+    reagents: Vec<WidgetName>
+}
+
+#[derive(Deserialize)]
+struct WidgetName {
+    widget: String
+}
+
+// the names of the recipes making `widget` and everything they take, all the way down
+fn reachable(outline: &Outline, widget: &str) -> BTreeSet<String> {
+    let mut makers: HashMap<&str, Vec<&RecipeOutline>> = HashMap::new();
+    for recipe in outline.recipes.iter() {
+        for product in recipe.products.iter() {
+            makers.entry(&product.widget).or_default().push(recipe);
+        }
+    }
+    let mut kept = BTreeSet::new();
+    let mut seen = HashSet::from([widget]);
+    let mut queue = vec![widget];
+    while let Some(widget) = queue.pop() {
+        for recipe in makers.get(widget).into_iter().flatten() {
+            kept.insert(recipe.name.clone());
+            for reagent in recipe.reagents.iter() {
+                if seen.insert(&reagent.widget) {
+                    queue.push(&reagent.widget);
+                }
+            }
+        }
+    }
+    kept
+}
+
+fn load_yaml<T: DeserializeOwned>(file_path: &PathBuf) -> Result<T> {
+    let file = File::open(file_path).map_err(| source | Error::Io { path: file_path.clone(), source })?;
+    let reader = BufReader::new(file);
+    serde_yaml::from_reader(reader).map_err(| source | Error::Parse { path: file_path.clone(), source })
+}
+
+// gzip and zstd by their magic numbers, with the command undoing each
+const COMPRESSED: [(&[u8], &str); 2] = [(b"\x1f\x8b", "gzip"), (b"\x28\xb5\x2f\xfd", "zstd")];
+
+/// The bytes of the game definition at `file_path`, decompressed first when it's gzip or zstd
+/// compressed, whatever it's named, by piping it through the system's `gzip` or `zstd`.
+pub fn read_definition(file_path: &PathBuf) -> Result<Vec<u8>> {
+    let io = | source | Error::Io { path: file_path.clone(), source };
+    let bytes = std::fs::read(file_path).map_err(io)?;
+    let tool = match COMPRESSED.iter().find(| (magic, _) | bytes.starts_with(magic)) {
+        Some((_, tool)) => *tool,
+        None => return Ok(bytes)
+    };
+    let mut child = Command::new(tool).arg("-dc").stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+        .map_err(| e | io(std::io::Error::new(e.kind(), format!("cannot run {} to decompress it: {}", tool, e))))?;
+    // fed from another thread so a full output pipe can't stall the decompressor; a failed write shows in how it exits
+    let mut input = child.stdin.take().expect("stdin is piped");
+    let feeder = std::thread::spawn(move || input.write_all(&bytes));
+    let output = child.wait_with_output().map_err(io)?;
+    let _ = feeder.join();
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Err(io(std::io::Error::other(format!("{} could not decompress it: {}", tool, message))));
+    }
+    Ok(output.stdout)
+}
+
+// clipboard commands on macOS, Wayland, X11 and Windows or WSL, in the order they're tried
+const CLIPBOARDS: [(&str, &[&str]); 5] = [("pbcopy", &[]), ("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[])];
+
+/// Puts `text` on the system clipboard through the first clipboard command that runs, returning its
+/// name. X11 tools stay behind holding the selection, so nothing is read back from them.
+pub fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    copy_through(&CLIPBOARDS, text)
+}
+
+// `text` piped into the first of `tools` that runs
+fn copy_through(tools: &[(&'static str, &[&str])], text: &str) -> Result<&'static str> {
+    for &(tool, args) in tools {
+        let mut child = match Command::new(tool).args(args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Clipboard(format!("cannot run {}: {}", tool, e)))
+        };
+        let written = child.stdin.take().expect("stdin is piped").write_all(text.as_bytes());
+        let status = child.wait().map_err(| e | Error::Clipboard(format!("{} failed: {}", tool, e)))?;
+        return match (written, status.success()) {
+            (Ok(()), true) => Ok(tool),
+            (Err(e), _) => Err(Error::Clipboard(format!("{} failed: {}", tool, e))),
+            (_, false) => Err(Error::Clipboard(format!("{} exited with {}", tool, status)))
+        };
+    }
+    Err(Error::Clipboard(format!("none of {} is installed", tools.iter().map(| (tool, _) | *tool).collect::<Vec<_>>().join(", "))))
+}
+
+/// Reads the game definition at `file_path`: widgets, builders, logistics tiers, extractors and recipes.
+pub fn load_recipes(file_path: &PathBuf) -> Result<Cookbook> {
+    Cookbook::parse(file_path)
+}
+
+impl Cookbook {
+    // YAML, or an index `compile` wrote
+    pub fn parse(file_path: &PathBuf) -> Result<Self> {
+        let bytes = read_definition(file_path)?;
+        if bytes.starts_with(index::MAGIC) {
+            return index::load(&bytes).map_err(| message | Error::IndexFormat { path: file_path.clone(), message });
+        }
+        serde_yaml::from_slice(&bytes).map_err(| source | Error::Parse { path: file_path.clone(), source })
+    }
+
+    /// Like `parse`, but keeps only the recipes `widget` can be made from: a first pass reads nothing
+    /// of the recipes but their names and widgets to find them, and the second drops every other
+    /// recipe as soon as it's read, so recipes in memory stay in proportion to the plan rather than the
+    /// definition. Widgets, builders and the rest are read whole. Compiled indexes are read whole too.
+    pub fn parse_for(file_path: &PathBuf, widget: &str) -> Result<Self> {
+        let bytes = read_definition(file_path)?;
+        if bytes.starts_with(index::MAGIC) {
+            return index::load(&bytes).map_err(| message | Error::IndexFormat { path: file_path.clone(), message });
+        }
+        let outline: Outline = serde_yaml::from_slice(&bytes).map_err(| source | Error::Parse { path: file_path.clone(), source })?;
+        let kept = reachable(&outline, widget);
+        drop(outline);
+        KeptCookbook(&kept).deserialize(serde_yaml::Deserializer::from_slice(&bytes)).map_err(| source | Error::Parse { path: file_path.clone(), source })
+    }
+
+    // the game definition from YAML already in memory, for hosts without a filesystem such as the browser
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        serde_yaml::from_str(text).map_err(Error::Definition)
+    }
+
+    // recipes with their builders resolved
+    fn resolved(&self) -> Vec<Recipe> {
+        let builders: BTreeMap<&String, &Builder> = self.builders.iter().map(| b | (&b.name, b)).collect();
+        self.recipes.iter().map(| recipe | {
+            let mut recipe = recipe.clone();
+            recipe.machine = recipe.builder.as_ref().and_then(| b | builders.get(b)).map(| b | (*b).clone());
+            recipe
+        }).collect()
+    }
+
+    // recipes with their builders resolved, as a graph of widgets
+    pub fn graph(&self) -> Result<Hypergraph<String, Recipe>> {
+        Hypergraph::from_recipes(&self.widgets, self.resolved())
+    }
+
+    // widgets whose recipes or extraction on `purity` nodes differ from those in `previous`, builders included
+    pub fn changed_widgets(&self, previous: &Cookbook, purity: &str) -> Result<HashSet<String>> {
+        let producers = | cookbook: &Cookbook | -> Result<BTreeMap<String, Vec<Recipe>>> {
+            let mut producers: BTreeMap<String, Vec<Recipe>> = BTreeMap::new();
+            for recipe in cookbook.resolved().into_iter().chain(cookbook.extraction(purity)?) {
+                for product in recipe.products.iter() {
+                    producers.entry(product.widget.clone()).or_default().push(recipe.clone());
+                }
+            }
+            // recipes sharing a name and builder are left as declared, a reordering only costs a replan
+            producers.values_mut().for_each(| recipes | recipes.sort_by(| a, b | (&a.name, &a.builder).cmp(&(&b.name, &b.builder))));
+            Ok(producers)
+        };
+        let (now, before) = (producers(self)?, producers(previous)?);
+        Ok(now.keys().chain(before.keys()).filter(| w | now.get(*w) != before.get(*w)).cloned().collect())
+    }
+
+    // one recipe per extractor and resource, placed on nodes of the given purity
+    pub fn extraction(&self, purity: &str) -> Result<Vec<Recipe>> {
+        let multiplier = *self.purities.get(purity).ok_or_else(|| Error::UnknownPurity(purity.to_owned()))?;
+        let mut recipes = Vec::new();
+        for extractor in self.extractors.iter() {
+            for resource in extractor.resources.iter() {
+                if !self.widgets.iter().any(| w | w.name() == resource) {
+                    return Err(Error::UnknownWidget(resource.clone()));
+                }
+                recipes.push(Recipe {
+                    name: format!("{resource} ({purity} node)", resource=resource, purity=purity),
+                    builder: Some(extractor.name.clone()),
+                    manual: false,
+                    duration: Rational64::from_integer(1),
+                    products: vec![Reagent { widget: resource.clone(), quantity: extractor.rate * multiplier }],
+                    reagents: vec![],
+                    tier: extractor.tier,
+                    alternate: false,
+                    category: None,
+                    power: None,
+                    speed: None,
+                    productivity: None,
+                    duration_spread: None,
+                    yield_spread: None,
+                    since: None,
+                    deprecated: None,
+                    machine: self.builders.iter().find(| b | b.name == extractor.name).cloned()
+                });
+            }
+        }
+        Ok(recipes)
+    }
+
+    pub fn is_fluid(&self, widget: &String) -> bool {
+        self.widgets.iter().any(| w | w.name() == widget && w.is_fluid())
+    }
+
+    pub fn prices(&self) -> BTreeMap<String, Rational64> {
+        self.widgets.iter().filter_map(| w | w.price().map(| p | (w.name().clone(), p))).collect()
+    }
+
+    // points per unit by widget, for those a sink takes
+    pub fn sink_values(&self) -> BTreeMap<String, Rational64> {
+        self.widgets.iter().filter_map(| w | w.sink().map(| p | (w.name().clone(), p))).collect()
+    }
+
+    pub fn generator(&self, name: &str) -> Result<&Generator> {
+        self.generators.iter().find(| g | g.name == name).ok_or_else(|| Error::UnknownGenerator(name.to_owned()))
+    }
+
+    // the belt or pipe tiers carrying this widget
+    pub fn transport(&self, widget: &String) -> &[Transport] {
+        if self.is_fluid(widget) { &self.pipes } else { &self.belts }
+    }
+
+    // items in units/second, fluids in m³/minute
+    pub fn format_rate(&self, widget: &String, rate: &Rational64, precision: Precision) -> String {
+        if self.is_fluid(widget) {
+            format!("{rate} m³/min", rate=precision.fixed(as_f64(rate) * 60.0, 3))
+        } else {
+            format!("{rate}/s", rate=precision.fixed(as_f64(rate), 3))
+        }
+    }
+}
+
+// assembles a cookbook in code instead of YAML, for tests, generators and importers
+pub struct CookbookBuilder {
+    cookbook: Cookbook
+}
+
+impl Cookbook {
+    pub fn builder() -> CookbookBuilder {
+        CookbookBuilder {
+            cookbook: Cookbook {
+                namespace: None,
+                widgets: Vec::new(),
+                builders: Vec::new(),
+                belts: Vec::new(),
+                pipes: Vec::new(),
+                extractors: Vec::new(),
+                purities: default_purities(),
+                generators: Vec::new(),
+                recipes: Vec::new()
+            }
+        }
+    }
+}
+
+impl CookbookBuilder {
+    pub fn item(mut self, name: &str) -> Self {
+        self.cookbook.widgets.push(Widget::Item(name.to_owned()));
+        self
+    }
+
+    pub fn fluid(mut self, name: &str) -> Self {
+        self.cookbook.widgets.push(Widget::Detailed { name: name.to_owned(), fluid: true, price: None, sink: None });
+        self
+    }
+
+    // an item with a market price per unit
+    pub fn priced(mut self, name: &str, price: Rational64) -> Self {
+        self.cookbook.widgets.push(Widget::Detailed { name: name.to_owned(), fluid: false, price: Some(price), sink: None });
+        self
+    }
+
+    // an item a sink pays `points` per unit for
+    pub fn sunk(mut self, name: &str, points: Rational64) -> Self {
+        self.cookbook.widgets.push(Widget::Detailed { name: name.to_owned(), fluid: false, price: None, sink: Some(points) });
+        self
+    }
+
+    // a builder drawing `power` MW per machine, running at any clock
+    pub fn machine(mut self, name: &str, power: Option<Rational64>) -> Self {
+        self.cookbook.builders.push(Builder {
+            name: name.to_owned(),
+            power,
+            min_clock: None,
+            max_clock: None,
+            power_exponent: None,
+            speed: None,
+            productivity: None,
+            width: None,
+            length: None,
+            operating_cost: None,
+            consumes: Vec::new()
+        });
+        self
+    }
+
+    // every `builder` machine using up `rate` units/second of `widget`, added with `machine` first
+    pub fn upkeep(mut self, builder: &str, widget: &str, rate: Rational64) -> Self {
+        if let Some(b) = self.cookbook.builders.iter_mut().find(| b | b.name == builder) {
+            b.consumes.push(Reagent { widget: widget.to_owned(), quantity: rate });
+        }
+        self
+    }
+
+    pub fn belt(mut self, name: &str, rate: Rational64) -> Self {
+        self.cookbook.belts.push(Transport { name: name.to_owned(), rate });
+        self
+    }
+
+    pub fn pipe(mut self, name: &str, rate: Rational64) -> Self {
+        self.cookbook.pipes.push(Transport { name: name.to_owned(), rate });
+        self
+    }
+
+    // `name` is the builder extracting `resources` at `rate` units/second on a normal node
+    pub fn extractor(mut self, name: &str, resources: &[&str], rate: Rational64) -> Self {
+        self.cookbook.extractors.push(Extractor { name: name.to_owned(), resources: resources.iter().map(| r | (*r).to_owned()).collect(), rate, tier: 0 });
+        self
+    }
+
+    pub fn recipe(mut self, recipe: Recipe) -> Self {
+        self.cookbook.recipes.push(recipe);
+        self
+    }
+
+    // unknown widgets are reported when the graph is built, as for YAML
+    pub fn build(self) -> Cookbook {
+        self.cookbook
+    }
+}
+
+impl Recipe {
+    // a hand-crafted recipe taking `duration` seconds, without reagents or products yet
+    pub fn new(name: &str, duration: Rational64) -> Self {
+        Recipe {
+            name: name.to_owned(),
+            builder: None,
+            manual: false,
+            duration,
+            products: Vec::new(),
+            reagents: Vec::new(),
+            tier: 0,
+            alternate: false,
+            category: None,
+            power: None,
+            speed: None,
+            productivity: None,
+            duration_spread: None,
+            yield_spread: None,
+            since: None,
+            deprecated: None,
+            machine: None
+        }
+    }
+
+    pub fn made_by(mut self, builder: &str) -> Self {
+        self.builder = Some(builder.to_owned());
+        self
+    }
+
+    pub fn reagent(mut self, widget: &str, quantity: Rational64) -> Self {
+        self.reagents.push(Reagent { widget: widget.to_owned(), quantity });
+        self
+    }
+
+    pub fn product(mut self, widget: &str, quantity: Rational64) -> Self {
+        self.products.push(Reagent { widget: widget.to_owned(), quantity });
+        self
+    }
+
+    pub fn at_tier(mut self, tier: u32) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    // locked until unlocked by name
+    pub fn as_alternate(mut self) -> Self {
+        self.alternate = true;
+        self
+    }
+
+    // in the game from `since` until `deprecated` takes it out, either open-ended when None
+    pub fn available(mut self, since: Option<version::GameVersion>, deprecated: Option<version::GameVersion>) -> Self {
+        self.since = since;
+        self.deprecated = deprecated;
+        self
+    }
+
+    pub fn in_category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_owned());
+        self
+    }
+
+    // crafts taking and yielding a draw from each times the stated duration and products, in simulations
+    pub fn varying(mut self, duration: Option<simulate::Distribution>, yields: Option<simulate::Distribution>) -> Self {
+        self.duration_spread = duration;
+        self.yield_spread = yields;
+        self
+    }
+}
+
+impl Hypergraph<String, Recipe> {
+    // widgets are nodes, each recipe an edge from its reagents to its products
+    pub fn from_recipes(widgets: &[Widget], recipes: impl IntoIterator<Item=Recipe>) -> Result<Self> {
+        let recipes = recipes.into_iter();
+        let mut graph = Hypergraph::with_capacity(widgets.len(), recipes.size_hint().0);
+        let names: HashSet<&String> = widgets.iter().map(Widget::name).collect();
+        graph.add_nodes(widgets.iter().map(| w | w.name().clone()));
+        for recipe in recipes {
+            if let Some(unknown) = recipe.reagents.iter().chain(recipe.products.iter()).find(| r | !names.contains(&r.widget)) {
+                return Err(Error::UnknownWidget(unknown.widget.clone()));
+            }
+            let sources: Vec<String> = recipe.reagents.iter().map(| r | r.widget.clone()).collect();
+            let destinations: Vec<String> = recipe.products.iter().map(| r | r.widget.clone()).collect();
+            graph.insert_edge(&sources, &destinations, recipe).expect("recipe widgets are all nodes");
+        }
+        Ok(graph)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Construction {
+    name: String,
+    cost: Vec<Reagent>,
+    // seconds to place one, for ramp-up timelines
+    #[serde(default, deserialize_with="deserialize_optional_duration")]
+    time: Option<Rational64>
+}
+
+// materials needed to place each builder and how long it takes, kept apart from the recipe data
+#[derive(Debug, Deserialize)]
+pub struct ConstructionCosts {
+    builders: Vec<Construction>
+}
+
+impl ConstructionCosts {
+    pub fn parse(file_path: &PathBuf) -> Result<Self> {
+        load_yaml(file_path)
+    }
+
+    pub fn cost(&self, builder: &str) -> Option<&[Reagent]> {
+        self.builders.iter().find(| b | b.name == builder).map(| b | b.cost.as_slice())
+    }
+
+    // seconds to place one `builder`, None without construction data or a time in it
+    pub fn time(&self, builder: &str) -> Option<Rational64> {
+        self.builders.iter().find(| b | b.name == builder).and_then(| b | b.time)
+    }
+
+    // one-time materials for every machine, plus the builders without cost data
+    pub fn bill(&self, machines: &BTreeMap<String, u64>) -> Result<(BTreeMap<String, Rational64>, Vec<String>)> {
+        let mut materials = BTreeMap::new();
+        let mut missing = Vec::new();
+        for (builder, count) in machines.iter() {
+            match self.builders.iter().find(| b | &b.name == builder) {
+                Some(construction) => {
+                    let fail = || Error::Arithmetic(format!("the materials for {count} of `{builder}` overflow 64-bit rationals", count=count, builder=builder));
+                    for reagent in construction.cost.iter() {
+                        let quantity = i64::try_from(*count).ok().and_then(| count | reagent.quantity.checked_mul(&Rational64::from_integer(count))).ok_or_else(fail)?;
+                        add_rate(&mut materials, &reagent.widget, quantity)?;
+                    }
+                },
+                None => missing.push(builder.clone())
+            }
+        }
+        Ok((materials, missing))
+    }
+}
+
+// how a stage's machine count comes from the exact number of machines its rate needs
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Rounding {
+    // whole machines, enough to make at least the requested rate
+    #[default]
+    Ceil,
+    // whole machines, the nearest number, making a little less than requested when rounding down
+    Nearest,
+    // the exact fractional number, running at exactly the requested rate
+    None
+}
+
+impl std::str::FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        match text {
+            "ceil" => Ok(Rounding::Ceil),
+            "nearest" => Ok(Rounding::Nearest),
+            "none" => Ok(Rounding::None),
+            _ => Err(format!("unknown rounding `{}`, expected ceil, nearest or none", text))
+        }
+    }
+}
+
+impl Rounding {
+    fn apply(self, machines: Rational64) -> Rational64 {
+        match self {
+            Rounding::Ceil => machines.ceil(),
+            // a stage with any demand keeps at least one machine
+            Rounding::Nearest if machines > Rational64::from_integer(0) => machines.round().max(Rational64::from_integer(1)),
+            Rounding::Nearest => machines,
+            Rounding::None => machines
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    pub max_tier: Option<u32>,
+    // skip hand-crafted recipes whenever a machine recipe exists
+    pub prefer_machines: bool,
+    // desired clock speeds as fractions, 100% when unset
+    pub min_clock: Option<Rational64>,
+    pub max_clock: Option<Rational64>,
+    // underclock each group so it produces exactly the requested rate
+    pub exact_clock: bool,
+    // recipe names the heuristic may not pick
+    pub excluded: HashSet<String>,
+    // alternate recipes the heuristic may pick
+    pub unlocked: HashSet<String>,
+    // recipe categories the heuristic may pick from, any when unset, and those it may not; recipes without a
+    // category, extraction among them, are never left out for theirs
+    pub categories: Option<HashSet<String>>,
+    pub excluded_categories: HashSet<String>,
+    // alternates are only picked over the standard recipes when their machines, or cost score, beat the best
+    // standard one's even raised by this fraction of themselves
+    pub alternate_penalty: Option<Rational64>,
+    // only recipes in the game at this version, by their since and deprecated versions; all of them when unset
+    pub game_version: Option<version::GameVersion>,
+    // available machines per builder
+    pub limits: BTreeMap<String, u64>,
+    // feed overproduction and byproducts to later demand in the same plan
+    pub share_surplus: bool,
+    // market prices per unit, widgets cheaper to buy than to craft are bought; empty to always craft
+    pub prices: BTreeMap<String, Rational64>,
+    // plan the recipe graph as one network instead of a tree of independent branches
+    pub network: bool,
+    // stages planned below the goal, counting it as the first; deeper widgets are supplied from outside
+    pub max_depth: Option<usize>,
+    // scores recipes for each stage, the lowest scoring is used and waste breaks ties; least waste alone when unset
+    pub cost: Option<cost::CostExpr>,
+    // judge waste by the idle machines across the whole subtree a recipe expands into, not only its own stage
+    pub downstream_waste: bool,
+    // pick the recipe whose whole subtree draws the least MW, cost and waste breaking ties
+    pub minimize_power: bool,
+    pub rounding: Rounding,
+    // points per unit a sink pays, surplus of these widgets being sunk for them; empty to waste surplus
+    pub sinks: BTreeMap<String, Rational64>
+}
+
+impl Constraints {
+    pub fn allows(&self, recipe: &Recipe) -> bool {
+        self.max_tier.is_none_or(| tier | recipe.tier <= tier) && !self.excluded.contains(&recipe.name)
+            && (!recipe.alternate || self.unlocked.contains(&recipe.name))
+            && recipe.category.as_ref().is_none_or(| c | self.categories.as_ref().is_none_or(| only | only.contains(c)) && !self.excluded_categories.contains(c))
+            && self.game_version.as_ref().is_none_or(| v | recipe.since.as_ref().is_none_or(| s | s <= v) && recipe.deprecated.as_ref().is_none_or(| d | v < d))
+    }
+
+    // desired clock range narrowed to what the recipe's builder supports
+    pub fn clock_range(&self, recipe: &Recipe) -> (Rational64, Rational64) {
+        let one = Rational64::from_integer(1);
+        if recipe.is_manual() {
+            return (one, one);
+        }
+        let percent = | p: Rational64 | p / Rational64::from_integer(100);
+        let capability = recipe.machine.as_ref();
+        let max = match capability.and_then(| m | m.max_clock) {
+            Some(cap) => self.max_clock.unwrap_or(one).min(percent(cap)),
+            None => self.max_clock.unwrap_or(one)
+        };
+        let default_min = if self.exact_clock { Rational64::from_integer(0) } else { one };
+        let min = match capability.and_then(| m | m.min_clock) {
+            Some(cap) => self.min_clock.unwrap_or(default_min).max(percent(cap)),
+            None => self.min_clock.unwrap_or(default_min)
+        };
+        (min.min(max), max)
+    }
+}
+
+fn producers<'a>(graph: &'a Hypergraph<String, Recipe>, widget: &String) -> Result<Vec<&'a Recipe>> {
+    let edges = graph.neighbor_of(widget).map_err(| _ | Error::UnknownWidget(widget.clone()))?;
+    Ok(edges.into_iter().map(| e | graph.get_weight(&e).expect("edge from neighbor_of exists")).collect())
+}
+
+// cheapest cost per unit of crafting `widget` from the best of bought and crafted inputs, None when it can only be bought;
+// extracted and unpriced raw resources are free, and byproducts don't offset the cost
+fn make_cost(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, constraints: &Constraints, visiting: &mut Vec<String>) -> Result<Option<Rational64>> {
+    let zero = Rational64::from_integer(0);
+    if is_raw(graph, widget)? {
+        let extracted = extraction.iter().any(| r | constraints.allows(r) && r.products.iter().any(| p | &p.widget == widget));
+        return Ok(if extracted || !constraints.prices.contains_key(widget) { Some(zero) } else { None });
+    }
+    if visiting.contains(widget) {
+        return Ok(None);
+    }
+    visiting.push(widget.clone());
+    let mut cheapest: Option<Rational64> = None;
+    'recipes: for recipe in producers(graph, widget)?.into_iter().filter(| r | constraints.allows(r)) {
+        let fail = || Error::Arithmetic(format!("the cost of `{widget}` with recipe `{name}` overflows 64-bit rationals", widget=widget, name=recipe.name));
+        let made = checked_total(recipe.products.iter().filter(| p | &p.widget == widget).map(| p | &p.quantity))
+            .and_then(| made | made.checked_mul(&recipe.productivity())).ok_or_else(fail)?;
+        if made <= zero {
+            continue;
+        }
+        let mut cost = zero;
+        for reagent in recipe.reagents.iter() {
+            match unit_cost(graph, extraction, &reagent.widget, constraints, visiting)? {
+                Some(unit) => unit.checked_mul(&reagent.quantity).and_then(| c | accumulate(&mut cost, c)).ok_or_else(fail)?,
+                None => continue 'recipes
+            }
+        }
+        let cost = cost.checked_div(&made).ok_or_else(fail)?;
+        if cheapest.is_none_or(| c | cost < c) {
+            cheapest = Some(cost);
+        }
+    }
+    visiting.pop();
+    Ok(cheapest)
+}
+
+// the lower of buying and crafting one unit, None when neither is possible
+fn unit_cost(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, constraints: &Constraints, visiting: &mut Vec<String>) -> Result<Option<Rational64>> {
+    let made = make_cost(graph, extraction, widget, constraints, visiting)?;
+    Ok(match (constraints.prices.get(widget), made) {
+        (Some(price), Some(made)) => Some(made.min(*price)),
+        (Some(price), None) => Some(*price),
+        (None, made) => made
+    })
+}
+
+// whether the market sells `widget` for no more than crafting it costs
+fn buys(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, constraints: &Constraints) -> Result<bool> {
+    match constraints.prices.get(widget) {
+        Some(price) => Ok(make_cost(graph, extraction, widget, constraints, &mut Vec::new())?.is_none_or(| made | *price <= made)),
+        None => Ok(false)
+    }
+}
+
+// the cost expression's score for running `machines` of `recipe` at `clock`, rounded up to whole machines
+#[allow(clippy::too_many_arguments)]
+fn score(graph: &Hypergraph<String, Recipe>, cost: &cost::CostExpr, recipe: &Recipe, widget: &String, machines: Rational64, clock: Rational64,
+    sinks: &BTreeMap<String, Rational64>) -> Result<Rational64> {
+    use cost::Metric;
+    let count = machines.ceil();
+    let fail = || Error::Arithmetic(format!("the cost expression divides by zero or overflows for recipe `{name}`", name=recipe.name));
+    // byproducts, and the widget made beyond the demand
+    let mut sunk = Rational64::from_integer(0);
+    for product in recipe.products.iter() {
+        if let Some(points) = sinks.get(&product.widget) {
+            let extra = if &product.widget == widget { count - machines } else { count };
+            recipe.rate(&product.widget)?.checked_mul(&extra).and_then(| r | r.checked_mul(&clock)).and_then(| r | r.checked_mul(points))
+                .and_then(| points | accumulate(&mut sunk, points)).ok_or_else(fail)?;
+        }
+    }
+    let drawn = | raw_only: bool | -> Result<Rational64> {
+        let mut total = Rational64::from_integer(0);
+        for reagent in recipe.reagents.iter() {
+            if !raw_only || is_raw(graph, &reagent.widget)? {
+                accumulate(&mut total, recipe.drawn(reagent, count, clock)?).ok_or_else(fail)?;
+            }
+        }
+        Ok(total)
+    };
+    let (inputs, raw) = (drawn(false)?, drawn(true)?);
+    let flag = | set: bool | Rational64::from_integer(set as i64);
+    let metric = | m: Metric | match m {
+        Metric::Machines => Some(count),
+        Metric::Power => recipe.power_at(clock).map_or(Some(Rational64::from_integer(0)), | p | p.checked_mul(&count)),
+        Metric::Waste => Some(count - machines),
+        Metric::Inputs => Some(inputs),
+        Metric::Raw => Some(raw),
+        Metric::Tier => Some(Rational64::from_integer(recipe.tier as i64)),
+        Metric::Alternate => Some(flag(recipe.alternate)),
+        Metric::Manual => Some(flag(recipe.is_manual())),
+        Metric::Sink => Some(sunk),
+        Metric::Footprint => recipe.machine.as_ref().and_then(Builder::area).map_or(Some(Rational64::from_integer(0)), | area | area.checked_mul(&count))
+    };
+    cost.eval(&metric).ok_or_else(fail)
+}
+
+// planning arithmetic gone past 64-bit rationals, named instead of panicking deep in a plan
+fn overflow(widget: &str, rate: Rational64, recipe: &Recipe) -> Error {
+    Error::Arithmetic(format!("planning {rate}/s of `{widget}` with recipe `{name}` overflows 64-bit rationals", rate=rate, widget=widget, name=recipe.name))
+}
+
+// adds `amount` to a running total, None when that overflows
+fn accumulate(total: &mut Rational64, amount: Rational64) -> Option<()> {
+    *total = total.checked_add(&amount)?;
+    Some(())
+}
+
+// the sum of `values`, None when it overflows
+fn checked_total<'a>(values: impl IntoIterator<Item=&'a Rational64>) -> Option<Rational64> {
+    values.into_iter().try_fold(Rational64::from_integer(0), | total, value | total.checked_add(value))
+}
+
+// adds to the units/second of `widget` in one of the summary's totals
+fn add_rate(totals: &mut BTreeMap<String, Rational64>, widget: &String, rate: Rational64) -> Result<()> {
+    accumulate(totals.entry(widget.clone()).or_insert_with(|| Rational64::from_integer(0)), rate)
+        .ok_or_else(|| Error::Arithmetic(format!("the total of `{widget}` overflows 64-bit rationals", widget=widget)))
+}
+
+// `measure` summed over the subtree `node` expands into, the stages below it picking recipes by
+// their own waste; raw resources, and widgets already being expanded, end the expansion
+fn subtree_total(graph: &Hypergraph<String, Recipe>, widget: &String, node: &PlanNode, constraints: &Constraints, measure: fn(&PlanNode) -> Rational64) -> Result<Rational64> {
+    let mut total = measure(node);
+    let mut path = vec![widget];
+    let mut stack = vec![(*node, node.recipe.reagents.iter())];
+    while let Some((node, reagents)) = stack.last_mut() {
+        let reagent = match reagents.next() {
+            Some(reagent) => reagent,
+            None => {
+                stack.pop();
+                path.pop();
+                continue;
+            }
+        };
+        if path.contains(&&reagent.widget) || is_raw(graph, &reagent.widget)? {
+            continue;
+        }
+        let rate = node.recipe.drawn(reagent, node.machines, node.clock)?;
+        match pick(graph, producers(graph, &reagent.widget)?, &reagent.widget, rate, constraints, false) {
+            Ok(below) => {
+                total = total.checked_add(&measure(&below)).ok_or_else(|| overflow(&reagent.widget, rate, below.recipe))?;
+                path.push(&reagent.widget);
+                stack.push((below, below.recipe.reagents.iter()));
+            },
+            // planning fails on it anyway, whichever recipe leads there
+            Err(Error::NoRecipes(_)) => {},
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(total)
+}
+
+// the machines a stage leaves idle
+fn idle(node: &PlanNode) -> Rational64 {
+    node.machines * (Rational64::from_integer(1) - node.utilization())
+}
+
+// the MW a stage's machines draw, nothing for hand-crafting and builders without power data
+fn drawn_power(node: &PlanNode) -> Rational64 {
+    node.recipe.power_at(node.clock).map_or(Rational64::from_integer(0), | p | p * node.machines.ceil())
+}
+
+fn least_waste_heuristic<'a>(graph: &Hypergraph<String, Recipe>, recipes: impl IntoIterator<Item=&'a Recipe>, widget: &String, rate: Rational64, constraints: &Constraints) -> Result<PlanNode<'a>> {
+    pick(graph, recipes, widget, rate, constraints, constraints.downstream_waste || constraints.minimize_power)
+}
+
+// the stage least_waste_heuristic() picks, weighing the power or waste of each candidate's subtree when
+// `downstream` and the constraints ask for it
+fn pick<'a>(graph: &Hypergraph<String, Recipe>, recipes: impl IntoIterator<Item=&'a Recipe>, widget: &String, rate: Rational64, constraints: &Constraints, downstream: bool) -> Result<PlanNode<'a>> {
+    let mut candidates = Vec::new();
+    for recipe in recipes {
+        if !constraints.allows(recipe) {
+            continue;
+        }
+        let (_, clock) = constraints.clock_range(recipe);
+        let recipe_rate = recipe.rate(widget)?.checked_mul(&clock).ok_or_else(|| overflow(widget, rate, recipe))?;
+        if recipe_rate == Rational64::from_integer(0) {
+            return Err(Error::Arithmetic(format!("recipe `{name}` produces no `{widget}`", name=recipe.name, widget=widget)));
+        }
+        candidates.push((recipe, rate.checked_div(&recipe_rate).ok_or_else(|| overflow(widget, rate, recipe))?, clock));
+    }
+    if constraints.prefer_machines && candidates.iter().any(| (r, _, _) | !r.is_manual()) {
+        candidates.retain(| (r, _, _) | !r.is_manual());
+    }
+    if let Some(penalty) = constraints.alternate_penalty {
+        let measure = | (recipe, machines, clock): &(&Recipe, Rational64, Rational64) | -> Result<Rational64> {
+            match &constraints.cost {
+                Some(cost) => score(graph, cost, recipe, widget, *machines, *clock, &constraints.sinks),
+                None => Ok(*machines)
+            }
+        };
+        let mut standard = None;
+        for candidate in candidates.iter().filter(| (r, _, _) | !r.alternate) {
+            let m = measure(candidate)?;
+            standard = Some(standard.map_or(m, | s: Rational64 | s.min(m)));
+        }
+        if let Some(standard) = standard {
+            let mut kept = Vec::new();
+            for candidate in candidates {
+                let m = measure(&candidate)?;
+                if !candidate.0.alternate || m + penalty * m.abs() < standard {
+                    kept.push(candidate);
+                }
+            }
+            candidates = kept;
+        }
+    }
+    if downstream && constraints.minimize_power && candidates.len() > 1 {
+        let mut scored = Vec::new();
+        for (recipe, machines, clock) in candidates {
+            let node = PlanNode::new(recipe, constraints.rounding.apply(machines), clock, widget, rate)?;
+            scored.push((subtree_total(graph, widget, &node, constraints, drawn_power)?, recipe, machines, clock));
+        }
+        let best = scored.iter().map(| (s, ..) | *s).min();
+        candidates = scored.into_iter().filter(| (s, ..) | Some(*s) == best).map(| (_, r, m, c) | (r, m, c)).collect();
+    }
+    if let Some(cost) = &constraints.cost {
+        let mut scored = Vec::new();
+        for (recipe, machines, clock) in candidates {
+            scored.push((score(graph, cost, recipe, widget, machines, clock, &constraints.sinks)?, recipe, machines, clock));
+        }
+        let best = scored.iter().map(| (s, ..) | *s).min();
+        candidates = scored.into_iter().filter(| (s, ..) | Some(*s) == best).map(| (_, r, m, c) | (r, m, c)).collect();
+    }
+    if downstream && constraints.downstream_waste && candidates.len() > 1 {
+        let mut scored = Vec::new();
+        for (recipe, machines, clock) in candidates {
+            let node = PlanNode::new(recipe, constraints.rounding.apply(machines), clock, widget, rate)?;
+            scored.push((subtree_total(graph, widget, &node, constraints, idle)?, recipe, machines, clock));
+        }
+        let best = scored.iter().map(| (s, ..) | *s).min();
+        candidates = scored.into_iter().filter(| (s, ..) | Some(*s) == best).map(| (_, r, m, c) | (r, m, c)).collect();
+    }
+    // the least idle capacity wins, then the fewest machines, then the recipe name, so a plan never
+    // depends on the order recipes are declared in
+    let best_recipe = candidates.into_iter().min_by(
+        |(recipe, machines, _), (min_recipe, min_machines, _)| -> Ordering {
+            machines.fract().cmp(&min_machines.fract())
+                .then_with(|| machines.ceil().cmp(&min_machines.ceil()))
+                .then_with(|| recipe.name.cmp(&min_recipe.name))
+        });
+    match best_recipe {
+        Some((r, machines, clock)) => {
+            let count = constraints.rounding.apply(machines);
+            if constraints.exact_clock && !r.is_manual() && count > Rational64::from_integer(0) {
+                // rounding to the nearest count can leave too few machines to make the rate at the fastest
+                // clock allowed, which they then run at
+                let (min, max) = constraints.clock_range(r);
+                let exact = count.checked_mul(&r.rate(widget)?).and_then(| made | rate.checked_div(&made)).ok_or_else(|| overflow(widget, rate, r))?;
+                return PlanNode::new(r, count, exact.max(min).min(max), widget, rate);
+            }
+            PlanNode::new(r, count, clock, widget, rate)
+        },
+        None => Err(Error::NoRecipes(widget.clone()))
+    }
+}
+
+// one stage of the plan: `machines` builders running `recipe` at `clock` for `requested_rate` of a widget,
+// the count rounded as the constraints say making `actual_rate` of it and `surplus` beyond what was requested
+#[derive(Clone, Copy, Debug)]
+pub struct PlanNode<'a> {
+    pub recipe: &'a Recipe,
+    // whole machines to build, `machines` rounded up when it is fractional
+    pub machine_count: u64,
+    pub machines: Rational64,
+    pub clock: Rational64,
+    pub requested_rate: Rational64,
+    pub actual_rate: Rational64,
+    pub surplus: Rational64
+}
+
+impl<'a> PlanNode<'a> {
+    fn new(recipe: &'a Recipe, machines: Rational64, clock: Rational64, widget: &String, requested_rate: Rational64) -> Result<Self> {
+        let fail = || overflow(widget, requested_rate, recipe);
+        let actual_rate = recipe.rate(widget)?.checked_mul(&machines).and_then(| r | r.checked_mul(&clock)).ok_or_else(fail)?;
+        let node = PlanNode { recipe, machine_count: machines.ceil().to_integer() as u64, machines, clock, requested_rate, actual_rate, surplus: actual_rate.checked_sub(&requested_rate).ok_or_else(fail)? };
+        node.check_draws()?;
+        Ok(node)
+    }
+
+    // fails when what the stage draws of any reagent overflows, every planned or loaded stage having passed it
+    pub fn check_draws(&self) -> Result<()> {
+        for reagent in self.recipe.reagents.iter() {
+            self.recipe.drawn(reagent, self.machines, self.clock)?;
+        }
+        Ok(())
+    }
+
+    // units/second of `reagent` the stage draws
+    pub fn draws(&self, reagent: &Reagent) -> Rational64 {
+        self.recipe.drawn(reagent, self.machines, self.clock).expect("stages are checked with check_draws")
+    }
+
+    // share of the rounded-up machines' output that was asked for
+    fn utilization(&self) -> Rational64 {
+        if self.actual_rate > Rational64::from_integer(0) { self.requested_rate / self.actual_rate } else { Rational64::from_integer(0) }
+    }
+}
+
+pub type PlanTree<'a> = NTree<PlanNode<'a>>;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Summary {
+    pub raw: BTreeMap<String, Rational64>,
+    // seconds of hand-crafting per second of production
+    pub crafting_time: Option<Rational64>,
+    // MW per builder
+    pub power: BTreeMap<String, Rational64>,
+    pub machines: BTreeMap<String, u64>,
+    // units/second made beyond what the plan consumes
+    pub surplus: BTreeMap<String, Rational64>,
+    // units/second of demand met from surplus elsewhere in the plan
+    pub shared: BTreeMap<String, Rational64>,
+    // units/second bought instead of crafted
+    pub purchases: BTreeMap<String, Rational64>,
+    // units/second supplied from outside the plan, past the expansion depth
+    pub supplied: BTreeMap<String, Rational64>
+}
+
+impl Summary {
+    pub fn total_power(&self) -> Result<Rational64> {
+        checked_total(self.power.values()).ok_or_else(|| Error::Arithmetic("the total power overflows 64-bit rationals".to_owned()))
+    }
+
+    // points/second for sinking the surplus
+    pub fn sink_points(&self, sinks: &BTreeMap<String, Rational64>) -> Result<Rational64> {
+        let mut total = Rational64::from_integer(0);
+        for (widget, rate) in self.surplus.iter().filter(| (_, r) | **r > Rational64::from_integer(0)) {
+            if let Some(points) = sinks.get(widget) {
+                rate.checked_mul(points).and_then(| points | accumulate(&mut total, points))
+                    .ok_or_else(|| Error::Arithmetic(format!("sinking {rate}/s of `{widget}` overflows 64-bit rationals", rate=rate, widget=widget)))?;
+            }
+        }
+        Ok(total)
+    }
+
+    // fails when a total overflows, leaving the summary partly merged
+    pub fn merge(&mut self, other: Summary) -> Result<()> {
+        let overflow = | total: &str | Error::Arithmetic(format!("the merged {total} overflows", total=total));
+        for (widget, rate) in other.raw {
+            add_rate(&mut self.raw, &widget, rate)?;
+        }
+        if let Some(time) = other.crafting_time {
+            accumulate(self.crafting_time.get_or_insert_with(|| Rational64::from_integer(0)), time).ok_or_else(|| overflow("crafting time"))?;
+        }
+        for (builder, power) in other.power {
+            accumulate(self.power.entry(builder).or_insert_with(|| Rational64::from_integer(0)), power).ok_or_else(|| overflow("power"))?;
+        }
+        for (builder, count) in other.machines {
+            let total = self.machines.entry(builder).or_insert(0);
+            *total = total.checked_add(count).ok_or_else(|| overflow("machine count"))?;
+        }
+        for (widget, rate) in other.surplus {
+            add_rate(&mut self.surplus, &widget, rate)?;
+        }
+        for (widget, rate) in other.shared {
+            add_rate(&mut self.shared, &widget, rate)?;
+        }
+        for (widget, rate) in other.purchases {
+            add_rate(&mut self.purchases, &widget, rate)?;
+        }
+        for (widget, rate) in other.supplied {
+            add_rate(&mut self.supplied, &widget, rate)?;
+        }
+        Ok(())
+    }
+
+    // what was added since `before`, which it grew from without drawing on surplus
+    fn since(&self, before: &Summary) -> Result<Summary> {
+        let zero = Rational64::from_integer(0);
+        let fail = | total: &str | Error::Arithmetic(format!("what a subtree adds to the {total} overflows 64-bit rationals", total=total));
+        let added = | after: &BTreeMap<String, Rational64>, before: &BTreeMap<String, Rational64> | -> Result<BTreeMap<String, Rational64>> {
+            let mut added = BTreeMap::new();
+            for (k, v) in after.iter() {
+                let v = v.checked_sub(&before.get(k).copied().unwrap_or(zero)).ok_or_else(|| fail(k))?;
+                if v != zero {
+                    added.insert(k.clone(), v);
+                }
+            }
+            Ok(added)
+        };
+        let crafting_time = match self.crafting_time {
+            Some(time) => Some(time.checked_sub(&before.crafting_time.unwrap_or(zero)).ok_or_else(|| fail("crafting time"))?),
+            None => None
+        };
+        Ok(Summary {
+            raw: added(&self.raw, &before.raw)?,
+            crafting_time,
+            power: added(&self.power, &before.power)?,
+            machines: self.machines.iter().map(| (k, v) | (k.clone(), v - before.machines.get(k).copied().unwrap_or(0))).filter(| (_, v) | *v > 0).collect(),
+            surplus: added(&self.surplus, &before.surplus)?,
+            shared: added(&self.shared, &before.shared)?,
+            purchases: added(&self.purchases, &before.purchases)?,
+            supplied: added(&self.supplied, &before.supplied)?
+        })
+    }
+
+    // takes what surplus there is of `widget`, returning the rate still to be produced
+    fn draw_surplus(&mut self, widget: &String, rate: Rational64) -> Result<Rational64> {
+        let zero = Rational64::from_integer(0);
+        let fail = || Error::Arithmetic(format!("drawing {rate}/s of `{widget}` from surplus overflows 64-bit rationals", rate=rate, widget=widget));
+        match self.surplus.get_mut(widget).filter(| r | **r > zero) {
+            Some(surplus) => {
+                let taken = rate.min(*surplus);
+                *surplus = surplus.checked_sub(&taken).ok_or_else(fail)?;
+                add_rate(&mut self.shared, widget, taken)?;
+                rate.checked_sub(&taken).ok_or_else(fail)
+            },
+            None => Ok(rate)
+        }
+    }
+}
+
+// widgets nothing produces are extracted, not crafted
+fn is_raw(graph: &Hypergraph<String, Recipe>, widget: &String) -> Result<bool> {
+    graph.neighbor_of(widget).map(| edges | edges.is_empty()).map_err(| _ | Error::UnknownWidget(widget.clone()))
+}
+
+fn record(summary: &mut Summary, node: &PlanNode, widget: &String) -> Result<()> {
+    let PlanNode { recipe, machine_count: count, machines, clock, requested_rate: rate, .. } = *node;
+    let fail = || overflow(widget, rate, recipe);
+    if recipe.is_manual() {
+        let time = rate.checked_div(&recipe.rate(widget)?).ok_or_else(fail)?;
+        accumulate(summary.crafting_time.get_or_insert_with(|| Rational64::from_integer(0)), time).ok_or_else(fail)?;
+    }
+    let total = summary.machines.entry(recipe.builder_name().to_owned()).or_insert(0);
+    *total = total.checked_add(count).ok_or_else(fail)?;
+    if let Some(power) = recipe.power_at(clock) {
+        let drawn = power.checked_mul(&machines).ok_or_else(fail)?;
+        accumulate(summary.power.entry(recipe.builder_name().to_owned()).or_insert_with(|| Rational64::from_integer(0)), drawn).ok_or_else(fail)?;
+    }
+    // rounding up machine counts overproduces the requested widget, and every other product is a byproduct
+    for product in recipe.products.iter() {
+        let mut made = recipe.rate(&product.widget)?.checked_mul(&machines).and_then(| r | r.checked_mul(&clock)).ok_or_else(fail)?;
+        if &product.widget == widget {
+            made = made.checked_sub(&rate).ok_or_else(fail)?;
+        }
+        if made > Rational64::from_integer(0) {
+            accumulate(summary.surplus.entry(product.widget.clone()).or_insert_with(|| Rational64::from_integer(0)), made).ok_or_else(fail)?;
+        }
+    }
+    Ok(())
+}
+
+// what one solve already worked out, for widgets needed at the same rate in several places
+#[derive(Default)]
+struct Memo<'a> {
+    // the heuristic's stage by widget and rate
+    stages: HashMap<(String, Rational64), PlanNode<'a>>,
+    // where finished subtrees are in the plan with what they added to the summary, by widget, rate and, when expansion is
+    // limited, depth; unused when sharing surplus, as a subtree then depends on what was planned before it
+    subtrees: HashMap<(String, Rational64, usize), (Vec<usize>, Summary)>
+}
+
+impl<'a> Memo<'a> {
+    fn stage(&mut self, graph: &Hypergraph<String, Recipe>, recipes: impl IntoIterator<Item=&'a Recipe>, widget: &String, rate: Rational64, constraints: &Constraints) -> Result<PlanNode<'a>> {
+        if let Some(node) = self.stages.get(&(widget.clone(), rate)) {
+            return Ok(*node);
+        }
+        let node = least_waste_heuristic(graph, recipes, widget, rate, constraints)?;
+        self.stages.insert((widget.clone(), rate), node);
+        Ok(node)
+    }
+
+    // the stages of `previous` making widgets outside `changed`, with their recipes looked up again in
+    // `graph` and `extraction`; stages whose recipe is gone are left to be planned afresh
+    fn seeded(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], previous: &PlanTree, widget: &str, changed: &HashSet<String>) -> Result<Self> {
+        let mut memo = Memo::default();
+        let mut stages = vec![(previous, widget.to_owned())];
+        while let Some((tree, widget)) = stages.pop() {
+            for (child, (reagent, _)) in tree.children().iter().zip(child_flows(tree)) {
+                stages.push((child, reagent.widget.clone()));
+            }
+            if changed.contains(&widget) || memo.stages.contains_key(&(widget.clone(), tree.requested_rate)) {
+                continue;
+            }
+            let same = | r: &&'a Recipe | r.name == tree.recipe.name && r.builder == tree.recipe.builder;
+            let recipe = match is_raw(graph, &widget)? {
+                true => extraction.iter().find(same),
+                false => producers(graph, &widget)?.into_iter().find(same)
+            };
+            if let Some(node) = recipe.map(| recipe | PlanNode { recipe, ..**tree }).filter(| node | node.check_draws().is_ok()) {
+                memo.stages.insert((widget, tree.requested_rate), node);
+            }
+        }
+        Ok(memo)
+    }
+
+    fn subtree_key(widget: &str, rate: Rational64, depth: usize, constraints: &Constraints) -> Option<(String, Rational64, usize)> {
+        (!constraints.share_surplus).then(|| (widget.to_owned(), rate, if constraints.max_depth.is_some() { depth } else { 0 }))
+    }
+}
+
+// plans a single stage for `widget` `depth` stages down, the flag telling whether its reagents still need planning;
+// raw resources are recorded in the summary and expanded into extractors when any can reach them, and None is
+// returned for what is supplied, bought or extracted by nothing
+#[allow(clippy::too_many_arguments)]
+fn plan_stage<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, depth: usize, constraints: &Constraints, summary: &mut Summary,
+    memo: &mut Memo<'a>) -> Result<Option<(PlanNode<'a>, bool)>> {
+    // only surplus from branches already planned can be shared
+    let rate = if constraints.share_surplus { summary.draw_surplus(widget, rate)? } else { rate };
+    if rate <= Rational64::from_integer(0) {
+        return Ok(None);
+    }
+    if constraints.max_depth.is_some_and(| max | depth > max) {
+        add_rate(&mut summary.supplied, widget, rate)?;
+        return Ok(None);
+    }
+    if buys(graph, extraction, widget, constraints)? {
+        add_rate(&mut summary.purchases, widget, rate)?;
+        return Ok(None);
+    }
+    if is_raw(graph, widget)? {
+        add_rate(&mut summary.raw, widget, rate)?;
+        let extractors = extraction.iter().filter(| r | r.products.iter().any(| p | &p.widget == widget));
+        return match memo.stage(graph, extractors, widget, rate, constraints) {
+            Ok(node) => {
+                record(summary, &node, widget)?;
+                Ok(Some((node, false)))
+            },
+            Err(Error::NoRecipes(_)) => Ok(None),
+            Err(e) => Err(e)
+        };
+    }
+    let node = memo.stage(graph, producers(graph, widget)?, widget, rate, constraints)?;
+    record(summary, &node, widget)?;
+    Ok(Some((node, true)))
+}
+
+// plans depth first on an explicit stack of the stages still taking children, so the length of a
+// chain is bounded by memory instead of the call stack
+// `on_stage` sees every stage as it is planned, with its path from the root in the finished tree,
+// the root being `start` stages down from the goal
+#[allow(clippy::too_many_arguments)]
+fn dep_tree<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, start: usize, constraints: &Constraints, summary: &mut Summary,
+    mut memo: Memo<'a>, on_stage: &mut dyn FnMut(&[usize], &PlanNode)) -> Result<Option<PlanTree<'a>>> {
+    let node = match plan_stage(graph, extraction, widget, rate, start, constraints, summary, &mut memo)? {
+        Some((node, expand)) => {
+            on_stage(&[], &node);
+            if !expand {
+                return Ok(Some(NTree::new(node)));
+            }
+            node
+        },
+        None => return Ok(None)
+    };
+    // each stage taking children keeps its memo key and the summary from before it, to memoize its subtree once done
+    let mut stack = vec![(NTree::new(node), node.recipe.reagents.iter(), None)];
+    // the path of the stage on top of the stack, children being appended in the order they are planned
+    let mut path = Vec::new();
+    loop {
+        let depth = start + stack.len();
+        let (tree, reagents, _) = stack.last_mut().expect("the root stays on the stack until it is done");
+        match reagents.next() {
+            Some(reagent) => {
+                let requested_rate = tree.recipe.drawn(reagent, tree.machines, tree.clock)?;
+                let key = Memo::subtree_key(&reagent.widget, requested_rate, depth, constraints);
+                if let Some((at, added)) = key.as_ref().and_then(| k | memo.subtrees.get(k)) {
+                    // stack[k] is the stage at the first k indices of `path`, and the finished subtree hangs
+                    // below the one at the longest prefix its path shares with it
+                    let shared = path.iter().zip(at).take_while(| (a, b) | a == b).count();
+                    let copy = stack[shared].0.at(&at[shared..]).expect("memoized subtrees stay in the plan").clone();
+                    path.push(stack.last().expect("the stack is not empty").0.children().len());
+                    each_stage(&copy, &mut path, on_stage);
+                    path.pop();
+                    summary.merge(added.clone())?;
+                    stack.last_mut().expect("the stack is not empty").0.insert(copy);
+                    continue;
+                }
+                let before = key.as_ref().map(| _ | summary.clone());
+                if let Some((child, expand)) = plan_stage(graph, extraction, &reagent.widget, requested_rate, depth, constraints, summary, &mut memo)? {
+                    path.push(tree.children().len());
+                    on_stage(&path, &child);
+                    if expand {
+                        stack.push((NTree::new(child), child.recipe.reagents.iter(), key.zip(before)));
+                    } else {
+                        path.pop();
+                        tree.insert(NTree::new(child));
+                    }
+                }
+            },
+            None => {
+                let (done, _, memoize) = stack.pop().expect("the stack is not empty");
+                if let Some((key, before)) = memoize {
+                    memo.subtrees.insert(key, (path.clone(), summary.since(&before)?));
+                }
+                path.pop();
+                match stack.last_mut() {
+                    Some((parent, _, _)) => parent.insert(done),
+                    None => return Ok(Some(done))
+                }
+            }
+        }
+    }
+}
+
+/// The machines that make a widget: a tree of stages from the goal down to its inputs, the goal
+/// being a raw resource when there is none, with the totals over all of them.
+pub struct Plan<'a> {
+    pub widget: String,
+    // units/second
+    pub rate: Rational64,
+    pub tree: Option<PlanTree<'a>>,
+    pub summary: Summary
+}
+
+/// How [`Plan::render`] writes a plan out.
+pub enum Format {
+    // the stage tree as `solve` prints it
+    Text,
+    // the saved plan format `show` reads, without builder limits
+    Json
+}
+
+impl<'a> Plan<'a> {
+    pub fn total_machines(&self) -> u64 {
+        self.summary.machines.values().sum()
+    }
+
+    // units/second by raw resource
+    pub fn raw_inputs(&self) -> &BTreeMap<String, Rational64> {
+        &self.summary.raw
+    }
+
+    // the plan written out, with the transport warnings of a text tree
+    pub fn render(&self, format: Format, cookbook: &Cookbook, precision: Precision) -> (String, Vec<String>) {
+        match (format, &self.tree) {
+            (Format::Text, Some(tree)) => tree_text(tree, None, cookbook, precision),
+            (Format::Text, None) => (format!("{widget} is a raw resource\n", widget=self.widget), Vec::new()),
+            (Format::Json, tree) => {
+                let saved = saved::SavedPlan::new(cookbook, &self.widget, self.rate, tree.as_ref(), self.summary.clone(), &BTreeMap::new());
+                (serde_json::to_string_pretty(&saved).expect("plans serialize to JSON"), Vec::new())
+            }
+        }
+    }
+}
+
+/// Plans `widget` at `rate` units/second from the recipes in `graph` and the `extraction` recipes
+/// of [`Cookbook::extraction`], within `constraints`.
+/// Branches below the goal are planned on threads of their own when the `threads` feature is on, there
+/// are cores for them and no surplus is shared between them; without it, as for `wasm32-unknown-unknown`,
+/// they are planned in turn.
+pub fn solve<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Plan<'a>> {
+    #[cfg(feature = "threads")]
+    {
+        let cores = std::thread::available_parallelism().map_or(1, | n | n.get());
+        if !constraints.network && !constraints.share_surplus && cores > 1 {
+            return solve_parallel(graph, extraction, widget, rate, constraints);
+        }
+    }
+    solve_streaming(graph, extraction, widget, rate, constraints, &mut | _, _ | {})
+}
+
+#[cfg(feature = "threads")]
+fn solve_parallel<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Plan<'a>> {
+    let mut summary = Summary::default();
+    let node = match plan_stage(graph, extraction, widget, rate, 1, constraints, &mut summary, &mut Memo::default())? {
+        Some((node, true)) => node,
+        Some((node, false)) => return Ok(Plan { widget: widget.clone(), rate, tree: Some(NTree::new(node)), summary }),
+        None => return Ok(Plan { widget: widget.clone(), rate, tree: None, summary })
+    };
+    // each branch gets its own summary, merged in reagent order so the totals come out as planning them in turn would
+    let branches: Vec<Result<(Option<PlanTree<'a>>, Summary)>> = std::thread::scope(| scope | {
+        let handles: Vec<_> = node.recipe.reagents.iter().map(| reagent | {
+            let requested_rate = node.recipe.drawn(reagent, node.machines, node.clock);
+            scope.spawn(move || {
+                let mut branch = Summary::default();
+                let requested_rate = requested_rate?;
+                dep_tree(graph, extraction, &reagent.widget, requested_rate, 2, constraints, &mut branch, Memo::default(), &mut | _, _ | {}).map(| tree | (tree, branch))
+            })
+        }).collect();
+        handles.into_iter().map(| h | h.join().unwrap_or_else(| panic | std::panic::resume_unwind(panic))).collect()
+    });
+    let mut tree = NTree::new(node);
+    for branch in branches {
+        let (child, branch) = branch?;
+        summary.merge(branch)?;
+        if let Some(child) = child {
+            tree.insert(child);
+        }
+    }
+    Ok(Plan { widget: widget.clone(), rate, tree: Some(tree), summary })
+}
+
+// every stage of `tree` with its path, parents before children
+fn each_stage(tree: &PlanTree, path: &mut Vec<usize>, on_stage: &mut dyn FnMut(&[usize], &PlanNode)) {
+    on_stage(path, tree);
+    for (i, child) in tree.children().iter().enumerate() {
+        path.push(i);
+        each_stage(child, path, on_stage);
+        path.pop();
+    }
+}
+
+/// Like [`solve`], handing each stage to `on_stage` with its path in the plan's tree as soon as it
+/// is planned, for showing big plans while they are worked out. Networks are solved as a whole and
+/// hand over their stages once done.
+pub fn solve_streaming<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    on_stage: &mut dyn FnMut(&[usize], &PlanNode)) -> Result<Plan<'a>> {
+    if constraints.network {
+        let plan = network::solve(graph, extraction, widget, rate, constraints)?;
+        if let Some(tree) = &plan.tree {
+            each_stage(tree, &mut Vec::new(), on_stage);
+        }
+        return Ok(plan);
+    }
+    let mut summary = Summary::default();
+    let tree = dep_tree(graph, extraction, widget, rate, 1, constraints, &mut summary, Memo::default(), on_stage)?;
+    Ok(Plan { widget: widget.clone(), rate, tree, summary })
+}
+
+/// Like [`solve`], reusing the stages of `previous`, a plan for the same goal from an earlier
+/// version of the game definition, for every widget outside `changed` (see
+/// [`Cookbook::changed_widgets`]) instead of choosing their recipes again. Solves from scratch when
+/// the choice of a stage can depend on more than its own widget's recipes: with surplus sharing,
+/// networks, prices, a cost expression, downstream waste or minimizing power.
+pub fn solve_incremental<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    previous: &PlanTree, changed: &HashSet<String>) -> Result<Plan<'a>> {
+    if constraints.share_surplus || constraints.network || !constraints.prices.is_empty() || constraints.cost.is_some() || constraints.downstream_waste || constraints.minimize_power {
+        return solve(graph, extraction, widget, rate, constraints);
+    }
+    let memo = Memo::seeded(graph, extraction, previous, widget, changed)?;
+    let mut summary = Summary::default();
+    let tree = dep_tree(graph, extraction, widget, rate, 1, constraints, &mut summary, memo, &mut | _, _ | {})?;
+    Ok(Plan { widget: widget.clone(), rate, tree, summary })
+}
+
+fn normal_purity() -> String {
+    "normal".to_owned()
+}
+
+// a solve as the C interface and the HTTP server take it in JSON, e.g. {"widget": "stator", "rate": "1/3",
+// "purity": "pure", "max_tier": 4, "unlocked": ["..."], "limits": {"Assembler": 10}}
+#[derive(Deserialize)]
+pub struct SolveRequest {
+    pub widget: String,
+    // units/second
+    #[serde(deserialize_with="deserialize_rational")]
+    pub rate: Rational64,
+    #[serde(default="normal_purity")]
+    pub purity: String,
+    #[serde(default)]
+    pub max_tier: Option<u32>,
+    #[serde(default)]
+    pub unlocked: Vec<String>,
+    #[serde(default)]
+    pub limits: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub exact_clock: bool,
+    #[serde(default)]
+    pub share_surplus: bool,
+    #[serde(default)]
+    pub network: bool,
+    #[serde(default)]
+    pub game_version: Option<version::GameVersion>
+}
+
+impl SolveRequest {
+    pub fn constraints(&self) -> Constraints {
+        Constraints {
+            max_tier: self.max_tier,
+            exact_clock: self.exact_clock,
+            unlocked: self.unlocked.iter().cloned().collect(),
+            limits: self.limits.clone(),
+            share_surplus: self.share_surplus,
+            network: self.network,
+            game_version: self.game_version.clone(),
+            ..Default::default()
+        }
+    }
+
+    // `extraction` being the cookbook's for the requested purity; fails when the limits can't fit the plan
+    pub fn solve<'a>(&self, graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe]) -> Result<Plan<'a>> {
+        self.solve_streaming(graph, extraction, &mut | _, _ | {})
+    }
+
+    // as solve_streaming(), plans within builder limits handing over their stages once they fit
+    pub fn solve_streaming<'a>(&self, graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], on_stage: &mut dyn FnMut(&[usize], &PlanNode)) -> Result<Plan<'a>> {
+        let constraints = self.constraints();
+        if constraints.limits.is_empty() {
+            return solve_streaming(graph, extraction, &self.widget, self.rate, &constraints, on_stage);
+        }
+        let plan = solve_within_limits(graph, extraction, &self.widget, self.rate, &constraints)?.ok_or_else(|| Error::LimitsExceeded(self.widget.clone()))?;
+        if let Some(tree) = &plan.tree {
+            each_stage(tree, &mut Vec::new(), on_stage);
+        }
+        Ok(plan)
+    }
+}
+
+// machines needed beyond the builder limits
+fn excess(summary: &Summary, constraints: &Constraints) -> u64 {
+    constraints.limits.iter().map(| (builder, limit) | summary.machines.get(builder).map_or(0, | used | used.saturating_sub(*limit))).sum()
+}
+
+fn collect_recipes<'a>(tree: &PlanTree<'a>, recipes: &mut Vec<&'a Recipe>) {
+    recipes.push(tree.recipe);
+    for child in tree.children() {
+        collect_recipes(child, recipes);
+    }
+}
+
+// greedily excludes recipes running on over-limit builders until the plan fits, None if it never does
+pub fn solve_within_limits<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Option<Plan<'a>>> {
+    match search_within_limits(graph, extraction, widget, rate, constraints, None)? {
+        Search::Fits(plan) => Ok(Some(plan)),
+        Search::Exhausted | Search::TimedOut(..) => Ok(None)
+    }
+}
+
+// how a search for a plan within the builder limits ended
+pub enum Search<'a> {
+    Fits(Plan<'a>),
+    // excluding more recipes doesn't bring the plan any closer
+    Exhausted,
+    // the deadline passed first, leaving the plan closest to fitting and how many machines it is over
+    TimedOut(Plan<'a>, u64)
+}
+
+// solve_within_limits(), giving up at `deadline` with the best plan so far
+pub fn search_within_limits<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    deadline: Option<Instant>) -> Result<Search<'a>> {
+    let mut constraints = constraints.clone();
+    let mut best = solve(graph, extraction, widget, rate, &constraints)?;
+    let mut best_excess = excess(&best.summary, &constraints);
+    while best_excess > 0 {
+        let mut candidates = Vec::new();
+        if let Some(tree) = &best.tree {
+            collect_recipes(tree, &mut candidates);
+        }
+        candidates.retain(| r | constraints.limits.get(r.builder_name()).is_some_and(| limit | best.summary.machines[r.builder_name()] > *limit));
+        // a recipe used in several branches is tried once, where it first appears
+        let mut seen = HashSet::new();
+        candidates.retain(| r | seen.insert(&r.name));
+        let mut improved: Option<(Plan<'a>, Constraints, u64)> = None;
+        for recipe in candidates {
+            if deadline.is_some_and(| d | Instant::now() >= d) {
+                return Ok(Search::TimedOut(improved.map_or(best, | (plan, ..) | plan), best_excess));
+            }
+            let mut trial = constraints.clone();
+            if !trial.excluded.insert(recipe.name.clone()) {
+                continue;
+            }
+            let solution = match solve(graph, extraction, widget, rate, &trial) {
+                Ok(solution) => solution,
+                Err(Error::NoRecipes(_)) => continue,
+                Err(e) => return Err(e)
+            };
+            let trial_excess = excess(&solution.summary, &trial);
+            if trial_excess < improved.as_ref().map_or(best_excess, | (_, _, e) | *e) {
+                improved = Some((solution, trial, trial_excess));
+            }
+        }
+        match improved {
+            Some((solution, trial, trial_excess)) => {
+                best = solution;
+                constraints = trial;
+                best_excess = trial_excess;
+            },
+            None => return Ok(Search::Exhausted)
+        }
+    }
+    Ok(Search::Fits(best))
+}
+
+// a rate that fits the builder limits and its plan, if any does
+pub type BestRate<'a> = Option<(Rational64, Plan<'a>)>;
+
+// highest fraction of `rate` (in 1/1024 steps) that fits the builder limits
+pub fn best_rate_within_limits<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<BestRate<'a>> {
+    best_rate_by(graph, extraction, widget, rate, constraints, None).map(| (best, _) | best)
+}
+
+// best_rate_within_limits(), giving up at `deadline`; cut short, the lowest rate found not to fit comes
+// along, bounding how far off the best so far can be
+pub fn best_rate_by<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    deadline: Option<Instant>) -> Result<(BestRate<'a>, Option<Rational64>)> {
+    let steps = 1024;
+    let fail = || Error::Arithmetic(format!("searching for the best rate up to {rate}/s of `{widget}` overflows 64-bit rationals", rate=rate, widget=widget));
+    let (mut lo, mut hi) = (0, steps);
+    let mut best = None;
+    while hi - lo > 1 && deadline.is_none_or(| d | Instant::now() < d) {
+        let mid = (lo + hi) / 2;
+        let trial_rate = rate.checked_mul(&Rational64::new(mid, steps)).ok_or_else(fail)?;
+        match search_within_limits(graph, extraction, widget, trial_rate, constraints, deadline)? {
+            Search::Fits(solution) => {
+                lo = mid;
+                best = Some((trial_rate, solution));
+            },
+            Search::Exhausted => hi = mid,
+            // neither bound moves on an unfinished trial
+            Search::TimedOut(..) => break
+        }
+    }
+    let bound = if hi - lo > 1 { Some(rate.checked_mul(&Rational64::new(hi, steps)).ok_or_else(fail)?) } else { None };
+    Ok((best, bound))
+}
+
+pub struct PowerPlant<'a> {
+    pub generator: &'a Generator,
+    pub count: u64,
+    pub fuel: Vec<PlanTree<'a>>,
+    // the fuel chains, generators included as machines
+    pub summary: Summary
+}
+
+// generators covering `demand` plus whatever their own fuel chains draw
+pub fn power_plant<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], generator: &'a Generator, demand: Rational64, constraints: &Constraints) -> Result<PowerPlant<'a>> {
+    if generator.power <= Rational64::from_integer(0) {
+        return Err(Error::Arithmetic(format!("generator `{name}` has a non-positive power", name=generator.name)));
+    }
+    let overflow = || Error::Arithmetic(format!("powering {demand} MW with `{name}` overflows 64-bit rationals", demand=demand, name=generator.name));
+    let generators = | power: Rational64 | power.checked_div(&generator.power).map(| count | count.ceil().to_integer() as u64).ok_or_else(overflow);
+    let mut count = generators(demand)?;
+    // the count only grows, so a chain drawing less than its generators supply settles quickly
+    for _ in 0..100 {
+        let mut summary = Summary::default();
+        let mut fuel = Vec::new();
+        for reagent in generator.fuel.iter() {
+            let rate = i64::try_from(count).ok().and_then(| count | reagent.quantity.checked_mul(&Rational64::from_integer(count))).ok_or_else(overflow)?;
+            if let Some(tree) = dep_tree(graph, extraction, &reagent.widget, rate, 1, constraints, &mut summary, Memo::default(), &mut | _, _ | {})? {
+                fuel.push(tree);
+            }
+        }
+        let needed = generators(demand.checked_add(&summary.total_power()?).ok_or_else(overflow)?)?;
+        if needed <= count {
+            summary.machines.insert(generator.name.clone(), count);
+            return Ok(PowerPlant { generator, count, fuel, summary });
+        }
+        count = needed;
+    }
+    Err(Error::PowerDiverges(generator.name.clone()))
+}
+
+impl Cookbook {
+    // units/second by widget the builders' own consumption takes for `machines` of each
+    pub fn upkeep(&self, machines: &BTreeMap<String, u64>) -> Result<BTreeMap<String, Rational64>> {
+        let mut demand = BTreeMap::new();
+        for builder in self.builders.iter() {
+            let machines = machines.get(&builder.name).copied().unwrap_or(0);
+            let fail = || Error::Arithmetic(format!("the upkeep of {machines} `{name}` overflows 64-bit rationals", machines=machines, name=builder.name));
+            let count = Rational64::from_integer(i64::try_from(machines).map_err(| _ | fail())?);
+            for reagent in builder.consumes.iter().filter(| _ | count > Rational64::from_integer(0)) {
+                add_rate(&mut demand, &reagent.widget, reagent.quantity.checked_mul(&count).ok_or_else(fail)?)?;
+            }
+        }
+        Ok(demand)
+    }
+}
+
+pub struct Upkeep<'a> {
+    // units/second by widget, for the plan's machines and those of the chains making it
+    pub demand: BTreeMap<String, Rational64>,
+    pub chains: Vec<PlanTree<'a>>,
+    pub summary: Summary
+}
+
+/// The chains making what `machines` and the chains' own machines use up as they run, found by
+/// solving again until the machines stop changing; None when no builder consumes anything.
+pub fn upkeep<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], cookbook: &Cookbook, machines: &BTreeMap<String, u64>, constraints: &Constraints) -> Result<Option<Upkeep<'a>>> {
+    let mut chain_machines: BTreeMap<String, u64> = BTreeMap::new();
+    for _ in 0..100 {
+        let mut total = machines.clone();
+        for (builder, count) in chain_machines.iter() {
+            let machines = total.entry(builder.clone()).or_insert(0);
+            *machines = machines.checked_add(*count).ok_or_else(|| Error::Arithmetic(format!("the count of `{builder}` overflows", builder=builder)))?;
+        }
+        let demand = cookbook.upkeep(&total)?;
+        if demand.is_empty() {
+            return Ok(None);
+        }
+        let mut summary = Summary::default();
+        let mut chains = Vec::new();
+        for (widget, rate) in demand.iter() {
+            if let Some(tree) = dep_tree(graph, extraction, widget, *rate, 1, constraints, &mut summary, Memo::default(), &mut | _, _ | {})? {
+                chains.push(tree);
+            }
+        }
+        if summary.machines == chain_machines {
+            return Ok(Some(Upkeep { demand, chains, summary }));
+        }
+        chain_machines = summary.machines.clone();
+    }
+    Err(Error::UpkeepDiverges)
+}
+
+pub fn describe(node: &PlanNode, precision: Precision) -> String {
+    let mut line = format!("{quantity}x {builder}", quantity=decimal(&node.machines, 3), builder=node.recipe.builder_name());
+    if node.clock != Rational64::from_integer(1) {
+        line.push_str(&format!(" @ {clock}%", clock=precision.percent(&node.clock)));
+    }
+    line.push_str(&format!(" -> {name}", name=node.recipe.name));
+    if let Some(power) = node.recipe.power_at(node.clock) {
+        line.push_str(&format!(" ({power} MW)", power=precision.fixed(as_f64(&(power * node.machines)), 1)));
+    }
+    line
+}
+
+// the reagent each child supplies and the rate its parent draws it at, children being in reagent order
+fn child_flows<'a>(tree: &PlanTree<'a>) -> Vec<(&'a Reagent, Rational64)> {
+    let recipe = tree.recipe;
+    let mut reagents = recipe.reagents.iter();
+    tree.children().iter().map(| child | {
+        let reagent = reagents.find(| r | child.recipe.products.iter().any(| p | p.widget == r.widget)).expect("children follow reagent order");
+        (reagent, tree.draws(reagent))
+    }).collect()
+}
+
+fn logistics(cookbook: &Cookbook, reagent: &Reagent, flow: Rational64) -> String {
+    match transport_for(cookbook.transport(&reagent.widget), flow) {
+        Some((tier, count)) => format!(" [{count}x {tier}]", count=count, tier=tier.name),
+        None => "".to_owned()
+    }
+}
+
+/// The flows between stages too much for a single belt or pipe of the fastest tier, in the order
+/// [`tree_text`] draws them, for the caller to warn about.
+pub fn transport_warnings(tree: &PlanTree, cookbook: &Cookbook, precision: Precision) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (child, (reagent, flow)) in tree.children().iter().zip(child_flows(tree)) {
+        if let Some((tier, _)) = transport_for(cookbook.transport(&reagent.widget), flow).filter(| (_, count) | *count > 1) {
+            warnings.push(format!("{rate} of {widget} exceeds a single {tier}", rate=cookbook.format_rate(&reagent.widget, &flow, precision), widget=reagent.widget, tier=tier.name));
+        }
+        warnings.extend(transport_warnings(child, cookbook, precision));
+    }
+    warnings
+}
+
+// a plan node's running cost, its own machines plus everything feeding it
+pub struct NodeCost {
+    pub per_second: Rational64,
+    // per unit delivered to the parent, or out of the factory for the root
+    pub per_unit: Rational64
+}
+
+pub type CostTree = NTree<NodeCost>;
+
+// rolls operating costs up the plan, inputs no node makes being valued at their market price
+pub fn cost_tree(tree: &PlanTree, delivered: Rational64, prices: &BTreeMap<String, Rational64>) -> Result<CostTree> {
+    let recipe = tree.recipe;
+    let overflow = || Error::Arithmetic(format!("the operating cost of recipe `{name}` overflows 64-bit rationals", name=recipe.name));
+    let flows = child_flows(tree);
+    let children = tree.children().iter().zip(flows.iter()).map(| (child, (_, flow)) | cost_tree(child, *flow, prices)).collect::<Result<Vec<CostTree>>>()?;
+    let mut per_second = recipe.operating_cost().checked_mul(&tree.machines).ok_or_else(overflow)?;
+    for child in children.iter() {
+        accumulate(&mut per_second, child.per_second).ok_or_else(overflow)?;
+    }
+    for reagent in recipe.reagents.iter().filter(| r | !flows.iter().any(| (fed, _) | fed.widget == r.widget)) {
+        if let Some(price) = prices.get(&reagent.widget) {
+            price.checked_mul(&tree.draws(reagent)).and_then(| cost | accumulate(&mut per_second, cost)).ok_or_else(overflow)?;
+        }
+    }
+    let per_unit = if delivered > Rational64::from_integer(0) { per_second.checked_div(&delivered).ok_or_else(overflow)? } else { Rational64::from_integer(0) };
+    let mut node = NTree::new(NodeCost { per_second, per_unit });
+    for child in children {
+        node.insert(child);
+    }
+    Ok(node)
+}
+
+// how much of a node's capacity its consumer draws once machine counts are rounded up
+pub struct NodeLoad<'a> {
+    recipe: &'a Recipe,
+    count: u64,
+    widget: String,
+    // delivered over capacity
+    load: Rational64,
+    // units/second the node could make on top
+    spare: Rational64
+}
+
+pub type LoadTree<'a> = NTree<NodeLoad<'a>>;
+
+pub fn load_tree<'a>(tree: &PlanTree<'a>, widget: &String, delivered: Rational64) -> Result<LoadTree<'a>> {
+    let PlanNode { recipe, machine_count: count, machines, clock, .. } = **tree;
+    let overflow = || Error::Arithmetic(format!("the load on recipe `{name}` overflows 64-bit rationals", name=recipe.name));
+    let capacity = recipe.rate(widget)?.checked_mul(&machines).and_then(| r | r.checked_mul(&clock)).ok_or_else(overflow)?;
+    let load = if capacity > Rational64::from_integer(0) { delivered.checked_div(&capacity).ok_or_else(overflow)? } else { Rational64::from_integer(0) };
+    let spare = capacity.checked_sub(&delivered).ok_or_else(overflow)?;
+    let mut node = NTree::new(NodeLoad { recipe, count, widget: widget.clone(), load, spare });
+    // children are sized for the node at full capacity but are only drawn on as much as it is
+    for (child, (reagent, flow)) in tree.children().iter().zip(child_flows(tree)) {
+        node.insert(load_tree(child, &reagent.widget, flow.checked_mul(&load).ok_or_else(overflow)?)?);
+    }
+    Ok(node)
+}
+
+fn collect_loads<'a, 'b>(tree: &'b LoadTree<'a>, loads: &mut Vec<&'b NodeLoad<'a>>) {
+    loads.push(tree);
+    for child in tree.children() {
+        collect_loads(child, loads);
+    }
+}
+
+// extra remarks per plan node, in the same shape as the plan
+pub type Notes = NTree<String>;
+
+// appends `extra` to every node's notes
+pub fn merge_notes(notes: &Notes, extra: &Notes) -> Notes {
+    let mut node = NTree::new(format!("{}{}", **notes, **extra));
+    for (child, extra_child) in notes.children().iter().zip(extra.children()) {
+        node.insert(merge_notes(child, extra_child));
+    }
+    node
+}
+
+pub fn annotate_bottlenecks(load: &LoadTree, costs: Option<&CostTree>) -> Notes {
+    let mut loads = Vec::new();
+    collect_loads(load, &mut loads);
+    let bottleneck = loads.iter().map(| l | l.load).max().unwrap_or_else(|| Rational64::from_integer(1));
+    annotate(load, bottleneck, costs)
+}
+
+// marks the stages closest to capacity, which need machines first when scaling up
+fn annotate(load: &LoadTree, bottleneck: Rational64, costs: Option<&CostTree>) -> Notes {
+    let mut note = String::new();
+    if let Some(cost) = costs {
+        note.push_str(&format!(" [{cost:.2} per unit]", cost=as_f64(&cost.per_unit)));
+    }
+    if load.load >= bottleneck {
+        note.push_str(" [bottleneck]");
+    }
+    let mut node = NTree::new(note);
+    for (i, child) in load.children().iter().enumerate() {
+        node.insert(annotate(child, bottleneck, costs.map(| c | &c.children()[i])));
+    }
+    node
+}
+
+// what a leaf draws from outside the plan: the resource an extractor works, or a crafted leaf's reagents
+fn leaf_inputs(tree: &PlanTree, cookbook: &Cookbook, precision: Precision) -> String {
+    if !tree.children().is_empty() {
+        return String::new();
+    }
+    let PlanNode { recipe, requested_rate, .. } = **tree;
+    let inputs: Vec<(&String, Rational64)> = match recipe.products.first() {
+        Some(product) if recipe.reagents.is_empty() => vec![(&product.widget, requested_rate)],
+        _ => recipe.reagents.iter().map(| r | (&r.widget, tree.draws(r))).collect()
+    };
+    inputs.iter().map(| (widget, rate) | format!(" ← {rate} {widget}", rate=cookbook.format_rate(widget, rate, precision), widget=widget)).collect()
+}
+
+// each stage with the belts or pipes into its parent and its notes, with the transport warnings to print
+pub fn tree_text(tree: &PlanTree, notes: Option<&Notes>, cookbook: &Cookbook, precision: Precision) -> (String, Vec<String>) {
+    let text = tree.render(| node, path | {
+        let edge = match path.split_last() {
+            Some((i, parent)) => {
+                let (reagent, flow) = child_flows(tree.at(parent).expect("parent of a rendered node"))[*i];
+                logistics(cookbook, reagent, flow)
+            },
+            None => String::new()
+        };
+        let note = notes.and_then(| n | n.at(path)).map_or("", | n | n.as_str());
+        format!("{node} [{utilization}% used]{edge}{inputs}{note}", node=describe(node, precision), utilization=decimal(&(node.utilization() * 100), precision.places(1)), edge=edge,
+            inputs=leaf_inputs(tree.at(path).expect("rendered node"), cookbook, precision), note=note)
+    });
+    (text, transport_warnings(tree, cookbook, precision))
+}
+
+pub fn bottlenecks_text(load: &LoadTree, cookbook: &Cookbook, precision: Precision) -> String {
+    let mut text = String::new();
+    let mut loads = Vec::new();
+    collect_loads(load, &mut loads);
+    loads.sort_by_key(| l | std::cmp::Reverse(l.load));
+    writeln!(text).unwrap();
+    writeln!(text, "Bottlenecks:").unwrap();
+    for node in loads.iter().take(3) {
+        writeln!(text, "  {count}x {builder} -> {name}: {load}% of capacity, {spare} spare", count=node.count, builder=node.recipe.builder_name(), name=node.recipe.name,
+            load=precision.fixed(as_f64(&node.load) * 100.0, 1), spare=cookbook.format_rate(&node.widget, &node.spare, precision)).unwrap();
+    }
+    text
+}
+
+pub fn as_f64(r: &Rational64) -> f64 {
+    *r.numer() as f64 / *r.denom() as f64
+}
+
+// decimal places for rates, percentages and power in an output, None leaving each figure its own
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Precision(pub Option<usize>);
+
+impl Precision {
+    // `default` decimal places unless the precision was set
+    pub fn places(self, default: usize) -> usize {
+        self.0.unwrap_or(default)
+    }
+
+    // `value` to `default` decimal places, or to the precision set
+    pub fn fixed(self, value: f64, default: usize) -> String {
+        format!("{:.*}", self.places(default), value)
+    }
+
+    // clock speeds are entered with up to four decimals in game
+    pub fn percent(self, r: &Rational64) -> String {
+        decimal(&(r * Rational64::from_integer(100)), self.places(4))
+    }
+}
+
+// at most `places` decimals, trailing zeros dropped
+pub fn decimal(r: &Rational64, places: usize) -> String {
+    let formatted = format!("{:.*}", places, as_f64(r));
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        formatted
+    }
+}
+
+// floor area per tree depth, the root being the first stage
+fn stage_footprint(tree: &PlanTree, depth: usize, stages: &mut Vec<Rational64>) -> Result<()> {
+    if stages.len() <= depth {
+        stages.push(Rational64::from_integer(0));
+    }
+    if let Some(area) = tree.recipe.machine.as_ref().and_then(Builder::area) {
+        i64::try_from(tree.machine_count).ok().and_then(| count | area.checked_mul(&Rational64::from_integer(count))).and_then(| area | accumulate(&mut stages[depth], area))
+            .ok_or_else(|| Error::Arithmetic(format!("the floor area of stage {stage} overflows 64-bit rationals", stage=depth + 1)))?;
+    }
+    for child in tree.children() {
+        stage_footprint(child, depth + 1, stages)?;
+    }
+    Ok(())
+}
+
+// floor area per stage and in all
+fn stage_footprints(tree: &PlanTree) -> Result<(Vec<Rational64>, Rational64)> {
+    let mut stages = Vec::new();
+    stage_footprint(tree, 0, &mut stages)?;
+    let total = checked_total(stages.iter()).ok_or_else(|| Error::Arithmetic("the total floor area overflows 64-bit rationals".to_owned()))?;
+    Ok((stages, total))
+}
+
+// m² the plan's builders cover, those without dimensions not counting
+pub fn footprint(tree: &PlanTree) -> Result<Rational64> {
+    stage_footprints(tree).map(| (_, total) | total)
+}
+
+pub fn footprint_text(tree: &PlanTree) -> Result<String> {
+    let mut text = String::new();
+    let (stages, total) = stage_footprints(tree)?;
+    if total == Rational64::from_integer(0) {
+        return Ok(text);
+    }
+    writeln!(text).unwrap();
+    writeln!(text, "Footprint: {total:.0} m²", total=as_f64(&total)).unwrap();
+    for (stage, area) in stages.iter().enumerate() {
+        writeln!(text, "  stage {stage}: {area:.0} m²", stage=stage + 1, area=as_f64(area)).unwrap();
+    }
+    Ok(text)
+}
+
+pub fn construction_text(summary: &Summary, costs: &ConstructionCosts) -> Result<String> {
+    let mut text = String::new();
+    let (materials, missing) = costs.bill(&summary.machines)?;
+    writeln!(text).unwrap();
+    writeln!(text, "Construction materials:").unwrap();
+    for (widget, quantity) in materials.iter() {
+        writeln!(text, "  {widget}: {quantity}", widget=widget, quantity=quantity).unwrap();
+    }
+    if !missing.is_empty() {
+        writeln!(text, "  no construction data for {builders}", builders=missing.join(", ")).unwrap();
+    }
+    Ok(text)
+}
+
+pub fn summary_text(summary: &Summary, constraints: &Constraints, cookbook: &Cookbook, precision: Precision) -> Result<String> {
+    let mut text = String::new();
+    let (limits, prices) = (&constraints.limits, &constraints.prices);
+    if !summary.raw.is_empty() {
+        writeln!(text).unwrap();
+        writeln!(text, "Raw resources:").unwrap();
+        for (widget, rate) in summary.raw.iter() {
+            writeln!(text, "  {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision)).unwrap();
+        }
+    }
+    if let Some(time) = summary.crafting_time {
+        writeln!(text).unwrap();
+        writeln!(text, "Hand-crafting: {time}s per second", time=precision.fixed(as_f64(&time), 3)).unwrap();
+    }
+    if !summary.machines.is_empty() {
+        writeln!(text).unwrap();
+        writeln!(text, "Machines:").unwrap();
+        for (builder, count) in summary.machines.iter() {
+            match limits.get(builder) {
+                Some(limit) => writeln!(text, "  {builder}: {count} of {limit}", builder=builder, count=count, limit=limit).unwrap(),
+                None => writeln!(text, "  {builder}: {count}", builder=builder, count=count).unwrap()
+            }
+        }
+    }
+    if !summary.purchases.is_empty() {
+        let mut costs = Vec::new();
+        for (widget, rate) in summary.purchases.iter() {
+            let cost = prices.get(widget).map_or(Some(Rational64::from_integer(0)), | p | p.checked_mul(rate))
+                .ok_or_else(|| Error::Arithmetic(format!("buying {rate}/s of `{widget}` overflows 64-bit rationals", rate=rate, widget=widget)))?;
+            costs.push((widget, rate, cost));
+        }
+        let total = checked_total(costs.iter().map(| (_, _, cost) | cost)).ok_or_else(|| Error::Arithmetic("the shopping list's total overflows 64-bit rationals".to_owned()))?;
+        writeln!(text).unwrap();
+        writeln!(text, "Shopping list: {total:.2} per second", total=as_f64(&total)).unwrap();
+        for (widget, rate, cost) in costs {
+            writeln!(text, "  {widget}: {rate} ({cost:.2} per second)", widget=widget, rate=cookbook.format_rate(widget, rate, precision), cost=as_f64(&cost)).unwrap();
+        }
+    }
+    if !summary.supplied.is_empty() {
+        writeln!(text).unwrap();
+        writeln!(text, "Supplied inputs:").unwrap();
+        for (widget, rate) in summary.supplied.iter() {
+            writeln!(text, "  {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision)).unwrap();
+        }
+    }
+    if !summary.shared.is_empty() {
+        writeln!(text).unwrap();
+        writeln!(text, "Shared surplus:").unwrap();
+        for (widget, rate) in summary.shared.iter() {
+            writeln!(text, "  {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision)).unwrap();
+        }
+    }
+    if summary.surplus.values().any(| r | *r > Rational64::from_integer(0)) {
+        writeln!(text).unwrap();
+        writeln!(text, "Surplus:").unwrap();
+        for (widget, rate) in summary.surplus.iter().filter(| (_, r) | **r > Rational64::from_integer(0)) {
+            match constraints.sinks.get(widget) {
+                Some(points) => {
+                    let points = rate.checked_mul(points).and_then(| points | points.checked_mul(&Rational64::from_integer(60)))
+                        .ok_or_else(|| Error::Arithmetic(format!("sinking {rate}/s of `{widget}` overflows 64-bit rationals", rate=rate, widget=widget)))?;
+                    writeln!(text, "  {widget}: {rate}, sunk for {points} points/min", widget=widget, rate=cookbook.format_rate(widget, rate, precision),
+                        points=precision.fixed(as_f64(&points), 1)).unwrap()
+                },
+                None => writeln!(text, "  {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision)).unwrap()
+            }
+        }
+        if !constraints.sinks.is_empty() {
+            let points = summary.sink_points(&constraints.sinks)?.checked_mul(&Rational64::from_integer(60))
+                .ok_or_else(|| Error::Arithmetic("the sink points per minute overflow 64-bit rationals".to_owned()))?;
+            writeln!(text, "Sink: {points} points/min", points=precision.fixed(as_f64(&points), 1)).unwrap();
+        }
+    }
+    if !summary.power.is_empty() {
+        writeln!(text).unwrap();
+        writeln!(text, "Power: {total} MW", total=precision.fixed(as_f64(&summary.total_power()?), 1)).unwrap();
+        for (builder, power) in summary.power.iter() {
+            writeln!(text, "  {builder}: {power} MW", builder=builder, power=precision.fixed(as_f64(power), 1)).unwrap();
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_chain_test() {
+        // w0 is made from w1, w1 from w2 and so on down to the raw w10000
+        let depth = 10_000;
+        let one = Rational64::from_integer(1);
+        let mut book = Cookbook::builder().machine("Assembler", None);
+        for i in 0..=depth {
+            book = book.item(&format!("w{}", i));
+        }
+        for i in 0..depth {
+            book = book.recipe(Recipe::new(&format!("r{}", i), one).made_by("Assembler").reagent(&format!("w{}", i + 1), one).product(&format!("w{}", i), one));
+        }
+        let graph = book.build().graph().unwrap();
+        let plan = solve(&graph, &[], &"w0".to_owned(), one, &Constraints::default()).unwrap();
+        assert_eq!(plan.tree.as_ref().unwrap().iter_dfs().count(), depth);
+        assert_eq!(plan.total_machines(), depth as u64);
+        assert_eq!(plan.raw_inputs()[&format!("w{}", depth)], one);
+    }
+
+    #[test]
+    fn shared_subtree_test() {
+        // w0 takes w1 twice, w1 takes w2 twice and so on, every copy of a subtree coming from the memo but the first
+        let depth = 12;
+        let one = Rational64::from_integer(1);
+        let mut book = Cookbook::builder().machine("Assembler", Some(one));
+        for i in 0..=depth {
+            book = book.item(&format!("w{}", i));
+        }
+        for i in 0..depth {
+            let reagent = format!("w{}", i + 1);
+            book = book.recipe(Recipe::new(&format!("r{}", i), one).made_by("Assembler").reagent(&reagent, one).reagent(&reagent, one).product(&format!("w{}", i), one));
+        }
+        let graph = book.build().graph().unwrap();
+        let mut streamed = 0;
+        let plan = solve_streaming(&graph, &[], &"w0".to_owned(), one, &Constraints::default(), &mut | _, _ | streamed += 1).unwrap();
+        let stages = (1 << depth) - 1;
+        assert_eq!(plan.tree.as_ref().unwrap().iter_dfs().count(), stages);
+        assert_eq!(streamed, stages);
+        assert_eq!(plan.total_machines(), stages as u64);
+        assert_eq!(plan.summary.total_power().unwrap(), Rational64::from_integer(stages as i64));
+        assert_eq!(plan.raw_inputs()[&format!("w{}", depth)], Rational64::from_integer(1 << depth));
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn parallel_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let widget = "versatile-framework".to_owned();
+        let rate = Rational64::new(3, 2);
+        let parallel = solve_parallel(&graph, &extraction, &widget, rate, &Constraints::default()).unwrap();
+        let streamed = solve_streaming(&graph, &extraction, &widget, rate, &Constraints::default(), &mut | _, _ | {}).unwrap();
+        assert_eq!(parallel.render(Format::Json, &cookbook, Precision::default()).0, streamed.render(Format::Json, &cookbook, Precision::default()).0);
+    }
+
+    #[test]
+    fn incremental_test() {
+        let text = std::fs::read_to_string("satisfactory.yaml").unwrap();
+        let screw = "name: \"Screw\"\n    builder: \"Constructor\"\n    duration: ";
+        let faster = text.replace(&format!("{}6.0", screw), &format!("{}2.0", screw));
+        let (before, after) = (Cookbook::from_yaml(&text).unwrap(), Cookbook::from_yaml(&faster).unwrap());
+        let changed = after.changed_widgets(&before, "normal").unwrap();
+        assert_eq!(changed, HashSet::from(["screw".to_owned()]));
+        assert!(before.changed_widgets(&Cookbook::from_yaml(&text).unwrap(), "normal").unwrap().is_empty());
+        let widget = "modular-frame".to_owned();
+        let rate = Rational64::new(1, 2);
+        let (old_graph, old_extraction) = (before.graph().unwrap(), before.extraction("normal").unwrap());
+        let previous = solve(&old_graph, &old_extraction, &widget, rate, &Constraints::default()).unwrap();
+        let (graph, extraction) = (after.graph().unwrap(), after.extraction("normal").unwrap());
+        let incremental = solve_incremental(&graph, &extraction, &widget, rate, &Constraints::default(), previous.tree.as_ref().unwrap(), &changed).unwrap();
+        let full = solve(&graph, &extraction, &widget, rate, &Constraints::default()).unwrap();
+        assert_eq!(incremental.render(Format::Json, &after, Precision::default()).0, full.render(Format::Json, &after, Precision::default()).0);
+        assert_ne!(incremental.render(Format::Json, &after, Precision::default()).0, previous.render(Format::Json, &before, Precision::default()).0);
+    }
+
+    #[test]
+    fn overflow_test() {
+        // every stage multiplies the rate by a huge prime until it no longer fits
+        let one = Rational64::from_integer(1);
+        let huge = Rational64::from_integer(2_147_483_647);
+        let book = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("c").item("d")
+            .recipe(Recipe::new("A", Rational64::new(1, 3)).made_by("Assembler").reagent("b", huge).product("a", one))
+            .recipe(Recipe::new("B", Rational64::new(1, 5)).made_by("Assembler").reagent("c", huge).product("b", one))
+            .recipe(Recipe::new("C", Rational64::new(1, 7)).made_by("Assembler").reagent("d", huge).product("c", one))
+            .build();
+        let graph = book.graph().unwrap();
+        match solve(&graph, &[], &"a".to_owned(), Rational64::new(1, 11), &Constraints::default()) {
+            Err(Error::Arithmetic(message)) => assert!(message.contains("overflows"), "{}", message),
+            other => panic!("expected an overflow, got {:?}", other.map(| p | p.summary))
+        }
+        // as does pricing a unit of `a` with `d` at the most it can cost
+        let prices = Constraints { prices: BTreeMap::from([("d".to_owned(), Rational64::from_integer(i64::MAX))]), ..Default::default() };
+        assert!(matches!(make_cost(&graph, &[], &"a".to_owned(), &prices, &mut Vec::new()), Err(Error::Arithmetic(_))));
+        // and summing a summary's totals
+        let max = Rational64::from_integer(i64::MAX);
+        let summary = Summary { power: BTreeMap::from([("Assembler".to_owned(), max), ("Smelter".to_owned(), max)]), ..Default::default() };
+        assert!(matches!(summary.total_power(), Err(Error::Arithmetic(_))));
+        assert!(matches!(summary_text(&summary, &Constraints::default(), &book, Precision::default()), Err(Error::Arithmetic(_))));
+        let summary = Summary { surplus: BTreeMap::from([("a".to_owned(), max)]), ..Default::default() };
+        assert!(matches!(summary.sink_points(&BTreeMap::from([("a".to_owned(), huge)])), Err(Error::Arithmetic(_))));
+    }
+
+    fn one() -> Rational64 {
+        Rational64::from_integer(1)
+    }
+
+    // `a` made from a `b` in `seconds` by an Assembler
+    fn a_from_b(name: &str, seconds: i64) -> Recipe {
+        Recipe::new(name, Rational64::from_integer(seconds)).made_by("Assembler").reagent("b", one()).product("a", one())
+    }
+
+    // an Assembler and widgets a, b, c and ore, made by `recipes`
+    fn build_basic_book(recipes: Vec<Recipe>) -> Cookbook {
+        let book = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("c").item("ore");
+        recipes.into_iter().fold(book, | book, recipe | book.recipe(recipe)).build()
+    }
+
+    // the recipe planned for `rate` of `a`
+    fn chosen(book: &Cookbook, rate: Rational64, constraints: &Constraints) -> Result<String> {
+        solve(&book.graph()?, &[], &"a".to_owned(), rate, constraints).map(| plan | plan.tree.expect("a is crafted").recipe.name.clone())
+    }
+
+    #[test]
+    fn tie_break_test() {
+        // every recipe leaves the same idle capacity: fewer machines wins, then the name, whatever the order
+        for reversed in [false, true] {
+            let mut recipes = vec![a_from_b("Slow", 2), a_from_b("Beta", 1), a_from_b("Alpha", 1)];
+            if reversed {
+                recipes.reverse();
+            }
+            assert_eq!(chosen(&build_basic_book(recipes), Rational64::from_integer(2), &Constraints::default()).unwrap(), "Alpha");
+        }
+    }
+
+    #[test]
+    fn downstream_waste_test() {
+        // Quick makes `a` on one machine but leaves half a machine making `b` idle, Slow needs two machines and wastes nothing
+        let book = build_basic_book(vec![
+            Recipe::new("Quick", one()).made_by("Assembler").reagent("b", Rational64::from_integer(3)).product("a", one()),
+            Recipe::new("Slow", Rational64::from_integer(2)).made_by("Assembler").reagent("c", one()).product("a", one()),
+            Recipe::new("B", one()).made_by("Assembler").reagent("ore", one()).product("b", Rational64::from_integer(2)),
+            Recipe::new("C", one()).made_by("Assembler").reagent("ore", one()).product("c", one())
+        ]);
+        assert_eq!(chosen(&book, one(), &Constraints::default()).unwrap(), "Quick");
+        assert_eq!(chosen(&book, one(), &Constraints { downstream_waste: true, ..Default::default() }).unwrap(), "Slow");
+    }
+
+    #[test]
+    fn minimize_power_test() {
+        // Direct draws 10 MW with nothing below it, Staged draws 6 MW itself and 6 MW more making its `b`
+        let one = Rational64::from_integer(1);
+        let book = Cookbook::builder().machine("Big", Some(Rational64::from_integer(10))).machine("Small", Some(Rational64::from_integer(6)))
+            .item("a").item("b").item("ore")
+            .recipe(Recipe::new("Direct", one).made_by("Big").reagent("ore", one).product("a", one))
+            .recipe(Recipe::new("Staged", one).made_by("Small").reagent("b", one).product("a", one))
+            .recipe(Recipe::new("B", one).made_by("Small").reagent("ore", one).product("b", one))
+            .build();
+        let graph = book.graph().unwrap();
+        let chosen = | constraints: Constraints | solve(&graph, &[], &"a".to_owned(), one, &constraints).unwrap().tree.unwrap().recipe.name.clone();
+        assert_eq!(chosen(Constraints { cost: Some("power".parse().unwrap()), ..Default::default() }), "Staged");
+        assert_eq!(chosen(Constraints { minimize_power: true, ..Default::default() }), "Direct");
+    }
+
+    #[test]
+    fn sink_test() {
+        let one = Rational64::from_integer(1);
+        let book = Cookbook::builder().machine("Assembler", None).item("a").item("b").sunk("c", Rational64::from_integer(5))
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", one).product("a", one))
+            .recipe(Recipe::new("B", one).made_by("Assembler").reagent("b", one).product("a", one).product("c", one))
+            .build();
+        let graph = book.graph().unwrap();
+        let cost = Some("machines - sink".parse().unwrap());
+        let picked = | sinks: BTreeMap<String, Rational64> | {
+            let constraints = Constraints { cost: cost.clone(), sinks, ..Default::default() };
+            let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &constraints).unwrap();
+            (plan.tree.unwrap().recipe.name.clone(), plan.summary.sink_points(&book.sink_values()).unwrap())
+        };
+        assert_eq!(picked(BTreeMap::new()), ("A".to_owned(), Rational64::from_integer(0)));
+        assert_eq!(picked(book.sink_values()), ("B".to_owned(), Rational64::from_integer(10)));
+    }
+
+    #[test]
+    fn upkeep_test() {
+        let one = Rational64::from_integer(1);
+        // smelters burn fuel, and so do the refineries making it
+        let book = Cookbook::builder().machine("Smelter", None).machine("Refinery", None).upkeep("Smelter", "fuel", Rational64::new(1, 2))
+            .upkeep("Refinery", "fuel", Rational64::new(1, 4)).item("ore").item("plate").item("fuel")
+            .recipe(Recipe::new("Plate", one).made_by("Smelter").reagent("ore", one).product("plate", one))
+            .recipe(Recipe::new("Fuel", one).made_by("Refinery").reagent("ore", one).product("fuel", one))
+            .build();
+        let graph = book.graph().unwrap();
+        let constraints = Constraints::default();
+        let plan = solve(&graph, &[], &"plate".to_owned(), Rational64::from_integer(4), &constraints).unwrap();
+        let fueled = upkeep(&graph, &[], &book, &plan.summary.machines, &constraints).unwrap().unwrap();
+        // 4 smelters burn 2/s, and the 3 refineries it takes another 3/4, still within their 3/s
+        assert_eq!(fueled.demand.get("fuel"), Some(&Rational64::new(11, 4)));
+        assert_eq!(fueled.summary.machines.get("Refinery"), Some(&3));
+        assert!(upkeep(&graph, &[], &book, &BTreeMap::new(), &constraints).unwrap().is_none());
+    }
+
+    #[test]
+    fn category_test() {
+        let book = build_basic_book(vec![
+            a_from_b("Smelted", 1).in_category("smelting"),
+            Recipe::new("Refined", one()).made_by("Assembler").reagent("c", one()).product("a", one()).in_category("refining"),
+            Recipe::new("B", one()).made_by("Assembler").reagent("c", one()).product("b", one())
+        ]);
+        let picked = | constraints: Constraints | chosen(&book, one(), &constraints);
+        let only = | categories: &[&str] | Some(categories.iter().map(| c | c.to_string()).collect());
+        // uncategorized B still feeds smelting
+        assert_eq!(picked(Constraints { categories: only(&["smelting"]), ..Default::default() }).unwrap(), "Smelted");
+        assert_eq!(picked(Constraints { excluded_categories: ["smelting".to_owned()].into(), ..Default::default() }).unwrap(), "Refined");
+        assert!(picked(Constraints { categories: only(&["assembly"]), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn game_version_test() {
+        let release: version::GameVersion = "1.0".parse().unwrap();
+        let book = build_basic_book(vec![a_from_b("Old", 1).available(None, Some(release.clone())), a_from_b("New", 1).available(Some(release), None)]);
+        let picked = | version: &str | chosen(&book, one(), &Constraints { game_version: Some(version.parse().unwrap()), ..Default::default() }).unwrap();
+        assert_eq!(picked("0.8"), "Old");
+        assert_eq!(picked("1"), "New");
+    }
+
+    #[test]
+    fn alternate_penalty_test() {
+        // the alternate runs a third faster
+        let book = build_basic_book(vec![a_from_b("Standard", 4), a_from_b("Alternate", 3).as_alternate()]);
+        let picked = | penalty: Option<Rational64> | {
+            chosen(&book, one(), &Constraints { unlocked: ["Alternate".to_owned()].into(), alternate_penalty: penalty, ..Default::default() }).unwrap()
+        };
+        assert_eq!(picked(None), "Alternate");
+        assert_eq!(picked(Some(Rational64::new(1, 4))), "Alternate");
+        assert_eq!(picked(Some(Rational64::new(1, 2))), "Standard");
+    }
+
+    #[test]
+    fn rounding_test() {
+        let graph = build_basic_book(vec![a_from_b("A", 1)]).graph().unwrap();
+        let rate = Rational64::new(9, 4);
+        for (rounding, machines, made) in [(Rounding::Ceil, 3, Rational64::from_integer(3)), (Rounding::Nearest, 2, Rational64::from_integer(2)), (Rounding::None, 3, rate)] {
+            let constraints = Constraints { rounding, ..Default::default() };
+            let plan = solve(&graph, &[], &"a".to_owned(), rate, &constraints).unwrap();
+            let tree = plan.tree.unwrap();
+            assert_eq!((tree.machine_count, tree.machines * tree.clock, tree.actual_rate), (machines, made, made), "{:?}", rounding);
+        }
+    }
+
+    #[test]
+    fn limits_test() {
+        // two Assembler-made `a` a second take two Assemblers, or two Smelters by the slower recipe
+        let book = Cookbook::builder().machine("Assembler", None).machine("Smelter", None).item("a").item("b")
+            .recipe(a_from_b("Assembled", 1))
+            .recipe(Recipe::new("Smelted", one()).made_by("Smelter").reagent("b", one()).product("a", one()))
+            .build();
+        let graph = book.graph().unwrap();
+        let request = | limits: &[(&str, u64)] | SolveRequest {
+            widget: "a".to_owned(), rate: Rational64::from_integer(2), purity: normal_purity(), max_tier: None, unlocked: Vec::new(),
+            limits: limits.iter().map(| (b, n) | (b.to_string(), *n)).collect(), exact_clock: false, share_surplus: false, network: false, game_version: None
+        };
+        let plan = request(&[("Assembler", 1)]).solve(&graph, &[]).unwrap();
+        assert_eq!(plan.tree.unwrap().recipe.name, "Smelted");
+        assert_eq!(plan.summary.machines, BTreeMap::from([("Smelter".to_owned(), 2)]));
+        match request(&[("Assembler", 1), ("Smelter", 1)]).solve(&graph, &[]) {
+            Err(Error::LimitsExceeded(widget)) => assert_eq!(widget, "a"),
+            other => panic!("expected the limits to be exceeded, got {:?}", other.map(| p | p.summary))
+        }
+    }
+
+    #[test]
+    fn parse_for_test() {
+        let path = PathBuf::from("satisfactory.yaml");
+        let whole = load_recipes(&path).unwrap();
+        let pruned = Cookbook::parse_for(&path, "iron-plate").unwrap();
+        assert!(pruned.recipes.len() < whole.recipes.len());
+        assert_eq!((pruned.widgets.len(), pruned.builders.len(), pruned.purities.clone()), (whole.widgets.len(), whole.builders.len(), whole.purities.clone()));
+        // alternates and what they take are kept, locked or not
+        assert!(pruned.recipes.iter().any(| r | r.name == "Pure Iron Ingot"));
+        assert!(pruned.recipes.iter().all(| r | r.products.iter().all(| p | p.widget != "reinforced-iron-plate")));
+        let plan = | cookbook: &Cookbook | {
+            let graph = cookbook.graph().unwrap();
+            let extraction = cookbook.extraction("normal").unwrap();
+            solve(&graph, &extraction, &"iron-plate".to_owned(), Rational64::from_integer(1), &Constraints::default()).unwrap().raw_inputs().clone()
+        };
+        assert_eq!(plan(&whole), plan(&pruned));
+    }
+
+    #[test]
+    fn timeout_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let widget = "modular-frame".to_owned();
+        let rate = Rational64::from_integer(2);
+        let constraints = Constraints { limits: BTreeMap::from([("Assembler".to_owned(), 20)]), ..Default::default() };
+        match search_within_limits(&graph, &extraction, &widget, rate, &constraints, Some(Instant::now())).unwrap() {
+            Search::TimedOut(_, over) => assert!(over > 0),
+            _ => panic!("expected the search to run out of time")
+        }
+        let (best, bound) = best_rate_by(&graph, &extraction, &widget, rate, &constraints, None).unwrap();
+        assert!(best.is_some() && bound.is_none());
+        let (_, bound) = best_rate_by(&graph, &extraction, &widget, rate, &constraints, Some(Instant::now())).unwrap();
+        assert_eq!(bound, Some(rate));
+    }
+
+    #[test]
+    fn parse_decimal_test() {
+        assert_eq!(parse_decimal("0.1"), Some(Rational64::new(1, 10)));
+        assert_eq!(parse_decimal("-1.5"), Some(Rational64::new(-3, 2)));
+        assert_eq!(parse_decimal("2.5e-3"), Some(Rational64::new(1, 400)));
+        assert_eq!(parse_decimal("12"), Some(Rational64::from_integer(12)));
+        assert_eq!(parse_decimal(".5"), Some(Rational64::new(1, 2)));
+        assert_eq!(parse_decimal("1e3"), Some(Rational64::from_integer(1000)));
+        assert_eq!(parse_decimal("0.1234567890123456789012"), None);
+        for bad in ["", ".", "-", "1.2.3", "ten", "1e"] {
+            assert_eq!(parse_decimal(bad), None, "{}", bad);
+        }
+        let book = Cookbook::from_yaml("widgets: [a]\nrecipes:\n  - {name: A, duration: 0.3, reagents: [], products: [{widget: a, quantity: \"0.7\"}]}").unwrap();
+        assert_eq!((book.recipes[0].duration, book.recipes[0].products[0].quantity), (Rational64::new(3, 10), Rational64::new(7, 10)));
+    }
+
+    #[test]
+    fn parse_rate_test() {
+        assert_eq!(parse_rate("45/min").unwrap(), Rational64::new(3, 4));
+        assert_eq!(parse_rate("1/3").unwrap(), Rational64::new(1, 3));
+        assert_eq!(parse_rate("2/3/min").unwrap(), Rational64::new(1, 90));
+        assert_eq!(parse_rate("2.5/s").unwrap(), Rational64::new(5, 2));
+        assert_eq!(parse_rate("600/h").unwrap(), Rational64::new(1, 6));
+        assert!(parse_rate("0").is_err() && parse_rate("fast").is_err() && parse_rate("1/0").is_err());
+        assert_eq!(parse_duration("1.5min"), Some(Rational64::from_integer(90)));
+        assert_eq!(parse_duration("1/3 h"), Some(Rational64::from_integer(1200)));
+        assert_eq!(parse_duration("4"), Some(Rational64::from_integer(4)));
+        assert_eq!(parse_duration("4 days"), None);
+    }
+
+    #[test]
+    fn tier_test() {
+        let book = build_basic_book(vec![a_from_b("Early", 2), a_from_b("Late", 1).at_tier(3)]);
+        let picked = | max_tier: Option<u32> | chosen(&book, one(), &Constraints { max_tier, ..Default::default() });
+        assert_eq!(picked(None).unwrap(), "Late");
+        assert_eq!(picked(Some(3)).unwrap(), "Late");
+        assert_eq!(picked(Some(2)).unwrap(), "Early");
+        // nothing left to make it from
+        let late = build_basic_book(vec![a_from_b("Late", 1).at_tier(3)]);
+        assert!(matches!(chosen(&late, one(), &Constraints { max_tier: Some(2), ..Default::default() }), Err(Error::NoRecipes(_))));
+    }
+
+    #[test]
+    fn raw_test() {
+        // `a` takes ore straight and again through `b`
+        let book = build_basic_book(vec![
+            Recipe::new("A", one()).made_by("Assembler").reagent("b", one()).reagent("ore", Rational64::from_integer(2)).product("a", one()),
+            Recipe::new("B", one()).made_by("Assembler").reagent("ore", Rational64::from_integer(3)).product("b", one())
+        ]);
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default()).unwrap();
+        assert_eq!(plan.raw_inputs(), &BTreeMap::from([("ore".to_owned(), Rational64::from_integer(10))]));
+        let ore = solve(&graph, &[], &"ore".to_owned(), one(), &Constraints::default()).unwrap();
+        assert!(ore.tree.is_none());
+        assert_eq!(ore.raw_inputs(), &BTreeMap::from([("ore".to_owned(), one())]));
+    }
+
+    #[test]
+    fn error_test() {
+        let graph = build_basic_book(vec![a_from_b("A", 1)]).graph().unwrap();
+        assert!(matches!(solve(&graph, &[], &"gear".to_owned(), one(), &Constraints::default()), Err(Error::UnknownWidget(w)) if w == "gear"));
+        let missing = load_recipes(&"no-such-game.yaml".into()).err().unwrap();
+        assert!(matches!(missing, Error::Io { .. }) && missing.to_string().starts_with("could not read no-such-game.yaml"), "{}", missing);
+        assert!(matches!(Cookbook::from_yaml("recipes: 3"), Err(Error::Definition(_))));
+        let stray = Recipe::new("Gear", one()).made_by("Assembler").reagent("b", one()).product("gear", one());
+        assert!(matches!(build_basic_book(vec![stray]).graph(), Err(Error::UnknownWidget(w)) if w == "gear"));
+    }
+
+    #[test]
+    fn hand_craft_test() {
+        let hand = Recipe::new("Hand", one()).reagent("b", one()).product("a", one());
+        let graph = build_basic_book(vec![hand.clone()]).graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(3), &Constraints::default()).unwrap();
+        assert!(plan.tree.unwrap().recipe.is_manual());
+        // three crafts a second, each taking a second by hand
+        assert_eq!(plan.summary.crafting_time, Some(Rational64::from_integer(3)));
+        assert_eq!(plan.summary.machines, BTreeMap::from([("Handcraft".to_owned(), 3)]));
+        let book = build_basic_book(vec![hand, a_from_b("Machine", 4)]);
+        assert_eq!(chosen(&book, one(), &Constraints::default()).unwrap(), "Hand");
+        assert_eq!(chosen(&book, one(), &Constraints { prefer_machines: true, ..Default::default() }).unwrap(), "Machine");
+    }
+
+    #[test]
+    fn rational_quantity_test() {
+        let book = Cookbook::from_yaml("widgets: [a, ore]\nbuilders: [{name: Assembler}]\nrecipes:\n  - {name: A, builder: Assembler, duration: 3, \
+            reagents: [{widget: ore, quantity: \"2/3\"}], products: [{widget: a, quantity: 1.5}]}").unwrap();
+        assert_eq!(book.recipes[0].reagents[0].quantity, Rational64::new(2, 3));
+        assert_eq!(book.recipes[0].rate(&"a".to_owned()).unwrap(), Rational64::new(1, 2));
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), one(), &Constraints::default()).unwrap();
+        // two machines crafting every three seconds take 2/3 ore each time
+        assert_eq!(plan.raw_inputs()["ore"], Rational64::new(4, 9));
+        // a negative denominator whose sign can't move to the numerator is refused, not overflowed
+        for quantity in ["[-9223372036854775808, -1]", "\"-9223372036854775808/-1\"", "[1, -9223372036854775808]"] {
+            let text = format!("widgets: [a]\nrecipes:\n  - {{name: A, duration: 1, products: [{{widget: a, quantity: {}}}]}}", quantity);
+            assert!(Cookbook::from_yaml(&text).unwrap_err().to_string().contains("Bad number"), "{}", quantity);
+        }
+        assert_eq!(checked_ratio(3, -6), Some(Rational64::new(-1, 2)));
+    }
+
+    #[test]
+    fn power_test() {
+        let ten = Rational64::from_integer(10);
+        let frugal = Recipe { power: Some(Rational64::from_integer(3)), ..Recipe::new("B", one()).made_by("Assembler").reagent("ore", one()).product("b", one()) };
+        let book = Cookbook::builder().machine("Assembler", Some(ten)).item("a").item("b").item("ore").recipe(a_from_b("A", 1)).recipe(frugal).build();
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default()).unwrap();
+        // two Assemblers at the builder's 10 MW, and two more at the recipe's own 3 MW
+        assert_eq!(plan.summary.power, BTreeMap::from([("Assembler".to_owned(), Rational64::from_integer(26))]));
+        assert_eq!(plan.summary.total_power().unwrap(), Rational64::from_integer(26));
+        assert_eq!(a_from_b("A", 1).power_at(one()), None);
+    }
+
+    #[test]
+    fn clock_range_test() {
+        let book = Cookbook::from_yaml("widgets: [a, b]\nbuilders: [{name: Assembler, power: 4, min_clock: 50, max_clock: 250, power_exponent: 2}]\nrecipes:\n  \
+            - {name: A, builder: Assembler, duration: 1, reagents: [{widget: b, quantity: 1}], products: [{widget: a, quantity: 1}]}").unwrap();
+        let graph = book.graph().unwrap();
+        let constraints = Constraints { max_clock: Some(Rational64::from_integer(2)), ..Default::default() };
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(4), &constraints).unwrap();
+        let tree = plan.tree.unwrap();
+        assert_eq!((tree.machine_count, tree.clock), (2, Rational64::from_integer(2)));
+        // twice the speed draws four times the power
+        assert_eq!(plan.summary.total_power().unwrap(), Rational64::from_integer(32));
+        // asked-for clocks are narrowed to the 50-250% the builder runs at
+        let wide = Constraints { min_clock: Some(Rational64::new(1, 4)), max_clock: Some(Rational64::from_integer(3)), ..Default::default() };
+        assert_eq!(wide.clock_range(tree.recipe), (Rational64::new(1, 2), Rational64::new(5, 2)));
+    }
+
+    #[test]
+    fn exact_clock_test() {
+        let graph = build_basic_book(vec![a_from_b("A", 1)]).graph().unwrap();
+        let rate = Rational64::new(9, 4);
+        let plan = solve(&graph, &[], &"a".to_owned(), rate, &Constraints { exact_clock: true, ..Default::default() }).unwrap();
+        let tree = plan.tree.unwrap();
+        // three machines underclocked to 75% make exactly what was asked, with nothing left over
+        assert_eq!((tree.machine_count, tree.clock, tree.actual_rate), (3, Rational64::new(3, 4), rate));
+        assert!(plan.summary.surplus.is_empty());
+        let full = solve(&graph, &[], &"a".to_owned(), rate, &Constraints::default()).unwrap();
+        assert_eq!(full.summary.surplus["a"], Rational64::new(3, 4));
+        // two machines, the nearest count, stay within 100% and make a little less
+        let nearest = Constraints { exact_clock: true, rounding: Rounding::Nearest, ..Default::default() };
+        let tree = solve(&graph, &[], &"a".to_owned(), rate, &nearest).unwrap().tree.unwrap();
+        assert_eq!((tree.machine_count, tree.clock, tree.actual_rate), (2, one(), Rational64::from_integer(2)));
+    }
+
+    #[test]
+    fn bonus_test() {
+        let half = Some(Rational64::new(1, 2));
+        let boosted = Recipe { speed: half, productivity: half, ..a_from_b("A", 1) };
+        assert_eq!(boosted.rate(&"a".to_owned()).unwrap(), Rational64::new(9, 4));
+        let graph = build_basic_book(vec![boosted]).graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::new(9, 4), &Constraints::default()).unwrap();
+        // speed makes the one machine take reagents faster, productivity makes more of `a` from them
+        assert_eq!(plan.total_machines(), 1);
+        assert_eq!(plan.raw_inputs()["b"], Rational64::new(3, 2));
+    }
+
+    #[test]
+    fn construction_test() {
+        let costs: ConstructionCosts = serde_yaml::from_str("builders:\n  - {name: Assembler, time: 30, cost: [{widget: plate, quantity: 8}, {widget: rotor, quantity: 4}]}\n  \
+            - {name: Smelter, cost: [{widget: plate, quantity: 5}]}").unwrap();
+        let machines = BTreeMap::from([("Assembler".to_owned(), 2), ("Smelter".to_owned(), 3), ("Refinery".to_owned(), 1)]);
+        let (materials, missing) = costs.bill(&machines).unwrap();
+        assert_eq!(materials, BTreeMap::from([("plate".to_owned(), Rational64::from_integer(31)), ("rotor".to_owned(), Rational64::from_integer(8))]));
+        assert_eq!(missing, ["Refinery"]);
+        assert_eq!((costs.time("Assembler"), costs.time("Smelter")), (Some(Rational64::from_integer(30)), None));
+    }
+
+    #[test]
+    fn footprint_test() {
+        let book = Cookbook::from_yaml("widgets: [a, b, ore]\nbuilders: [{name: Assembler, width: 10, length: 15}, {name: Smelter}]\nrecipes:\n  \
+            - {name: A, builder: Assembler, duration: 1, reagents: [{widget: b, quantity: 1}], products: [{widget: a, quantity: 1}]}\n  \
+            - {name: B, builder: Smelter, duration: 1, reagents: [{widget: ore, quantity: 1}], products: [{widget: b, quantity: 1}]}").unwrap();
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(3), &Constraints::default()).unwrap();
+        // three Assemblers of 150 m², the Smelters having no dimensions to count
+        assert_eq!(footprint(plan.tree.as_ref().unwrap()).unwrap(), Rational64::from_integer(450));
+    }
+
+    #[test]
+    fn belt_test() {
+        let tiers = [Transport { name: "Mk1".to_owned(), rate: one() }, Transport { name: "Mk2".to_owned(), rate: Rational64::from_integer(2) }];
+        let tier = | flow: Rational64 | transport_for(&tiers, flow).map(| (t, count) | (t.name.as_str(), count));
+        assert_eq!(tier(one()), Some(("Mk1", 1)));
+        assert_eq!(tier(Rational64::new(3, 2)), Some(("Mk2", 1)));
+        assert_eq!(tier(Rational64::from_integer(5)), Some(("Mk2", 3)));
+        assert!(transport_for(&[], one()).is_none());
+        // a zero-rate tier is skipped rather than divided by
+        let stalled = [Transport { name: "Stalled".to_owned(), rate: Rational64::from_integer(0) }, tiers[0].clone()];
+        assert_eq!(transport_for(&stalled, Rational64::from_integer(3)).map(| (t, count) | (t.name.as_str(), count)), Some(("Mk1", 3)));
+        assert!(transport_for(&stalled[..1], one()).is_none());
+        let book = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("ore").belt("Mk1", one()).belt("Mk2", Rational64::from_integer(2))
+            .recipe(a_from_b("A", 1)).recipe(Recipe::new("B", one()).made_by("Assembler").reagent("ore", one()).product("b", one())).build();
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::new(3, 2), &Constraints::default()).unwrap();
+        // the two Assemblers making `a` take 2/s of `b` between them
+        let (text, warnings) = tree_text(plan.tree.as_ref().unwrap(), None, &book, Precision::default());
+        assert!(text.contains("[1x Mk2]") && warnings.is_empty());
+        // 3/s of `b` takes two of the fastest belt, which is warned about rather than printed
+        let plan = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(3), &Constraints::default()).unwrap();
+        let (text, warnings) = tree_text(plan.tree.as_ref().unwrap(), None, &book, Precision::default());
+        assert!(text.contains("[2x Mk2]"));
+        assert_eq!(warnings, ["3.000/s of b exceeds a single Mk2"]);
+    }
+
+    #[test]
+    fn fluid_test() {
+        let book = Cookbook::from_yaml("widgets: [plate, {name: water, fluid: true}]\nbelts: [{name: Belt, rate: 1}]\npipes: [{name: Pipe, rate: 5}]\nrecipes: []").unwrap();
+        let (plate, water) = ("plate".to_owned(), "water".to_owned());
+        assert!(book.is_fluid(&water) && !book.is_fluid(&plate));
+        assert_eq!((book.transport(&plate)[0].name.as_str(), book.transport(&water)[0].name.as_str()), ("Belt", "Pipe"));
+        assert_eq!(book.format_rate(&plate, &Rational64::new(1, 2), Precision::default()), "0.500/s");
+        assert_eq!(book.format_rate(&water, &Rational64::new(1, 2), Precision::default()), "30.000 m³/min");
+    }
+
+    #[test]
+    fn extraction_test() {
+        let book = Cookbook::builder().machine("Assembler", None).machine("Miner", None).item("a").item("ore").extractor("Miner", &["ore"], one())
+            .recipe(Recipe::new("A", one()).made_by("Assembler").reagent("ore", Rational64::from_integer(3)).product("a", one())).build();
+        let graph = book.graph().unwrap();
+        let miners = | purity: &str | {
+            let extraction = book.extraction(purity).unwrap();
+            let plan = solve(&graph, &extraction, &"a".to_owned(), one(), &Constraints::default()).unwrap();
+            assert_eq!(plan.raw_inputs()["ore"], Rational64::from_integer(3));
+            plan.summary.machines["Miner"]
+        };
+        // three ore a second from nodes yielding half, one and two a second per miner
+        assert_eq!([miners("impure"), miners("normal"), miners("pure")], [6, 3, 2]);
+        assert!(matches!(book.extraction("rich"), Err(Error::UnknownPurity(p)) if p == "rich"));
+    }
+
+    #[test]
+    fn power_plant_test() {
+        let ten = Rational64::from_integer(10);
+        let plant = | refinery: Rational64, demand: Rational64 | {
+            let book = Cookbook::builder().machine("Refinery", Some(refinery)).item("fuel").item("ore")
+                .recipe(Recipe::new("Fuel", one()).made_by("Refinery").reagent("ore", one()).product("fuel", one())).build();
+            let graph = book.graph().unwrap();
+            let generator = Generator { name: "Burner".to_owned(), power: ten, fuel: vec![Reagent { widget: "fuel".to_owned(), quantity: one() }] };
+            power_plant(&graph, &[], &generator, demand, &Constraints::default()).map(| p | (p.count, p.summary.machines.clone()))
+        };
+        // 20 MW, and 5 MW more for every burner's refinery, takes four burners
+        let (count, machines) = plant(Rational64::from_integer(5), Rational64::from_integer(20)).unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(machines, BTreeMap::from([("Burner".to_owned(), 4), ("Refinery".to_owned(), 4)]));
+        // a refinery drawing all its burner makes never settles
+        assert!(matches!(plant(ten, Rational64::from_integer(20)), Err(Error::PowerDiverges(g)) if g == "Burner"));
+    }
+
+    #[test]
+    fn checked_arithmetic_test() {
+        let book = build_basic_book(vec![a_from_b("A", 1)]);
+        let graph = book.graph().unwrap();
+        let dead = Generator { name: "Dead".to_owned(), power: Rational64::from_integer(0), fuel: vec![] };
+        assert!(matches!(power_plant(&graph, &[], &dead, one(), &Constraints::default()), Err(Error::Arithmetic(_))));
+        let instant = Recipe::new("Instant", Rational64::from_integer(0)).reagent("b", one()).product("a", one());
+        assert!(matches!(instant.consumption(&instant.reagents[0]), Err(Error::Arithmetic(_))));
+        let full = | | Summary { machines: BTreeMap::from([("Assembler".to_owned(), u64::MAX)]), ..Default::default() };
+        let mut summary = full();
+        assert!(matches!(summary.merge(full()), Err(Error::Arithmetic(_))));
+    }
+
+    #[test]
+    fn share_surplus_test() {
+        // making `b` leaves a `c` over, just what `a` takes after it
+        let book = build_basic_book(vec![
+            Recipe::new("A", one()).made_by("Assembler").reagent("b", one()).reagent("c", one()).product("a", one()),
+            Recipe::new("B", one()).made_by("Assembler").reagent("ore", one()).product("b", one()).product("c", one()),
+            Recipe::new("C", one()).made_by("Assembler").reagent("ore", one()).product("c", one())
+        ]);
+        let graph = book.graph().unwrap();
+        let plan = | share_surplus: bool | solve(&graph, &[], &"a".to_owned(), one(), &Constraints { share_surplus, ..Default::default() }).unwrap().summary;
+        let wasted = plan(false);
+        assert_eq!((wasted.machines["Assembler"], wasted.surplus["c"], wasted.raw["ore"]), (3, one(), Rational64::from_integer(2)));
+        let shared = plan(true);
+        assert_eq!((shared.machines["Assembler"], shared.shared["c"], shared.raw["ore"]), (2, one(), one()));
+        assert!(shared.surplus.get("c").is_none_or(| c | *c == Rational64::from_integer(0)));
+    }
+
+    #[test]
+    fn buy_test() {
+        // `b` takes two ore at 1 each to craft
+        let book = build_basic_book(vec![a_from_b("A", 1), Recipe::new("B", one()).made_by("Assembler").reagent("ore", Rational64::from_integer(2)).product("b", one())]);
+        let graph = book.graph().unwrap();
+        let priced = | b: Rational64 | Constraints { prices: BTreeMap::from([("ore".to_owned(), one()), ("b".to_owned(), b)]), ..Default::default() };
+        let (a, b) = ("a".to_owned(), "b".to_owned());
+        assert_eq!(make_cost(&graph, &[], &a, &priced(Rational64::new(3, 2)), &mut Vec::new()).unwrap(), Some(Rational64::new(3, 2)));
+        assert!(buys(&graph, &[], &b, &priced(Rational64::new(3, 2))).unwrap() && !buys(&graph, &[], &b, &priced(Rational64::from_integer(5))).unwrap());
+        let plan = solve(&graph, &[], &a, Rational64::from_integer(2), &priced(Rational64::new(3, 2))).unwrap();
+        assert_eq!(plan.summary.purchases, BTreeMap::from([(b.clone(), Rational64::from_integer(2))]));
+        assert!(plan.raw_inputs().is_empty());
+        let plan = solve(&graph, &[], &a, Rational64::from_integer(2), &priced(Rational64::from_integer(5))).unwrap();
+        assert_eq!(plan.summary.purchases, BTreeMap::from([("ore".to_owned(), Rational64::from_integer(4))]));
+    }
+
+    #[test]
+    fn cost_tree_test() {
+        let book = Cookbook::from_yaml("widgets: [a, b, ore]\nbuilders: [{name: Assembler, operating_cost: 2}]\nrecipes:\n  \
+            - {name: A, builder: Assembler, duration: 1, reagents: [{widget: b, quantity: 1}], products: [{widget: a, quantity: 1}]}\n  \
+            - {name: B, builder: Assembler, duration: 1, reagents: [{widget: ore, quantity: 1}], products: [{widget: b, quantity: 1}]}").unwrap();
+        let graph = book.graph().unwrap();
+        let rate = Rational64::from_integer(2);
+        let plan = solve(&graph, &[], &"a".to_owned(), rate, &Constraints::default()).unwrap();
+        let costs = cost_tree(plan.tree.as_ref().unwrap(), rate, &BTreeMap::from([("ore".to_owned(), one())])).unwrap();
+        // two machines at 2 a second each stage, and 2 ore a second at 1 each
+        let b = &costs.children()[0];
+        assert_eq!((b.per_second, b.per_unit), (Rational64::from_integer(6), Rational64::from_integer(3)));
+        assert_eq!((costs.per_second, costs.per_unit), (Rational64::from_integer(10), Rational64::from_integer(5)));
+    }
+
+    #[test]
+    fn bottleneck_test() {
+        // the Assemblers making `a` run at 3/4 of capacity, the three making `b` for them at 2/3
+        let book = build_basic_book(vec![a_from_b("A", 1), Recipe::new("B", Rational64::from_integer(4)).made_by("Assembler").reagent("ore", one()).product("b", Rational64::from_integer(3))]);
+        let graph = book.graph().unwrap();
+        let (widget, rate) = ("a".to_owned(), Rational64::new(3, 2));
+        let tree = solve(&graph, &[], &widget, rate, &Constraints::default()).unwrap().tree.unwrap();
+        let load = load_tree(&tree, &widget, rate).unwrap();
+        assert_eq!((load.load, load.spare), (Rational64::new(3, 4), Rational64::new(1, 2)));
+        assert_eq!((load.children()[0].count, load.children()[0].load), (3, Rational64::new(2, 3)));
+        let notes = annotate_bottlenecks(&load, None);
+        assert_eq!((notes.as_str(), notes.children()[0].as_str()), (" [bottleneck]", ""));
+    }
+
+    #[test]
+    fn from_recipes_test() {
+        let widgets: Vec<Widget> = ["a", "b", "c", "unused"].iter().map(| w | Widget::Item(w.to_string())).collect();
+        let two = Recipe::new("Two", one()).made_by("Assembler").reagent("b", one()).product("a", one()).product("c", one());
+        let graph = Hypergraph::from_recipes(&widgets, vec![a_from_b("A", 1), two.clone()]).unwrap();
+        // every widget is a node, used or not, and every recipe one edge however many it makes
+        assert_eq!((graph.order(), graph.size()), (4, 2));
+        let mut names: Vec<&str> = producers(&graph, &"a".to_owned()).unwrap().iter().map(| r | r.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["A", "Two"]);
+        assert!(is_raw(&graph, &"b".to_owned()).unwrap() && !is_raw(&graph, &"c".to_owned()).unwrap());
+        assert!(matches!(Hypergraph::from_recipes(&widgets[..2], vec![two]), Err(Error::UnknownWidget(w)) if w == "c"));
+    }
+
+    #[test]
+    fn summary_text_test() {
+        let book = build_basic_book(vec![a_from_b("A", 1)]);
+        let graph = book.graph().unwrap();
+        let plan = solve(&graph, &[], &"a".to_owned(), one(), &Constraints::default()).unwrap();
+        let limits = Constraints { limits: BTreeMap::from([("Assembler".to_owned(), 2)]), ..Default::default() };
+        assert_eq!(summary_text(&plan.summary, &limits, &book, Precision::default()).unwrap(), "\nRaw resources:\n  b: 1.000/s\n\nMachines:\n  Assembler: 1 of 2\n");
+        assert_eq!(footprint_text(plan.tree.as_ref().unwrap()).unwrap(), "");
+    }
+
+    #[test]
+    fn plan_node_test() {
+        // 3/2 of `a` a second takes one and a half Assemblers, two built at full clock
+        let book = build_basic_book(vec![a_from_b("A", 1)]);
+        let graph = book.graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::new(3, 2), &Constraints::default()).unwrap().tree.unwrap();
+        let PlanNode { recipe, machine_count, machines, clock, requested_rate, actual_rate, surplus } = *tree;
+        assert_eq!((recipe.name.as_str(), machine_count, clock), ("A", 2, one()));
+        assert_eq!((requested_rate, actual_rate, surplus), (Rational64::new(3, 2), Rational64::from_integer(2), Rational64::new(1, 2)));
+        assert_eq!((machines, tree.draws(&recipe.reagents[0])), (Rational64::from_integer(2), Rational64::from_integer(2)));
+    }
+
+    #[test]
+    fn utilization_test() {
+        // two Assemblers asked for 3/2 of `a` are three quarters used
+        let book = build_basic_book(vec![a_from_b("A", 1)]);
+        let graph = book.graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::new(3, 2), &Constraints::default()).unwrap().tree.unwrap();
+        assert_eq!(tree.utilization(), Rational64::new(3, 4));
+        let (text, _) = tree_text(&tree, None, &book, Precision::default());
+        assert!(text.contains(" [75% used]"), "{}", text);
+        // to a tenth of a percent unless the precision is set
+        let two_thirds = solve(&graph, &[], &"a".to_owned(), Rational64::new(2, 3), &Constraints::default()).unwrap().tree.unwrap();
+        assert!(tree_text(&two_thirds, None, &book, Precision::default()).0.contains(" [66.7% used]"));
+        assert!(tree_text(&two_thirds, None, &book, Precision(Some(2))).0.contains(" [66.67% used]"));
+    }
+
+    #[test]
+    fn leaf_inputs_test() {
+        let book = build_basic_book(vec![a_from_b("A", 1)]);
+        let graph = book.graph().unwrap();
+        // a crafted leaf shows the reagents its machines draw
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::new(3, 2), &Constraints::default()).unwrap().tree.unwrap();
+        assert_eq!(leaf_inputs(&tree, &book, Precision::default()), " ← 2.000/s b");
+        // an extractor shows the resource it works, and a stage with children nothing
+        let extraction = vec![Recipe::new("Mine", one()).made_by("Assembler").product("b", one())];
+        let tree = solve(&graph, &extraction, &"a".to_owned(), one(), &Constraints::default()).unwrap().tree.unwrap();
+        assert_eq!((leaf_inputs(&tree, &book, Precision::default()).as_str(), leaf_inputs(&tree.children()[0], &book, Precision::default()).as_str()), ("", " ← 1.000/s b"));
+    }
+
+    #[test]
+    fn read_definition_test() {
+        // decompressing goes through the system's gzip, so there is nothing to check without one
+        if Command::new("gzip").arg("--version").stdout(Stdio::null()).status().is_err() {
+            eprintln!("gzip is not installed, skipping");
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("supply-solver-compressed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plain = std::fs::read("satisfactory.yaml").unwrap();
+        // told apart by their bytes, not their names
+        let (named, packed, broken) = (dir.join("plain.yaml.gz"), PathBuf::from("compressed.yaml.gz"), dir.join("broken.yaml"));
+        std::fs::write(&named, &plain).unwrap();
+        std::fs::write(&broken, b"\x1f\x8bnot really").unwrap();
+        assert_eq!(read_definition(&named).unwrap(), plain);
+        assert!(String::from_utf8(read_definition(&packed).unwrap()).unwrap().starts_with("widgets: [ore, plate]\n"));
+        let cookbook = load_recipes(&packed).unwrap();
+        assert_eq!((cookbook.recipes.len(), cookbook.recipes[0].rate(&"plate".to_owned()).unwrap()), (1, Rational64::from_integer(1)));
+        assert!(matches!(read_definition(&broken), Err(Error::Io { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn clipboard_test() {
+        // tools that aren't installed are passed over for the next
+        assert_eq!(copy_through(&[("supply-solver-no-such-tool", &[]), ("cat", &[])], "plan").unwrap(), "cat");
+        // one that runs and fails is the end of it, whether it took the text or not
+        assert!(matches!(copy_through(&[("false", &[]), ("cat", &[])], "plan"), Err(Error::Clipboard(e)) if e.starts_with("false ")));
+        assert!(matches!(copy_through(&[("supply-solver-no-such-tool", &[])], "plan"), Err(Error::Clipboard(e)) if e == "none of supply-solver-no-such-tool is installed"));
+    }
+}
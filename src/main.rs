@@ -1,135 +1,1341 @@
 use num::Rational64;
-use serde::{Deserialize, Deserializer};
-use std::cmp::Ordering;
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
-mod tree;
-use crate::tree::NTree;
+use supply_solver::error::{Error, Result};
+use supply_solver::hypergraph::Hypergraph;
+use supply_solver::tree::{self, NTree};
+use supply_solver::*;
 
-mod hypergraph;
-use crate::hypergraph::Hypergraph;
+#[derive(StructOpt)]
+struct Cli {
+    /// Directory to look for game definitions given by name in, before the others (repeatable)
+    #[structopt(long, global = true, number_of_values = 1, parse(from_os_str))]
+    data_path: Vec<PathBuf>,
+
+    /// Decimal places for rates, percentages and power in every output, instead of each one's own
+    #[structopt(long, global = true)]
+    precision: Option<usize>,
+
+    #[structopt(subcommand)]
+    command: Command
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Plan the builders needed to make a widget
+    Solve(Goal),
+    /// Plan every production line listed in a request file in one run, each a widget, rate and constraints as POST /solve
+    /// takes them, reporting each line and all of them together
+    SolveAll(SolveAll),
+    /// Step a solved plan over time to check it delivers what the math promises
+    Simulate(Simulation),
+    /// Report where the plan changes as the target rate varies
+    Analyze(Analysis),
+    /// Browse a solved plan's tree interactively, expanding, collapsing and searching its stages, and re-solve it
+    /// as alternates are toggled, recipes pinned or excluded and the rate nudged
+    Explore(Goal),
+    /// Render a plan saved with `solve --save` without solving again
+    Show(Show),
+    /// Compare two plans saved with `solve --save`, stage by stage
+    Diff(PlanDiff),
+    /// Compare two versions of a game definition, listing the widgets, recipes and builders added, removed
+    /// or changed, and which saved plans given with --plans need solving again
+    DataDiff(DataDiff),
+    /// Check that every stage of a saved plan is fed what it takes by the stages below it at their stated machine
+    /// counts, for catching solver bugs, hand edits and bad imported data
+    Check(Show),
+    /// Turn a saved plan from JSON into MessagePack or back by the output's extension, or print it as JSON
+    Convert(Convert),
+    /// Rank the locked alternate recipes by how much unlocking each improves the plan
+    Unlocks(Unlocks),
+    /// Search the recipe for every widget as a whole for a plan scoring lower on an objective than the heuristic's,
+    /// by simulated annealing or evolving a population of plans
+    Optimize(Optimize),
+    /// Print the game definition's recipe network as Graphviz DOT
+    Dot(Dot),
+    /// List a game definition's widgets or recipes, filtered by name, builder and tier
+    List(List),
+    /// List the recipes taking a widget as a reagent and, with --transitive, everything downstream of them
+    Uses(Uses),
+    /// List everything that can be made from only the given widgets, step by step
+    Craftable(Craftable),
+    /// List the end products a surplus of one widget can be turned into, and how much of each, using only
+    /// the surplus and any widgets given with --with
+    Consume(Consume),
+    /// Report the shape of the game definition's recipe network, for spotting gaps in the data
+    Stats(Dataset),
+    /// Check the game definition for every problem at once: undeclared widgets, duplicate names, non-positive
+    /// durations, unknown builders and widgets that can never be made
+    Validate(Dataset),
+    /// Write the game definition as a binary index that loads without parsing YAML, for every other subcommand to take
+    /// in its place
+    Compile(Compile),
+    /// Answer solve requests over HTTP: POST /solve, GET /widgets and GET /recipes/<widget> in JSON, and stage by stage
+    /// over a WebSocket at /solve/stream; POST /reload loads the game definition again without restarting, GET /metrics reports
+    /// for Prometheus
+    Serve(Serve),
+    /// Keep a game definition loaded and answer solve, raw, uses and makes queries typed one after another
+    Repl(Repl),
+    /// List where game definitions given by name are looked for and the ones found there
+    Datasets,
+    /// Print a JSON Schema of the game definition format, for editors and importers to validate against
+    Schema
+}
+
+impl Command {
+    // the game definition the subcommand loads, if it loads one
+    fn game_def(&mut self) -> Option<&mut PathBuf> {
+        match self {
+            Command::Solve(goal) | Command::Explore(goal) => Some(&mut goal.game_def),
+            Command::Simulate(Simulation { goal, .. }) | Command::Analyze(Analysis { goal, .. }) | Command::Unlocks(Unlocks { goal, .. })
+                | Command::Optimize(Optimize { goal, .. }) => Some(&mut goal.game_def),
+            Command::Dot(Dot { game_def, .. }) | Command::Stats(Dataset { game_def }) | Command::Validate(Dataset { game_def }) | Command::Serve(Serve { game_def, .. }) | Command::Repl(Repl { game_def, .. }) => Some(game_def),
+            Command::List(List::Widgets(Listing { game_def, .. }) | List::Recipes(Listing { game_def, .. })) => Some(game_def),
+            Command::Uses(Uses { game_def, .. }) | Command::Craftable(Craftable { game_def, .. }) | Command::Consume(Consume { game_def, .. })
+                | Command::SolveAll(SolveAll { game_def, .. }) | Command::Compile(Compile { game_def, .. }) => Some(game_def),
+            Command::Show(_) | Command::Diff(_) | Command::DataDiff(_) | Command::Check(_) | Command::Convert(_) | Command::Datasets | Command::Schema => None
+        }
+    }
+}
+
+#[derive(StructOpt)]
+struct Goal {
+    #[structopt(parse(from_os_str))]
+    game_def: std::path::PathBuf,
+
+    widget: String,
+    /// Units/second such as 2.5 or 1/3, or per unit of time such as 45/min; a total quantity with --batch
+    #[structopt(parse(try_from_str = parse_rate))]
+    rate: Rational64,
+
+    /// Produce RATE units in total instead of a steady rate
+    #[structopt(long)]
+    batch: bool,
+
+    /// Seconds a batch should take, by default the final stage runs on a single machine
+    #[structopt(long)]
+    within: Option<f64>,
+
+    /// Widgets already on hand, a YAML map of widget to quantity that batches use up first
+    #[structopt(long, requires = "batch", parse(from_os_str))]
+    stock: Option<PathBuf>,
+
+    /// Print the batch as a timeline of builder groups instead of a tree
+    #[structopt(long, requires = "batch", possible_values = &["mermaid", "csv"])]
+    gantt: Option<String>,
+
+    /// Only use recipes unlocked at or below this tier
+    #[structopt(long)]
+    max_tier: Option<u32>,
+
+    /// Only use recipes in the game at this version, such as 1.0, by their since and deprecated versions
+    #[structopt(long)]
+    game_version: Option<version::GameVersion>,
+
+    /// Never hand-craft a widget that a machine can make
+    #[structopt(long)]
+    prefer_machines: bool,
+
+    /// Lowest clock speed in percent builders may run at
+    #[structopt(long)]
+    min_clock: Option<f64>,
+
+    /// Highest clock speed in percent builders may run at
+    #[structopt(long)]
+    max_clock: Option<f64>,
+
+    /// Underclock machines to hit the requested rate exactly instead of overproducing
+    #[structopt(long)]
+    exact_clock: bool,
+
+    /// Builder construction costs, for a bill of materials of the placed machines
+    #[structopt(long, parse(from_os_str))]
+    construction: Option<PathBuf>,
+
+    /// Extend the plan with this generator and the fuel chains needed to power it
+    #[structopt(long)]
+    generator: Option<String>,
+
+    /// Purity of the resource nodes extractors are placed on
+    #[structopt(long, default_value = "normal")]
+    purity: String,
+
+    /// Feed overproduction and byproducts of one branch into demand elsewhere in the plan
+    #[structopt(long)]
+    share_surplus: bool,
+
+    /// Roll widget prices and builder operating costs up into a cost per unit for every stage
+    #[structopt(long)]
+    costs: bool,
+
+    /// Split the plan into this many build phases, each fed by the ones before it
+    #[structopt(long, conflicts_with = "batch")]
+    phases: Option<usize>,
+
+    /// Time placing the plan's machines one by one from the construction times in --construction, reporting
+    /// when the goal's rate climbs to full production
+    #[structopt(long, requires = "construction", conflicts_with = "batch")]
+    ramp_up: bool,
+
+    /// Take construction materials the plan makes from its own stages once they have output to spare
+    #[structopt(long, requires = "ramp-up")]
+    self_supply: bool,
+
+    /// List the machines and rates per widget across the whole plan
+    #[structopt(long, conflicts_with = "batch")]
+    bill: bool,
+
+    /// List the builder limits and logistics links that cap the plan's throughput when scaling it up
+    #[structopt(long, conflicts_with = "batch")]
+    chokepoints: bool,
+
+    /// Divide the plan among this many players, listing what each hands to the others
+    #[structopt(long, conflicts_with = "batch")]
+    players: Option<usize>,
+
+    /// Balance players by machine count or by the construction materials of their machines
+    #[structopt(long, requires = "players", requires_if("effort", "construction"), possible_values = &["machines", "effort"])]
+    split_by: Option<String>,
+
+    /// Factory sites with their local resources and the links between them, to spread the plan over
+    #[structopt(long, parse(from_os_str), conflicts_with = "batch")]
+    sites: Option<PathBuf>,
+
+    /// Save the solved plan for `show`, as JSON or as MessagePack when it ends in .msgpack, e.g. factory.plan.json
+    #[structopt(long, parse(from_os_str), conflicts_with = "batch")]
+    save: Option<PathBuf>,
+
+    /// Print the plan through a template file instead of the usual report, a Handlebars subset given the goal, every
+    /// stage, the stage tree, raw resources, machines and power per builder, and surplus, for wiki pages and checklists
+    #[structopt(long, parse(from_os_str), conflicts_with = "batch")]
+    template: Option<PathBuf>,
+
+    /// Draw the plan's stage tree as an SVG image to this file, for printing and embedding in pages
+    #[structopt(long, parse(from_os_str), conflicts_with = "batch")]
+    svg: Option<PathBuf>,
+
+    /// Write a rough Factorio blueprint string of the plan to this file, a row of machines per stage between
+    /// requester and provider chests, for definitions using Factorio's entity and recipe names
+    #[structopt(long, parse(from_os_str), conflicts_with = "batch")]
+    blueprint: Option<PathBuf>,
+
+    /// Copy the stage tree, the tree as a Markdown code block for pasting into chat, or the --share link to the
+    /// clipboard
+    #[structopt(long, conflicts_with = "batch", possible_values = &["tree", "markdown", "share"])]
+    copy: Option<String>,
+
+    /// Print a link opening the plan's goal and recipe choices in a KirkMcDonald-style web calculator
+    #[structopt(long, conflicts_with = "batch")]
+    share: bool,
+
+    /// Calculator page share links point at
+    #[structopt(long, default_value = share::KIRK_SATISFACTORY)]
+    share_base: String,
+
+    /// Plan the recipe graph as one network: shared intermediates come from a single group,
+    /// byproducts feed any stage needing them and recipe loops are solved as a whole
+    #[structopt(long, conflicts_with = "batch")]
+    network: bool,
+
+    /// Plan only this many stages down from the goal, treating widgets needed deeper as supplied from outside
+    #[structopt(long, conflicts_with_all = &["batch", "network"])]
+    max_expand_depth: Option<usize>,
+
+    /// Buy widgets at their market price whenever that is no dearer than crafting them
+    #[structopt(long)]
+    buy: bool,
+
+    /// Feed surplus to a sink for the points the game definition says each widget is worth, reporting points per minute
+    #[structopt(long)]
+    sink: bool,
+
+    /// Keep to standard recipes unless an unlocked alternate needs fewer machines, or scores lower on --cost, by
+    /// more than this fraction, e.g. 0.25 for a quarter better
+    #[structopt(long)]
+    prefer_standard: Option<f64>,
+
+    /// Allow an alternate recipe by name (repeatable)
+    #[structopt(long = "unlock")]
+    unlocks: Vec<String>,
+
+    /// Pick each stage's recipe by the lowest score of an expression over machines, power, waste, inputs,
+    /// raw, tier, alternate, manual, footprint and, with --sink, sink points, e.g. "machines*2 + power*0.1 + raw"; least waste
+    /// breaks ties. `@file` reads a score file instead: lines of `name = expression` defining sub-costs from
+    /// the metrics and earlier names, then the expression to score by, # starting comments
+    #[structopt(long)]
+    cost: Option<String>,
+
+    /// Weigh each recipe by the machines left idle across everything it expands into, not only at its own
+    /// stage; slower, but better on products with many branches
+    #[structopt(long)]
+    downstream_waste: bool,
+
+    /// Pick each stage's recipe by the least MW drawn across everything it expands into, for when
+    /// generation is scarce; --cost and least waste break ties. `optimize --objective power` searches further
+    #[structopt(long)]
+    minimize_power: bool,
+
+    /// Round each stage's machine count up to whole machines, to the nearest whole machine, or not at all,
+    /// leaving fractional counts to plan clock speeds from; machine totals always count whole machines to build
+    #[structopt(long, possible_values = &["ceil", "nearest", "none"], default_value = "ceil")]
+    round: Rounding,
+
+    /// Never use a recipe, by name (repeatable)
+    #[structopt(long = "exclude")]
+    excludes: Vec<String>,
+
+    /// Use only recipes of these categories, e.g. smelting,assembly; recipes without a category are always allowed
+    #[structopt(long, use_delimiter = true)]
+    only_categories: Vec<String>,
+
+    /// Never use recipes of these categories, e.g. refining
+    #[structopt(long, use_delimiter = true)]
+    exclude_categories: Vec<String>,
+
+    /// Machines available for a builder, as BUILDER=COUNT (repeatable)
+    #[structopt(long = "limit", parse(try_from_str = parse_limit))]
+    limits: Vec<(String, u64)>,
+
+    /// Give up searching for a plan within the builder limits after this many seconds, taking the best found so far
+    #[structopt(long, requires = "limits")]
+    timeout: Option<f64>,
+
+    /// Solve again instead of reusing the plan cached for the same game definition, goal and options
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Merge another game definition into this one, such as a mod's (repeatable); widgets two define are told
+    /// apart as NAMESPACE:WIDGET, by the definition's `namespace` or file name
+    #[structopt(long = "merge", parse(from_os_str))]
+    merges: Vec<PathBuf>,
+
+    /// Namespace to take a widget from when several merged definitions declare it and it's named without one
+    /// (repeatable, the first listed winning)
+    #[structopt(long = "prefer-namespace")]
+    prefer_namespaces: Vec<String>,
+
+    /// Read only the recipes the widget can be made from, for game definitions too large to hold whole
+    #[structopt(long)]
+    lazy: bool,
+
+    /// Solve again whenever the game definition or another input file is saved, listing what changed
+    #[structopt(long)]
+    watch: bool
+}
+
+#[derive(StructOpt)]
+struct Simulation {
+    #[structopt(flatten)]
+    goal: Goal,
+
+    /// Simulated seconds, throughput is measured over the second half
+    #[structopt(long, default_value = "600")]
+    seconds: f64,
+
+    /// Seconds per simulation step
+    #[structopt(long, default_value = "0.1")]
+    step: f64,
+
+    /// Crafts' worth of units every machine can buffer per input and output
+    #[structopt(long, default_value = "2")]
+    buffer: f64,
+
+    /// Seconds units spend on belts and pipes between machine groups
+    #[structopt(long, default_value = "5")]
+    delay: f64,
+
+    /// Simulate this many times, reporting the range of throughput recipes with a duration or yield spread achieve
+    #[structopt(long, default_value = "1")]
+    runs: usize,
+
+    /// Seed for the draws of craft times and yields, the same seed giving the same runs
+    #[structopt(long, default_value = "1")]
+    seed: u64
+}
+
+#[derive(StructOpt)]
+struct Analysis {
+    #[structopt(flatten)]
+    goal: Goal,
+
+    /// Lowest rate to consider, half the target by default
+    #[structopt(long)]
+    from: Option<f64>,
+
+    /// Highest rate to consider, twice the target by default
+    #[structopt(long)]
+    to: Option<f64>,
+
+    /// Rates sampled across the range before narrowing down each change
+    #[structopt(long, default_value = "100")]
+    steps: u64
+}
+
+#[derive(StructOpt)]
+struct Unlocks {
+    #[structopt(flatten)]
+    goal: Goal,
+
+    /// Rank by raw resource usage or by machine count
+    #[structopt(long, possible_values = &["raw", "machines"], default_value = "raw")]
+    rank_by: String
+}
+
+#[derive(StructOpt)]
+struct Optimize {
+    #[structopt(flatten)]
+    goal: Goal,
+
+    /// What to minimize across the whole plan: raw, machines, power, footprint, or sink to maximize, or an
+    /// expression weighing the plan's totals of the --cost metrics, e.g. "0.5*raw + 0.3*power + 0.2*machines",
+    /// which the heuristic's plan to start from also picks each stage's recipe by unless --cost is given.
+    /// Several separated by commas for the Pareto front of plans trading them off, searched by evolving
+    #[structopt(long = "objective", default_value = "raw", use_delimiter = true)]
+    objectives: Vec<optimize::Objective>,
+
+    /// Anneal a single plan, or evolve a population for several good plans to pick from
+    #[structopt(long, possible_values = &["anneal", "evolve"], default_value = "anneal")]
+    method: optimize::Method,
+
+    /// Recipe changes to try when annealing
+    #[structopt(long, default_value = "1000")]
+    steps: u64,
+
+    /// Generations to evolve
+    #[structopt(long, default_value = "50")]
+    generations: u64,
+
+    /// Plans in each generation
+    #[structopt(long, default_value = "20")]
+    population: usize,
+
+    /// Distinct plans of the last generation to list, best first
+    #[structopt(long, default_value = "5")]
+    keep: usize,
+
+    /// Seed for the random choices, the same seed giving the same plan
+    #[structopt(long, default_value = "1")]
+    seed: u64
+}
+
+#[derive(StructOpt)]
+struct Dataset {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf
+}
+
+#[derive(StructOpt)]
+struct Compile {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Where to write the index, e.g. modpack.ssi
+    #[structopt(parse(from_os_str))]
+    output: PathBuf
+}
+
+#[derive(StructOpt)]
+enum List {
+    /// Widgets whose names contain PATTERN, with how many recipes make each
+    Widgets(Listing),
+    /// Recipes making a widget whose name contains PATTERN, or named like it
+    Recipes(Listing)
+}
+
+#[derive(StructOpt)]
+struct Listing {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Part of the name to look for, in any case
+    pattern: Option<String>,
+
+    /// Only recipes run by this builder, or widgets it makes or extracts
+    #[structopt(long)]
+    builder: Option<String>,
+
+    /// Only recipes unlocked at or below this tier
+    #[structopt(long)]
+    max_tier: Option<u32>
+}
+
+#[derive(StructOpt)]
+struct Uses {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    widget: String,
+
+    /// Follow the products of those recipes to the recipes using them in turn
+    #[structopt(long)]
+    transitive: bool
+}
+
+#[derive(StructOpt)]
+struct Craftable {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Widgets on hand, comma separated, e.g. iron-plate,screw,wire
+    #[structopt(long, use_delimiter = true, required = true)]
+    have: Vec<String>,
+
+    /// Only recipes run by this builder
+    #[structopt(long)]
+    builder: Option<String>,
+
+    /// Only recipes unlocked at or below this tier
+    #[structopt(long)]
+    max_tier: Option<u32>
+}
+
+#[derive(StructOpt)]
+struct Consume {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
 
-#[derive(Clone, Debug, Deserialize)]
-struct Reagent {
     widget: String,
-    quantity: u64
+    /// Units/second of it to spare, such as 1.5 or 90/min
+    #[structopt(parse(try_from_str = parse_rate))]
+    rate: Rational64,
+
+    /// Other widgets to draw on as much as needed, comma separated, e.g. water
+    #[structopt(long, use_delimiter = true)]
+    with: Vec<String>,
+
+    /// Only recipes unlocked at or below this tier
+    #[structopt(long)]
+    max_tier: Option<u32>,
+
+    /// Allow an alternate recipe by name (repeatable)
+    #[structopt(long = "unlock")]
+    unlocks: Vec<String>
 }
 
-#[derive(Clone, Debug, Deserialize)]
-struct Recipe {
-    name: String,
-    builder: String,
-    // in seconds
-    #[serde(deserialize_with="deserialize_decimal")]
-    duration: Rational64,
-    products: Vec<Reagent>,
-    reagents: Vec<Reagent>
+#[derive(StructOpt)]
+struct SolveAll {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// A YAML or JSON list of requests, e.g. `- {name: north rotors, widget: rotor, rate: 1/15, max_tier: 4}`
+    #[structopt(parse(from_os_str))]
+    requests: PathBuf,
+
+    /// Solve every line again instead of reusing plans cached for the same game definition, goal and options
+    #[structopt(long)]
+    no_cache: bool
 }
 
-fn deserialize_decimal<'de, D>(deserializer: D) -> Result<Rational64, D::Error> where D: Deserializer<'de> {
-    Rational64::approximate_float(f64::deserialize(deserializer)?).ok_or(serde::de::Error::custom("Bad decimal"))
+#[derive(StructOpt)]
+struct Dot {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Leave out recipes whose inputs already lead to their outputs through other recipes, for big datasets
+    #[structopt(long)]
+    reduce: bool
 }
 
-impl Recipe {
-    // units/second
-    pub fn rate(&self, widget: &String) -> Rational64 {
-        let reagent = self.products.iter().filter(| r | widget == &r.widget).next().unwrap();
-        Rational64::from_integer(reagent.quantity as i64) / self.duration
+#[derive(StructOpt)]
+struct Serve {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Address and port to listen on
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Load the game definition again whenever the file changes, checked as each request comes in; POST
+    /// /reload always does
+    #[structopt(long)]
+    watch: bool
+}
+
+#[derive(StructOpt)]
+struct Repl {
+    #[structopt(parse(from_os_str))]
+    game_def: PathBuf,
+
+    /// Purity of the resource nodes extractors are placed on, until changed with `purity`
+    #[structopt(long, default_value = "normal")]
+    purity: String,
+
+    /// Only use recipes unlocked at or below this tier
+    #[structopt(long)]
+    max_tier: Option<u32>,
+
+    /// Allow an alternate recipe by name (repeatable), more can be toggled with `unlock`
+    #[structopt(long = "unlock")]
+    unlocks: Vec<String>
+}
+
+#[derive(StructOpt)]
+struct Show {
+    #[structopt(parse(from_os_str))]
+    plan: PathBuf
+}
+
+#[derive(StructOpt)]
+struct Convert {
+    #[structopt(parse(from_os_str))]
+    plan: PathBuf,
+
+    /// Where to write it, MessagePack when it ends in .msgpack and JSON otherwise; printed as JSON without one
+    #[structopt(parse(from_os_str))]
+    output: Option<PathBuf>
+}
+
+#[derive(StructOpt)]
+struct PlanDiff {
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+
+    #[structopt(parse(from_os_str))]
+    new: PathBuf
+}
+
+#[derive(StructOpt)]
+struct DataDiff {
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+
+    /// Saved plans made with the old version, to check against the changes (repeatable)
+    #[structopt(long = "plans", parse(from_os_str))]
+    plans: Vec<PathBuf>
+}
+
+fn parse_limit(s: &str) -> std::result::Result<(String, u64), String> {
+    let (builder, count) = s.rsplit_once('=').ok_or_else(|| format!("expected BUILDER=COUNT, got `{}`", s))?;
+    let count = count.trim().parse::<u64>().map_err(| e | format!("bad machine count in `{}`: {}", s, e))?;
+    Ok((builder.trim().to_owned(), count))
+}
+
+// the game definition with anything merged into it, the goal's widget resolved to its namespaced name
+fn load_goal(args: &mut Goal) -> Result<Cookbook> {
+    let load = | path: &PathBuf | if args.lazy && args.merges.is_empty() { Cookbook::parse_for(path, &args.widget) } else { load_recipes(path) };
+    let mut cookbook = load(&args.game_def)?;
+    if !args.merges.is_empty() {
+        let stem = | path: &PathBuf | path.file_stem().map_or_else(String::new, | s | s.to_string_lossy().into_owned());
+        let mut datasets = vec![(stem(&args.game_def), cookbook)];
+        for path in args.merges.iter() {
+            datasets.push((stem(path), load(path)?));
+        }
+        let (merged, collisions) = namespace::merge(datasets, &args.prefer_namespaces);
+        for collision in collisions {
+            let (kind, name, by) = (collision.kind, &collision.name, collision.namespaces.join(" and "));
+            match kind {
+                "widget" => eprintln!("warning: widget `{name}` is defined by {by}, as {qualified}; {chosen}'s is meant where it's named without a namespace", name=name, by=by,
+                    qualified=collision.namespaces.iter().map(| ns | format!("{}:{}", ns, name)).collect::<Vec<_>>().join(" and "), chosen=collision.chosen),
+                "recipe" => eprintln!("warning: recipe `{name}` is defined by {by}, the later renamed `{ns}:{name}`", name=name, by=by,
+                    ns=collision.namespaces.last().expect("collisions have namespaces")),
+                _ => eprintln!("warning: {kind} `{name}` is defined differently by {by}, using {chosen}'s", kind=kind, name=name, by=by, chosen=collision.chosen)
+            }
+        }
+        cookbook = merged;
+    }
+    if let Some(widget) = cookbook.resolve_widget(&args.widget, &args.prefer_namespaces) {
+        args.widget = widget;
+    }
+    Ok(cookbook)
+}
+
+// --cost's expression, or with `@file` the score file it names
+fn cost_expr(arg: &str) -> Result<cost::CostExpr> {
+    match arg.strip_prefix('@') {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            let text = std::fs::read_to_string(&path).map_err(| source | Error::Io { path: path.clone(), source })?;
+            cost::CostExpr::from_script(&text).map_err(| e | Error::Usage(format!("{path}: {e}", path=path.display(), e=e)))
+        },
+        None => arg.parse()
+    }
+}
+
+fn constraints(args: &Goal, cookbook: &Cookbook) -> Result<Constraints> {
+    let clock = | percent: Option<f64> | -> Result<Option<Rational64>> {
+        percent.map(| p | Rational64::approximate_float(p / 100.0).ok_or_else(|| Error::Arithmetic(format!("clock {p}% is not representable", p=p)))).transpose()
+    };
+    Ok(Constraints {
+        max_tier: args.max_tier,
+        prefer_machines: args.prefer_machines,
+        min_clock: clock(args.min_clock)?,
+        max_clock: clock(args.max_clock)?,
+        exact_clock: args.exact_clock,
+        excluded: args.excludes.iter().cloned().collect(),
+        categories: (!args.only_categories.is_empty()).then(|| args.only_categories.iter().cloned().collect()),
+        excluded_categories: args.exclude_categories.iter().cloned().collect(),
+        game_version: args.game_version.clone(),
+        alternate_penalty: args.prefer_standard.map(| p | Rational64::approximate_float(p).filter(| p | *p >= Rational64::from_integer(0))
+            .ok_or_else(|| Error::Usage(format!("--prefer-standard takes a fraction of at least 0, not {p}", p=p)))).transpose()?,
+        unlocked: args.unlocks.iter().cloned().collect(),
+        limits: args.limits.iter().cloned().collect(),
+        share_surplus: args.share_surplus,
+        prices: if args.buy { cookbook.prices() } else { BTreeMap::new() },
+        network: args.network,
+        max_depth: args.max_expand_depth,
+        cost: args.cost.as_deref().map(cost_expr).transpose()?,
+        downstream_waste: args.downstream_waste,
+        minimize_power: args.minimize_power,
+        rounding: args.round,
+        sinks: if args.sink { cookbook.sink_values() } else { BTreeMap::new() }
+    })
+}
+
+// solves for the goal, falling back to the best rate the builder limits allow
+fn plan<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], args: &Goal, rate: Rational64, constraints: &Constraints, precision: Precision) -> Result<Plan<'a>> {
+    if constraints.limits.is_empty() {
+        return solve(graph, extraction, &args.widget, rate, constraints);
+    }
+    let deadline = args.timeout.map(| seconds | std::time::Instant::now() + std::time::Duration::from_secs_f64(seconds));
+    match search_within_limits(graph, extraction, &args.widget, rate, constraints, deadline)? {
+        Search::Fits(solution) => Ok(solution),
+        Search::TimedOut(solution, over) => {
+            println!("Out of time looking for a plan within the builder limits, this one is {over} machines over them", over=over);
+            println!();
+            Ok(solution)
+        },
+        Search::Exhausted => {
+            let (best, bound) = best_rate_by(graph, extraction, &args.widget, rate, constraints, deadline)?;
+            let (best_rate, solution) = match (best, bound) {
+                (Some(best), _) => best,
+                (None, Some(_)) => return Err(Error::Usage(format!("out of time before finding any rate of {widget} the builder limits allow", widget=args.widget))),
+                (None, None) => return Err(Error::LimitsExceeded(args.widget.clone()))
+            };
+            if let Some(bound) = bound {
+                println!("Out of time, builder limits allow at least {best}/s of {widget} and less than {bound}/s ({requested}/s requested)", best=precision.fixed(as_f64(&best_rate), 3),
+                    widget=args.widget, bound=precision.fixed(as_f64(&bound), 3), requested=precision.fixed(as_f64(&rate), 3));
+            } else {
+                println!("Builder limits allow at most {best}/s of {widget} ({requested}/s requested)", best=precision.fixed(as_f64(&best_rate), 3), widget=args.widget, requested=precision.fixed(as_f64(&rate), 3));
+            }
+            println!();
+            Ok(solution)
+        }
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct Cookbook {
-    widgets: Vec<String>,
-    recipes: Vec<Recipe>
+// the files a solve reads, by when they were last modified
+fn modified(args: &Goal) -> Vec<Option<std::time::SystemTime>> {
+    let paths = [Some(&args.game_def), args.construction.as_ref(), args.stock.as_ref(), args.sites.as_ref()];
+    paths.into_iter().flatten().chain(args.merges.iter()).map(| path | std::fs::metadata(path).and_then(| m | m.modified()).ok()).collect()
 }
 
-impl Cookbook {
-    pub fn parse(file_path: &PathBuf) -> Self {
-        let file = File::open(file_path).unwrap();
-        let reader = BufReader::new(file);
-        serde_yaml::from_reader(reader).unwrap()
+fn run_solve(mut args: Goal, precision: Precision) -> Result<()> {
+    if !args.watch {
+        return solve_goal(&mut args, precision).map(| _ | ());
+    }
+    // a broken save mid-edit is reported and the watch carries on with the last good plan to compare against
+    let attempt = | args: &mut Goal | solve_goal(args, precision).unwrap_or_else(| e | {
+        eprintln!("error: {}", e);
+        None
+    });
+    let mut seen = modified(&args);
+    let mut previous = attempt(&mut args);
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        let now = modified(&args);
+        if now == seen {
+            continue;
+        }
+        seen = now;
+        println!();
+        println!("== {path} changed, solving again ==", path=args.game_def.display());
+        let stages = attempt(&mut args);
+        if let (Some(old), Some(new)) = (&previous, &stages) {
+            println!();
+            println!("Changes since the last solve:");
+            print_stage_diff(old, new);
+        }
+        if stages.is_some() {
+            previous = stages;
+        }
     }
 }
 
-fn least_waste_heuristic<'a>(graph: &'a Hypergraph<String, Recipe>, widget: &String, rate: Rational64) -> Option<(&'a Recipe, u64)> {
-    let best_recipe = graph.neighbor_of(widget).unwrap().iter().map(| e | graph.get_weight(e).unwrap()).min_by(
-        |recipe, min_rate| -> Ordering {
-            let waste = (rate / recipe.rate(widget)).fract();
-            let min_waste = (rate / min_rate.rate(widget)).fract();
-            waste.cmp(&min_waste)
-        }); 
-    match best_recipe {
-        Some(r) => {
-            let frac = (rate / r.rate(widget)).ceil();
-            Some((r, (frac.numer() / frac.denom()) as u64))
+// prints the solve and returns its stages as recipe names and rendered lines, for watching it change
+fn solve_goal(args: &mut Goal, precision: Precision) -> Result<Option<NTree<(String, String)>>> {
+    let cookbook = load_goal(args)?;
+    let costs = args.construction.as_ref().map(ConstructionCosts::parse).transpose()?;
+    let template = args.template.as_ref().map(template::Template::load).transpose()?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.purity)?;
+    let constraints = constraints(args, &cookbook)?;
+    let rate = args.rate;
+    if args.batch {
+        let within = args.within.map(| w | Rational64::approximate_float(w).ok_or_else(|| Error::Arithmetic(format!("time {w} is not representable", w=w)))).transpose()?;
+        let stock = args.stock.as_ref().map(| path | batch::Stock::parse(path, &graph)).transpose()?.unwrap_or_default();
+        let mut materials = batch::Materials::new(stock);
+        match batch::batch_tree(&graph, &extraction, &args.widget, rate, within, &constraints, &mut materials)? {
+            Some(tree) => match args.gantt.as_deref() {
+                Some("csv") => batch::print_csv(&tree, &cookbook.prices()),
+                Some(_) => batch::print_mermaid(&tree, &args.widget),
+                None => batch::print_batch(&tree, args.costs.then(|| cookbook.prices()).as_ref(), &materials, &constraints, &cookbook)
+            },
+            None if materials.raw.is_empty() && materials.purchased.is_empty() => println!("Stock already covers {widget}", widget=args.widget),
+            None => {
+                if materials.purchased.contains_key(&args.widget) {
+                    println!("Buying {widget} is no dearer than crafting it", widget=args.widget);
+                } else {
+                    println!("{widget} is a raw resource", widget=args.widget);
+                }
+                batch::print_materials(&materials, &constraints, &cookbook);
+            }
+        }
+        return Ok(None);
+    }
+    // plans cut down to the builder limits aren't cached, for the note saying so, nor are those of merged
+    // definitions, the cache keying on one
+    let cache = match (args.no_cache || !constraints.limits.is_empty() || !args.merges.is_empty(), cache::locate(), read_definition(&args.game_def)) {
+        (false, Some(dir), Ok(definition)) => Some((
+            cache::entry(&dir, &definition, &args.widget, rate, &args.purity, &constraints),
+            cache::goal_entry(&dir, &args.game_def, &args.widget, rate, &args.purity, &constraints),
+            definition
+        )),
+        _ => None
+    };
+    let cached = cache.as_ref().and_then(| (entry, ..) | cache::load(entry)).filter(| saved | saved.widget == args.widget && saved.rate == rate);
+    let Plan { tree, mut summary, .. } = match &cached {
+        Some(saved) => Plan { widget: saved.widget.clone(), rate, tree: saved.tree.as_ref().map(saved::SavedNode::tree).transpose()?, summary: saved.summary.clone() },
+        None => {
+            // after an edit, only stages making widgets whose recipes changed are chosen again
+            let previous = cache.as_ref().and_then(| (_, goal, _) | cache::load_previous(goal));
+            let solved = match previous.as_ref().and_then(| (old, saved) | Some((old, saved.tree.as_ref()?))) {
+                Some((old, tree)) => solve_incremental(&graph, &extraction, &args.widget, rate, &constraints, &tree.tree()?, &cookbook.changed_widgets(old, &args.purity)?)?,
+                None => plan(&graph, &extraction, args, rate, &constraints, precision)?
+            };
+            if let Some((entry, goal, definition)) = &cache {
+                let saved = saved::SavedPlan::new(&cookbook, &args.widget, rate, solved.tree.as_ref(), solved.summary.clone(), &constraints.limits);
+                cache::store(entry, &saved);
+                cache::store_previous(goal, definition, &saved);
+            }
+            solved
+        }
+    };
+    if let Some(template) = &template {
+        warn(&tree.as_ref().map(| tree | transport_warnings(tree, &cookbook, precision)).unwrap_or_default());
+        print!("{}", template.render(&template::context(&cookbook, &args.widget, rate, tree.as_ref(), &summary, precision)?));
+        return Ok(tree.as_ref().map(| tree | tree.map(&| node | (node.recipe.name.clone(), describe(node, precision)))));
+    }
+    let prices = cookbook.prices();
+    let unit_costs = match &tree {
+        Some(tree) if args.costs => Some(cost_tree(tree, rate, &prices)?),
+        _ => None
+    };
+    let load = tree.as_ref().map(| tree | load_tree(tree, &args.widget, rate)).transpose()?;
+    let mut notes = load.as_ref().map(| load | annotate_bottlenecks(load, unit_costs.as_ref()));
+    if let (true, Some(tree), Some(current)) = (constraints.network, &tree, &notes) {
+        notes = Some(merge_notes(current, &network::shared_notes(tree)));
+    }
+    let sites = args.sites.as_ref().map(sites::Sites::parse).transpose()?;
+    let placement = match (&sites, &tree) {
+        (Some(sites), Some(tree)) => Some(sites::place(sites, tree, &args.widget, rate)?),
+        _ => None
+    };
+    if let (Some(sites), Some(root), Some(current)) = (&sites, placement.as_ref().and_then(| p | p.sites.as_ref()), &notes) {
+        notes = Some(merge_notes(current, &sites::site_notes(sites, root)));
+    }
+    let text = match &tree {
+        Some(tree) => {
+            let (text, warnings) = tree_text(tree, notes.as_ref(), &cookbook, precision);
+            warn(&warnings);
+            text
         },
-        None => None
+        None => format!("{widget} is a raw resource\n", widget=args.widget)
+    };
+    print!("{}", text);
+    // the share link, when that's what is copied, is made further down
+    let mut copied = match args.copy.as_deref() {
+        Some("tree") => Some(text),
+        Some("markdown") => Some(format!("{rate} of {widget}:\n```\n{text}```\n", rate=cookbook.format_rate(&args.widget, &rate, precision), widget=args.widget, text=text)),
+        _ => None
+    };
+    if let Some(name) = &args.generator {
+        let generator = cookbook.generator(name)?;
+        let plant = power_plant(&graph, &extraction, generator, summary.total_power()?, &constraints)?;
+        println!();
+        println!("Power plant: {count}x {generator} ({supplied} MW)", count=plant.count, generator=plant.generator.name, supplied=precision.fixed(as_f64(&(plant.generator.power * Rational64::from_integer(plant.count as i64))), 1));
+        for fuel in plant.fuel.iter() {
+            print_tree(fuel, None, &cookbook, precision);
+        }
+        summary.merge(plant.summary)?;
+    }
+    if let Some(upkeep) = upkeep(&graph, &extraction, &cookbook, &summary.machines, &constraints)? {
+        println!();
+        println!("Upkeep: {demand}", demand=upkeep.demand.iter().map(| (widget, rate) | format!("{rate} of {widget}", rate=cookbook.format_rate(widget, rate, precision), widget=widget))
+            .collect::<Vec<_>>().join(", "));
+        for chain in upkeep.chains.iter() {
+            print_tree(chain, None, &cookbook, precision);
+        }
+        summary.merge(upkeep.summary)?;
+    }
+    print_summary(&summary, &constraints, &cookbook, precision)?;
+    if let Some(load) = &load {
+        print_bottlenecks(load, &cookbook, precision);
     }
+    if let (Some(tree), true) = (&tree, args.bill) {
+        bill::print_bill(&bill::bill_of_materials(tree, &args.widget), &cookbook, precision);
+    }
+    if let (Some(tree), true) = (&tree, args.chokepoints) {
+        chokepoints::print_chokepoints(&chokepoints::chokepoints(tree, &cookbook, &constraints, precision), &args.widget, &rate, &cookbook, precision);
+    }
+    if let Some(unit_costs) = &unit_costs {
+        println!();
+        println!("Cost: {unit:.2} per {widget}, {second:.2} per second", unit=as_f64(&unit_costs.per_unit), widget=args.widget, second=as_f64(&unit_costs.per_second));
+    }
+    if let Some(tree) = &tree {
+        print_footprint(tree)?;
+    }
+    if let Some(costs) = &costs {
+        print_construction(&summary, costs)?;
+    }
+    if let (Some(sites), Some(tree), Some(placement)) = (&sites, &tree, &placement) {
+        sites::print_placement(sites, tree, placement, &cookbook, precision);
+    }
+    if let (Some(tree), Some(count)) = (&tree, args.phases) {
+        phases::print_phases(&phases::phases(tree, count), &args.widget, &rate, costs.as_ref(), &cookbook, precision)?;
+    }
+    if let (Some(tree), Some(costs), true) = (&tree, &costs, args.ramp_up) {
+        ramp::print_ramp_up(&ramp::ramp_up(tree, &args.widget, costs, args.self_supply), &args.widget, &rate, &cookbook, precision);
+    }
+    if let (Some(tree), Some(count)) = (&tree, args.players) {
+        let (by, weights) = match args.split_by.as_deref() {
+            Some("effort") => ("materials", costs.as_ref()),
+            _ => ("machines", None)
+        };
+        players::print_split(&players::split(tree, count, weights)?, &args.widget, &rate, by, &cookbook, precision);
+    }
+    if args.share || args.copy.as_deref() == Some("share") {
+        let url = share::kirk_url(&args.share_base, &cookbook, &args.widget, rate, tree.as_ref());
+        if args.share {
+            println!();
+            println!("Share: {url}", url=url);
+        }
+        if args.copy.as_deref() == Some("share") {
+            copied = Some(url);
+        }
+    }
+    let stages = tree.as_ref().map(| tree | tree.map(&| node | (node.recipe.name.clone(), describe(node, precision))));
+    if let (Some(tree), Some(path)) = (&tree, &args.svg) {
+        std::fs::write(path, svg::render(tree, &args.widget, &cookbook, precision)).map_err(| source | Error::Io { path: path.clone(), source })?;
+    }
+    if let (Some(tree), Some(path)) = (&tree, &args.blueprint) {
+        let blueprint = blueprint::blueprint(tree, &args.widget, rate, &cookbook, precision);
+        std::fs::write(path, &blueprint.string).map_err(| source | Error::Io { path: path.clone(), source })?;
+        println!();
+        println!("Wrote a blueprint of {machines} machines to {path}", machines=blueprint.machines, path=path.display());
+        if !blueprint.skipped.is_empty() {
+            println!("  left to build by hand: {stages}", stages=blueprint.skipped.join(", "));
+        }
+    }
+    if let Some(path) = &args.save {
+        saved::SavedPlan::new(&cookbook, &args.widget, rate, tree.as_ref(), summary, &constraints.limits).save(path)?;
+    }
+    // last, so a missing clipboard doesn't keep the plan from being saved
+    if let Some(copied) = &copied {
+        println!();
+        println!("Copied the {what} to the clipboard with {tool}", what=if args.copy.as_deref() == Some("share") { "share link" } else { "plan" },
+            tool=copy_to_clipboard(copied)?);
+    }
+    Ok(stages)
 }
 
-fn dep_tree<'a>(graph: &'a Hypergraph<String, Recipe>, widget: &String, rate: Rational64) -> NTree<(&'a Recipe, u64)> {
-    let recipe = least_waste_heuristic(graph, widget, rate).unwrap();
-    let mut tree = NTree::new(recipe);
-    for reagent in (*tree).0.reagents.iter() {
-        let requested_rate = Rational64::from_integer(reagent.quantity as i64 * recipe.1 as i64) / recipe.0.duration;
-        tree.insert(dep_tree(graph, &reagent.widget, requested_rate));
+fn warn(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
     }
-    tree
 }
 
-fn print_tree_helper(tree: &NTree<(&Recipe, u64)>, prefix: String, is_last: bool) {
-    let new_prefix = if is_last { format!("{prefix}└── ", prefix=prefix) } else { format!("{prefix}├── ", prefix=prefix) };
-    println!("{prefix}{quantity}x {builder} -> {name}", prefix=new_prefix, quantity=(*tree).1, builder=(*tree).0.builder, name=(*tree).0.name);
-    let children = tree.children();
-    let (last, rest) = match children.split_last() {
-        Some(x) => x,
-        None => return
+fn print_tree(tree: &PlanTree, notes: Option<&Notes>, cookbook: &Cookbook, precision: Precision) {
+    let (text, warnings) = tree_text(tree, notes, cookbook, precision);
+    warn(&warnings);
+    print!("{}", text);
+}
+
+fn print_summary(summary: &Summary, constraints: &Constraints, cookbook: &Cookbook, precision: Precision) -> Result<()> {
+    print!("{}", summary_text(summary, constraints, cookbook, precision)?);
+    Ok(())
+}
+
+fn print_bottlenecks(load: &LoadTree, cookbook: &Cookbook, precision: Precision) {
+    print!("{}", bottlenecks_text(load, cookbook, precision));
+}
+
+fn print_footprint(tree: &PlanTree) -> Result<()> {
+    print!("{}", footprint_text(tree)?);
+    Ok(())
+}
+
+fn print_construction(summary: &Summary, costs: &ConstructionCosts) -> Result<()> {
+    print!("{}", construction_text(summary, costs)?);
+    Ok(())
+}
+
+fn run_show(args: Show, precision: Precision) -> Result<()> {
+    let plan = saved::SavedPlan::load(&args.plan)?;
+    let cookbook = plan.cookbook();
+    let tree = plan.tree.as_ref().map(saved::SavedNode::tree).transpose()?;
+    let load = tree.as_ref().map(| tree | load_tree(tree, &plan.widget, plan.rate)).transpose()?;
+    match (&tree, &load) {
+        (Some(tree), Some(load)) => print_tree(tree, Some(&annotate_bottlenecks(load, None)), &cookbook, precision),
+        _ => println!("{widget} is a raw resource", widget=plan.widget)
+    }
+    let constraints = Constraints { limits: plan.limits.clone(), ..Default::default() };
+    print_summary(&plan.summary, &constraints, &cookbook, precision)?;
+    if let Some(load) = &load {
+        print_bottlenecks(load, &cookbook, precision);
+    }
+    if let Some(tree) = &tree {
+        print_footprint(tree)?;
+    }
+    Ok(())
+}
+
+// recipe names from the root down to the stage at `path`
+fn locate(tree: &NTree<(String, String)>, path: &[usize]) -> String {
+    (0..=path.len()).filter_map(| depth | tree.at(&path[..depth])).map(| node | node.0.as_str()).collect::<Vec<_>>().join(" > ")
+}
+
+fn run_convert(args: Convert) -> Result<()> {
+    let plan = saved::SavedPlan::load(&args.plan)?;
+    match &args.output {
+        Some(output) => plan.save(output),
+        None => {
+            println!("{}", serde_json::to_string_pretty(&plan).expect("plans serialize to JSON"));
+            Ok(())
+        }
+    }
+}
+
+fn run_diff(args: PlanDiff, precision: Precision) -> Result<()> {
+    let (old, new) = (saved::SavedPlan::load(&args.old)?, saved::SavedPlan::load(&args.new)?);
+    println!("{old_widget} at {old_rate}/s -> {new_widget} at {new_rate}/s", old_widget=old.widget, old_rate=precision.fixed(as_f64(&old.rate), 3), new_widget=new.widget, new_rate=precision.fixed(as_f64(&new.rate), 3));
+    let stages = | plan: &saved::SavedPlan | plan.tree.as_ref().map(| t | t.tree().map(| tree | tree.map(&| node | (node.recipe.name.clone(), describe(node, precision))))).transpose();
+    let (old_tree, new_tree) = match (stages(&old)?, stages(&new)?) {
+        (Some(old_tree), Some(new_tree)) => (old_tree, new_tree),
+        _ => {
+            println!("  one of the plans has no stages to compare");
+            return Ok(());
+        }
     };
-    let spacer = if is_last { "    " } else { "│   " };
-    for child in rest.iter() {
-        print_tree_helper(child, format!("{prefix}{spacer}", prefix=prefix, spacer=spacer), false);
+    print_stage_diff(&old_tree, &new_tree);
+    Ok(())
+}
+
+// stages pair up by recipe and differ when anything rendered about them does
+fn print_stage_diff(old_tree: &NTree<(String, String)>, new_tree: &NTree<(String, String)>) {
+    let edits = old_tree.diff(new_tree, | (name, _) | name.clone());
+    if edits.is_empty() {
+        println!("  no changes");
+    }
+    for edit in edits {
+        match edit {
+            tree::Edit::Added(path, added) => println!("  + {at}: {stage}", at=locate(new_tree, &path), stage=added.1),
+            tree::Edit::Removed(path, removed) => println!("  - {at}: {stage}", at=locate(old_tree, &path), stage=removed.1),
+            tree::Edit::Changed(path, before, after) => println!("  ~ {at}: {before} => {after}", at=locate(new_tree, &path), before=before.1, after=after.1)
+        }
+    }
+}
+
+fn run_simulate(mut args: Simulation, precision: Precision) -> Result<()> {
+    if args.goal.batch {
+        return Err(Error::Usage("simulate runs steady-state plans, not batches".to_owned()));
+    }
+    if args.step <= 0.0 || args.seconds <= 0.0 {
+        return Err(Error::Usage("simulated seconds and steps must be positive".to_owned()));
+    }
+    let cookbook = load_goal(&mut args.goal)?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.goal.purity)?;
+    let constraints = constraints(&args.goal, &cookbook)?;
+    let rate = args.goal.rate;
+    let tree = plan(&graph, &extraction, &args.goal, rate, &constraints, precision)?.tree;
+    let settings = simulate::Settings { seconds: args.seconds, step: args.step, buffer: args.buffer, delay: args.delay, seed: args.seed };
+    match &tree {
+        Some(tree) if args.runs > 1 => simulate::print_monte_carlo(&simulate::monte_carlo(&cookbook, tree, &args.goal.widget, rate, &settings, args.runs), &settings, precision),
+        Some(tree) => simulate::print_report(&simulate::simulate(&cookbook, tree, &args.goal.widget, rate, &settings), &settings, precision),
+        None => println!("{widget} is a raw resource", widget=args.goal.widget)
+    }
+    Ok(())
+}
+
+fn run_explore(mut args: Goal, precision: Precision) -> Result<()> {
+    if args.batch {
+        return Err(Error::Usage("explore browses steady-state plans, not batches".to_owned()));
     }
-    print_tree_helper(last,format!("{prefix}{spacer}", prefix=prefix, spacer=spacer), true);
+    let cookbook = load_goal(&mut args)?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.purity)?;
+    let constraints = constraints(&args, &cookbook)?;
+    let rate = args.rate;
+    explore::explore(plan(&graph, &extraction, &args, rate, &constraints, precision)?, &constraints, &cookbook, precision, std::io::stdin().lock(),
+        | rate, constraints | plan(&graph, &extraction, &args, rate, constraints, precision));
+    Ok(())
 }
 
-fn print_tree(tree: &NTree<(&Recipe, u64)>) {
-    println!("{quantity}x {builder} -> {name}", quantity=(*tree).1, builder=(*tree).0.builder, name=(*tree).0.name);
-    let children = tree.children();
-    let (last, rest) = match children.split_last() {
-        Some(x) => x,
-        None => return
+fn run_analyze(mut args: Analysis, precision: Precision) -> Result<()> {
+    if args.goal.batch {
+        return Err(Error::Usage("analyze works on steady-state plans, not batches".to_owned()));
+    }
+    let cookbook = load_goal(&mut args.goal)?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.goal.purity)?;
+    let constraints = constraints(&args.goal, &cookbook)?;
+    let rate = args.goal.rate;
+    let bound = | value: Option<f64>, default: Rational64 | -> Result<Rational64> {
+        value.map(| v | Rational64::approximate_float(v).ok_or_else(|| Error::Arithmetic(format!("rate {v} is not representable", v=v)))).unwrap_or(Ok(default))
     };
-    for child in rest.iter() {
-        print_tree_helper(child, "".to_owned(), false);
+    let from = bound(args.from, rate / Rational64::from_integer(2))?;
+    let to = bound(args.to, rate * Rational64::from_integer(2))?;
+    if from <= Rational64::from_integer(0) || to <= from || args.steps == 0 {
+        return Err(Error::Usage("analyze needs 0 < --from < --to and at least one step".to_owned()));
     }
-    print_tree_helper(last, "".to_owned(), true);
+    let breakpoints = analyze::analyze(&graph, &extraction, &args.goal.widget, from, to, args.steps, &constraints)?;
+    analyze::print_analysis(&args.goal.widget, rate, from, to, &breakpoints, precision);
+    Ok(())
 }
 
-#[derive(StructOpt)]
-struct Cli {
-    #[structopt(parse(from_os_str))]
-    game_def: std::path::PathBuf,
+fn run_optimize(mut args: Optimize, precision: Precision) -> Result<()> {
+    if args.goal.batch {
+        return Err(Error::Usage("optimize works on steady-state plans, not batches".to_owned()));
+    }
+    let cookbook = load_goal(&mut args.goal)?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.goal.purity)?;
+    let mut constraints = constraints(&args.goal, &cookbook)?;
+    // sinking is the objective, --sink or not
+    if args.objectives.iter().any(optimize::Objective::uses_sink) {
+        constraints.sinks = cookbook.sink_values();
+    }
+    if args.objectives.len() > 1 {
+        let front = optimize::pareto(&graph, &extraction, &args.goal.widget, args.goal.rate, &constraints, &args.objectives,
+            args.generations, args.population, args.seed)?;
+        optimize::print_front(&front, &args.objectives, precision);
+        return Ok(());
+    }
+    let objective = &args.objectives[0];
+    if let (optimize::Objective::Weighted(_, expr), None) = (objective, &constraints.cost) {
+        constraints.cost = Some(expr.clone());
+    }
+    let optimized = match args.method {
+        optimize::Method::Anneal => {
+            let optimized = optimize::anneal(&graph, &extraction, &args.goal.widget, args.goal.rate, &constraints, objective, args.steps, args.seed)?;
+            optimize::print_optimized(&optimized, objective, precision);
+            optimized
+        },
+        optimize::Method::Evolve => {
+            let plans = optimize::evolve(&graph, &extraction, &args.goal.widget, args.goal.rate, &constraints, objective,
+                args.generations, args.population, args.keep.max(1), args.seed)?;
+            optimize::print_evolved(&plans, objective, precision);
+            plans.into_iter().next().ok_or_else(|| Error::LimitsExceeded(args.goal.widget.clone()))?
+        }
+    };
+    let (text, warnings) = optimized.plan.render(Format::Text, &cookbook, precision);
+    warn(&warnings);
+    print!("{}", text);
+    print_summary(&optimized.plan.summary, &constraints, &cookbook, precision)?;
+    Ok(())
+}
 
-    widget: String,
-    rate: f64
+fn run_unlocks(mut args: Unlocks, precision: Precision) -> Result<()> {
+    if args.goal.batch {
+        return Err(Error::Usage("unlocks works on steady-state plans, not batches".to_owned()));
+    }
+    let cookbook = load_goal(&mut args.goal)?;
+    let graph = cookbook.graph()?;
+    let extraction = cookbook.extraction(&args.goal.purity)?;
+    let constraints = constraints(&args.goal, &cookbook)?;
+    let rate = args.goal.rate;
+    let by_machines = args.rank_by == "machines";
+    let ranking = unlocks::rank(&cookbook, &graph, &extraction, &args.goal.widget, rate, &constraints, by_machines)?;
+    unlocks::print_ranking(&ranking, &args.goal.widget, rate, precision);
+    Ok(())
 }
 
-fn main() {
-    let args = Cli::from_args();
-    let cookbook = Cookbook::parse(&args.game_def);
-    let mut graph = Hypergraph::<String, Recipe>::new();
-    for widget in cookbook.widgets {
-        graph.insert_node(widget.clone());
+fn run_dot(args: Dot) -> Result<()> {
+    let mut graph = load_recipes(&args.game_def)?.graph()?;
+    if args.reduce {
+        graph = graph.transitive_reduction();
+    }
+    print!("{}", graph.to_dot(| widget | widget.clone(), | recipe | format!("{name} ({builder})", name=recipe.name, builder=recipe.builder_name())));
+    Ok(())
+}
+
+fn run_stats(args: Dataset) -> Result<()> {
+    let graph = load_recipes(&args.game_def)?.graph()?;
+    let stats = graph.statistics();
+    let names = | nodes: &[usize] | nodes.iter().map(| n | graph.get_node(n).expect("node exists").as_str()).collect::<Vec<_>>().join(", ");
+    println!("{nodes} widgets, {edges} recipes", nodes=stats.nodes, edges=stats.edges);
+    println!("  most recipes making one widget: {max}", max=stats.max_in_degree);
+    println!("  most recipes using one widget: {max}", max=stats.max_out_degree);
+    for ((reagents, products), count) in stats.cardinalities.iter() {
+        println!("  {count} recipes with {reagents} reagents and {products} products", count=count, reagents=reagents, products=products);
+    }
+    if !stats.sources.is_empty() {
+        println!("Raw (nothing makes them): {widgets}", widgets=names(&stats.sources));
+    }
+    if !stats.sinks.is_empty() {
+        println!("Final (nothing uses them): {widgets}", widgets=names(&stats.sinks));
     }
-    for recipe in cookbook.recipes {
-        let sources: Vec<String> = recipe.reagents.iter().map(| r | r.widget.clone()).collect();
-        let destinations: Vec<String> = recipe.products.iter().map(| r | r.widget.clone()).collect();
-        graph.insert_edge(&sources, &destinations, recipe.clone());
+    if !stats.isolated.is_empty() {
+        println!("Unused (no recipe mentions them): {widgets}", widgets=names(&stats.isolated));
     }
+    Ok(())
+}
+
+fn run_check(args: Show) -> Result<()> {
+    let (problems, stages) = check::check(&saved::SavedPlan::load(&args.plan)?);
+    for problem in problems.iter() {
+        println!("{location}: {message}", location=problem.location, message=problem.message);
+    }
+    match problems.len() {
+        0 => {
+            println!("{path}: all {stages} stages balance", path=args.plan.display(), stages=stages);
+            Ok(())
+        },
+        count => Err(Error::Usage(format!("{count} problem{s} in {path}", count=count, s=if count == 1 { "" } else { "s" }, path=args.plan.display())))
+    }
+}
+
+fn run_validate(args: Dataset) -> Result<()> {
+    let problems = validate::validate(&load_recipes(&args.game_def)?);
+    for problem in problems.iter() {
+        println!("{location}: {message}", location=problem.location, message=problem.message);
+    }
+    match problems.len() {
+        0 => {
+            println!("{path}: no problems found", path=args.game_def.display());
+            Ok(())
+        },
+        count => Err(Error::Usage(format!("{count} problem{s} in {path}", count=count, s=if count == 1 { "" } else { "s" }, path=args.game_def.display())))
+    }
+}
 
-    print_tree(&dep_tree(&graph, &args.widget, Rational64::approximate_float(args.rate).unwrap()));
+fn run(args: Cli, config: &config::Config, config_path: Option<&std::path::Path>) -> Result<()> {
+    let search_path = datasets::search_path(&args.data_path, config, config_path);
+    let precision = Precision(args.precision);
+    let mut command = args.command;
+    if let Some(game_def) = command.game_def() {
+        *game_def = search_path.resolve(game_def);
+    }
+    match command {
+        Command::Solve(goal) => run_solve(goal, precision),
+        Command::Simulate(simulation) => run_simulate(simulation, precision),
+        Command::Analyze(analysis) => run_analyze(analysis, precision),
+        Command::Optimize(optimize) => run_optimize(optimize, precision),
+        Command::Convert(convert) => run_convert(convert),
+        Command::Check(args) => run_check(args),
+        Command::Explore(goal) => run_explore(goal, precision),
+        Command::Show(show) => run_show(show, precision),
+        Command::Unlocks(unlocks) => run_unlocks(unlocks, precision),
+        Command::Dot(dataset) => run_dot(dataset),
+        Command::Diff(diff) => run_diff(diff, precision),
+        Command::DataDiff(args) => {
+            let (old, new) = (load_recipes(&search_path.resolve(&args.old))?, load_recipes(&search_path.resolve(&args.new))?);
+            let diff = datadiff::diff(&old, &new);
+            let mut plans = Vec::new();
+            for path in args.plans {
+                let reasons = diff.affects(&saved::SavedPlan::load(&path)?, &new);
+                plans.push((path, reasons));
+            }
+            datadiff::print_diff(&diff, &plans);
+            Ok(())
+        },
+        Command::List(list) => {
+            let (listing, widgets) = match list {
+                List::Widgets(listing) => (listing, true),
+                List::Recipes(listing) => (listing, false)
+            };
+            let cookbook = load_recipes(&listing.game_def)?;
+            let filter = catalog::Filter { pattern: listing.pattern, builder: listing.builder, max_tier: listing.max_tier };
+            if widgets { catalog::print_widgets(&cookbook, &filter) } else { catalog::print_recipes(&cookbook, &filter) }
+            Ok(())
+        },
+        Command::Uses(args) => catalog::print_uses(&load_recipes(&args.game_def)?, &args.widget, args.transitive),
+        Command::Craftable(args) => {
+            let filter = catalog::Filter { pattern: None, builder: args.builder, max_tier: args.max_tier };
+            catalog::print_craftable(&load_recipes(&args.game_def)?, &args.have, &filter)
+        },
+        Command::Consume(args) => {
+            let cookbook = load_recipes(&args.game_def)?;
+            let constraints = Constraints { max_tier: args.max_tier, unlocked: args.unlocks.into_iter().collect(), ..Default::default() };
+            let outlets = surplus::outlets(&cookbook, &args.widget, args.rate, &args.with, &constraints)?;
+            surplus::print_outlets(&cookbook, &args.widget, args.rate, &outlets, precision);
+            Ok(())
+        },
+        Command::SolveAll(args) => {
+            let (cookbook, lines) = (load_recipes(&args.game_def)?, requests::load(&args.requests)?);
+            let graph = cookbook.graph()?;
+            let (dir, definition) = (cache::locate().filter(| _ | !args.no_cache), read_definition(&args.game_def).ok());
+            let cache = dir.as_deref().zip(definition.as_deref());
+            requests::print_report(&cookbook, &requests::solve_all(&cookbook, &graph, &lines, cache), precision)?;
+            Ok(())
+        },
+        Command::Stats(dataset) => run_stats(dataset),
+        Command::Validate(dataset) => run_validate(dataset),
+        Command::Serve(args) => serve::serve(&args.game_def, &args.listen, args.watch),
+        Command::Repl(args) => {
+            let constraints = Constraints { max_tier: args.max_tier, unlocked: args.unlocks.into_iter().collect(), ..Default::default() };
+            repl::repl(&load_recipes(&args.game_def)?, &args.purity, &constraints, std::io::stdin().lock(), precision)
+        },
+        Command::Datasets => {
+            datasets::print_search_path(&search_path);
+            Ok(())
+        },
+        Command::Compile(args) => {
+            let bytes = index::compile(&load_recipes(&args.game_def)?);
+            std::fs::write(&args.output, &bytes).map_err(| source | Error::Io { path: args.output.clone(), source })?;
+            println!("Wrote {output} ({size} bytes)", output=args.output.display(), size=bytes.len());
+            Ok(())
+        },
+        Command::Schema => {
+            println!("{}", serde_json::to_string_pretty(&schema::game_definition()).expect("schemas serialize to JSON"));
+            Ok(())
+        }
+    }
+}
+
+// the config file's defaults are filled into the command line before it is parsed
+fn main() {
+    let config_path = config::locate();
+    let result = config_path.as_ref().map(| path | config::Config::load(path)).transpose().and_then(| config | {
+        let config = config.unwrap_or_default();
+        let args = config.apply(std::env::args_os().collect());
+        run(Cli::from_iter(args), &config, config_path.as_deref())
+    });
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_test() {
+        let dir = std::env::temp_dir().join(format!("supply-solver-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("game.yaml");
+        let game = | duration: u32 | format!("widgets: [ore, plate]\nbuilders: [{{name: Constructor, power: 4}}]\nrecipes:\n  - {{name: Plate, builder: Constructor, duration: {}, \
+            reagents: [{{widget: ore, quantity: 1}}], products: [{{widget: plate, quantity: 1}}]}}\n", duration);
+        std::fs::write(&path, game(1)).unwrap();
+        let mut args = Goal::from_iter(["solve", path.to_str().unwrap(), "plate", "2", "--no-cache"]);
+        let seen = modified(&args);
+        let before = solve_goal(&mut args, Precision::default()).unwrap().unwrap();
+        // a slower recipe saved over the file is noticed, and solving again changes the stage making plates
+        std::fs::write(&path, game(2)).unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        assert_eq!(modified(&args), [Some(later)]);
+        assert_ne!(modified(&args), seen);
+        let after = solve_goal(&mut args, Precision::default()).unwrap().unwrap();
+        assert!(matches!(before.diff(&after, | (name, _) | name.clone()).as_slice(), [tree::Edit::Changed(path, _, _)] if path.is_empty()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(modified(&args), [None]);
+    }
 }
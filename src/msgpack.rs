@@ -0,0 +1,263 @@
+use serde_json::{Map, Number, Value};
+
+// MessagePack for the JSON values plans serialize to, keys and all, each in its smallest encoding
+
+fn length(out: &mut Vec<u8>, n: usize, fix: u8, fix_max: usize, tags: [u8; 3]) {
+    match n {
+        _ if n < fix_max => out.push(fix | n as u8),
+        _ if tags[0] != 0 && n <= u8::MAX as usize => out.extend([tags[0], n as u8]),
+        _ if n <= u16::MAX as usize => {
+            out.push(tags[1]);
+            out.extend((n as u16).to_be_bytes());
+        },
+        _ => {
+            out.push(tags[2]);
+            out.extend((n as u32).to_be_bytes());
+        }
+    }
+}
+
+fn number(out: &mut Vec<u8>, n: &Number) {
+    if let Some(n) = n.as_u64() {
+        match n {
+            0..=0x7f => out.push(n as u8),
+            _ if n <= u8::MAX as u64 => out.extend([0xcc, n as u8]),
+            _ if n <= u16::MAX as u64 => {
+                out.push(0xcd);
+                out.extend((n as u16).to_be_bytes());
+            },
+            _ if n <= u32::MAX as u64 => {
+                out.push(0xce);
+                out.extend((n as u32).to_be_bytes());
+            },
+            _ => {
+                out.push(0xcf);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    } else if let Some(n) = n.as_i64() {
+        // negative, as_u64 takes the rest
+        match n {
+            -32..=-1 => out.push(n as i8 as u8),
+            _ if n >= i8::MIN as i64 => out.extend([0xd0, n as i8 as u8]),
+            _ if n >= i16::MIN as i64 => {
+                out.push(0xd1);
+                out.extend((n as i16).to_be_bytes());
+            },
+            _ if n >= i32::MIN as i64 => {
+                out.push(0xd2);
+                out.extend((n as i32).to_be_bytes());
+            },
+            _ => {
+                out.push(0xd3);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    } else {
+        out.push(0xcb);
+        out.extend(n.as_f64().unwrap_or(0.0).to_be_bytes());
+    }
+}
+
+fn write(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Value::Number(n) => number(out, n),
+        Value::String(s) => {
+            length(out, s.len(), 0xa0, 32, [0xd9, 0xda, 0xdb]);
+            out.extend(s.as_bytes());
+        },
+        Value::Array(items) => {
+            length(out, items.len(), 0x90, 16, [0, 0xdc, 0xdd]);
+            items.iter().for_each(| item | write(out, item));
+        },
+        Value::Object(entries) => {
+            length(out, entries.len(), 0x80, 16, [0, 0xde, 0xdf]);
+            for (key, value) in entries {
+                length(out, key.len(), 0xa0, 32, [0xd9, 0xda, 0xdb]);
+                out.extend(key.as_bytes());
+                write(out, value);
+            }
+        }
+    }
+}
+
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(&mut out, value);
+    out
+}
+
+// arrays and maps nested deeper than this are refused rather than recursed into, as serde_json does
+const MAX_DEPTH: usize = 128;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+    // arrays and maps open around the value being read
+    depth: usize
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let taken = self.bytes.get(self.at..self.at + n).ok_or_else(|| format!("ends {} bytes in, in the middle of a value", self.bytes.len()))?;
+        self.at += n;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, String> {
+        Ok(u16::from_be_bytes(self.array()?) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, String> {
+        Ok(u32::from_be_bytes(self.array()?) as usize)
+    }
+
+    fn text(&mut self, n: usize) -> Result<Value, String> {
+        let at = self.at;
+        String::from_utf8(self.take(n)?.to_vec()).map(Value::String).map_err(| _ | format!("the string at byte {} isn't UTF-8", at))
+    }
+
+    fn nest(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("nested more than {} deep at byte {}", MAX_DEPTH, self.at));
+        }
+        Ok(())
+    }
+
+    fn items(&mut self, n: usize) -> Result<Value, String> {
+        self.nest()?;
+        let items = (0..n).map(| _ | self.value()).collect::<Result<_, _>>().map(Value::Array);
+        self.depth -= 1;
+        items
+    }
+
+    fn entries(&mut self, n: usize) -> Result<Value, String> {
+        self.nest()?;
+        let mut entries = Map::new();
+        for _ in 0..n {
+            let at = self.at;
+            let key = match self.value()? {
+                Value::String(key) => key,
+                _ => return Err(format!("the map key at byte {} isn't a string", at))
+            };
+            entries.insert(key, self.value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Object(entries))
+    }
+
+    fn float(&mut self, f: f64) -> Result<Value, String> {
+        Number::from_f64(f).map(Value::Number).ok_or_else(|| format!("the float before byte {} isn't finite", self.at))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let at = self.at;
+        let tag = self.u8()?;
+        match tag {
+            0x00..=0x7f => Ok(Value::from(tag)),
+            0x80..=0x8f => self.entries((tag & 0x0f) as usize),
+            0x90..=0x9f => self.items((tag & 0x0f) as usize),
+            0xa0..=0xbf => self.text((tag & 0x1f) as usize),
+            0xc0 => Ok(Value::Null),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xca => {
+                let f = f32::from_be_bytes(self.array()?) as f64;
+                self.float(f)
+            },
+            0xcb => {
+                let f = f64::from_be_bytes(self.array()?);
+                self.float(f)
+            },
+            0xcc => Ok(Value::from(self.u8()?)),
+            0xcd => Ok(Value::from(self.u16()?)),
+            0xce => Ok(Value::from(self.u32()?)),
+            0xcf => Ok(Value::from(u64::from_be_bytes(self.array()?))),
+            0xd0 => Ok(Value::from(self.u8()? as i8)),
+            0xd1 => Ok(Value::from(i16::from_be_bytes(self.array()?))),
+            0xd2 => Ok(Value::from(i32::from_be_bytes(self.array()?))),
+            0xd3 => Ok(Value::from(i64::from_be_bytes(self.array()?))),
+            0xd9 => {
+                let n = self.u8()? as usize;
+                self.text(n)
+            },
+            0xda => {
+                let n = self.u16()?;
+                self.text(n)
+            },
+            0xdb => {
+                let n = self.u32()?;
+                self.text(n)
+            },
+            0xdc => {
+                let n = self.u16()?;
+                self.items(n)
+            },
+            0xdd => {
+                let n = self.u32()?;
+                self.items(n)
+            },
+            0xde => {
+                let n = self.u16()?;
+                self.entries(n)
+            },
+            0xdf => {
+                let n = self.u32()?;
+                self.entries(n)
+            },
+            0xe0..=0xff => Ok(Value::from(tag as i8)),
+            _ => Err(format!("unsupported type 0x{:02x} at byte {}", tag, at))
+        }
+    }
+}
+
+// one value taking up all of `bytes`
+pub fn decode(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader { bytes, at: 0, depth: 0 };
+    let value = reader.value()?;
+    if reader.at != bytes.len() {
+        return Err(format!("{} bytes left over after the value", bytes.len() - reader.at));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip_test() {
+        let long = "x".repeat(300);
+        let value = json!({
+            "small": [0, 127, 128, 65535, 65536, u64::MAX],
+            "negative": [-1, -32, -33, -200, -40000, i64::MIN],
+            "other": [1.5, null, true, false, "", long],
+            "nested": { "empty": {}, "many": (0..20).collect::<Vec<_>>() }
+        });
+        let bytes = encode(&value);
+        assert_eq!(decode(&bytes), Ok(value));
+        assert_eq!(encode(&json!([1, -1, "a"])), vec![0x93, 0x01, 0xff, 0xa1, b'a']);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn depth_test() {
+        // a long run of one-element arrays is refused, not followed down the stack
+        assert_eq!(decode(&[0x91; 100_000]), Err("nested more than 128 deep at byte 129".to_owned()));
+        let mut bytes = vec![0x91; MAX_DEPTH];
+        bytes.push(0xc0);
+        assert!(decode(&bytes).is_ok());
+    }
+}
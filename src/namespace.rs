@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Cookbook, Reagent, Widget};
+
+/// The name without its `modname:` namespace, all of it for names without one.
+pub fn unqualified(name: &str) -> &str {
+    name.split_once(':').map_or(name, | (_, rest) | rest)
+}
+
+pub fn namespace(name: &str) -> Option<&str> {
+    name.split_once(':').map(| (namespace, _) | namespace)
+}
+
+// of the namespaces declaring a name, the first preferred, else the first loaded
+fn preferred<'n>(declaring: &'n [String], prefer: &[String]) -> &'n String {
+    prefer.iter().find_map(| p | declaring.iter().find(| d | *d == p)).unwrap_or(&declaring[0])
+}
+
+// a name more than one merged dataset declares
+pub struct Collision {
+    // widget, recipe, builder, extractor, generator or transport
+    pub kind: &'static str,
+    pub name: String,
+    // the datasets declaring it, in load order
+    pub namespaces: Vec<String>,
+    // the one unqualified references from elsewhere go to, or whose definition is kept
+    pub chosen: String
+}
+
+impl Cookbook {
+    /// The widget `name` stands for: itself when a widget has that exact name, otherwise the one named
+    /// `namespace:name`, those in `prefer` first and otherwise in the order they're declared.
+    pub fn resolve_widget(&self, name: &str, prefer: &[String]) -> Option<String> {
+        if self.widgets.iter().any(| w | w.name() == name) {
+            return Some(name.to_owned());
+        }
+        let candidates: Vec<String> = self.widgets.iter().map(Widget::name).filter(| w | namespace(w).is_some() && unqualified(w) == name).cloned().collect();
+        let namespaces: Vec<String> = candidates.iter().filter_map(| c | namespace(c)).map(str::to_owned).collect();
+        (!candidates.is_empty()).then(|| format!("{}:{}", preferred(&namespaces, prefer), name))
+    }
+
+    // every widget name the dataset declares or refers to, for renaming
+    fn widget_names(&mut self) -> Vec<&mut String> {
+        let mut names: Vec<&mut String> = self.widgets.iter_mut().map(| w | match w {
+            Widget::Item(name) | Widget::Detailed { name, .. } => name
+        }).collect();
+        for recipe in self.recipes.iter_mut() {
+            names.extend(reagents(&mut recipe.reagents));
+            names.extend(reagents(&mut recipe.products));
+        }
+        for builder in self.builders.iter_mut() {
+            names.extend(reagents(&mut builder.consumes));
+        }
+        for generator in self.generators.iter_mut() {
+            names.extend(reagents(&mut generator.fuel));
+        }
+        for extractor in self.extractors.iter_mut() {
+            names.extend(extractor.resources.iter_mut());
+        }
+        names
+    }
+}
+
+fn reagents(reagents: &mut [Reagent]) -> impl Iterator<Item=&mut String> {
+    reagents.iter_mut().map(| r | &mut r.widget)
+}
+
+// adds `items` whose name `into` doesn't have yet, a differing definition under one it has being a collision
+fn add<T: std::fmt::Debug>(kind: &'static str, into: &mut Vec<(String, T)>, items: Vec<T>, name: impl Fn(&T) -> &String, ns: &str, collisions: &mut Vec<Collision>) {
+    for item in items {
+        match into.iter().find(| (_, kept) | name(kept) == name(&item)) {
+            Some((first, kept)) => if format!("{:?}", kept) != format!("{:?}", item) {
+                collisions.push(Collision { kind, name: name(&item).clone(), namespaces: vec![first.clone(), ns.to_owned()], chosen: first.clone() });
+            },
+            None => into.push((ns.to_owned(), item))
+        }
+    }
+}
+
+/// Merges game definitions, each under its `namespace` or the name given with it. A widget declared by
+/// more than one is renamed `namespace:widget` in each, and so are references to it in the dataset
+/// declaring it; other datasets' references go to the one in `prefer` first, the first loaded
+/// otherwise. Recipes sharing a name keep the first's and the later are qualified; builders,
+/// extractors, generators and transport tiers are taken from the first defining them. Every
+/// collision is returned, for telling the user how it was settled.
+pub fn merge(datasets: Vec<(String, Cookbook)>, prefer: &[String]) -> (Cookbook, Vec<Collision>) {
+    let datasets: Vec<(String, Cookbook)> = datasets.into_iter().map(| (name, cookbook) | (cookbook.namespace.clone().unwrap_or(name), cookbook)).collect();
+    let mut declaring: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (ns, cookbook) in datasets.iter() {
+        for widget in cookbook.widgets.iter().map(Widget::name).collect::<BTreeSet<_>>() {
+            declaring.entry(widget.clone()).or_default().push(ns.clone());
+        }
+    }
+    declaring.retain(| _, namespaces | namespaces.len() > 1);
+    let mut collisions: Vec<Collision> = declaring.iter().map(| (name, namespaces) | Collision { kind: "widget", name: name.clone(), namespaces: namespaces.clone(),
+        chosen: preferred(namespaces, prefer).clone() }).collect();
+
+    // the dataset each recipe kept under its own name came from
+    let mut recipes: BTreeMap<String, String> = BTreeMap::new();
+    let mut merged = Cookbook::builder().build();
+    merged.purities.clear();
+    let (mut builders, mut extractors, mut generators, mut belts, mut pipes) = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (ns, mut cookbook) in datasets {
+        let own: BTreeSet<String> = cookbook.widgets.iter().map(| w | w.name().clone()).collect();
+        for name in cookbook.widget_names() {
+            if let Some(namespaces) = declaring.get(name.as_str()) {
+                *name = format!("{}:{}", if own.contains(name.as_str()) { &ns } else { preferred(namespaces, prefer) }, name);
+            }
+        }
+        merged.widgets.extend(cookbook.widgets);
+        for mut recipe in cookbook.recipes {
+            match recipes.get(&recipe.name) {
+                Some(first) => {
+                    collisions.push(Collision { kind: "recipe", name: recipe.name.clone(), namespaces: vec![first.clone(), ns.clone()], chosen: first.clone() });
+                    recipe.name = format!("{}:{}", ns, recipe.name);
+                },
+                None => {
+                    recipes.insert(recipe.name.clone(), ns.clone());
+                }
+            }
+            merged.recipes.push(recipe);
+        }
+        add("builder", &mut builders, cookbook.builders, | b | &b.name, &ns, &mut collisions);
+        add("extractor", &mut extractors, cookbook.extractors, | e | &e.name, &ns, &mut collisions);
+        add("generator", &mut generators, cookbook.generators, | g | &g.name, &ns, &mut collisions);
+        add("transport", &mut belts, cookbook.belts, | t | &t.name, &ns, &mut collisions);
+        add("transport", &mut pipes, cookbook.pipes, | t | &t.name, &ns, &mut collisions);
+        for (purity, multiplier) in cookbook.purities {
+            merged.purities.entry(purity).or_insert(multiplier);
+        }
+    }
+    merged.builders = builders.into_iter().map(| (_, b) | b).collect();
+    merged.extractors = extractors.into_iter().map(| (_, e) | e).collect();
+    merged.generators = generators.into_iter().map(| (_, g) | g).collect();
+    merged.belts = belts.into_iter().map(| (_, t) | t).collect();
+    merged.pipes = pipes.into_iter().map(| (_, t) | t).collect();
+    (merged, collisions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Recipe;
+    use num::Rational64;
+
+    #[test]
+    fn merge_test() {
+        let one = Rational64::from_integer(1);
+        let base = Cookbook::builder().machine("Assembler", None).item("iron").item("steel-plate")
+            .recipe(Recipe::new("Steel", one).made_by("Assembler").reagent("iron", one).product("steel-plate", one))
+            .build();
+        let by_mod = Cookbook::builder().machine("Assembler", None).item("steel-plate").item("beam")
+            .recipe(Recipe::new("Steel", one).made_by("Assembler").reagent("iron", one).product("steel-plate", one))
+            .recipe(Recipe::new("Beam", one).made_by("Assembler").reagent("steel-plate", one).reagent("iron", one).product("beam", one))
+            .build();
+        let (merged, collisions) = merge(vec![("base".to_owned(), base), ("mod".to_owned(), by_mod)], &[]);
+        let summary: Vec<(&str, &str)> = collisions.iter().map(| c | (c.kind, c.name.as_str())).collect();
+        assert_eq!(summary, [("widget", "steel-plate"), ("recipe", "Steel")]);
+        let beam = merged.recipes.iter().find(| r | r.name == "Beam").unwrap();
+        // its own steel plate, and iron from the only dataset declaring it
+        assert_eq!(beam.reagents.iter().map(| r | r.widget.as_str()).collect::<Vec<_>>(), ["mod:steel-plate", "iron"]);
+        assert!(merged.graph().is_ok());
+        assert_eq!(merged.resolve_widget("steel-plate", &[]).as_deref(), Some("base:steel-plate"));
+        assert_eq!(merged.resolve_widget("steel-plate", &["mod".to_owned()]).as_deref(), Some("mod:steel-plate"));
+        assert_eq!(merged.resolve_widget("beam", &[]).as_deref(), Some("beam"));
+        assert!(merged.resolve_widget("copper", &[]).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use num::traits::{CheckedDiv, CheckedMul, CheckedSub};
+use num::Rational64;
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::tree::NTree;
+use crate::{buys, is_raw, least_waste_heuristic, producers, record, Constraints, Notes, PlanNode, PlanTree, Plan, Recipe, Summary};
+
+// where a widget in the network comes from
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Made(&'a Recipe),
+    // raw resources nothing extracts
+    Raw,
+    Bought
+}
+
+fn zero() -> Rational64 {
+    Rational64::from_integer(0)
+}
+
+// units of `widget` a craft of `recipe` yields
+fn yields(recipe: &Recipe, widget: &String) -> Rational64 {
+    recipe.products.iter().filter(| p | &p.widget == widget).map(| p | p.quantity).sum::<Rational64>() * recipe.productivity()
+}
+
+fn consumes(recipe: &Recipe, widget: &String) -> Rational64 {
+    recipe.reagents.iter().filter(| r | &r.widget == widget).map(| r | r.quantity).sum()
+}
+
+fn net(recipe: &Recipe, widget: &String) -> Rational64 {
+    yields(recipe, widget) - consumes(recipe, widget)
+}
+
+// picks one source per widget reachable from the goal, sizing by the first demand seen for it
+fn choose<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints, sources: &mut IndexMap<String, Source<'a>>) -> Result<()> {
+    if sources.contains_key(widget) {
+        return Ok(());
+    }
+    if buys(graph, extraction, widget, constraints)? {
+        sources.insert(widget.clone(), Source::Bought);
+        return Ok(());
+    }
+    if is_raw(graph, widget)? {
+        let extractors = extraction.iter().filter(| r | r.products.iter().any(| p | &p.widget == widget));
+        let source = match least_waste_heuristic(graph, extractors, widget, rate, constraints) {
+            Ok(node) => Source::Made(node.recipe),
+            Err(Error::NoRecipes(_)) => Source::Raw,
+            Err(e) => return Err(e)
+        };
+        sources.insert(widget.clone(), source);
+        return Ok(());
+    }
+    let PlanNode { recipe, machines, clock, .. } = least_waste_heuristic(graph, producers(graph, widget)?, widget, rate, constraints)?;
+    sources.insert(widget.clone(), Source::Made(recipe));
+    for reagent in recipe.reagents.iter() {
+        let requested_rate = recipe.drawn(reagent, machines, clock)?;
+        choose(graph, extraction, &reagent.widget, requested_rate, constraints, sources)?;
+    }
+    Ok(())
+}
+
+// solves `matrix * x = rhs` by Gauss-Jordan elimination, None when the system is singular
+fn gauss(mut matrix: Vec<Vec<Rational64>>, mut rhs: Vec<Rational64>) -> Result<Option<Vec<Rational64>>> {
+    let overflow = || Error::Arithmetic("solving the recipe network overflows 64-bit rationals".to_owned());
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = match (col..n).find(| row | matrix[*row][col] != zero()) {
+            Some(pivot) => pivot,
+            None => return Ok(None)
+        };
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let pivot_row = matrix[col].clone();
+        for row in 0..n {
+            if row != col && matrix[row][col] != zero() {
+                let factor = matrix[row][col].checked_div(&pivot_row[col]).ok_or_else(overflow)?;
+                for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row.iter()).skip(col) {
+                    *value = factor.checked_mul(pivot_value).and_then(| delta | value.checked_sub(&delta)).ok_or_else(overflow)?;
+                }
+                rhs[row] = factor.checked_mul(&rhs[col]).and_then(| delta | rhs[row].checked_sub(&delta)).ok_or_else(overflow)?;
+            }
+        }
+    }
+    (0..n).map(| i | rhs[i].checked_div(&matrix[i][i]).ok_or_else(overflow)).collect::<Result<_>>().map(Some)
+}
+
+// crafts/second of every group so each crafted widget's net output meets its demand exactly;
+// groups whose widget other groups' byproducts already cover are switched off
+fn crafts(groups: &[(&String, &Recipe)], widget: &String, rate: Rational64) -> Result<Vec<Rational64>> {
+    let mut active: Vec<usize> = (0..groups.len()).collect();
+    loop {
+        let matrix = active.iter().map(| row | active.iter().map(| col | net(groups[*col].1, groups[*row].0)).collect()).collect();
+        let rhs = active.iter().map(| row | if groups[*row].0 == widget { rate } else { zero() }).collect();
+        let solution = gauss(matrix, rhs)?.ok_or_else(|| Error::Arithmetic(format!("the recipes chosen for {widget} form a loop that makes no net output", widget=widget)))?;
+        match solution.iter().position(| x | *x < zero()) {
+            Some(i) => {
+                active.remove(i);
+            },
+            None => {
+                let mut crafts = vec![zero(); groups.len()];
+                for (i, x) in active.iter().zip(solution) {
+                    crafts[*i] = x;
+                }
+                return Ok(crafts);
+            }
+        }
+    }
+}
+
+// the plan for the whole network as a tree, every group shown once under the first stage it feeds
+fn view<'a>(widget: &String, groups: &BTreeMap<&String, PlanNode<'a>>, seen: &mut HashSet<String>) -> Option<PlanTree<'a>> {
+    let node = *groups.get(widget)?;
+    if !seen.insert(widget.clone()) {
+        return None;
+    }
+    let mut tree = NTree::new(node);
+    for reagent in node.recipe.reagents.iter() {
+        if let Some(child) = view(&reagent.widget, groups, seen) {
+            tree.insert(child);
+        }
+    }
+    Some(tree)
+}
+
+// plans the goal by propagating demand over the recipe graph instead of down a tree, so intermediates
+// used in several places come from one group, byproducts feed anything that needs them and loops
+// are solved as a whole
+pub fn solve<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Plan<'a>> {
+    let mut sources = IndexMap::new();
+    choose(graph, extraction, widget, rate, constraints, &mut sources)?;
+    let groups: Vec<(&String, &Recipe)> = sources.iter().filter_map(| (w, s) | match s {
+        Source::Made(recipe) => Some((w, *recipe)),
+        _ => None
+    }).collect();
+    let crafts = crafts(&groups, widget, rate)?;
+
+    let mut summary = Summary::default();
+    let mut sized = BTreeMap::new();
+    for ((made, recipe), x) in groups.iter().zip(crafts.iter()) {
+        if *x > zero() {
+            let delivered = *x * yields(recipe, made);
+            let node = least_waste_heuristic(graph, [*recipe], made, delivered, constraints)?;
+            record(&mut summary, &node, made)?;
+            sized.insert(*made, node);
+        }
+    }
+
+    // record() takes every other product for surplus, here it is what the rounded-up groups make beyond all demand
+    summary.surplus.clear();
+    for (w, source) in sources.iter() {
+        let used = groups.iter().zip(crafts.iter()).map(| ((_, recipe), x) | *x * consumes(recipe, w)).sum::<Rational64>() + if w == widget { rate } else { zero() };
+        match source {
+            // taken only by groups switched off
+            Source::Raw | Source::Bought if used == zero() => {},
+            Source::Raw => *summary.raw.entry(w.clone()).or_insert_with(zero) += used,
+            Source::Bought => *summary.purchases.entry(w.clone()).or_insert_with(zero) += used,
+            Source::Made(chosen) => {
+                if is_raw(graph, w)? {
+                    *summary.raw.entry(w.clone()).or_insert_with(zero) += used;
+                }
+                let mut made = zero();
+                let mut byproduct = zero();
+                for PlanNode { recipe, machines, clock, .. } in sized.values() {
+                    if recipe.products.iter().any(| p | &p.widget == w) {
+                        let rate = recipe.rate(w)? * machines * clock;
+                        made += rate;
+                        if recipe.name != chosen.name {
+                            byproduct += rate;
+                        }
+                    }
+                }
+                if made > used {
+                    summary.surplus.insert(w.clone(), made - used);
+                }
+                if byproduct > zero() && w != widget {
+                    summary.shared.insert(w.clone(), byproduct.min(used));
+                }
+            }
+        }
+    }
+    Ok(Plan { widget: widget.clone(), rate, tree: view(widget, &sized, &mut HashSet::new()), summary })
+}
+
+fn collect_consumers(tree: &PlanTree, consumers: &mut BTreeMap<String, Vec<String>>) {
+    for reagent in tree.recipe.reagents.iter() {
+        consumers.entry(reagent.widget.clone()).or_default().push(tree.recipe.name.clone());
+    }
+    for child in tree.children() {
+        collect_consumers(child, consumers);
+    }
+}
+
+fn annotate(tree: &PlanTree, consumers: &BTreeMap<String, Vec<String>>) -> Notes {
+    let made: HashSet<&String> = tree.recipe.products.iter().map(| p | &p.widget).collect();
+    let mut fed: Vec<&String> = consumers.iter().filter(| (w, _) | made.contains(w)).flat_map(| (_, c) | c.iter()).collect();
+    fed.sort();
+    fed.dedup();
+    let note = if fed.len() > 1 { format!(" [feeds {consumers}]", consumers=fed.iter().map(| c | c.as_str()).collect::<Vec<_>>().join(", ")) } else { String::new() };
+    let mut node = NTree::new(note);
+    for child in tree.children() {
+        node.insert(annotate(child, consumers));
+    }
+    node
+}
+
+// marks the groups the network shares between several stages
+pub fn shared_notes(tree: &PlanTree) -> Notes {
+    let mut consumers = BTreeMap::new();
+    collect_consumers(tree, &mut consumers);
+    annotate(tree, &consumers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cookbook;
+
+    fn one() -> Rational64 {
+        Rational64::from_integer(1)
+    }
+
+    // every widget the tests use, and `recipes`
+    fn graph(recipes: Vec<Recipe>) -> Hypergraph<String, Recipe> {
+        let mut book = Cookbook::builder().machine("Assembler", None);
+        for widget in ["frame", "plate", "rod", "ore", "slag", "stone", "plastic", "rubber", "fuel"] {
+            book = book.item(widget);
+        }
+        for recipe in recipes {
+            book = book.recipe(recipe);
+        }
+        book.build().graph().unwrap()
+    }
+
+    // a craft a second on an Assembler
+    fn recipe(name: &str) -> Recipe {
+        Recipe::new(name, one()).made_by("Assembler")
+    }
+
+    #[test]
+    fn shared_intermediate_test() {
+        // frames and the rods going into them both take plates, which come from one group
+        let graph = graph(vec![
+            recipe("Frame").reagent("plate", one()).reagent("rod", one()).product("frame", one()),
+            recipe("Rod").reagent("plate", one()).product("rod", one()),
+            recipe("Plate").reagent("ore", one()).product("plate", one())
+        ]);
+        let plan = solve(&graph, &[], &"frame".to_owned(), one(), &Constraints::default()).unwrap();
+        let tree = plan.tree.as_ref().unwrap();
+        assert_eq!(tree.iter_dfs().map(| n | n.recipe.name.as_str()).collect::<Vec<_>>(), ["Frame", "Plate", "Rod"]);
+        assert_eq!(tree.iter_dfs().find(| n | n.recipe.name == "Plate").unwrap().machine_count, 2);
+        assert_eq!(plan.summary.raw["ore"], Rational64::from_integer(2));
+        assert_eq!(shared_notes(tree).iter_dfs().filter(| note | note.contains("feeds Frame, Rod")).count(), 1);
+    }
+
+    #[test]
+    fn byproduct_test() {
+        // plates leave slag that frames take, so the slag recipe is switched off, having come out negative
+        let graph = graph(vec![
+            recipe("Frame").reagent("plate", one()).reagent("slag", one()).product("frame", one()),
+            recipe("Plate").reagent("ore", one()).product("plate", one()).product("slag", Rational64::from_integer(2)),
+            recipe("Slag").reagent("stone", one()).product("slag", one())
+        ]);
+        let plan = solve(&graph, &[], &"frame".to_owned(), one(), &Constraints::default()).unwrap();
+        let recipes: Vec<&str> = plan.tree.as_ref().unwrap().iter_dfs().map(| n | n.recipe.name.as_str()).collect();
+        assert!(!recipes.contains(&"Slag"), "{:?}", recipes);
+        assert!(!plan.summary.raw.contains_key("stone"));
+        assert_eq!(plan.summary.surplus["slag"], one());
+    }
+
+    #[test]
+    fn loop_test() {
+        // plastic is recycled from rubber and rubber from plastic, each craft doubling what it takes
+        let graph = graph(vec![
+            recipe("Recycled Plastic").reagent("rubber", one()).reagent("fuel", one()).product("plastic", Rational64::from_integer(2)),
+            recipe("Recycled Rubber").reagent("plastic", one()).reagent("fuel", one()).product("rubber", Rational64::from_integer(2))
+        ]);
+        let plan = solve(&graph, &[], &"plastic".to_owned(), one(), &Constraints::default()).unwrap();
+        // 2p - r = 1 and 2r - p = 0 make 2/3 plastic crafts and 1/3 rubber crafts a second
+        assert_eq!(plan.summary.raw["fuel"], one());
+        assert_eq!(plan.tree.as_ref().unwrap().iter_dfs().count(), 2);
+    }
+
+    #[test]
+    fn no_net_output_test() {
+        let graph = graph(vec![
+            recipe("Recycled Plastic").reagent("rubber", one()).product("plastic", one()),
+            recipe("Recycled Rubber").reagent("plastic", one()).product("rubber", one())
+        ]);
+        match solve(&graph, &[], &"plastic".to_owned(), one(), &Constraints::default()) {
+            Err(Error::Arithmetic(message)) => assert!(message.contains("no net output"), "{}", message),
+            other => panic!("expected a loop error, got {:?}", other.map(| p | p.summary))
+        }
+    }
+}
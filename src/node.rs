@@ -0,0 +1,163 @@
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::error::{Error, Result};
+use crate::ffi::{solve_request, SolvedPlan};
+use crate::{load_recipes, Cookbook};
+
+// the handful of Node-API calls the addon makes, resolved against the node process loading it
+type Env = *mut c_void;
+type Value = *mut c_void;
+type CallbackInfo = *mut c_void;
+type Callback = unsafe extern "C" fn(Env, CallbackInfo) -> Value;
+type Finalize = unsafe extern "C" fn(Env, *mut c_void, *mut c_void);
+
+#[repr(C)]
+struct TypeTag {
+    lower: u64,
+    upper: u64
+}
+
+extern "C" {
+    fn napi_create_function(env: Env, name: *const c_char, length: usize, cb: Callback, data: *mut c_void, result: *mut Value) -> i32;
+    fn napi_set_named_property(env: Env, object: Value, name: *const c_char, value: Value) -> i32;
+    fn napi_get_cb_info(env: Env, info: CallbackInfo, argc: *mut usize, argv: *mut Value, this: *mut Value, data: *mut *mut c_void) -> i32;
+    fn napi_get_value_string_utf8(env: Env, value: Value, buf: *mut c_char, size: usize, result: *mut usize) -> i32;
+    fn napi_create_string_utf8(env: Env, text: *const c_char, length: usize, result: *mut Value) -> i32;
+    fn napi_create_double(env: Env, value: f64, result: *mut Value) -> i32;
+    fn napi_create_object(env: Env, result: *mut Value) -> i32;
+    fn napi_create_external(env: Env, data: *mut c_void, finalize: Finalize, hint: *mut c_void, result: *mut Value) -> i32;
+    fn napi_get_value_external(env: Env, value: Value, result: *mut *mut c_void) -> i32;
+    fn napi_type_tag_object(env: Env, value: Value, tag: *const TypeTag) -> i32;
+    fn napi_check_object_type_tag(env: Env, value: Value, tag: *const TypeTag, result: *mut bool) -> i32;
+    fn napi_throw_error(env: Env, code: *const c_char, message: *const c_char) -> i32;
+}
+
+// marks the externals holding a cookbook, so no other addon's externals are taken for one
+const COOKBOOK: TypeTag = TypeTag { lower: 0x7375_7070_6c79_2d73, upper: 0x636f_6f6b_626f_6f6b };
+
+fn check(status: i32, call: &str) -> Result<()> {
+    if status == 0 { Ok(()) } else { Err(Error::Usage(format!("{call} failed with Node-API status {status}", call=call, status=status))) }
+}
+
+unsafe fn throw(env: Env, error: Error) -> Value {
+    let message = CString::new(error.to_string().replace('\0', " ")).expect("nul bytes are replaced");
+    napi_throw_error(env, ptr::null(), message.as_ptr());
+    ptr::null_mut()
+}
+
+// the call's first `N` arguments, undefined past those given
+unsafe fn arguments<const N: usize>(env: Env, info: CallbackInfo) -> Result<[Value; N]> {
+    let mut argv = [ptr::null_mut(); N];
+    let mut argc = N;
+    check(napi_get_cb_info(env, info, &mut argc, argv.as_mut_ptr(), ptr::null_mut(), ptr::null_mut()), "napi_get_cb_info")?;
+    Ok(argv)
+}
+
+unsafe fn string(env: Env, value: Value, what: &str) -> Result<String> {
+    let mut length = 0;
+    check(napi_get_value_string_utf8(env, value, ptr::null_mut(), 0, &mut length), "napi_get_value_string_utf8")
+        .map_err(| _ | Error::Usage(format!("{what} must be a string", what=what)))?;
+    let mut bytes = vec![0u8; length + 1];
+    check(napi_get_value_string_utf8(env, value, bytes.as_mut_ptr().cast(), bytes.len(), &mut length), "napi_get_value_string_utf8")?;
+    bytes.truncate(length);
+    String::from_utf8(bytes).map_err(| e | Error::Usage(format!("{what} is not UTF-8: {e}", what=what, e=e)))
+}
+
+unsafe fn js_string(env: Env, text: &str) -> Result<Value> {
+    let mut value = ptr::null_mut();
+    check(napi_create_string_utf8(env, text.as_ptr().cast(), text.len(), &mut value), "napi_create_string_utf8")?;
+    Ok(value)
+}
+
+unsafe extern "C" fn free_cookbook(_env: Env, data: *mut c_void, _hint: *mut c_void) {
+    drop(Box::from_raw(data.cast::<Cookbook>()));
+}
+
+// the cookbook as an external JavaScript frees when it is collected
+unsafe fn external(env: Env, cookbook: Cookbook) -> Result<Value> {
+    let data = Box::into_raw(Box::new(cookbook)).cast();
+    let mut value = ptr::null_mut();
+    if let Err(e) = check(napi_create_external(env, data, free_cookbook, ptr::null_mut(), &mut value), "napi_create_external") {
+        free_cookbook(env, data, ptr::null_mut());
+        return Err(e);
+    }
+    check(napi_type_tag_object(env, value, &COOKBOOK), "napi_type_tag_object")?;
+    Ok(value)
+}
+
+unsafe fn cookbook<'a>(env: Env, value: Value) -> Result<&'a Cookbook> {
+    let mut tagged = false;
+    let not_one = || Error::Usage("expected a cookbook from loadRecipes or fromYaml".to_owned());
+    check(napi_check_object_type_tag(env, value, &COOKBOOK, &mut tagged), "napi_check_object_type_tag").map_err(| _ | not_one())?;
+    if !tagged {
+        return Err(not_one());
+    }
+    let mut data = ptr::null_mut();
+    check(napi_get_value_external(env, value, &mut data), "napi_get_value_external")?;
+    Ok(&*data.cast::<Cookbook>())
+}
+
+// {json, text, machines} of the plan, parsed by index.js
+unsafe fn plan_object(env: Env, plan: SolvedPlan) -> Result<Value> {
+    let mut object = ptr::null_mut();
+    check(napi_create_object(env, &mut object), "napi_create_object")?;
+    for (name, text) in [(c"json", &plan.json), (c"text", &plan.text)] {
+        let value = js_string(env, text.to_str().expect("plans are rendered from UTF-8"))?;
+        check(napi_set_named_property(env, object, name.as_ptr(), value), "napi_set_named_property")?;
+    }
+    let mut machines = ptr::null_mut();
+    check(napi_create_double(env, plan.machines as f64, &mut machines), "napi_create_double")?;
+    check(napi_set_named_property(env, object, c"machines".as_ptr(), machines), "napi_set_named_property")?;
+    Ok(object)
+}
+
+// loadRecipes(path): the game definition at `path`, read as the command line reads it
+unsafe extern "C" fn js_load_recipes(env: Env, info: CallbackInfo) -> Value {
+    let loaded = arguments::<1>(env, info).and_then(| [path] | string(env, path, "the path")).and_then(| path | load_recipes(&path.into()));
+    match loaded.and_then(| cookbook | external(env, cookbook)) {
+        Ok(value) => value,
+        Err(e) => throw(env, e)
+    }
+}
+
+// fromYaml(text): a game definition given as YAML
+unsafe extern "C" fn js_from_yaml(env: Env, info: CallbackInfo) -> Value {
+    let parsed = arguments::<1>(env, info).and_then(| [yaml] | string(env, yaml, "the definition")).and_then(| yaml | Cookbook::from_yaml(&yaml));
+    match parsed.and_then(| cookbook | external(env, cookbook)) {
+        Ok(value) => value,
+        Err(e) => throw(env, e)
+    }
+}
+
+// solve(cookbook, request): a JSON SolveRequest solved into {json, text, machines}
+unsafe extern "C" fn js_solve(env: Env, info: CallbackInfo) -> Value {
+    let solved = arguments::<2>(env, info).and_then(| [handle, request] | {
+        let cookbook = cookbook(env, handle)?;
+        solve_request(cookbook, &string(env, request, "the request")?)
+    });
+    match solved.and_then(| plan | plan_object(env, plan)) {
+        Ok(value) => value,
+        Err(e) => throw(env, e)
+    }
+}
+
+/// Registers `loadRecipes`, `fromYaml` and `solve` on the module's exports when node loads the
+/// library as an addon; index.js wraps them in a Cookbook class.
+///
+/// # Safety
+/// Only node calls this, with a live `env` and the module's `exports`.
+#[no_mangle]
+pub unsafe extern "C" fn napi_register_module_v1(env: Env, exports: Value) -> Value {
+    let functions: [(&std::ffi::CStr, Callback); 3] = [(c"loadRecipes", js_load_recipes), (c"fromYaml", js_from_yaml), (c"solve", js_solve)];
+    for (name, callback) in functions {
+        let mut function = ptr::null_mut();
+        let registered = check(napi_create_function(env, name.as_ptr(), name.to_bytes().len(), callback, ptr::null_mut(), &mut function), "napi_create_function")
+            .and_then(| _ | check(napi_set_named_property(env, exports, name.as_ptr(), function), "napi_set_named_property"));
+        if let Err(e) = registered {
+            return throw(env, e);
+        }
+    }
+    exports
+}
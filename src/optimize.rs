@@ -0,0 +1,603 @@
+use num::traits::{CheckedAdd, CheckedSub};
+use num::Rational64;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+
+use crate::cost::{CostExpr, Metric};
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::{as_f64, child_flows, footprint, producers, solve, solve_within_limits, Constraints, Plan, PlanTree, Precision, Recipe};
+
+// what a whole plan is judged by, lower being better
+#[derive(Clone, Debug, PartialEq)]
+pub enum Objective {
+    // units/second of raw resources
+    Raw,
+    Machines,
+    // MW
+    Power,
+    // m² of builders with a footprint
+    Footprint,
+    // points/second for the surplus sunk, negated so more points score lower
+    Sink,
+    // a cost expression over the plan's totals, as written
+    Weighted(String, CostExpr)
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        match text {
+            "raw" => Ok(Objective::Raw),
+            "machines" => Ok(Objective::Machines),
+            "power" => Ok(Objective::Power),
+            "footprint" => Ok(Objective::Footprint),
+            "sink" => Ok(Objective::Sink),
+            _ => match text.parse::<CostExpr>() {
+                Ok(expr) => Ok(Objective::Weighted(text.trim().to_owned(), expr)),
+                Err(e) => Err(format!("unknown objective `{}`, expected raw, machines, power, footprint, sink or an expression over them: {}", text, e))
+            }
+        }
+    }
+}
+
+// a cost metric summed over every stage of the plan, the highest tier for tier; None when it overflows
+fn plan_metric(plan: &Plan, constraints: &Constraints, metric: Metric) -> Option<Rational64> {
+    fn stages(tree: &PlanTree, metric: Metric, total: &mut Rational64) -> Option<()> {
+        let recipe = tree.recipe;
+        let count = Rational64::from_integer(tree.machine_count as i64);
+        let flag = | set: bool | Rational64::from_integer(set as i64);
+        match metric {
+            Metric::Waste => *total = total.checked_add(&(count - tree.machines))?,
+            Metric::Inputs => for reagent in recipe.reagents.iter() {
+                *total = total.checked_add(&recipe.drawn(reagent, count, tree.clock).ok()?)?;
+            },
+            Metric::Tier => *total = (*total).max(Rational64::from_integer(recipe.tier as i64)),
+            Metric::Alternate => *total += flag(recipe.alternate),
+            Metric::Manual => *total += flag(recipe.is_manual()),
+            _ => {}
+        }
+        for child in tree.children() {
+            stages(child, metric, total)?;
+        }
+        Some(())
+    }
+    let zero = Rational64::from_integer(0);
+    match metric {
+        Metric::Machines => Objective::Machines.totals(plan, constraints),
+        Metric::Power => Objective::Power.totals(plan, constraints),
+        Metric::Raw => Objective::Raw.totals(plan, constraints),
+        Metric::Footprint => Objective::Footprint.totals(plan, constraints),
+        Metric::Sink => plan.summary.sink_points(&constraints.sinks).ok(),
+        _ => {
+            let mut total = zero;
+            if let Some(tree) = &plan.tree {
+                stages(tree, metric, &mut total)?;
+            }
+            Some(total)
+        }
+    }
+}
+
+impl Objective {
+    // the built-in objectives' scores, nothing for an expression; None when a total overflows
+    fn totals(&self, plan: &Plan, constraints: &Constraints) -> Option<Rational64> {
+        let zero = Rational64::from_integer(0);
+        match self {
+            Objective::Raw => plan.raw_inputs().values().try_fold(zero, | acc, r | acc.checked_add(r)),
+            Objective::Machines => i64::try_from(plan.total_machines()).ok().map(Rational64::from_integer),
+            Objective::Power => plan.summary.total_power().ok(),
+            Objective::Footprint => plan.tree.as_ref().map_or(Some(zero), | tree | footprint(tree).ok()),
+            Objective::Sink => zero.checked_sub(&plan.summary.sink_points(&constraints.sinks).ok()?),
+            Objective::Weighted(..) => Some(zero)
+        }
+    }
+
+    // sink points by the constraints' sink values; None when a total or an expression divides by zero or overflows
+    pub fn measure(&self, plan: &Plan, constraints: &Constraints) -> Option<Rational64> {
+        match self {
+            Objective::Weighted(_, expr) => expr.eval(&| m | plan_metric(plan, constraints, m)),
+            objective => objective.totals(plan, constraints)
+        }
+    }
+
+    pub fn uses_sink(&self) -> bool {
+        match self {
+            Objective::Sink => true,
+            Objective::Weighted(_, expr) => expr.uses(Metric::Sink),
+            _ => false
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Objective::Weighted(text, _) => text.clone(),
+            objective => format!("{:?}", objective).to_lowercase()
+        }
+    }
+
+    pub fn describe(&self, value: &Rational64, precision: Precision) -> String {
+        match self {
+            Objective::Raw => format!("{}/s raw", precision.fixed(as_f64(value), 3)),
+            Objective::Machines => format!("{} machines", value),
+            Objective::Power => format!("{} MW", precision.fixed(as_f64(value), 1)),
+            Objective::Footprint => format!("{} m²", precision.fixed(as_f64(value), 0)),
+            Objective::Sink => format!("{} points/min", precision.fixed(as_f64(&(-value * 60)), 1)),
+            Objective::Weighted(..) => precision.fixed(as_f64(value), 3)
+        }
+    }
+}
+
+// the heuristic's own plan has to score for there to be anything to beat
+fn scored(objective: &Objective, plan: &Plan, constraints: &Constraints) -> Result<Rational64> {
+    objective.measure(plan, constraints)
+        .ok_or_else(|| Error::Arithmetic(format!("the objective `{}` divides by zero or overflows for the heuristic's plan", objective.name())))
+}
+
+// every widget the goal can end up needing that more than one allowed recipe makes, with those recipes
+pub struct Space<'a> {
+    pub widgets: Vec<String>,
+    pub options: Vec<Vec<&'a Recipe>>
+}
+
+impl<'a> Space<'a> {
+    pub fn new(graph: &'a Hypergraph<String, Recipe>, widget: &str, constraints: &Constraints) -> Result<Self> {
+        let mut space = Space { widgets: Vec::new(), options: Vec::new() };
+        let mut seen = BTreeSet::from([widget.to_owned()]);
+        let mut queue = vec![widget.to_owned()];
+        while let Some(widget) = queue.pop() {
+            let recipes: Vec<&Recipe> = producers(graph, &widget)?.into_iter().filter(| r | constraints.allows(r)).collect();
+            for reagent in recipes.iter().flat_map(| r | r.reagents.iter()) {
+                if seen.insert(reagent.widget.clone()) {
+                    queue.push(reagent.widget.clone());
+                }
+            }
+            if recipes.len() > 1 {
+                space.widgets.push(widget);
+                space.options.push(recipes);
+            }
+        }
+        Ok(space)
+    }
+
+    // `constraints` with every recipe competing with a choice excluded, unless another widget chose it
+    pub fn constrained(&self, constraints: &Constraints, choice: &[usize]) -> Constraints {
+        let mut constrained = constraints.clone();
+        let chosen: BTreeSet<&String> = self.options.iter().zip(choice).map(| (options, at) | &options[*at].name).collect();
+        for options in self.options.iter() {
+            constrained.excluded.extend(options.iter().map(| r | &r.name).filter(| name | !chosen.contains(name)).cloned());
+        }
+        constrained
+    }
+
+    // the choice the plan made for each widget, the first recipe for those it doesn't make
+    pub fn choice_of(&self, plan: &Plan) -> Vec<usize> {
+        let made = made(plan);
+        self.widgets.iter().zip(self.options.iter())
+            .map(| (widget, options) | made.get(widget).and_then(| name | options.iter().position(| r | &r.name == name)).unwrap_or(0))
+            .collect()
+    }
+}
+
+fn recipes(tree: &PlanTree, widget: &str, made: &mut BTreeMap<String, String>) {
+    made.entry(widget.to_owned()).or_insert_with(|| tree.recipe.name.clone());
+    for (child, (reagent, _)) in tree.children().iter().zip(child_flows(tree)) {
+        recipes(child, &reagent.widget, made);
+    }
+}
+
+// the recipe making each widget in the plan, the first one found for widgets made in several places
+fn made(plan: &Plan) -> BTreeMap<String, String> {
+    let mut made = BTreeMap::new();
+    if let Some(tree) = &plan.tree {
+        recipes(tree, &plan.widget, &mut made);
+    }
+    made
+}
+
+// the recipes `plan` makes widgets with that `baseline` doesn't, as `widget: recipe`
+pub fn changes(baseline: &Plan, plan: &Plan) -> Vec<String> {
+    let before = made(baseline);
+    made(plan).into_iter().filter(| (widget, recipe) | before.get(widget) != Some(recipe))
+        .map(| (widget, recipe) | format!("{widget}: {recipe}", widget=widget, recipe=recipe)).collect()
+}
+
+// None when the recipes allowed leave a widget without any or can't fit the builder limits
+fn plan<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Option<Plan<'a>>> {
+    let solved = if constraints.limits.is_empty() {
+        solve(graph, extraction, widget, rate, constraints).map(Some)
+    } else {
+        solve_within_limits(graph, extraction, widget, rate, constraints)
+    };
+    match solved {
+        Err(Error::NoRecipes(_)) | Err(Error::LimitsExceeded(_)) => Ok(None),
+        other => other
+    }
+}
+
+// plans a choice of recipes, one per widget of `space`
+pub fn evaluate<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    space: &Space, choice: &[usize]) -> Result<Option<Plan<'a>>> {
+    plan(graph, extraction, widget, rate, &space.constrained(constraints, choice))
+}
+
+// the heuristic's plan under `constraints` as it stands, and its choices in `space`
+pub fn baseline<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    space: &Space) -> Result<(Plan<'a>, Vec<usize>)> {
+    let plan = plan(graph, extraction, widget, rate, constraints)?.ok_or_else(|| Error::LimitsExceeded(widget.clone()))?;
+    let choice = space.choice_of(&plan);
+    Ok((plan, choice))
+}
+
+// xorshift64*, so a seed always gives the same run without pulling in a crate
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn draw(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.draw() % n as u64) as usize
+    }
+
+    // uniform in [0, 1)
+    pub fn unit(&mut self) -> f64 {
+        (self.draw() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub struct Optimized<'a> {
+    pub plan: Plan<'a>,
+    pub score: Rational64,
+    // the plan the heuristic makes on its own
+    pub baseline: Rational64,
+    // recipes chosen differently from it
+    pub changes: Vec<String>,
+    // distinct recipe choices planned
+    pub evaluated: usize
+}
+
+/// Simulated annealing over the recipe each widget is made by: every step switches one widget to
+/// another of its recipes and keeps the change when it lowers `objective`, or with a chance that
+/// shrinks as the run cools when it doesn't, starting from the heuristic's own choices.
+#[allow(clippy::too_many_arguments)]
+pub fn anneal<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    objective: &Objective, steps: u64, seed: u64) -> Result<Optimized<'a>> {
+    let space = Space::new(graph, widget, constraints)?;
+    let (start, baseline_choice) = baseline(graph, extraction, widget, rate, constraints, &space)?;
+    let baseline = scored(objective, &start, constraints)?;
+    // better than the heuristic, with its score
+    let mut best: Option<(Plan<'a>, Rational64)> = None;
+    let mut current = (baseline_choice.clone(), baseline);
+    let mut scores: HashMap<Vec<usize>, Option<Rational64>> = HashMap::from([(baseline_choice.clone(), Some(baseline))]);
+    let mut rng = Rng::new(seed);
+    // the run starts hot enough to take a step a tenth worse than the heuristic now and then, and cools
+    // geometrically to a thousandth of that
+    let scale = as_f64(&baseline).abs().max(1.0);
+    for step in 0..if space.widgets.is_empty() { 0 } else { steps } {
+        let mut choice = current.0.clone();
+        let at = rng.below(choice.len());
+        let options = space.options[at].len();
+        choice[at] = (choice[at] + 1 + rng.below(options - 1)) % options;
+        let score = match scores.get(&choice) {
+            Some(score) => *score,
+            None => {
+                let plan = evaluate(graph, extraction, widget, rate, constraints, &space, &choice)?;
+                let score = plan.as_ref().and_then(| p | objective.measure(p, constraints));
+                if let (Some(plan), Some(score)) = (plan, score) {
+                    if score < best.as_ref().map_or(baseline, | (_, b) | *b) {
+                        best = Some((plan, score));
+                    }
+                }
+                scores.insert(choice.clone(), score);
+                score
+            }
+        };
+        let score = match score {
+            Some(score) => score,
+            None => continue
+        };
+        let temperature = scale * 0.1 * 0.001f64.powf(step as f64 / steps as f64);
+        let worse = as_f64(&(score - current.1));
+        if worse <= 0.0 || rng.unit() < (-worse / temperature).exp() {
+            current = (choice, score);
+        }
+    }
+    let evaluated = scores.len();
+    Ok(match best {
+        Some((plan, score)) => Optimized { changes: changes(&start, &plan), plan, score, baseline, evaluated },
+        None => Optimized { plan: start, score: baseline, baseline, changes: Vec::new(), evaluated }
+    })
+}
+
+// how the recipe choices are searched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Anneal,
+    Evolve
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        match text {
+            "anneal" => Ok(Method::Anneal),
+            "evolve" => Ok(Method::Evolve),
+            _ => Err(format!("unknown method `{}`, expected anneal or evolve", text))
+        }
+    }
+}
+
+// the better of two members picked at random
+fn tournament<'c>(ranked: &'c [(Vec<usize>, Rational64)], rng: &mut Rng) -> &'c [usize] {
+    let (a, b) = (rng.below(ranked.len()), rng.below(ranked.len()));
+    &ranked[a.min(b)].0
+}
+
+/// Evolves a population of recipe choices, seeded with the heuristic's own and random ones: each
+/// generation breeds as many children as there are members, each recipe taken from either of two
+/// parents and now and then switched at random, and the best distinct choices of parents and children
+/// together live on. Returns the best `keep` distinct plans of the last generation, best first, for a
+/// spread of good plans rather than one.
+#[allow(clippy::too_many_arguments)]
+pub fn evolve<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    objective: &Objective, generations: u64, population: usize, keep: usize, seed: u64) -> Result<Vec<Optimized<'a>>> {
+    let space = Space::new(graph, widget, constraints)?;
+    let (start, baseline_choice) = baseline(graph, extraction, widget, rate, constraints, &space)?;
+    let baseline = scored(objective, &start, constraints)?;
+    let mut scores: HashMap<Vec<usize>, Option<Rational64>> = HashMap::from([(baseline_choice.clone(), Some(baseline))]);
+    let mut score = | choice: &Vec<usize> | -> Result<Option<Rational64>> {
+        if let Some(score) = scores.get(choice) {
+            return Ok(*score);
+        }
+        let score = evaluate(graph, extraction, widget, rate, constraints, &space, choice)?.and_then(| p | objective.measure(&p, constraints));
+        scores.insert(choice.clone(), score);
+        Ok(score)
+    };
+    let mut rng = Rng::new(seed);
+    let mut candidates = vec![baseline_choice.clone()];
+    if !space.widgets.is_empty() {
+        candidates.extend((1..population).map(| _ | space.options.iter().map(| options | rng.below(options.len())).collect()));
+    }
+    let mut ranked: Vec<(Vec<usize>, Rational64)> = Vec::new();
+    for generation in 0..=if space.widgets.is_empty() { 0 } else { generations } {
+        if generation > 0 {
+            candidates = (0..population).map(| _ | {
+                let (a, b) = (tournament(&ranked, &mut rng).to_vec(), tournament(&ranked, &mut rng).to_vec());
+                a.iter().zip(b.iter()).zip(space.options.iter()).map(| ((a, b), options) | {
+                    let gene = if rng.below(2) == 0 { *a } else { *b };
+                    // about one switch per child
+                    if rng.below(space.widgets.len()) == 0 { rng.below(options.len()) } else { gene }
+                }).collect()
+            }).collect();
+        }
+        for choice in candidates.drain(..) {
+            if ranked.iter().all(| (c, _) | *c != choice) {
+                if let Some(score) = score(&choice)? {
+                    ranked.push((choice, score));
+                }
+            }
+        }
+        ranked.sort_by(| (a, x), (b, y) | x.cmp(y).then_with(|| a.cmp(b)));
+        ranked.truncate(population.max(1));
+    }
+    let evaluated = scores.len();
+    let mut plans = Vec::new();
+    for (choice, score) in ranked.into_iter().take(keep) {
+        let plan = if choice == baseline_choice {
+            plan(graph, extraction, widget, rate, constraints)?
+        } else {
+            evaluate(graph, extraction, widget, rate, constraints, &space, &choice)?
+        };
+        if let Some(plan) = plan {
+            plans.push(Optimized { changes: changes(&start, &plan), plan, score, baseline, evaluated });
+        }
+    }
+    Ok(plans)
+}
+
+pub fn print_evolved(plans: &[Optimized], objective: &Objective, precision: Precision) {
+    if let Some(first) = plans.first() {
+        println!("{n} best plans, from {baseline} with the heuristic's recipes, after planning {evaluated} recipe choices",
+            n=plans.len(), baseline=objective.describe(&first.baseline, precision), evaluated=first.evaluated);
+    }
+    for (i, optimized) in plans.iter().enumerate() {
+        let changes = if optimized.changes.is_empty() { "the heuristic's recipes".to_owned() } else { optimized.changes.join(", ") };
+        println!("  {rank}. {score}: {changes}", rank=i + 1, score=objective.describe(&optimized.score, precision), changes=changes);
+    }
+    println!();
+}
+
+// a plan no other plan found beats on every objective
+pub struct Member<'a> {
+    pub plan: Plan<'a>,
+    // in the order of the objectives
+    pub scores: Vec<Rational64>,
+    pub changes: Vec<String>
+}
+
+pub struct Front<'a> {
+    // by their scores, the first objective first
+    pub members: Vec<Member<'a>>,
+    pub evaluated: usize
+}
+
+// no worse on any objective and better on one
+fn dominates(a: &[Rational64], b: &[Rational64]) -> bool {
+    a.iter().zip(b).all(| (a, b) | a <= b) && a.iter().zip(b).any(| (a, b) | a < b)
+}
+
+/// The Pareto front of plans over several objectives, searched by evolving recipe choices like
+/// `evolve`, parents being drawn from the plans nothing found so far dominates. Plans scoring the same
+/// on every objective are kept once.
+#[allow(clippy::too_many_arguments)]
+pub fn pareto<'a>(graph: &'a Hypergraph<String, Recipe>, extraction: &'a [Recipe], widget: &String, rate: Rational64, constraints: &Constraints,
+    objectives: &[Objective], generations: u64, population: usize, seed: u64) -> Result<Front<'a>> {
+    let space = Space::new(graph, widget, constraints)?;
+    let (start, baseline_choice) = baseline(graph, extraction, widget, rate, constraints, &space)?;
+    let measure = | plan: &Plan | objectives.iter().map(| o | o.measure(plan, constraints)).collect::<Option<Vec<_>>>();
+    let mut front = vec![(baseline_choice.clone(), objectives.iter().map(| o | scored(o, &start, constraints)).collect::<Result<Vec<_>>>()?)];
+    let mut seen = BTreeSet::from([baseline_choice.clone()]);
+    let mut rng = Rng::new(seed);
+    let mut candidates: Vec<Vec<usize>> = Vec::new();
+    if !space.widgets.is_empty() {
+        candidates.extend((1..population).map(| _ | space.options.iter().map(| options | rng.below(options.len())).collect()));
+    }
+    for generation in 0..=if space.widgets.is_empty() { 0 } else { generations } {
+        if generation > 0 {
+            candidates = (0..population).map(| _ | {
+                let (a, b) = (&front[rng.below(front.len())].0, &front[rng.below(front.len())].0);
+                a.iter().zip(b.iter()).zip(space.options.iter()).map(| ((a, b), options) | {
+                    let gene = if rng.below(2) == 0 { *a } else { *b };
+                    if rng.below(space.widgets.len()) == 0 { rng.below(options.len()) } else { gene }
+                }).collect()
+            }).collect();
+        }
+        for choice in candidates.drain(..) {
+            if !seen.insert(choice.clone()) {
+                continue;
+            }
+            let scores = match evaluate(graph, extraction, widget, rate, constraints, &space, &choice)?.and_then(| plan | measure(&plan)) {
+                Some(scores) => scores,
+                None => continue
+            };
+            if front.iter().any(| (_, s) | *s == scores || dominates(s, &scores)) {
+                continue;
+            }
+            front.retain(| (_, s) | !dominates(&scores, s));
+            front.push((choice, scores));
+        }
+    }
+    front.sort_by(| (a, x), (b, y) | x.cmp(y).then_with(|| a.cmp(b)));
+    let mut members = Vec::new();
+    for (choice, scores) in front {
+        let plan = if choice == baseline_choice {
+            plan(graph, extraction, widget, rate, constraints)?
+        } else {
+            evaluate(graph, extraction, widget, rate, constraints, &space, &choice)?
+        };
+        if let Some(plan) = plan {
+            members.push(Member { changes: changes(&start, &plan), plan, scores });
+        }
+    }
+    Ok(Front { members, evaluated: seen.len() })
+}
+
+// the front as a table, a row per plan and a column per objective, then the recipes it changes
+pub fn print_front(front: &Front, objectives: &[Objective], precision: Precision) {
+    println!("{n} plans on the Pareto front, after planning {evaluated} recipe choices", n=front.members.len(), evaluated=front.evaluated);
+    let names: Vec<String> = objectives.iter().map(Objective::name).collect();
+    let rows: Vec<Vec<String>> = front.members.iter().map(| m | objectives.iter().zip(m.scores.iter()).map(| (o, s) | o.describe(s, precision)).collect()).collect();
+    let widths: Vec<usize> = names.iter().enumerate()
+        .map(| (i, name) | rows.iter().map(| r | r[i].chars().count()).fold(name.chars().count(), usize::max)).collect();
+    let header: Vec<String> = names.iter().zip(widths.iter()).map(| (name, width) | format!("{name:>width$}", name=name, width=width)).collect();
+    println!("  {n:>3}  {columns}  recipes", n="#", columns=header.join("  "));
+    for (i, (member, row)) in front.members.iter().zip(rows.iter()).enumerate() {
+        let cells: Vec<String> = row.iter().zip(widths.iter()).map(| (cell, width) | format!("{cell:>width$}", cell=cell, width=width)).collect();
+        let changes = if member.changes.is_empty() { "the heuristic's".to_owned() } else { member.changes.join(", ") };
+        println!("  {n:>3}  {columns}  {changes}", n=i + 1, columns=cells.join("  "), changes=changes);
+    }
+    println!();
+}
+
+pub fn print_optimized(optimized: &Optimized, objective: &Objective, precision: Precision) {
+    println!("{score}, from {baseline} with the heuristic's recipes, after planning {n} recipe choices",
+        score=objective.describe(&optimized.score, precision), baseline=objective.describe(&optimized.baseline, precision), n=optimized.evaluated);
+    if optimized.changes.is_empty() {
+        println!("  nothing better than the heuristic's recipes");
+    }
+    for change in optimized.changes.iter() {
+        println!("  {}", change);
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_recipes, Cookbook};
+
+    #[test]
+    fn anneal_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let widget = "modular-frame".to_owned();
+        let mut constraints = Constraints::default();
+        constraints.unlocked.extend(["Pure Iron Ingot".to_owned(), "Iron Alloy Ingot".to_owned()]);
+        let optimized = anneal(&graph, &extraction, &widget, Rational64::from_integer(1), &constraints, &Objective::Raw, 50, 1).unwrap();
+        assert!(optimized.score < optimized.baseline);
+        assert!(optimized.changes.contains(&"iron-ingot: Pure Iron Ingot".to_owned()));
+        assert_eq!(Some(optimized.score), Objective::Raw.measure(&optimized.plan, &constraints));
+        let plans = evolve(&graph, &extraction, &widget, Rational64::from_integer(1), &constraints, &Objective::Raw, 5, 6, 3, 1).unwrap();
+        assert_eq!(plans[0].score, optimized.score);
+        assert!(plans.windows(2).all(| w | w[0].score <= w[1].score && w[0].changes != w[1].changes));
+    }
+
+    #[test]
+    fn weighted_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let constraints = Constraints::default();
+        let plan = solve(&graph, &extraction, &"modular-frame".to_owned(), Rational64::from_integer(1), &constraints).unwrap();
+        let weighted: Objective = "0.5*raw + 0.3*power + 0.2*machines".parse().unwrap();
+        let expected = Objective::Raw.measure(&plan, &constraints).unwrap() / 2 + Objective::Power.measure(&plan, &constraints).unwrap() * Rational64::new(3, 10)
+            + Objective::Machines.measure(&plan, &constraints).unwrap() / 5;
+        assert_eq!(weighted.measure(&plan, &constraints), Some(expected));
+        assert_eq!(weighted.name(), "0.5*raw + 0.3*power + 0.2*machines");
+        assert_eq!("power".parse::<Objective>().unwrap(), Objective::Power);
+        assert!("raw + ore".parse::<Objective>().is_err());
+    }
+
+    #[test]
+    fn pareto_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let mut constraints = Constraints::default();
+        constraints.unlocked.extend(["Pure Iron Ingot".to_owned(), "Iron Alloy Ingot".to_owned()]);
+        let front = pareto(&graph, &extraction, &"modular-frame".to_owned(), Rational64::from_integer(1), &constraints,
+            &[Objective::Raw, Objective::Machines], 5, 6, 1).unwrap();
+        assert!(front.members.len() > 1);
+        for a in front.members.iter() {
+            assert!(front.members.iter().all(| b | !dominates(&b.scores, &a.scores)));
+        }
+    }
+
+    #[test]
+    fn evolve_test() {
+        let one = Rational64::from_integer(1);
+        let graph = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("c").item("ore")
+            .recipe(Recipe::new("Wasteful A", one).made_by("Assembler").reagent("b", Rational64::from_integer(3)).product("a", one))
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", Rational64::from_integer(2)).product("a", one))
+            .recipe(Recipe::new("B", one).made_by("Assembler").reagent("ore", one).product("b", one))
+            .recipe(Recipe::new("Direct B", one).made_by("Assembler").reagent("ore", Rational64::from_integer(2)).product("b", one))
+            .build().graph().unwrap();
+        let (widget, constraints) = ("a".to_owned(), Constraints::default());
+        let run = | keep, seed | evolve(&graph, &[], &widget, one, &constraints, &Objective::Raw, 4, 4, keep, seed).unwrap();
+        // every one of the four choices is tried, the plan drawing the least ore first
+        let plans = run(4, 7);
+        assert_eq!(plans.iter().map(| p | p.score).collect::<Vec<_>>(), [2, 3, 4, 6].map(Rational64::from_integer));
+        assert_eq!((plans[0].evaluated, made(&plans[0].plan)["a"].as_str(), made(&plans[0].plan)["b"].as_str()), (4, "A", "B"));
+        // the same seed evolves the same plans, and only `keep` of them are returned
+        let again = run(2, 7);
+        assert_eq!(again.iter().map(| p | p.changes.clone()).collect::<Vec<_>>(), plans[..2].iter().map(| p | p.changes.clone()).collect::<Vec<_>>());
+        // with nothing to choose the heuristic's plan is all there is
+        let fixed = Constraints { excluded: ["Wasteful A".to_owned(), "Direct B".to_owned()].into(), ..Default::default() };
+        let only = evolve(&graph, &[], &widget, one, &fixed, &Objective::Raw, 4, 4, 4, 7).unwrap();
+        assert_eq!(only.iter().map(| p | (p.score, p.evaluated, p.changes.len())).collect::<Vec<_>>(), [(Rational64::from_integer(2), 1, 0)]);
+        assert_eq!("evolve".parse::<Method>(), Ok(Method::Evolve));
+        assert!("breed".parse::<Method>().is_err());
+    }
+}
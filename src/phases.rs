@@ -0,0 +1,123 @@
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::error::Result;
+use crate::tree::NTree;
+use crate::{child_flows, ConstructionCosts, Cookbook, PlanTree, Precision};
+
+// a slice of the plan to build in one go, fed only by earlier phases
+#[derive(Default)]
+pub struct Phase {
+    pub machines: BTreeMap<String, u64>,
+    // units/second handed to later phases
+    pub feeds: BTreeMap<String, Rational64>
+}
+
+// stages from the leaves up, a leaf being 0
+fn heights(tree: &PlanTree) -> NTree<usize> {
+    let children: Vec<NTree<usize>> = tree.children().iter().map(heights).collect();
+    let mut node = NTree::new(children.iter().map(| c | **c + 1).max().unwrap_or(0));
+    for child in children {
+        node.insert(child);
+    }
+    node
+}
+
+fn machines_by_height(tree: &PlanTree, heights: &NTree<usize>, machines: &mut Vec<u64>) {
+    if machines.len() <= **heights {
+        machines.resize(**heights + 1, 0);
+    }
+    machines[**heights] += tree.machine_count;
+    for (child, height) in tree.children().iter().zip(heights.children()) {
+        machines_by_height(child, height, machines);
+    }
+}
+
+fn assign(tree: &PlanTree, heights: &NTree<usize>, phase_of: &[usize], phases: &mut [Phase]) {
+    let phase = phase_of[**heights];
+    *phases[phase].machines.entry(tree.recipe.builder_name().to_owned()).or_insert(0) += tree.machine_count;
+    for ((child, height), (reagent, flow)) in tree.children().iter().zip(heights.children()).zip(child_flows(tree)) {
+        let child_phase = phase_of[**height];
+        if child_phase < phase {
+            *phases[child_phase].feeds.entry(reagent.widget.clone()).or_insert_with(|| Rational64::from_integer(0)) += flow;
+        }
+        assign(child, height, phase_of, phases);
+    }
+}
+
+// splits the plan from its raw inputs up into at most `count` phases of roughly equal machine counts
+pub fn phases(tree: &PlanTree, count: usize) -> Vec<Phase> {
+    let heights = heights(tree);
+    let mut machines = Vec::new();
+    machines_by_height(tree, &heights, &mut machines);
+    let total: u64 = machines.iter().sum();
+    let count = count.clamp(1, machines.len());
+
+    let mut phase_of = Vec::with_capacity(machines.len());
+    let (mut phase, mut built) = (0, 0);
+    for (height, machines_at) in machines.iter().enumerate() {
+        phase_of.push(phase);
+        built += machines_at;
+        // leave at least one height for every phase still to come
+        let heights_left = machines.len() - height - 1;
+        if phase + 1 < count && (built * count as u64 >= total * (phase as u64 + 1) || heights_left < count - phase) {
+            phase += 1;
+        }
+    }
+    let mut phases: Vec<Phase> = (0..=phase).map(| _ | Phase::default()).collect();
+    assign(tree, &heights, &phase_of, &mut phases);
+    phases
+}
+
+pub fn print_phases(phases: &[Phase], widget: &String, rate: &Rational64, costs: Option<&ConstructionCosts>, cookbook: &Cookbook, precision: Precision) -> Result<()> {
+    println!();
+    println!("Build phases:");
+    for (i, phase) in phases.iter().enumerate() {
+        let machines: Vec<String> = phase.machines.iter().map(| (builder, count) | format!("{count}x {builder}", count=count, builder=builder)).collect();
+        println!("  Phase {n}: {machines}", n=i + 1, machines=machines.join(", "));
+        for (fed, flow) in phase.feeds.iter() {
+            println!("    feeds {widget} at {rate}", widget=fed, rate=cookbook.format_rate(fed, flow, precision));
+        }
+        if i + 1 == phases.len() {
+            println!("    delivers {widget} at {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision));
+        }
+        if let Some(costs) = costs {
+            let (materials, missing) = costs.bill(&phase.machines)?;
+            let materials: Vec<String> = materials.iter().map(| (widget, quantity) | format!("{quantity} {widget}", quantity=quantity, widget=widget)).collect();
+            if !materials.is_empty() {
+                println!("    costs {materials}", materials=materials.join(", "));
+            }
+            if !missing.is_empty() {
+                println!("    no construction data for {builders}", builders=missing.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints, Recipe};
+
+    #[test]
+    fn phases_test() {
+        let one = Rational64::from_integer(1);
+        let step = | name: &str, builder: &str, from: &str, to: &str | Recipe::new(name, one).made_by(builder).reagent(from, one).product(to, one);
+        let graph = Cookbook::builder().machine("Assembler", None).machine("Constructor", None).machine("Smelter", None)
+            .item("a").item("b").item("c").item("ore")
+            .recipe(step("A", "Assembler", "b", "a")).recipe(step("B", "Constructor", "c", "b")).recipe(step("C", "Smelter", "ore", "c"))
+            .build().graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default()).unwrap().tree.unwrap();
+        // raw inputs up: the smelters first, feeding what is built after them
+        let split = phases(&tree, 3);
+        let builders: Vec<Vec<&str>> = split.iter().map(| p | p.machines.keys().map(String::as_str).collect()).collect();
+        assert_eq!(builders, [["Smelter"], ["Constructor"], ["Assembler"]]);
+        assert_eq!(split[0].feeds, BTreeMap::from([("c".to_owned(), Rational64::from_integer(2))]));
+        assert!(split[2].feeds.is_empty());
+        assert_eq!(phases(&tree, 10).len(), 3);
+        let whole = phases(&tree, 1);
+        assert_eq!((whole.len(), whole[0].machines.values().sum::<u64>()), (1, 6));
+        assert!(whole[0].feeds.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use num::traits::CheckedAdd;
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::tree::NTree;
+use crate::{child_flows, ConstructionCosts, Cookbook, PlanTree, Precision};
+
+// one player's share of the plan
+pub struct Player {
+    pub machines: BTreeMap<String, u64>,
+    pub workload: Rational64,
+    // units/second handed to other players, by receiving player
+    pub sends: BTreeMap<(usize, String), Rational64>
+}
+
+pub struct Split {
+    pub players: Vec<Player>,
+    // the player delivering the final product
+    pub delivers: usize
+}
+
+// a stage's own workload and its subtree's, in machines or construction materials
+fn workloads(tree: &PlanTree, costs: Option<&ConstructionCosts>) -> Result<NTree<(Rational64, Rational64)>> {
+    let fail = || Error::Arithmetic(format!("the workload below recipe `{name}` overflows 64-bit rationals", name=tree.recipe.name));
+    let count = Rational64::from_integer(i64::try_from(tree.machine_count).map_err(| _ | fail())?);
+    let own = match costs {
+        Some(costs) => {
+            let (materials, missing) = costs.bill(&BTreeMap::from([(tree.recipe.builder_name().to_owned(), tree.machine_count)]))?;
+            // builders without construction data count as a single unit of material each
+            if missing.is_empty() { materials.values().try_fold(Rational64::from_integer(0), | acc, q | acc.checked_add(q)).ok_or_else(fail)? } else { count }
+        },
+        None => count
+    };
+    let children = tree.children().iter().map(| c | workloads(c, costs)).collect::<Result<Vec<_>>>()?;
+    let total = children.iter().try_fold(own, | acc, c | acc.checked_add(&c.1)).ok_or_else(fail)?;
+    let mut node = NTree::new((own, total));
+    for child in children {
+        node.insert(child);
+    }
+    Ok(node)
+}
+
+// children join their parent's piece while it stays under `target`, anything larger starts a piece of its own
+fn cut(workloads: &NTree<(Rational64, Rational64)>, piece: usize, target: Rational64, pieces: &mut Vec<Rational64>) -> NTree<usize> {
+    pieces[piece] += workloads.0;
+    let mut node = NTree::new(piece);
+    for child in workloads.children() {
+        let child_piece = if pieces[piece] + child.1 <= target {
+            piece
+        } else {
+            pieces.push(Rational64::from_integer(0));
+            pieces.len() - 1
+        };
+        node.insert(cut(child, child_piece, target, pieces));
+    }
+    node
+}
+
+fn assign(tree: &PlanTree, pieces: &NTree<usize>, player_of: &[usize], players: &mut [Player]) {
+    let player = player_of[**pieces];
+    *players[player].machines.entry(tree.recipe.builder_name().to_owned()).or_insert(0) += tree.machine_count;
+    for ((child, piece), (reagent, flow)) in tree.children().iter().zip(pieces.children()).zip(child_flows(tree)) {
+        let child_player = player_of[**piece];
+        if child_player != player {
+            *players[child_player].sends.entry((player, reagent.widget.clone())).or_insert_with(|| Rational64::from_integer(0)) += flow;
+        }
+        assign(child, piece, player_of, players);
+    }
+}
+
+// divides the plan's subtrees among `count` players of roughly equal workloads, weighing stages
+// by their construction materials when costs are given and by machine count otherwise
+pub fn split(tree: &PlanTree, count: usize, costs: Option<&ConstructionCosts>) -> Result<Split> {
+    let count = count.max(1);
+    let workloads = workloads(tree, costs)?;
+    let target = workloads.1 / Rational64::from_integer(count as i64);
+    let mut pieces = vec![Rational64::from_integer(0)];
+    let pieces_tree = cut(&workloads, 0, target, &mut pieces);
+
+    // largest pieces first, each to the least busy player
+    let mut order: Vec<usize> = (0..pieces.len()).collect();
+    order.sort_by(| a, b | pieces[*b].cmp(&pieces[*a]).then(a.cmp(b)));
+    let mut players: Vec<Player> = (0..count).map(| _ | Player { machines: BTreeMap::new(), workload: Rational64::from_integer(0), sends: BTreeMap::new() }).collect();
+    let mut player_of = vec![0; pieces.len()];
+    for piece in order {
+        let player = (0..count).min_by_key(| p | players[*p].workload).unwrap_or(0);
+        player_of[piece] = player;
+        players[player].workload += pieces[piece];
+    }
+    assign(tree, &pieces_tree, &player_of, &mut players);
+    Ok(Split { delivers: player_of[*pieces_tree], players })
+}
+
+pub fn print_split(split: &Split, widget: &String, rate: &Rational64, by: &str, cookbook: &Cookbook, precision: Precision) {
+    println!();
+    println!("Players:");
+    for (i, player) in split.players.iter().enumerate() {
+        let machines: Vec<String> = player.machines.iter().map(| (builder, count) | format!("{count}x {builder}", count=count, builder=builder)).collect();
+        println!("  Player {n} ({workload} {by}): {machines}", n=i + 1, workload=crate::decimal(&player.workload, 1), by=by,
+            machines=if machines.is_empty() { "nothing".to_owned() } else { machines.join(", ") });
+        for ((to, sent), flow) in player.sends.iter() {
+            println!("    sends {widget} at {rate} to player {n}", widget=sent, rate=cookbook.format_rate(sent, flow, precision), n=to + 1);
+        }
+        if i == split.delivers {
+            println!("    delivers {widget} at {rate}", widget=widget, rate=cookbook.format_rate(widget, rate, precision));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints, Recipe};
+
+    #[test]
+    fn split_test() {
+        let one = Rational64::from_integer(1);
+        let graph = Cookbook::builder().machine("Assembler", None).machine("Constructor", None).machine("Smelter", None)
+            .item("a").item("b").item("c").item("ore")
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", one).reagent("c", one).product("a", one))
+            .recipe(Recipe::new("B", one).made_by("Constructor").reagent("ore", one).product("b", one))
+            .recipe(Recipe::new("C", one).made_by("Smelter").reagent("ore", one).product("c", one))
+            .build().graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default()).unwrap().tree.unwrap();
+        // two machines a stage: each branch would take its parent past the three a player should get, so starts a piece of its own,
+        // and the third piece goes to whoever has least
+        let split = split(&tree, 2, None).unwrap();
+        let machines = | builders: &[(&str, u64)] | builders.iter().map(| (b, n) | (b.to_string(), *n)).collect::<BTreeMap<_, _>>();
+        assert_eq!(split.players[0].machines, machines(&[("Assembler", 2), ("Smelter", 2)]));
+        assert_eq!(split.players[1].machines, machines(&[("Constructor", 2)]));
+        assert_eq!((split.players[0].workload, split.players[1].workload), (Rational64::from_integer(4), Rational64::from_integer(2)));
+        assert_eq!(split.players[1].sends, BTreeMap::from([((0, "b".to_owned()), Rational64::from_integer(2))]));
+        assert!(split.players[0].sends.is_empty() && split.delivers == 0);
+    }
+}
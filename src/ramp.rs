@@ -0,0 +1,191 @@
+use num::Rational64;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{as_f64, child_flows, ConstructionCosts, Cookbook, PlanTree, Precision};
+
+// a stage of the plan as the ramp-up places its machines
+struct Stage {
+    builder: String,
+    widget: String,
+    count: u64,
+    built: u64,
+    // units/second it makes once every machine is placed
+    rate: f64,
+    // stages from the raw inputs up, a leaf being 0
+    height: usize,
+    children: Vec<usize>,
+    parent: Option<usize>
+}
+
+// every stage after the one it feeds, the goal first
+fn flatten(tree: &PlanTree, widget: &str, parent: Option<usize>, stages: &mut Vec<Stage>) -> usize {
+    let at = stages.len();
+    stages.push(Stage { builder: tree.recipe.builder_name().to_owned(), widget: widget.to_owned(), count: tree.machine_count, built: 0,
+        rate: as_f64(&tree.requested_rate), height: 0, children: Vec::new(), parent });
+    let mut children = Vec::new();
+    for (child, (reagent, _)) in tree.children().iter().zip(child_flows(tree)) {
+        children.push(flatten(child, &reagent.widget, Some(at), stages));
+    }
+    stages[at].height = children.iter().map(| c | stages[*c].height + 1).max().unwrap_or(0);
+    stages[at].children = children;
+    at
+}
+
+// the share of its full rate each stage runs at: no more than its machines allow, nor than its inputs arrive
+fn running(stages: &[Stage]) -> Vec<f64> {
+    let mut share = vec![0.0; stages.len()];
+    // children come after their parent, so going backwards they are done first
+    for (i, stage) in stages.iter().enumerate().rev() {
+        let placed = if stage.count == 0 { 1.0 } else { stage.built as f64 / stage.count as f64 };
+        share[i] = stage.children.iter().map(| c | share[*c]).fold(placed, f64::min);
+    }
+    share
+}
+
+// units/second of each widget made beyond what the stages it feeds can take yet
+fn spare(stages: &[Stage], share: &[f64]) -> BTreeMap<String, f64> {
+    let mut spare = BTreeMap::new();
+    for (stage, running) in stages.iter().zip(share) {
+        if let Some(parent) = stage.parent {
+            *spare.entry(stage.widget.clone()).or_insert(0.0) += (running - share[parent]) * stage.rate;
+        }
+    }
+    spare
+}
+
+// the goal's rate at `share` of the plan from `time` on
+pub struct Milestone {
+    pub time: f64,
+    pub share: f64
+}
+
+pub struct RampUp {
+    // every time production of the goal goes up
+    pub milestones: Vec<Milestone>,
+    pub machines: u64,
+    // seconds spent waiting on the factory for construction materials
+    pub waited: f64,
+    // builders placed instantly, for lack of a construction time
+    pub untimed: BTreeSet<String>
+}
+
+/// Places the plan's machines one at a time, each taking its builder's construction time, always
+/// next on the stage furthest behind so the goal comes up evenly, those nearer the raw inputs first.
+/// With `self_supply` construction materials the plan makes are taken from what its stages have
+/// spare once running, waiting for them as needed; everything else comes from outside.
+pub fn ramp_up(tree: &PlanTree, widget: &str, costs: &ConstructionCosts, self_supply: bool) -> RampUp {
+    let mut stages = Vec::new();
+    flatten(tree, widget, None, &mut stages);
+    let machines = stages.iter().map(| s | s.count).sum();
+    let mut ramp = RampUp { milestones: Vec::new(), machines, waited: 0.0, untimed: BTreeSet::new() };
+    let mut stock: BTreeMap<String, f64> = BTreeMap::new();
+    let mut time = 0.0;
+    let mut share = running(&stages);
+    // materials pile up at the spare rate for `seconds`
+    let advance = | stock: &mut BTreeMap<String, f64>, share: &[f64], stages: &[Stage], seconds: f64 | {
+        for (widget, rate) in spare(stages, share) {
+            *stock.entry(widget).or_insert(0.0) += rate * seconds;
+        }
+    };
+    for _ in 0..machines {
+        let next = stages.iter().enumerate().filter(| (_, s) | s.built < s.count)
+            .min_by(| (_, a), (_, b) | (a.built as f64 / a.count as f64).total_cmp(&(b.built as f64 / b.count as f64)).then(a.height.cmp(&b.height)))
+            .map(| (i, _) | i).expect("a machine is left to place");
+        let builder = stages[next].builder.clone();
+        if self_supply {
+            for reagent in costs.cost(&builder).unwrap_or_default() {
+                let need = as_f64(&reagent.quantity);
+                let rate = spare(&stages, &share).get(&reagent.widget).copied().unwrap_or(0.0);
+                let have = stock.get(&reagent.widget).copied().unwrap_or(0.0);
+                // nothing making it yet, or nothing to spare, it comes from outside
+                if rate > 0.0 && have < need {
+                    let wait = (need - have) / rate;
+                    advance(&mut stock, &share, &stages, wait);
+                    time += wait;
+                    ramp.waited += wait;
+                }
+                if rate > 0.0 {
+                    *stock.entry(reagent.widget.clone()).or_insert(0.0) -= need;
+                }
+            }
+        }
+        let build = match costs.time(&builder) {
+            Some(seconds) => as_f64(&seconds),
+            None => {
+                ramp.untimed.insert(builder);
+                0.0
+            }
+        };
+        advance(&mut stock, &share, &stages, build);
+        time += build;
+        stages[next].built += 1;
+        let before = share[0];
+        share = running(&stages);
+        if share[0] > before {
+            ramp.milestones.push(Milestone { time, share: share[0] });
+        }
+    }
+    ramp
+}
+
+// seconds as hours, minutes and seconds
+fn duration(seconds: f64) -> String {
+    let whole = seconds.round() as u64;
+    match (whole / 3600, whole / 60 % 60, whole % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s)
+    }
+}
+
+pub fn print_ramp_up(ramp: &RampUp, widget: &String, rate: &Rational64, cookbook: &Cookbook, precision: Precision) {
+    println!();
+    println!("Ramp-up, placing {machines} machines one at a time:", machines=ramp.machines);
+    // the first of the goal, then every quarter of its rate reached
+    let mut quarter = 0;
+    let (last, climbing) = match ramp.milestones.split_last() {
+        Some(split) => split,
+        None => return
+    };
+    for (i, milestone) in climbing.iter().enumerate() {
+        let reached = (milestone.share * 4.0 + 1e-9).floor() as usize;
+        if i == 0 || reached > quarter {
+            let made = Rational64::approximate_float(as_f64(rate) * milestone.share).unwrap_or_else(|| Rational64::from_integer(0));
+            println!("  {time}: {made} of {widget} ({share}%)", time=duration(milestone.time), made=cookbook.format_rate(widget, &made, precision), widget=widget, share=precision.fixed(milestone.share * 100.0, 1));
+            quarter = reached;
+        }
+    }
+    println!("  {time}: full production, {rate} of {widget}", time=duration(last.time), rate=cookbook.format_rate(widget, rate, precision), widget=widget);
+    if ramp.waited > 0.0 {
+        println!("  {time} of it waiting on the factory for construction materials", time=duration(ramp.waited));
+    }
+    if !ramp.untimed.is_empty() {
+        println!("  no construction time for {builders}, placed instantly", builders=ramp.untimed.iter().cloned().collect::<Vec<_>>().join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints, Recipe};
+
+    #[test]
+    fn ramp_up_test() {
+        let one = Rational64::from_integer(1);
+        let step = | name: &str, builder: &str, from: &str, to: &str | Recipe::new(name, one).made_by(builder).reagent(from, one).product(to, one);
+        let graph = Cookbook::builder().machine("Assembler", None).machine("Constructor", None).machine("Smelter", None)
+            .item("a").item("b").item("c").item("ore")
+            .recipe(step("A", "Assembler", "b", "a")).recipe(step("B", "Constructor", "c", "b")).recipe(step("C", "Smelter", "ore", "c"))
+            .build().graph().unwrap();
+        let tree = solve(&graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default()).unwrap().tree.unwrap();
+        let costs: ConstructionCosts = serde_yaml::from_str("builders:\n  - {name: Assembler, time: 30, cost: []}\n  - {name: Constructor, time: 20, cost: []}\n  - {name: Smelter, time: 10, cost: []}").unwrap();
+        let ramp = ramp_up(&tree, "a", &costs, false);
+        assert_eq!(ramp.machines, 6);
+        assert!(ramp.untimed.is_empty());
+        // a machine on every stage, smelter first, brings up half the goal; the second round the rest
+        let milestones: Vec<(f64, f64)> = ramp.milestones.iter().map(| m | (m.time, m.share)).collect();
+        assert_eq!(milestones, [(60.0, 0.5), (120.0, 1.0)]);
+        let untimed = ramp_up(&tree, "a", &serde_yaml::from_str("builders: []").unwrap(), false);
+        assert_eq!((untimed.milestones.last().map(| m | (m.time, m.share)), untimed.untimed.len()), (Some((0.0, 1.0)), 3));
+    }
+}
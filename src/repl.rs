@@ -0,0 +1,141 @@
+use num::Rational64;
+use std::io::{BufRead, Write};
+
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::{as_f64, parse_rate, solve, summary_text, Constraints, Cookbook, Format, Precision, Recipe};
+
+const HELP: &str = "  solve WIDGET RATE  plan WIDGET at RATE, e.g. 45/min, 2.5/s or 1/3
+  raw WIDGET RATE    only the raw resources the plan draws
+  uses WIDGET        recipes that consume WIDGET
+  makes WIDGET       recipes that produce WIDGET
+  unlock RECIPE      allow or disallow an alternate recipe for later queries
+  purity PURITY      place extractors on nodes of this purity from now on
+  quit               leave";
+
+// the widget and rate of `solve` and `raw`, the widget being everything before the last word
+fn goal(argument: &str) -> Result<(String, Rational64)> {
+    match argument.rsplit_once(' ') {
+        Some((widget, rate)) => Ok((widget.trim().to_owned(), parse_rate(rate)?)),
+        None => Err(Error::Usage("expected a widget and a rate".to_owned()))
+    }
+}
+
+fn print_recipes<'a>(recipes: impl Iterator<Item=&'a Recipe>, widget: &str, verb: &str, constraints: &Constraints) {
+    let mut any = false;
+    for recipe in recipes {
+        let locked = if constraints.allows(recipe) { "" } else { " (locked)" };
+        println!("  {name} [{builder}]: {formula}{locked}", name=recipe.name, builder=recipe.builder_name(), formula=recipe.formula(), locked=locked);
+        any = true;
+    }
+    if !any {
+        println!("  nothing {verb} {widget}", verb=verb, widget=widget);
+    }
+}
+
+fn query(command: &str, cookbook: &Cookbook, graph: &Hypergraph<String, Recipe>, purity: &mut String, extraction: &mut Vec<Recipe>, constraints: &mut Constraints, precision: Precision) -> Result<()> {
+    let (word, argument) = command.split_once(' ').map_or((command, ""), | (w, a) | (w, a.trim()));
+    match word {
+        "solve" | "raw" => {
+            let (widget, rate) = goal(argument)?;
+            let plan = solve(graph, extraction, &widget, rate, constraints)?;
+            if word == "solve" {
+                let (text, warnings) = plan.render(Format::Text, cookbook, precision);
+                for warning in warnings {
+                    eprintln!("warning: {}", warning);
+                }
+                print!("{}", text);
+                print!("{}", summary_text(&plan.summary, constraints, cookbook, precision)?);
+            } else {
+                for (resource, rate) in plan.raw_inputs() {
+                    println!("  {resource}: {rate}", resource=resource, rate=cookbook.format_rate(resource, rate, precision));
+                }
+                println!("  {machines} machines, {power} MW", machines=plan.total_machines(), power=precision.fixed(as_f64(&plan.summary.total_power()?), 1));
+            }
+        },
+        "uses" | "makes" => {
+            let edges = if word == "uses" { graph.neighbors(argument) } else { graph.neighbor_of(argument) };
+            let edges = edges.map_err(| _ | Error::UnknownWidget(argument.to_owned()))?;
+            let mut recipes: Vec<&Recipe> = edges.iter().map(| e | graph.get_weight(e).expect("edge from the graph exists")).collect();
+            recipes.sort_by(| a, b | a.name.cmp(&b.name));
+            print_recipes(recipes.into_iter(), argument, if word == "uses" { "uses" } else { "makes" }, constraints);
+        },
+        "unlock" => {
+            if !constraints.unlocked.remove(argument) {
+                constraints.unlocked.insert(argument.to_owned());
+                println!("  unlocked {}", argument);
+            } else {
+                println!("  locked {}", argument);
+            }
+        },
+        "purity" => {
+            *extraction = cookbook.extraction(argument)?;
+            *purity = argument.to_owned();
+            println!("  extracting from {} nodes", purity);
+        },
+        "help" | "?" => println!("{}", HELP),
+        _ => return Err(Error::Usage(format!("unknown command `{}`, type help for commands", command)))
+    }
+    Ok(())
+}
+
+// answers queries read off `input` a line at a time against the one loaded game definition, until it ends or says quit;
+// a failed query prints its error and the session carries on
+pub fn repl(cookbook: &Cookbook, purity: &str, constraints: &Constraints, input: impl BufRead, precision: Precision) -> Result<()> {
+    let graph = cookbook.graph()?;
+    let mut purity = purity.to_owned();
+    let mut extraction = cookbook.extraction(&purity)?;
+    let mut constraints = constraints.clone();
+    let mut lines = input.lines();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().map_err(| e | Error::Usage(format!("cannot write the prompt: {}", e)))?;
+        let command = match lines.next() {
+            Some(Ok(command)) => command,
+            _ => break
+        };
+        let command = command.trim();
+        match command {
+            "" => continue,
+            "quit" | "q" | "exit" => break,
+            _ => if let Err(e) = query(command, cookbook, &graph, &mut purity, &mut extraction, &mut constraints, precision) {
+                println!("error: {}", e);
+            }
+        }
+    }
+    println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_recipes;
+
+    #[test]
+    fn goal_test() {
+        assert_eq!(goal("iron plate 45/min").unwrap(), ("iron plate".to_owned(), Rational64::new(3, 4)));
+        assert!(matches!(goal("iron-plate"), Err(Error::Usage(_))));
+        assert!(goal("iron-plate fast").is_err());
+    }
+
+    #[test]
+    fn query_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let (mut purity, mut constraints) = ("normal".to_owned(), Constraints::default());
+        let mut extraction = cookbook.extraction(&purity).unwrap();
+        let mut run = | command: &str | query(command, &cookbook, &graph, &mut purity, &mut extraction, &mut constraints, Precision::default());
+        // what a query changes carries over to the ones after it
+        run("unlock Pure Iron Ingot").unwrap();
+        run("purity pure").unwrap();
+        run("solve iron-plate 1").unwrap();
+        assert!(matches!(run("uses nothing"), Err(Error::UnknownWidget(w)) if w == "nothing"));
+        assert!(matches!(run("fly"), Err(Error::Usage(_))));
+        assert!(run("purity made-up").is_err());
+        assert_eq!((purity.as_str(), constraints.unlocked.len()), ("pure", 1));
+        // and a failed query doesn't end the session
+        let input = std::io::Cursor::new("solve nothing 1\nraw iron-plate 1\nquit\nsolve iron-plate 1\n");
+        assert!(repl(&cookbook, "normal", &Constraints::default(), input, Precision::default()).is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::cache;
+use crate::error::Result;
+use crate::hypergraph::Hypergraph;
+use crate::saved::SavedPlan;
+use crate::{as_f64, load_yaml, summary_text, Constraints, Cookbook, Precision, Recipe, SolveRequest, Summary};
+
+// one production line of a request file, a solve request as POST /solve takes it with a name to report it by,
+// e.g. {name: "north rotors", widget: rotor, rate: 1/15, max_tier: 4}
+#[derive(Deserialize)]
+pub struct Line {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub request: SolveRequest
+}
+
+impl Line {
+    // the line's name, or what it makes when it has none
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.request.widget.clone())
+    }
+}
+
+// a request file, a YAML or JSON list of lines
+pub fn load(path: &PathBuf) -> Result<Vec<Line>> {
+    load_yaml(path)
+}
+
+pub struct Solved {
+    pub label: String,
+    pub widget: String,
+    // the plan, shared with the earlier lines asking the same, or why it couldn't be made
+    pub plan: std::result::Result<Rc<SavedPlan>, String>,
+    // the earlier line asking the same, solved once for both
+    pub same_as: Option<usize>,
+    pub cached: bool
+}
+
+/// Solves every line of a request file against one loaded game definition, each line's own constraints
+/// applying to it alone. The extraction recipes of each purity are made once, lines asking exactly what an
+/// earlier one did share its plan, and with `cache` as the plan cache directory and the definition's bytes,
+/// plans are looked up and stored there as `solve` does. A line that fails is reported, not the end of the run.
+pub fn solve_all(cookbook: &Cookbook, graph: &Hypergraph<String, Recipe>, lines: &[Line], cache: Option<(&Path, &[u8])>) -> Vec<Solved> {
+    let mut extractions: BTreeMap<String, Vec<Recipe>> = BTreeMap::new();
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut solved: Vec<Solved> = Vec::new();
+    for line in lines {
+        let request = &line.request;
+        let constraints = request.constraints();
+        let key = format!("{} {} {} {}", request.widget, request.rate, request.purity, cache::constraints_key(&constraints));
+        let mut result = Solved { label: line.label(), widget: request.widget.clone(), plan: Err(String::new()), same_as: None, cached: false };
+        if let Some(&first) = seen.get(&key) {
+            result.plan = solved[first].plan.clone();
+            result.same_as = Some(first);
+            solved.push(result);
+            continue;
+        }
+        seen.insert(key, solved.len());
+        // plans cut down to builder limits aren't cached, as with `solve`
+        let entry = cache.filter(| _ | constraints.limits.is_empty())
+            .map(| (dir, definition) | cache::entry(dir, definition, &request.widget, request.rate, &request.purity, &constraints));
+        if let Some(saved) = entry.as_ref().and_then(| entry | cache::load(entry)).filter(| saved | saved.widget == request.widget && saved.rate == request.rate) {
+            result.plan = Ok(Rc::new(saved));
+            result.cached = true;
+            solved.push(result);
+            continue;
+        }
+        if !extractions.contains_key(&request.purity) {
+            match cookbook.extraction(&request.purity) {
+                Ok(extraction) => { extractions.insert(request.purity.clone(), extraction); },
+                Err(e) => {
+                    result.plan = Err(e.to_string());
+                    solved.push(result);
+                    continue;
+                }
+            }
+        }
+        result.plan = request.solve(graph, &extractions[&request.purity]).map(| plan | {
+            let saved = SavedPlan::new(cookbook, &plan.widget, plan.rate, plan.tree.as_ref(), plan.summary, &constraints.limits);
+            if let Some(entry) = &entry {
+                cache::store(entry, &saved);
+            }
+            Rc::new(saved)
+        }).map_err(| e | e.to_string());
+        solved.push(result);
+    }
+    solved
+}
+
+// every solved line's raw resources, machines, power and the rest added up
+pub fn combined(solved: &[Solved]) -> Result<Summary> {
+    let mut total = Summary::default();
+    for plan in solved.iter().filter_map(| s | s.plan.as_ref().ok()) {
+        total.merge(plan.summary.clone())?;
+    }
+    Ok(total)
+}
+
+pub fn print_report(cookbook: &Cookbook, solved: &[Solved], precision: Precision) -> Result<()> {
+    let failed = solved.iter().filter(| s | s.plan.is_err()).count();
+    println!("Solved {done} of {count} requests", done=solved.len() - failed, count=solved.len());
+    for line in solved {
+        match &line.plan {
+            Ok(plan) => {
+                let machines: u64 = plan.summary.machines.values().sum();
+                let mut text = format!("  {label}: {rate} of {widget}, {machines} machines, {power} MW", label=line.label, rate=cookbook.format_rate(&line.widget, &plan.rate, precision),
+                    widget=line.widget, machines=machines, power=precision.fixed(as_f64(&plan.summary.total_power()?), 1));
+                match line.same_as {
+                    Some(first) => text.push_str(&format!(" (same as {})", solved[first].label)),
+                    None if line.cached => text.push_str(" (cached)"),
+                    None => {}
+                }
+                println!("{}", text);
+            },
+            Err(e) => println!("  {label}: failed, {error}", label=line.label, error=e)
+        }
+    }
+    if failed < solved.len() {
+        println!();
+        println!("All lines together:");
+        print!("{}", summary_text(&combined(solved)?, &Constraints::default(), cookbook, precision)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_recipes;
+    use num::Rational64;
+
+    #[test]
+    fn solve_all_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let lines: Vec<Line> = serde_yaml::from_str("
+- {name: plates, widget: iron-plate, rate: 1}
+- {widget: iron-rod, rate: 2, purity: pure}
+- {name: again, widget: iron-plate, rate: 1}
+- {widget: nothing, rate: 1}
+").unwrap();
+        let solved = solve_all(&cookbook, &graph, &lines, None);
+        assert_eq!(solved.iter().map(| s | s.label.as_str()).collect::<Vec<_>>(), ["plates", "iron-rod", "again", "nothing"]);
+        assert_eq!(solved[2].same_as, Some(0));
+        assert!(solved[3].plan.is_err());
+        let one = Rational64::from_integer(1);
+        let ore = | s: &Solved | s.plan.as_ref().unwrap().summary.raw["iron-ore"];
+        assert_eq!(combined(&solved).unwrap().raw["iron-ore"], ore(&solved[0]) * 2 + ore(&solved[1]));
+        assert_eq!(ore(&solved[0]), one / 2 * 3);
+    }
+}
@@ -0,0 +1,163 @@
+use num::Rational64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::msgpack;
+use crate::tree::NTree;
+use crate::{Cookbook, PlanNode, PlanTree, Recipe, Summary, Transport, Widget};
+
+// bumped whenever a saved plan stops reading back the same
+const FORMAT: u32 = 4;
+
+#[derive(Deserialize, Serialize)]
+pub struct SavedNode {
+    pub recipe: Recipe,
+    pub count: u64,
+    // what `count` rounds up, fractional when counts aren't rounded
+    pub machines: Rational64,
+    pub clock: Rational64,
+    pub requested_rate: Rational64,
+    pub actual_rate: Rational64,
+    pub surplus: Rational64,
+    // requested over actual rate, for readers of the file; recomputed when loading
+    pub utilization: Rational64,
+    pub children: Vec<SavedNode>
+}
+
+// a solved plan with everything needed to render it again, rationals as [numer, denom]
+#[derive(Deserialize, Serialize)]
+pub struct SavedPlan {
+    pub format: u32,
+    pub widget: String,
+    pub rate: Rational64,
+    pub tree: Option<SavedNode>,
+    pub summary: Summary,
+    pub limits: BTreeMap<String, u64>,
+    // what rendering needs from the game definition
+    pub fluids: Vec<String>,
+    pub belts: Vec<Transport>,
+    pub pipes: Vec<Transport>
+}
+
+fn save_node(tree: &PlanTree) -> SavedNode {
+    let PlanNode { recipe, machine_count: count, machines, clock, requested_rate, actual_rate, surplus } = **tree;
+    SavedNode { recipe: recipe.clone(), count, machines, clock, requested_rate, actual_rate, surplus, utilization: tree.utilization(), children: tree.children().iter().map(save_node).collect() }
+}
+
+impl SavedNode {
+    // fails on a hand-edited stage drawing more of a reagent than 64-bit rationals hold, which `check` reports instead
+    pub fn tree(&self) -> Result<PlanTree<'_>> {
+        let node = PlanNode {
+            recipe: &self.recipe,
+            machine_count: self.count,
+            machines: self.machines,
+            clock: self.clock,
+            requested_rate: self.requested_rate,
+            actual_rate: self.actual_rate,
+            surplus: self.surplus
+        };
+        node.check_draws()?;
+        let mut tree = NTree::new(node);
+        for child in self.children.iter() {
+            tree.insert(child.tree()?);
+        }
+        Ok(tree)
+    }
+}
+
+impl SavedPlan {
+    pub fn new(cookbook: &Cookbook, widget: &str, rate: Rational64, tree: Option<&PlanTree>, summary: Summary, limits: &BTreeMap<String, u64>) -> Self {
+        SavedPlan {
+            format: FORMAT,
+            widget: widget.to_owned(),
+            rate,
+            tree: tree.map(save_node),
+            summary,
+            limits: limits.clone(),
+            fluids: cookbook.widgets.iter().filter(| w | w.is_fluid()).map(| w | w.name().clone()).collect(),
+            belts: cookbook.belts.clone(),
+            pipes: cookbook.pipes.clone()
+        }
+    }
+
+    // MessagePack, for moving large plans cheaply
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        msgpack::encode(&serde_json::to_value(self).expect("plans serialize to JSON"))
+    }
+
+    // MessagePack when `file_path` ends in .msgpack, JSON otherwise
+    pub fn save(&self, file_path: &PathBuf) -> Result<()> {
+        if file_path.extension().is_some_and(| e | e == "msgpack") {
+            return std::fs::write(file_path, self.to_msgpack()).map_err(| source | Error::Io { path: file_path.clone(), source });
+        }
+        let file = File::create(file_path).map_err(| source | Error::Io { path: file_path.clone(), source })?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self).map_err(| source | Error::PlanFormat { path: file_path.clone(), source })
+    }
+
+    // JSON or MessagePack, told apart by the first byte: a JSON plan opens with `{`, which MessagePack never starts with
+    pub fn load(file_path: &PathBuf) -> Result<Self> {
+        let bytes = std::fs::read(file_path).map_err(| source | Error::Io { path: file_path.clone(), source })?;
+        let format = | source | Error::PlanFormat { path: file_path.clone(), source };
+        let plan: SavedPlan = match bytes.iter().find(| b | !b.is_ascii_whitespace()) {
+            Some(b'{') | None => serde_json::from_slice(&bytes).map_err(format)?,
+            Some(_) => {
+                let value = msgpack::decode(&bytes).map_err(| message | format(serde::de::Error::custom(format!("bad MessagePack: {}", message))))?;
+                serde_json::from_value(value).map_err(format)?
+            }
+        };
+        if plan.format != FORMAT {
+            return Err(Error::Usage(format!("{path} is plan format {found}, this build reads format {FORMAT}", path=file_path.display(), found=plan.format, FORMAT=FORMAT)));
+        }
+        Ok(plan)
+    }
+
+    // just enough of a game definition to format rates and pick belts
+    pub fn cookbook(&self) -> Cookbook {
+        Cookbook {
+            namespace: None,
+            widgets: self.fluids.iter().map(| name | Widget::Detailed { name: name.clone(), fluid: true, price: None, sink: None }).collect(),
+            builders: vec![],
+            belts: self.belts.clone(),
+            pipes: self.pipes.clone(),
+            extractors: vec![],
+            purities: BTreeMap::new(),
+            generators: vec![],
+            recipes: vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, tree_text, Constraints, Precision};
+
+    #[test]
+    fn save_test() {
+        let cookbook = crate::load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let widget = "modular-frame".to_owned();
+        let plan = solve(&graph, &extraction, &widget, Rational64::new(1, 2), &Constraints::default()).unwrap();
+        let saved = SavedPlan::new(&cookbook, &widget, plan.rate, plan.tree.as_ref(), plan.summary.clone(), &BTreeMap::new());
+        let dir = std::env::temp_dir().join(format!("supply-solver-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // both encodings read back the plan they were written from
+        for name in ["plan.json", "plan.msgpack"] {
+            let path = dir.join(name);
+            saved.save(&path).unwrap();
+            let loaded = SavedPlan::load(&path).unwrap();
+            assert_eq!((loaded.widget.as_str(), loaded.rate, &loaded.summary.raw), (widget.as_str(), plan.rate, &plan.summary.raw));
+            let text = tree_text(&loaded.tree.as_ref().unwrap().tree().unwrap(), None, &loaded.cookbook(), Precision::default());
+            assert_eq!(text, tree_text(plan.tree.as_ref().unwrap(), None, &cookbook, Precision::default()), "{}", name);
+        }
+        let stale = dir.join("stale.json");
+        std::fs::write(&stale, std::fs::read_to_string(dir.join("plan.json")).unwrap().replacen(&format!("\"format\": {}", FORMAT), "\"format\": 1", 1)).unwrap();
+        assert!(matches!(SavedPlan::load(&stale), Err(Error::Usage(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
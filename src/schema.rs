@@ -0,0 +1,192 @@
+use serde_json::{json, Value};
+
+// integers, decimals, "numer/denom" and decimal strings and [numer, denom] pairs, as the loader accepts them
+fn number(description: &str) -> Value {
+    json!({
+        "description": description,
+        "oneOf": [
+            { "type": "number" },
+            { "type": "string", "pattern": "^\\s*(-?[0-9]+\\s*/\\s*[0-9]+|[-+]?([0-9]+(\\.[0-9]*)?|\\.[0-9]+)([eE][-+]?[0-9]+)?)\\s*$" },
+            { "type": "array", "items": { "type": "integer" }, "minItems": 2, "maxItems": 2 }
+        ]
+    })
+}
+
+// a number of seconds, or a string with a unit of time such as "1.5min", "90 s" or "1/3 h"
+fn duration(description: &str) -> Value {
+    let mut schema = number(description);
+    schema["oneOf"].as_array_mut().expect("numbers have alternatives")
+        .push(json!({ "type": "string", "pattern": "^\\s*(-?[0-9]+\\s*/\\s*[0-9]+|[-+]?([0-9]+(\\.[0-9]*)?|\\.[0-9]+)([eE][-+]?[0-9]+)?)\\s*(s|sec|second|m|min|minute|h|hr|hour)\\s*$" }));
+    schema
+}
+
+// a distribution as simulations draw from them, such as "normal(0.1)" or "uniform(0.8, 1.2)"
+fn version(description: &str) -> Value {
+    json!({ "description": description, "oneOf": [
+        { "type": "string", "pattern": "^\\s*[vV]?[0-9]+(\\.[0-9]+)*\\s*$" },
+        { "type": "number", "minimum": 0 }
+    ] })
+}
+
+fn spread(description: &str) -> Value {
+    let number = "\\s*[0-9]+(\\.[0-9]*)?\\s*";
+    json!({ "type": "string", "description": description,
+        "pattern": format!("^\\s*(normal\\({n}\\)|uniform\\({n},{n}\\)|triangular\\({n},{n},{n}\\)|exponential)\\s*$", n=number) })
+}
+
+fn text(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn object(description: &str, required: &[&str], properties: Value) -> Value {
+    json!({
+        "type": "object",
+        "description": description,
+        "required": required,
+        "properties": properties,
+        "additionalProperties": false
+    })
+}
+
+fn list(description: &str, items: Value) -> Value {
+    json!({ "type": "array", "description": description, "items": items })
+}
+
+/// A JSON Schema (draft 2020-12) for game definition files, for editors to validate and complete
+/// them and importers to check their output. Unknown keys, which loading ignores, are flagged as
+/// the likely typos they are.
+pub fn game_definition() -> Value {
+    let reagent = object("a quantity of a widget", &["widget", "quantity"], json!({
+        "widget": text("widget name from the widgets section"),
+        "quantity": number("units per craft")
+    }));
+    let reagents = | description: &str | list(description, json!({ "$ref": "#/$defs/reagent" }));
+    let widget = json!({
+        "description": "a bare name declares a solid item",
+        "oneOf": [
+            text("solid item name"),
+            object("a widget with details", &["name"], json!({
+                "name": text("widget name"),
+                "fluid": { "type": "boolean", "description": "measured in m³ and moved through pipes" },
+                "price": number("market price per unit, for buying instead of crafting"),
+                "sink": number("points per unit fed to a sink, for surplus worth something")
+            }))
+        ]
+    });
+    let builder = object("a machine that runs recipes", &["name"], json!({
+        "name": text("builder name recipes refer to"),
+        "power": number("MW per machine"),
+        "min_clock": number("lowest supported clock speed, in percent"),
+        "max_clock": number("highest supported clock speed, in percent"),
+        "power_exponent": { "type": "number", "description": "power draw scales with clock^power_exponent, linearly when unset" },
+        "speed": number("module or beacon speed bonus as a fraction, e.g. 0.5 for +50%"),
+        "productivity": number("productivity bonus as a fraction"),
+        "width": number("footprint width in meters"),
+        "length": number("footprint length in meters"),
+        "operating_cost": number("per machine and second of running, in the currency of widget prices"),
+        "consumes": reagents("units/second every machine uses up whatever it crafts, such as fuel or maintenance parts")
+    }));
+    let transport = object("a belt or pipe tier", &["name", "rate"], json!({
+        "name": text("tier name"),
+        "rate": number("units/second one of them carries")
+    }));
+    let extractor = object("produces raw resources at a rate scaled by the purity of its node", &["name", "resources", "rate"], json!({
+        "name": text("builder name, see the builders section for power and clocks"),
+        "resources": list("raw widgets it extracts", text("widget name")),
+        "rate": number("units/second on a node with multiplier 1"),
+        "tier": { "type": "integer", "minimum": 0, "description": "unlock tier" }
+    }));
+    let generator = object("burns fuel for power", &["name", "power", "fuel"], json!({
+        "name": text("generator name"),
+        "power": number("MW produced per generator"),
+        "fuel": reagents("units/second burnt per generator")
+    }));
+    let recipe = object("crafts products from reagents", &["name", "duration", "products", "reagents"], json!({
+        "name": text("recipe name, unique in the file"),
+        "builder": text("builder name, hand-crafted when unset"),
+        "manual": { "type": "boolean", "description": "hand-crafted even though it has a builder" },
+        "duration": duration("seconds per craft, or a time with a unit such as \"1.5min\""),
+        "products": reagents("widgets made per craft"),
+        "reagents": reagents("widgets used per craft"),
+        "tier": { "type": "integer", "minimum": 0, "description": "unlock tier or milestone, always available when unset" },
+        "alternate": { "type": "boolean", "description": "stays locked until unlocked by name" },
+        "category": text("production method such as smelting or refining, for --only-categories and --exclude-categories"),
+        "power": number("MW per machine, defaults to the builder's"),
+        "speed": number("speed bonus as a fraction, defaults to the builder's"),
+        "productivity": number("productivity bonus as a fraction, defaults to the builder's"),
+        "duration_spread": spread("what each simulated craft's time is multiplied by"),
+        "yield_spread": spread("what each simulated craft's products are multiplied by"),
+        "since": version("game version adding it, quoted when a part has a trailing zero such as \"1.10\""),
+        "deprecated": version("game version taking it out of the game, for --game-version")
+    }));
+    let mut schema = object("a game definition for supply-solver", &["widgets", "recipes"], json!({
+        "namespace": text("mod name qualifying widgets as modname:widget where they collide with another merged dataset's"),
+        "widgets": list("every widget recipes mention", json!({ "$ref": "#/$defs/widget" })),
+        "builders": list("machines, by the name recipes use", json!({ "$ref": "#/$defs/builder" })),
+        "belts": list("belt tiers", json!({ "$ref": "#/$defs/transport" })),
+        "pipes": list("pipe tiers", json!({ "$ref": "#/$defs/transport" })),
+        "extractors": list("raw resource extractors", json!({ "$ref": "#/$defs/extractor" })),
+        "purities": {
+            "type": "object",
+            "description": "resource node multipliers by purity, impure 1/2, normal 1 and pure 2 when unset",
+            "additionalProperties": number("multiplier")
+        },
+        "generators": list("power generators", json!({ "$ref": "#/$defs/generator" })),
+        "recipes": list("every recipe", json!({ "$ref": "#/$defs/recipe" }))
+    }));
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["title"] = json!("supply-solver game definition");
+    schema["$defs"] = json!({
+        "reagent": reagent,
+        "widget": widget,
+        "builder": builder,
+        "transport": transport,
+        "extractor": extractor,
+        "generator": generator,
+        "recipe": recipe
+    });
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keys of `value` the schema has no property for, following $refs, lists and the first matching object alternative
+    fn unknown_keys(schema: &Value, root: &Value, value: &Value, path: String, found: &mut Vec<String>) {
+        let schema = match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => &root["$defs"][reference.trim_start_matches("#/$defs/")],
+            None => schema
+        };
+        if let Some(alternatives) = schema.get("oneOf").and_then(Value::as_array) {
+            if let Some(object) = alternatives.iter().find(| a | a["type"] == "object" && value.is_object()) {
+                unknown_keys(object, root, value, path, found);
+            }
+            return;
+        }
+        match value {
+            Value::Object(fields) if schema.get("properties").is_some() => for (key, field) in fields {
+                match schema["properties"].get(key) {
+                    Some(property) => unknown_keys(property, root, field, format!("{}.{}", path, key), found),
+                    None => found.push(format!("{}.{}", path, key))
+                }
+            },
+            Value::Array(items) => for (i, item) in items.iter().enumerate() {
+                unknown_keys(&schema["items"], root, item, format!("{}[{}]", path, i), found);
+            },
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn covers_bundled_definitions_test() {
+        let schema = game_definition();
+        for file in ["satisfactory.yaml", "template.yaml"] {
+            let contents = std::fs::read_to_string(file).unwrap();
+            let value: Value = serde_yaml::from_str(&contents).unwrap();
+            let mut found = Vec::new();
+            unknown_keys(&schema, &schema, &value, String::new(), &mut found);
+            assert!(found.is_empty(), "{}: {:?}", file, found);
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::{load_recipes, msgpack, Cookbook, Format, PlanNode, Precision, Recipe, SolveRequest};
+
+// upper bounds in seconds of the solve latency histogram's buckets
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+// solve responses kept for repeated requests, all dropped once this many are cached
+const CACHED: usize = 256;
+// bytes of a request body read at most, a solve request being a few hundred
+const MAX_BODY: u64 = 1 << 20;
+// bytes of a request line and its headers read at most
+const MAX_HEAD: u64 = 16 << 10;
+// how long a connection may stall reading or writing before it is dropped, being served one at a time
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// what /metrics reports, kept across reloads
+#[derive(Default)]
+struct Metrics {
+    requests: u64,
+    solves: u64,
+    failed: u64,
+    // solves by the first bucket their time fits in, the last for slower ones
+    latencies: [u64; BUCKETS.len() + 1],
+    seconds: f64,
+    hits: u64,
+    misses: u64,
+    reloads: u64,
+    failed_reloads: u64
+}
+
+impl Metrics {
+    fn solved(&mut self, seconds: f64, ok: bool) {
+        self.solves += 1;
+        self.failed += !ok as u64;
+        self.seconds += seconds;
+        self.latencies[BUCKETS.iter().position(| b | seconds <= *b).unwrap_or(BUCKETS.len())] += 1;
+    }
+}
+
+// the Prometheus text format
+fn exposition(metrics: &Metrics, cookbook: &Cookbook, cached: usize) -> String {
+    let mut text = String::new();
+    let mut metric = | name: &str, kind: &str, help: &str, samples: &[(String, String)] | {
+        writeln!(text, "# HELP supply_solver_{name} {help}", name=name, help=help).unwrap();
+        writeln!(text, "# TYPE supply_solver_{name} {kind}", name=name, kind=kind).unwrap();
+        for (suffix, value) in samples {
+            writeln!(text, "supply_solver_{name}{suffix} {value}", name=name, suffix=suffix, value=value).unwrap();
+        }
+    };
+    let one = | value: u64 | vec![(String::new(), value.to_string())];
+    metric("requests_total", "counter", "HTTP requests and WebSocket solves answered", &one(metrics.requests));
+    metric("solves_total", "counter", "Solves run, by outcome", &[
+        ("{outcome=\"ok\"}".to_owned(), (metrics.solves - metrics.failed).to_string()),
+        ("{outcome=\"error\"}".to_owned(), metrics.failed.to_string())
+    ]);
+    let mut buckets = Vec::new();
+    let mut below = 0;
+    for (bound, count) in BUCKETS.iter().map(| b | b.to_string()).chain(["+Inf".to_owned()]).zip(metrics.latencies.iter()) {
+        below += count;
+        buckets.push((format!("_bucket{{le=\"{}\"}}", bound), below.to_string()));
+    }
+    buckets.push(("_sum".to_owned(), metrics.seconds.to_string()));
+    buckets.push(("_count".to_owned(), metrics.solves.to_string()));
+    metric("solve_seconds", "histogram", "Time taken by solves run", &buckets);
+    metric("cache_hits_total", "counter", "Solve requests answered from the response cache", &one(metrics.hits));
+    metric("cache_misses_total", "counter", "Solve requests the response cache didn't have", &one(metrics.misses));
+    metric("cache_entries", "gauge", "Solve responses cached", &one(cached as u64));
+    metric("reloads_total", "counter", "Game definition reloads, by outcome", &[
+        ("{outcome=\"ok\"}".to_owned(), metrics.reloads.to_string()),
+        ("{outcome=\"error\"}".to_owned(), metrics.failed_reloads.to_string())
+    ]);
+    metric("dataset_widgets", "gauge", "Widgets in the game definition served", &one(cookbook.widgets.len() as u64));
+    metric("dataset_recipes", "gauge", "Recipes in the game definition served", &one(cookbook.recipes.len() as u64));
+    metric("dataset_builders", "gauge", "Builders in the game definition served", &one(cookbook.builders.len() as u64));
+    text
+}
+
+// the game definition being served, replaced whole by a reload so a request never sees half of one
+struct Served {
+    path: PathBuf,
+    cookbook: Cookbook,
+    graph: Hypergraph<String, Recipe>,
+    // when the file was last written as of loading it
+    modified: Option<SystemTime>,
+    metrics: Metrics,
+    // solve responses by their request, as JSON with keys in order; dropped on reload
+    cache: HashMap<String, Response>
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(| m | m.modified()).ok()
+}
+
+impl Served {
+    fn load(path: &PathBuf) -> Result<Self> {
+        let modified = modified(path);
+        Served::new(path, load_recipes(path)?, modified)
+    }
+
+    fn new(path: &Path, cookbook: Cookbook, modified: Option<SystemTime>) -> Result<Self> {
+        let graph = cookbook.graph()?;
+        Ok(Served { path: path.to_path_buf(), cookbook, graph, modified, metrics: Metrics::default(), cache: HashMap::new() })
+    }
+
+    // the file again, keeping what is served now when it doesn't load
+    fn reload(&mut self) -> Result<()> {
+        let fresh = Served::load(&self.path);
+        self.swap(fresh)
+    }
+
+    // serves `fresh` in place of what is served now, carrying the metrics over, or counts the failed reload
+    fn swap(&mut self, fresh: Result<Served>) -> Result<()> {
+        match fresh {
+            Ok(fresh) => {
+                let metrics = std::mem::take(&mut self.metrics);
+                *self = Served { metrics, ..fresh };
+                self.metrics.reloads += 1;
+                Ok(())
+            },
+            Err(e) => {
+                self.metrics.failed_reloads += 1;
+                Err(e)
+            }
+        }
+    }
+
+    fn solve(&mut self, body: &str) -> Response {
+        let key = serde_json::from_str::<Value>(body).ok().map(| request | request.to_string());
+        if let Some(response) = key.as_ref().and_then(| k | self.cache.get(k)) {
+            self.metrics.hits += 1;
+            return response.clone();
+        }
+        self.metrics.misses += 1;
+        let started = Instant::now();
+        let response = solve(&self.cookbook, &self.graph, body);
+        let ok = response.0 == "200 OK";
+        self.metrics.solved(started.elapsed().as_secs_f64(), ok);
+        if let (true, Some(key)) = (ok, key) {
+            if self.cache.len() >= CACHED {
+                self.cache.clear();
+            }
+            self.cache.insert(key, response.clone());
+        }
+        response
+    }
+
+    fn changed(&self) -> bool {
+        modified(&self.path) != self.modified
+    }
+
+    fn reloaded(&mut self) -> Response {
+        match self.reload() {
+            Ok(()) => ("200 OK", json!({ "reloaded": self.path.display().to_string(), "widgets": self.cookbook.widgets.len(),
+                "recipes": self.cookbook.recipes.len() }).to_string()),
+            Err(e) => error("422 Unprocessable Entity", &format!("kept the dataset already loaded: {}", e))
+        }
+    }
+}
+
+// a status line and a JSON body
+type Response = (&'static str, String);
+
+// a request line and its lowercased headers
+type Head = (String, Vec<(String, String)>);
+
+fn error(status: &'static str, message: &str) -> Response {
+    (status, json!({ "error": message }).to_string())
+}
+
+// %XX escapes in a path segment, e.g. widget names with spaces
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], segment.get(i + 1..i + 3).and_then(| hex | u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn solve(cookbook: &Cookbook, graph: &Hypergraph<String, Recipe>, body: &str) -> Response {
+    let request: SolveRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return error("400 Bad Request", &format!("bad request: {}", e))
+    };
+    let plan = cookbook.extraction(&request.purity).and_then(| extraction | {
+        request.solve(graph, &extraction).map(| plan | plan.render(Format::Json, cookbook, Precision::default()).0)
+    });
+    match plan {
+        Ok(plan) => ("200 OK", plan),
+        Err(e @ Error::UnknownWidget(_)) => error("404 Not Found", &e.to_string()),
+        Err(e) => error("422 Unprocessable Entity", &e.to_string())
+    }
+}
+
+// the SHA-1 digest, which the WebSocket handshake is built on
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (word, bytes) in w.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6)
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, state) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&state.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for group in data.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, | acc, (i, byte) | acc | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            encoded.push(if i <= group.len() { ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    encoded
+}
+
+// the Sec-WebSocket-Accept answer to a client's Sec-WebSocket-Key
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key.trim()).as_bytes()))
+}
+
+const TEXT: u8 = 1;
+const CLOSE: u8 = 8;
+// bytes of a message taken at most, its fragments together
+const MAX_MESSAGE: u64 = MAX_BODY;
+
+enum Message {
+    Data(Vec<u8>),
+    Closed,
+    // over MAX_MESSAGE, left unread
+    TooBig
+}
+
+// the next data message from the client, unmasked and with fragments joined
+fn read_message(reader: &mut impl Read) -> std::io::Result<Message> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0f);
+        let length = match head[1] & 0x7f {
+            126 => {
+                let mut bytes = [0; 2];
+                reader.read_exact(&mut bytes)?;
+                u16::from_be_bytes(bytes) as u64
+            },
+            127 => {
+                let mut bytes = [0; 8];
+                reader.read_exact(&mut bytes)?;
+                u64::from_be_bytes(bytes)
+            },
+            short => short as u64
+        };
+        if length > MAX_MESSAGE - message.len() as u64 {
+            return Ok(Message::TooBig);
+        }
+        let mut mask = [0; 4];
+        if head[1] & 0x80 != 0 {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        match opcode {
+            CLOSE => return Ok(Message::Closed),
+            // pings and pongs need no answer from a server that only talks while solving
+            0x9 | 0xa => continue,
+            _ => message.extend(payload)
+        }
+        if fin {
+            return Ok(Message::Data(message));
+        }
+    }
+}
+
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        n if n < 126 => frame.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend((n as u16).to_be_bytes());
+        },
+        n => {
+            frame.push(127);
+            frame.extend((n as u64).to_be_bytes());
+        }
+    }
+    frame.extend(payload);
+    stream.write_all(&frame)
+}
+
+fn stage_message(path: &[usize], node: &PlanNode) -> String {
+    json!({ "stage": {
+        "path": path,
+        "recipe": node.recipe.name,
+        "builder": node.recipe.builder_name(),
+        "machines": node.machine_count,
+        "clock": node.clock,
+        "requested_rate": node.requested_rate,
+        "actual_rate": node.actual_rate
+    } }).to_string()
+}
+
+// takes one SolveRequest message, then sends a {"stage": ...} message per stage as it is planned and
+// the whole plan as {"plan": ...}, or {"error": ...}, before closing; whether it solved, None when the
+// client closed without asking
+fn stream_solve(cookbook: &Cookbook, graph: &Hypergraph<String, Recipe>, reader: &mut impl Read, mut stream: TcpStream) -> std::io::Result<Option<bool>> {
+    let request = match read_message(reader)? {
+        Message::Data(request) => request,
+        Message::Closed => return write_frame(&mut stream, CLOSE, &1000u16.to_be_bytes()).map(| _ | None),
+        // 1009, the message is too big to process
+        Message::TooBig => return write_frame(&mut stream, CLOSE, &1009u16.to_be_bytes()).map(| _ | None)
+    };
+    let mut sent = Ok(());
+    let plan = serde_json::from_slice::<SolveRequest>(&request).map_err(| e | Error::Usage(format!("bad request: {}", e))).and_then(| request | {
+        let extraction = cookbook.extraction(&request.purity)?;
+        let plan = request.solve_streaming(graph, &extraction, &mut | path, node | {
+            if sent.is_ok() {
+                sent = write_frame(&mut stream, TEXT, stage_message(path, node).as_bytes());
+            }
+        })?;
+        Ok(plan.render(Format::Json, cookbook, Precision::default()).0)
+    });
+    sent?;
+    let ok = plan.is_ok();
+    let last = match plan {
+        Ok(plan) => format!("{{\"plan\": {}}}", plan),
+        Err(e) => json!({ "error": e.to_string() }).to_string()
+    };
+    write_frame(&mut stream, TEXT, last.as_bytes())?;
+    write_frame(&mut stream, CLOSE, &1000u16.to_be_bytes())?;
+    Ok(Some(ok))
+}
+
+fn route(cookbook: &Cookbook, method: &str, path: &str) -> Response {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["widgets"]) => {
+            let widgets: Vec<_> = cookbook.widgets.iter().map(| w | json!({ "name": w.name(), "fluid": w.is_fluid() })).collect();
+            ("200 OK", json!(widgets).to_string())
+        },
+        ("GET", ["recipes", widget]) => {
+            let widget = decode(widget);
+            if !cookbook.widgets.iter().any(| w | w.name() == &widget) {
+                return error("404 Not Found", &Error::UnknownWidget(widget).to_string());
+            }
+            let recipes: Vec<&Recipe> = cookbook.recipes.iter().filter(| r | r.products.iter().any(| p | p.widget == widget)).collect();
+            ("200 OK", serde_json::to_string(&recipes).expect("recipes serialize to JSON"))
+        },
+        (_, ["solve", "stream"]) => error("426 Upgrade Required", "/solve/stream speaks WebSocket"),
+        (_, ["solve"]) | (_, ["widgets"]) | (_, ["recipes", _]) => error("405 Method Not Allowed", &format!("{method} is not supported on {path}", method=method, path=path)),
+        _ => error("404 Not Found", &format!("no endpoint at {path}", path=path))
+    }
+}
+
+// the `length` bytes of a request's body, None when that is over MAX_BODY and nothing is read
+fn read_body(reader: &mut impl Read, length: u64) -> std::io::Result<Option<Vec<u8>>> {
+    if length > MAX_BODY {
+        return Ok(None);
+    }
+    let mut body = Vec::new();
+    reader.take(length).read_to_end(&mut body)?;
+    if (body.len() as u64) < length {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "request body cut short"));
+    }
+    Ok(Some(body))
+}
+
+// the head up to the blank line, None when it runs past MAX_HEAD
+fn read_head(reader: &mut impl BufRead) -> std::io::Result<Option<Head>> {
+    let mut limited = reader.take(MAX_HEAD);
+    let mut request_line = String::new();
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let read = limited.read_line(&mut line)?;
+        if !line.ends_with('\n') && limited.limit() == 0 {
+            return Ok(None);
+        }
+        if request_line.is_empty() {
+            request_line = line;
+            if read == 0 {
+                break;
+            }
+            continue;
+        }
+        if read == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+    Ok(Some((request_line, headers)))
+}
+
+fn handle(served: &mut Served, stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let head = read_head(&mut reader)?;
+    let oversized = head.is_none();
+    let (request_line, headers) = head.unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+    let header = | name: &str | headers.iter().find(| (n, _) | n == name).map(| (_, v) | v.as_str());
+    let mut stream = stream;
+    if let ("GET", "/solve/stream", Some(key)) = (method, path, header("sec-websocket-key")) {
+        write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n", accept=accept_key(key))?;
+        served.metrics.requests += 1;
+        let started = Instant::now();
+        if let Some(ok) = stream_solve(&served.cookbook, &served.graph, &mut reader, stream)? {
+            served.metrics.solved(started.elapsed().as_secs_f64(), ok);
+        }
+        return Ok(());
+    }
+    let length = header("content-length").and_then(| l | l.parse().ok()).unwrap_or(0);
+    let body = read_body(&mut reader, length)?;
+    served.metrics.requests += 1;
+    let (status, body) = match (method, path.trim_matches('/'), body) {
+        _ if oversized => error("431 Request Header Fields Too Large", &format!("request lines and headers are limited to {max} bytes", max=MAX_HEAD)),
+        (_, _, None) => error("413 Payload Too Large", &format!("request bodies are limited to {max} bytes", max=MAX_BODY)),
+        ("POST", "reload", _) => served.reloaded(),
+        ("POST", "solve", Some(body)) => served.solve(&String::from_utf8_lossy(&body)),
+        ("GET", "metrics", _) => {
+            let text = exposition(&served.metrics, &served.cookbook, served.cache.len());
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {length}\r\nConnection: close\r\n\r\n",
+                length=text.len())?;
+            stream.write_all(text.as_bytes())?;
+            return stream.flush();
+        },
+        (_, "reload", _) | (_, "metrics", _) => error("405 Method Not Allowed", &format!("{method} is not supported on {path}", method=method, path=path)),
+        _ => route(&served.cookbook, method, path)
+    };
+    // the same body as MessagePack for clients that would rather have it
+    let (content_type, body) = match header("accept") {
+        Some(accept) if accept.contains("application/msgpack") => {
+            ("application/msgpack", msgpack::encode(&serde_json::from_str(&body).expect("responses are JSON")))
+        },
+        _ => ("application/json", body.into_bytes())
+    };
+    write!(stream, "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {length}\r\nConnection: close\r\n\r\n", status=status,
+        content_type=content_type, length=body.len())?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+// answers REST requests on `address` one connection at a time until the process is stopped:
+// POST /solve with a SolveRequest, GET /widgets and GET /recipes/<widget>, all in JSON, or answered
+// in MessagePack to `Accept: application/msgpack`, and streams solves stage by stage over a WebSocket at /solve/stream.
+// GET /metrics reports solve counts and latencies, response cache hits and the dataset's size for Prometheus.
+// POST /reload loads the game definition again, as does every request after the file changes when `watch`ing it;
+// the graph is rebuilt with it and a definition that fails to load leaves the previous one served
+pub fn serve(game_def: &PathBuf, address: &str, watch: bool) -> Result<()> {
+    let mut served = Served::load(game_def)?;
+    let listener = TcpListener::bind(address).map_err(| source | Error::Listen { address: address.to_owned(), source })?;
+    println!("Listening on http://{address}", address=address);
+    for stream in listener.incoming() {
+        if watch && served.changed() {
+            match served.reload() {
+                Ok(()) => println!("Reloaded {path}", path=game_def.display()),
+                Err(e) => {
+                    eprintln!("warning: kept the dataset already loaded: {}", e);
+                    // not reloading again until it is written again
+                    served.modified = modified(game_def);
+                }
+            }
+        }
+        let result = stream.and_then(| stream | handle(&mut served, stream));
+        if let Err(e) = result {
+            eprintln!("warning: dropped a connection: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_test() {
+        let mut served = Served::load(&"satisfactory.yaml".into()).unwrap();
+        let request = r#"{"widget": "iron-plate", "rate": "1"}"#;
+        assert_eq!(served.solve(request).0, "200 OK");
+        // the same request with its keys in another order
+        assert_eq!(served.solve(r#"{"rate": "1", "widget": "iron-plate"}"#).0, "200 OK");
+        assert_eq!(served.solve(r#"{"widget": "nothing", "rate": "1"}"#).0, "404 Not Found");
+        let text = exposition(&served.metrics, &served.cookbook, served.cache.len());
+        for line in ["supply_solver_solves_total{outcome=\"ok\"} 1", "supply_solver_solves_total{outcome=\"error\"} 1", "supply_solver_cache_hits_total 1",
+            "supply_solver_solve_seconds_bucket{le=\"+Inf\"} 2", "supply_solver_solve_seconds_count 2", "supply_solver_cache_entries 1"] {
+            assert!(text.lines().any(| l | l == line), "{} missing from\n{}", line, text);
+        }
+        served.reload().unwrap();
+        assert!(served.cache.is_empty() && served.metrics.reloads == 1 && served.metrics.solves == 2);
+    }
+
+    #[test]
+    fn read_body_test() {
+        let mut reader = std::io::Cursor::new(b"{}extra".to_vec());
+        assert_eq!(read_body(&mut reader, 2).unwrap(), Some(b"{}".to_vec()));
+        // a length past the limit is refused without reading or allocating it
+        assert_eq!(read_body(&mut reader, u64::MAX).unwrap(), None);
+        assert!(read_body(&mut std::io::Cursor::new(b"{".to_vec()), 2).is_err());
+    }
+
+    #[test]
+    fn read_head_test() {
+        let mut reader = std::io::Cursor::new(b"GET /widgets HTTP/1.1\r\nAccept: application/json\r\n\r\n{}".to_vec());
+        let (request_line, headers) = read_head(&mut reader).unwrap().unwrap();
+        assert_eq!(request_line, "GET /widgets HTTP/1.1\r\n");
+        assert_eq!(headers, [("accept".to_owned(), "application/json".to_owned())]);
+        // the body is left to read
+        assert_eq!(reader.position(), 51);
+        // one endless header is cut off at the limit rather than read into memory
+        let mut huge = b"GET / HTTP/1.1\r\nX-Padding: ".to_vec();
+        huge.resize(1 << 20, b'a');
+        assert_eq!(read_head(&mut std::io::Cursor::new(huge)).unwrap(), None);
+        // as are many short ones
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(10_000));
+        assert_eq!(read_head(&mut std::io::Cursor::new(many.into_bytes())).unwrap(), None);
+    }
+
+    #[test]
+    fn read_message_test() {
+        // a masked "hi" in one frame
+        let frame = [0x81, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2];
+        assert!(matches!(read_message(&mut &frame[..]).unwrap(), Message::Data(m) if m == b"hi"));
+        // a frame claiming 2^40 bytes is refused from its header alone
+        let mut huge = vec![0x82, 0xff];
+        huge.extend((1u64 << 40).to_be_bytes());
+        assert!(matches!(read_message(&mut &huge[..]).unwrap(), Message::TooBig));
+    }
+
+    #[test]
+    fn accept_key_test() {
+        // the example handshake of RFC 6455
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn swap_test() {
+        let one = num::Rational64::from_integer(1);
+        let book = | recipes: &[&str] | recipes.iter().fold(Cookbook::builder().machine("Assembler", None).item("a").item("b"), | book, name | {
+            book.recipe(Recipe::new(name, one).made_by("Assembler").reagent("b", one).product("a", one))
+        }).build();
+        let path = PathBuf::from("in-memory.yaml");
+        let mut served = Served::new(&path, book(&["A"]), None).unwrap();
+        let request = r#"{"widget": "a", "rate": "1"}"#;
+        assert_eq!(served.solve(request).0, "200 OK");
+        assert_eq!(served.cache.len(), 1);
+        // the fresh definition is served whole, the old one's responses dropped and the counts kept
+        served.swap(Served::new(&path, book(&["A", "Alt A"]), None)).unwrap();
+        assert_eq!((served.cookbook.recipes.len(), served.cache.len(), served.metrics.solves, served.metrics.reloads), (2, 0, 1, 1));
+        // one that fails to load leaves what is served alone
+        assert!(served.swap(Err(Error::UnknownWidget("c".to_owned()))).is_err());
+        assert_eq!((served.cookbook.recipes.len(), served.metrics.reloads, served.metrics.failed_reloads), (2, 1, 1));
+        // as does the file not being there, reported to the client that asked
+        assert_eq!(served.reloaded().0, "422 Unprocessable Entity");
+        assert_eq!((served.cookbook.recipes.len(), served.metrics.failed_reloads), (2, 2));
+    }
+}
@@ -0,0 +1,76 @@
+use num::Rational64;
+use std::collections::BTreeMap;
+
+use crate::{child_flows, Cookbook, PlanTree};
+
+// where `--share` links point unless --share-base says otherwise
+pub const KIRK_SATISFACTORY: &str = "https://kirkmcdonald.github.io/satisfactory-calc/calc.html";
+
+// the lowercase, hyphenated keys web calculators use for items and recipes
+fn key(name: &str) -> String {
+    let mut key = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            key.push(c.to_ascii_lowercase());
+        } else if !key.is_empty() && !key.ends_with('-') {
+            key.push('-');
+        }
+    }
+    key.trim_end_matches('-').to_owned()
+}
+
+fn recipes<'a>(tree: &PlanTree<'a>, widget: &str, chosen: &mut BTreeMap<String, &'a str>) {
+    chosen.entry(widget.to_owned()).or_insert(&tree.recipe.name);
+    for (child, (reagent, _)) in tree.children().iter().zip(child_flows(tree)) {
+        recipes(child, &reagent.widget, chosen);
+    }
+}
+
+/// A share link for KirkMcDonald-style calculators, opening them on `widget` at `rate` units/second
+/// with the recipe the plan picked for every widget more than one recipe makes, and the alternates
+/// it used enabled: `BASE#items=WIDGET:r:PER_MINUTE&rate=m&recipes=WIDGET:RECIPE,..&alt=RECIPE,..`.
+pub fn kirk_url(base: &str, cookbook: &Cookbook, widget: &str, rate: Rational64, tree: Option<&PlanTree>) -> String {
+    let mut chosen = BTreeMap::new();
+    if let Some(tree) = tree {
+        recipes(tree, widget, &mut chosen);
+    }
+    let mut overrides = Vec::new();
+    let mut alternates = Vec::new();
+    for (widget, recipe) in chosen.iter() {
+        if cookbook.recipes.iter().filter(| r | r.products.iter().any(| p | p.widget == *widget)).count() > 1 {
+            overrides.push(format!("{widget}:{recipe}", widget=key(widget), recipe=key(recipe)));
+        }
+        if cookbook.recipes.iter().any(| r | r.name == *recipe && r.alternate) && !alternates.contains(&key(recipe)) {
+            alternates.push(key(recipe));
+        }
+    }
+    let mut url = format!("{base}#items={widget}:r:{rate}&rate=m", base=base, widget=key(widget), rate=rate * Rational64::from_integer(60));
+    if !overrides.is_empty() {
+        url += &format!("&recipes={}", overrides.join(","));
+    }
+    if !alternates.is_empty() {
+        url += &format!("&alt={}", alternates.join(","));
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints};
+
+    #[test]
+    fn kirk_url_test() {
+        assert_eq!(key("Alternate: Pure Iron Ingot"), "alternate-pure-iron-ingot");
+        let cookbook = crate::load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let mut constraints = Constraints::default();
+        constraints.unlocked.insert("Pure Iron Ingot".to_owned());
+        let rate = Rational64::new(1, 3);
+        let plan = solve(&graph, &extraction, &"iron-plate".to_owned(), rate, &constraints).unwrap();
+        let url = kirk_url("calc.html", &cookbook, "iron-plate", rate, plan.tree.as_ref());
+        assert!(url.starts_with("calc.html#items=iron-plate:r:20&rate=m&recipes=iron-ingot:"), "{}", url);
+        assert_eq!(kirk_url("calc.html", &cookbook, "iron-ore", rate, None), "calc.html#items=iron-ore:r:20&rate=m");
+    }
+}
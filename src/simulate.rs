@@ -0,0 +1,452 @@
+use num::Rational64;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::optimize::Rng;
+use crate::{as_f64, child_flows, transport_for, Cookbook, PlanNode, PlanTree, Precision, Recipe};
+
+/// What a craft's time or yield is multiplied by, drawn afresh for every craft: `normal(0.1)` centred on 1
+/// with that standard deviation, `uniform(0.8, 1.2)`, `triangular(0.8, 1, 1.5)` peaking at its middle value,
+/// or `exponential` with a mean of 1, as for memoryless processes. Draws never go below zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    Normal(f64),
+    Uniform(f64, f64),
+    Triangular(f64, f64, f64),
+    Exponential
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut Rng) -> f64 {
+        let draw = match *self {
+            // Box-Muller, 1 - unit() keeping the logarithm finite
+            Distribution::Normal(sd) => 1.0 + sd * (-2.0 * (1.0 - rng.unit()).ln()).sqrt() * (std::f64::consts::TAU * rng.unit()).cos(),
+            Distribution::Uniform(low, high) => low + (high - low) * rng.unit(),
+            Distribution::Triangular(low, mode, high) => {
+                let u = rng.unit();
+                let split = if high > low { (mode - low) / (high - low) } else { 0.5 };
+                if u < split {
+                    low + (u * (high - low) * (mode - low)).sqrt()
+                } else {
+                    high - ((1.0 - u) * (high - low) * (high - mode)).sqrt()
+                }
+            },
+            Distribution::Exponential => -(1.0 - rng.unit()).ln()
+        };
+        draw.max(0.0)
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let (name, arguments) = match text.split_once('(') {
+            Some((name, rest)) => (name.trim(), rest.strip_suffix(')').ok_or_else(|| format!("`{}` is missing its closing parenthesis", text))?),
+            None => (text, "")
+        };
+        let numbers = arguments.split(',').map(str::trim).filter(| a | !a.is_empty())
+            .map(| a | a.parse::<f64>().ok().filter(| n | n.is_finite() && *n >= 0.0).ok_or_else(|| format!("`{}` isn't a non-negative number", a)))
+            .collect::<Result<Vec<f64>, String>>()?;
+        match (name.to_lowercase().as_str(), &numbers[..]) {
+            ("normal", &[sd]) => Ok(Distribution::Normal(sd)),
+            ("uniform", &[low, high]) if low <= high => Ok(Distribution::Uniform(low, high)),
+            ("triangular", &[low, mode, high]) if low <= mode && mode <= high => Ok(Distribution::Triangular(low, mode, high)),
+            ("exponential", []) => Ok(Distribution::Exponential),
+            ("normal" | "uniform" | "triangular" | "exponential", _) => Err(format!("`{}` needs normal(sd), uniform(low, high), triangular(low, mode, high) or exponential, \
+                low to high", text)),
+            _ => Err(format!("unknown distribution `{}`, expected normal, uniform, triangular or exponential", name))
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Distribution::Normal(sd) => write!(f, "normal({})", sd),
+            Distribution::Uniform(low, high) => write!(f, "uniform({}, {})", low, high),
+            Distribution::Triangular(low, mode, high) => write!(f, "triangular({}, {}, {})", low, mode, high),
+            Distribution::Exponential => write!(f, "exponential")
+        }
+    }
+}
+
+// as the game definition writes them, so saved plans read back the same
+impl Serialize for Distribution {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Distribution {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+pub struct Settings {
+    // simulated seconds, the second half is measured
+    pub seconds: f64,
+    pub step: f64,
+    // crafts' worth of units each machine buffers per input and output
+    pub buffer: f64,
+    // seconds units spend between a group's output and its consumer
+    pub delay: f64,
+    // for the draws of recipes with a duration or yield spread
+    pub seed: u64
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Machine {
+    Idle,
+    // seconds left and units the craft yields
+    Crafting(f64, f64),
+    // finished a craft but the output buffer is full
+    Blocked(f64)
+}
+
+struct Input {
+    per_craft: f64,
+    stock: f64,
+    capacity: f64,
+    // raw inputs without a feeding group never run out
+    fed: bool
+}
+
+// a machine group from the plan, flattened in plan order
+struct Group<'a> {
+    recipe: &'a Recipe,
+    // parent group and the input slot this group fills there, None for the final stage
+    consumer: Option<(usize, usize)>,
+    machines: Vec<Machine>,
+    craft_time: f64,
+    per_craft: f64,
+    // units/s the plan expects this group to deliver
+    demand: f64,
+    inputs: Vec<Input>,
+    output: f64,
+    capacity: f64,
+    // units/s the belts or pipes to the consumer carry
+    link_rate: f64,
+    // arrival time and units on the way to the consumer
+    in_flight: VecDeque<(f64, f64)>,
+    in_flight_total: f64,
+    working: f64,
+    starved: f64,
+    blocked: f64,
+    // units made while measuring
+    made: f64
+}
+
+pub struct GroupReport<'a> {
+    pub recipe: &'a Recipe,
+    pub machines: usize,
+    // planned units/s against measured ones
+    pub expected: f64,
+    pub actual: f64,
+    // shares of machine time
+    pub working: f64,
+    pub starved: f64,
+    pub blocked: f64
+}
+
+pub struct Report<'a> {
+    pub widget: String,
+    pub expected: f64,
+    pub actual: f64,
+    pub groups: Vec<GroupReport<'a>>
+}
+
+fn flatten<'a>(cookbook: &Cookbook, tree: &PlanTree<'a>, widget: &String, demand: f64, consumer: Option<(usize, usize)>, settings: &Settings, groups: &mut Vec<Group<'a>>) {
+    let PlanNode { recipe, machine_count: count, clock, .. } = **tree;
+    let machines = count as f64;
+    // without transport tiers in the game definition links never hold anything up
+    let link_rate = Rational64::approximate_float(demand).and_then(| flow | transport_for(cookbook.transport(widget), flow))
+        .map_or(f64::INFINITY, | (tier, count) | as_f64(&tier.rate) * count as f64);
+    let per_craft = recipe.products.iter().filter(| p | &p.widget == widget).map(| p | as_f64(&p.quantity)).sum::<f64>() * as_f64(&recipe.productivity());
+    let inputs = recipe.reagents.iter().map(| r | {
+        let per_craft = as_f64(&r.quantity);
+        Input { per_craft, stock: 0.0, capacity: (settings.buffer * per_craft * machines).max(per_craft), fed: false }
+    }).collect();
+    let index = groups.len();
+    groups.push(Group {
+        recipe,
+        consumer,
+        machines: vec![Machine::Idle; count as usize],
+        craft_time: as_f64(&(recipe.duration / (recipe.speed() * clock))),
+        per_craft,
+        demand,
+        inputs,
+        output: 0.0,
+        capacity: (settings.buffer * per_craft * machines).max(per_craft),
+        link_rate,
+        in_flight: VecDeque::new(),
+        in_flight_total: 0.0,
+        working: 0.0,
+        starved: 0.0,
+        blocked: 0.0,
+        made: 0.0
+    });
+    for (child, (reagent, flow)) in tree.children().iter().zip(child_flows(tree)) {
+        let slot = recipe.reagents.iter().position(| r | r.widget == reagent.widget).expect("children follow reagent order");
+        groups[index].inputs[slot].fed = true;
+        flatten(cookbook, child, &reagent.widget, as_f64(&flow), Some((index, slot)), settings, groups);
+    }
+}
+
+fn tick(groups: &mut [Group], now: f64, settings: &Settings, measuring: bool, rng: &mut Rng) {
+    let dt = settings.step;
+    // feeders sit after their consumers, so walking backwards moves units up the plan within a tick
+    for i in (0..groups.len()).rev() {
+        // units back up on the belt while the consumer's buffer is full
+        if let Some((parent, slot)) = groups[i].consumer {
+            while let Some(&(arrival, units)) = groups[i].in_flight.front() {
+                let input = &mut groups[parent].inputs[slot];
+                let accepted = units.min(input.capacity - input.stock);
+                if arrival > now || accepted <= 0.0 {
+                    break;
+                }
+                input.stock += accepted;
+                groups[i].in_flight_total -= accepted;
+                if accepted < units {
+                    groups[i].in_flight[0].1 -= accepted;
+                    break;
+                }
+                groups[i].in_flight.pop_front();
+            }
+        }
+
+        let group = &mut groups[i];
+        for m in 0..group.machines.len() {
+            let mut state = group.machines[m];
+            // time left in this step once the current craft is done
+            let mut leftover = dt;
+            let mut busy = false;
+            if let Machine::Crafting(remaining, units) = state {
+                busy = true;
+                if remaining > leftover + 1e-9 {
+                    state = Machine::Crafting(remaining - leftover, units);
+                    leftover = 0.0;
+                } else {
+                    leftover -= remaining;
+                    state = Machine::Blocked(units);
+                }
+            }
+            if let Machine::Blocked(units) = state {
+                // a yield beyond the buffer still goes into an empty one
+                if group.capacity - group.output >= units - 1e-9 || group.output <= 0.0 {
+                    group.output += units;
+                    if measuring {
+                        group.made += units;
+                    }
+                    state = Machine::Idle;
+                } else if !busy {
+                    group.blocked += dt;
+                }
+            }
+            if state == Machine::Idle && leftover > 0.0 {
+                if group.inputs.iter().all(| input | !input.fed || input.stock >= input.per_craft - 1e-9) {
+                    for input in group.inputs.iter_mut().filter(| input | input.fed) {
+                        input.stock -= input.per_craft;
+                    }
+                    let craft_time = group.craft_time * group.recipe.duration_spread.map_or(1.0, | d | d.sample(rng));
+                    let units = group.per_craft * group.recipe.yield_spread.map_or(1.0, | d | d.sample(rng));
+                    state = Machine::Crafting((craft_time - leftover).max(0.0), units);
+                    busy = true;
+                } else if !busy {
+                    group.starved += dt;
+                }
+            }
+            if busy {
+                group.working += dt;
+            }
+            group.machines[m] = state;
+        }
+
+        match groups[i].consumer {
+            Some(_) => {
+                let group = &mut groups[i];
+                // a full belt holds its rate times the travel time
+                let room = (group.link_rate * settings.delay).max(group.link_rate * dt) - group.in_flight_total;
+                let units = group.output.min(group.link_rate * dt).min(room);
+                if units > 0.0 {
+                    group.output -= units;
+                    group.in_flight_total += units;
+                    group.in_flight.push_back((now + settings.delay, units));
+                }
+            },
+            // the final stage's output leaves the factory
+            None => groups[i].output = 0.0
+        }
+    }
+}
+
+// steps the plan in fixed time steps, machines crafting whenever they have inputs and room for outputs
+pub fn simulate<'a>(cookbook: &Cookbook, tree: &PlanTree<'a>, widget: &String, rate: Rational64, settings: &Settings) -> Report<'a> {
+    let mut groups = Vec::new();
+    flatten(cookbook, tree, widget, as_f64(&rate), None, settings, &mut groups);
+
+    let mut rng = Rng::new(settings.seed);
+    let steps = (settings.seconds / settings.step).ceil() as usize;
+    let warmup = steps / 2;
+    for step in 0..steps {
+        if step == warmup {
+            for group in groups.iter_mut() {
+                group.working = 0.0;
+                group.starved = 0.0;
+                group.blocked = 0.0;
+            }
+        }
+        tick(&mut groups, step as f64 * settings.step, settings, step >= warmup, &mut rng);
+    }
+
+    let measured = (steps - warmup) as f64 * settings.step;
+    let groups: Vec<GroupReport> = groups.into_iter().map(| g | {
+        let machine_time = (g.working + g.starved + g.blocked).max(f64::MIN_POSITIVE);
+        GroupReport {
+            recipe: g.recipe,
+            machines: g.machines.len(),
+            expected: g.demand,
+            actual: g.made / measured,
+            working: g.working / machine_time,
+            starved: g.starved / machine_time,
+            blocked: g.blocked / machine_time
+        }
+    }).collect();
+    Report { widget: widget.clone(), expected: groups[0].expected, actual: groups[0].actual, groups }
+}
+
+pub fn print_report(report: &Report, settings: &Settings, precision: Precision) {
+    println!("Simulated {seconds}s in {step}s steps, measuring the last {half}s", seconds=settings.seconds, step=settings.step, half=settings.seconds / 2.0);
+    println!("{widget}: {actual}/s of {expected}/s planned ({share}%)", widget=report.widget, actual=precision.fixed(report.actual, 3), expected=precision.fixed(report.expected, 3),
+        share=precision.fixed(report.actual / report.expected * 100.0, 1));
+    println!();
+    for group in report.groups.iter() {
+        println!("  {machines}x {builder} -> {name}: {actual}/s of {expected}/s, working {working}%, starved {starved}%, blocked {blocked}%",
+            machines=group.machines, builder=group.recipe.builder_name(), name=group.recipe.name, actual=precision.fixed(group.actual, 3), expected=precision.fixed(group.expected, 3),
+            working=precision.fixed(group.working * 100.0, 0), starved=precision.fixed(group.starved * 100.0, 0), blocked=precision.fixed(group.blocked * 100.0, 0));
+    }
+}
+
+// the mean of a measure across runs and the range the middle 90% of them fall in
+pub struct Interval {
+    pub mean: f64,
+    pub low: f64,
+    pub high: f64
+}
+
+impl Interval {
+    fn of(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        let at = | share: f64 | values[((values.len() - 1) as f64 * share).round() as usize];
+        Interval { mean: values.iter().sum::<f64>() / values.len() as f64, low: at(0.05), high: at(0.95) }
+    }
+}
+
+pub struct GroupSpread<'a> {
+    pub recipe: &'a Recipe,
+    pub machines: usize,
+    pub expected: f64,
+    pub actual: Interval
+}
+
+pub struct MonteCarlo<'a> {
+    pub widget: String,
+    pub runs: usize,
+    pub expected: f64,
+    pub actual: Interval,
+    pub groups: Vec<GroupSpread<'a>>,
+    // whether any recipe in the plan varies at all, every run being the same otherwise
+    pub varies: bool
+}
+
+/// Simulates the plan `runs` times from consecutive seeds, each craft's time and yield drawn from its
+/// recipe's spreads, for the range of throughput non-deterministic crafts achieve.
+pub fn monte_carlo<'a>(cookbook: &Cookbook, tree: &PlanTree<'a>, widget: &String, rate: Rational64, settings: &Settings, runs: usize) -> MonteCarlo<'a> {
+    let reports: Vec<Report<'a>> = (0..runs.max(1) as u64).map(| run | {
+        simulate(cookbook, tree, widget, rate, &Settings { seed: settings.seed.wrapping_add(run), ..*settings })
+    }).collect();
+    let first = &reports[0];
+    let groups = first.groups.iter().enumerate().map(| (i, group) | GroupSpread {
+        recipe: group.recipe,
+        machines: group.machines,
+        expected: group.expected,
+        actual: Interval::of(reports.iter().map(| r | r.groups[i].actual).collect())
+    }).collect::<Vec<_>>();
+    MonteCarlo {
+        widget: first.widget.clone(),
+        runs: reports.len(),
+        expected: first.expected,
+        actual: Interval::of(reports.iter().map(| r | r.actual).collect()),
+        varies: groups.iter().any(| g | g.recipe.duration_spread.is_some() || g.recipe.yield_spread.is_some()),
+        groups
+    }
+}
+
+pub fn print_monte_carlo(result: &MonteCarlo, settings: &Settings, precision: Precision) {
+    let interval = | i: &Interval | format!("{mean}/s, 90% of runs {low} to {high}/s", mean=precision.fixed(i.mean, 3), low=precision.fixed(i.low, 3), high=precision.fixed(i.high, 3));
+    println!("Simulated {seconds}s in {step}s steps {runs} times, measuring the last {half}s of each", seconds=settings.seconds, step=settings.step, runs=result.runs,
+        half=settings.seconds / 2.0);
+    if !result.varies {
+        println!("no recipe in the plan has a duration or yield spread, so every run is the same");
+    }
+    println!("{widget}: {actual} of {expected}/s planned ({low}% to {high}%)", widget=result.widget, actual=interval(&result.actual),
+        expected=precision.fixed(result.expected, 3), low=precision.fixed(result.actual.low / result.expected * 100.0, 1), high=precision.fixed(result.actual.high / result.expected * 100.0, 1));
+    println!();
+    for group in result.groups.iter() {
+        println!("  {machines}x {builder} -> {name}: {actual} of {expected}/s", machines=group.machines, builder=group.recipe.builder_name(), name=group.recipe.name,
+            actual=interval(&group.actual), expected=precision.fixed(group.expected, 3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints};
+
+    #[test]
+    fn monte_carlo_test() {
+        assert_eq!("triangular(0.8, 1, 1.5)".parse(), Ok(Distribution::Triangular(0.8, 1.0, 1.5)));
+        assert_eq!(Distribution::Uniform(0.5, 2.0).to_string().parse(), Ok(Distribution::Uniform(0.5, 2.0)));
+        assert!("uniform(2, 1)".parse::<Distribution>().is_err() && "poisson(1)".parse::<Distribution>().is_err());
+
+        let cookbook = Cookbook::builder().item("ore").item("plate")
+            .recipe(Recipe::new("Plate", Rational64::from_integer(2)).reagent("ore", Rational64::from_integer(1)).product("plate", Rational64::from_integer(1))
+                .varying(Some(Distribution::Uniform(0.5, 1.5)), None))
+            .build();
+        let graph = cookbook.graph().unwrap();
+        let rate = Rational64::from_integer(2);
+        let plan = solve(&graph, &[], &"plate".to_owned(), rate, &Constraints::default()).unwrap();
+        let settings = Settings { seconds: 200.0, step: 0.1, buffer: 2.0, delay: 1.0, seed: 1 };
+        let result = monte_carlo(&cookbook, plan.tree.as_ref().unwrap(), &"plate".to_owned(), rate, &settings, 20);
+        // craft times averaging the stated one keep throughput around plan, runs spreading either side
+        assert!(result.varies && result.actual.low < result.actual.high);
+        assert!((result.actual.mean - 2.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn simulate_test() {
+        let one = Rational64::from_integer(1);
+        let cookbook = Cookbook::builder().machine("Assembler", None).item("a").item("b").item("ore")
+            .recipe(Recipe::new("A", one).made_by("Assembler").reagent("b", one).product("a", one))
+            .recipe(Recipe::new("B", Rational64::from_integer(2)).made_by("Assembler").reagent("ore", one).product("b", one))
+            .build();
+        let graph = cookbook.graph().unwrap();
+        let settings = Settings { seconds: 200.0, step: 0.1, buffer: 2.0, delay: 1.0, seed: 1 };
+        let (widget, rate) = ("a".to_owned(), Rational64::from_integer(2));
+        let mut tree = solve(&graph, &[], &widget, rate, &Constraints::default()).unwrap().tree.unwrap();
+        // the plan runs at its rate once the buffers fill
+        let report = simulate(&cookbook, &tree, &widget, rate, &settings);
+        assert!((report.actual - 2.0).abs() < 0.05 && report.groups[0].starved < 0.05, "{}", report.actual);
+        // with half the machines making `b` the final stage gets half of it, idling the rest of the time
+        let mut cursor = tree.cursor();
+        cursor.down(0);
+        cursor.data_mut().machine_count = 2;
+        let report = simulate(&cookbook, &tree, &widget, rate, &settings);
+        assert_eq!(report.groups[1].machines, 2);
+        assert!((report.actual - 1.0).abs() < 0.05 && (report.groups[0].starved - 0.5).abs() < 0.05, "{} {}", report.actual, report.groups[0].starved);
+    }
+}
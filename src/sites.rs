@@ -0,0 +1,258 @@
+use num::Rational64;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::tree::NTree;
+use crate::{as_f64, child_flows, deserialize_rational, deserialize_rational_map, load_yaml, Cookbook, PlanTree, Precision};
+
+#[derive(Debug, Deserialize)]
+pub struct Site {
+    pub name: String,
+    // raw resources the site can supply, in units/second
+    #[serde(default, deserialize_with="deserialize_rational_map")]
+    pub resources: BTreeMap<String, Rational64>
+}
+
+// carries up to `capacity` units/second each way between two sites
+#[derive(Debug, Deserialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+    #[serde(deserialize_with="deserialize_rational")]
+    pub capacity: Rational64
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Sites {
+    pub sites: Vec<Site>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    // where the final product goes, the first site when unset
+    #[serde(default)]
+    pub deliver_to: Option<String>
+}
+
+impl Sites {
+    pub fn parse(file_path: &PathBuf) -> Result<Self> {
+        let sites: Sites = load_yaml(file_path)?;
+        if sites.sites.is_empty() {
+            return Err(Error::Usage(format!("{path} declares no sites", path=file_path.display())));
+        }
+        for name in sites.links.iter().flat_map(| l | [&l.from, &l.to]).chain(sites.deliver_to.iter()) {
+            sites.index(name)?;
+        }
+        Ok(sites)
+    }
+
+    fn index(&self, name: &String) -> Result<usize> {
+        self.sites.iter().position(| s | &s.name == name).ok_or_else(|| Error::Usage(format!("unknown site `{name}`", name=name)))
+    }
+
+    fn capacity(&self, from: usize, to: usize) -> Option<Rational64> {
+        let (from, to) = (&self.sites[from].name, &self.sites[to].name);
+        self.links.iter().filter(| l | (&l.from == from && &l.to == to) || (&l.from == to && &l.to == from)).map(| l | l.capacity).reduce(| a, b | a + b)
+    }
+}
+
+#[derive(Default)]
+pub struct Placement {
+    // site index per plan node
+    pub sites: Option<NTree<usize>>,
+    // units/second per widget from one site to another
+    pub shipping: BTreeMap<(usize, usize), BTreeMap<String, Rational64>>,
+    // raw resources a site was asked for beyond what it has
+    pub shortfalls: BTreeMap<(usize, String), Rational64>
+}
+
+struct Placer<'a> {
+    sites: &'a Sites,
+    remaining: Vec<BTreeMap<String, Rational64>>,
+    default: usize,
+    // units/second already shipped between each pair of sites, either way
+    shipped: BTreeMap<(usize, usize), Rational64>,
+    placement: Placement
+}
+
+fn pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl Placer<'_> {
+    fn fits(&self, from: usize, to: usize, flow: Rational64) -> bool {
+        let used = self.shipped.get(&pair(from, to)).copied().unwrap_or_else(|| Rational64::from_integer(0));
+        from == to || self.sites.capacity(from, to).is_some_and(| capacity | used + flow <= capacity)
+    }
+
+    fn ship(&mut self, from: usize, to: usize, widget: &str, flow: Rational64) {
+        if from != to {
+            let zero = Rational64::from_integer(0);
+            *self.shipped.entry(pair(from, to)).or_insert(zero) += flow;
+            *self.placement.shipping.entry((from, to)).or_default().entry(widget.to_owned()).or_insert(zero) += flow;
+        }
+    }
+
+    fn draw(&mut self, site: usize, widget: &String, flow: Rational64) {
+        let zero = Rational64::from_integer(0);
+        let left = self.remaining[site].get(widget).copied().unwrap_or(zero);
+        let taken = flow.min(left).max(zero);
+        if taken > zero {
+            self.remaining[site].insert(widget.clone(), left - taken);
+        }
+        if flow > taken {
+            *self.placement.shortfalls.entry((site, widget.clone())).or_insert(zero) += flow - taken;
+        }
+    }
+
+    // children go first, then the node joins the site its inputs mostly come from or are found at,
+    // preferring sites the links can feed; `to` is where the output has to go, if already known
+    fn place(&mut self, tree: &PlanTree, widget: &str, delivered: Rational64, to: Option<usize>) -> NTree<usize> {
+        let zero = Rational64::from_integer(0);
+        let recipe = tree.recipe;
+        let flows = child_flows(tree);
+        let children: Vec<NTree<usize>> = tree.children().iter().zip(flows.iter()).map(| (child, (reagent, flow)) | self.place(child, &reagent.widget, *flow, None)).collect();
+        // raw inputs nothing in the plan makes, or the resource an extractor digs up
+        let local: Vec<(String, Rational64)> = if recipe.reagents.is_empty() {
+            vec![(widget.to_owned(), delivered)]
+        } else {
+            recipe.reagents.iter().filter(| r | !flows.iter().any(| (fed, _) | fed.widget == r.widget))
+                .map(| r | (r.widget.clone(), tree.draws(r))).collect()
+        };
+
+        let mut scores = vec![zero; self.sites.sites.len()];
+        for (child, (_, flow)) in children.iter().zip(flows.iter()) {
+            scores[**child] += *flow;
+        }
+        for (site, score) in scores.iter_mut().enumerate() {
+            for (resource, flow) in local.iter() {
+                *score += self.remaining[site].get(resource).copied().unwrap_or(zero).min(*flow);
+            }
+        }
+        let mut inbound: BTreeMap<usize, Rational64> = BTreeMap::new();
+        for (child, (_, flow)) in children.iter().zip(flows.iter()) {
+            *inbound.entry(**child).or_insert(zero) += *flow;
+        }
+        let feasible: Vec<bool> = (0..scores.len()).map(| site | {
+            inbound.iter().all(| (from, flow) | self.fits(*from, site, *flow)) && to.is_none_or(| to | self.fits(site, to, delivered))
+        }).collect();
+        let rank = | site: usize | (feasible[site], scores[site]);
+        let best = (0..scores.len()).map(rank).max().unwrap_or((false, zero));
+        let site = if rank(self.default) == best { self.default } else { (0..scores.len()).find(| s | rank(*s) == best).unwrap_or(self.default) };
+
+        for (resource, flow) in local {
+            self.draw(site, &resource, flow);
+        }
+        for (child, (reagent, flow)) in children.iter().zip(flows.iter()) {
+            self.ship(**child, site, &reagent.widget, *flow);
+        }
+        if let Some(to) = to {
+            self.ship(site, to, widget, delivered);
+        }
+        let mut node = NTree::new(site);
+        for child in children {
+            node.insert(child);
+        }
+        node
+    }
+}
+
+// greedily assigns every stage to a site, from the raw inputs up
+pub fn place(sites: &Sites, tree: &PlanTree, widget: &str, rate: Rational64) -> Result<Placement> {
+    let default = match &sites.deliver_to {
+        Some(name) => sites.index(name)?,
+        None => 0
+    };
+    let mut placer = Placer { sites, remaining: sites.sites.iter().map(| s | s.resources.clone()).collect(), default, shipped: BTreeMap::new(), placement: Placement::default() };
+    let root = placer.place(tree, widget, rate, Some(default));
+    placer.placement.sites = Some(root);
+    Ok(placer.placement)
+}
+
+pub fn site_notes(sites: &Sites, placement: &NTree<usize>) -> NTree<String> {
+    let mut node = NTree::new(format!(" {{{site}}}", site=sites.sites[**placement].name));
+    for child in placement.children() {
+        node.insert(site_notes(sites, child));
+    }
+    node
+}
+
+fn collect_machines(tree: &PlanTree, placement: &NTree<usize>, machines: &mut [BTreeMap<String, u64>]) {
+    *machines[**placement].entry(tree.recipe.builder_name().to_owned()).or_insert(0) += tree.machine_count;
+    for (child, site) in tree.children().iter().zip(placement.children()) {
+        collect_machines(child, site, machines);
+    }
+}
+
+pub fn print_placement(sites: &Sites, tree: &PlanTree, placement: &Placement, cookbook: &Cookbook, precision: Precision) {
+    let mut machines = vec![BTreeMap::new(); sites.sites.len()];
+    if let Some(root) = &placement.sites {
+        collect_machines(tree, root, &mut machines);
+    }
+    println!();
+    println!("Sites:");
+    for (site, machines) in sites.sites.iter().zip(machines.iter()) {
+        let machines: Vec<String> = machines.iter().map(| (builder, count) | format!("{count}x {builder}", count=count, builder=builder)).collect();
+        println!("  {site}: {machines}", site=site.name, machines=if machines.is_empty() { "nothing".to_owned() } else { machines.join(", ") });
+    }
+    if !placement.shipping.is_empty() {
+        println!();
+        println!("Shipping:");
+        for ((from, to), widgets) in placement.shipping.iter() {
+            let total = widgets.values().fold(Rational64::from_integer(0), | acc, r | acc + r);
+            let capacity = match sites.capacity(*from, *to) {
+                Some(capacity) if total > capacity => format!("over the {capacity}/s link", capacity=precision.fixed(as_f64(&capacity), 3)),
+                Some(capacity) => format!("{total}/s of a {capacity}/s link", total=precision.fixed(as_f64(&total), 3), capacity=precision.fixed(as_f64(&capacity), 3)),
+                None => "no link".to_owned()
+            };
+            println!("  {from} -> {to} ({capacity}):", from=sites.sites[*from].name, to=sites.sites[*to].name, capacity=capacity);
+            for (widget, flow) in widgets.iter() {
+                println!("    {widget}: {rate}", widget=widget, rate=cookbook.format_rate(widget, flow, precision));
+            }
+        }
+    }
+    if !placement.shortfalls.is_empty() {
+        println!();
+        println!("Shortfalls:");
+        for ((site, widget), flow) in placement.shortfalls.iter() {
+            println!("  {site} lacks {rate} of {widget}", site=sites.sites[*site].name, rate=cookbook.format_rate(widget, flow, precision), widget=widget);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Constraints, Recipe};
+
+    fn placed(capacity: &str) -> Placement {
+        let one = Rational64::from_integer(1);
+        let graph = Cookbook::builder().machine("Constructor", None).item("ore").item("plate")
+            .recipe(Recipe::new("Plate", one).made_by("Constructor").reagent("ore", one).product("plate", one))
+            .build().graph().unwrap();
+        let plan = solve(&graph, &[], &"plate".to_owned(), one, &Constraints::default()).unwrap();
+        let sites: Sites = serde_yaml::from_str(&format!("
+sites: [{{name: base}}, {{name: mine, resources: {{ore: 10}}}}]
+links: [{{from: mine, to: base, capacity: {}}}]
+", capacity)).unwrap();
+        place(&sites, plan.tree.as_ref().unwrap(), "plate", one).unwrap()
+    }
+
+    #[test]
+    fn place_test() {
+        // plates are made where the ore is and shipped to the base
+        let placement = placed("5");
+        assert_eq!(*placement.sites.unwrap(), 1);
+        assert_eq!(placement.shipping[&(1, 0)]["plate"], Rational64::from_integer(1));
+        assert!(placement.shortfalls.is_empty());
+    }
+
+    #[test]
+    fn narrow_link_test() {
+        // a link too narrow for the plates keeps them at the base, short of the ore it doesn't have
+        let placement = placed("1/2");
+        assert_eq!(*placement.sites.unwrap(), 0);
+        assert!(placement.shipping.is_empty());
+        assert_eq!(placement.shortfalls[&(0, "ore".to_owned())], Rational64::from_integer(1));
+    }
+}
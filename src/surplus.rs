@@ -0,0 +1,117 @@
+use num::Rational64;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::{Error, Result};
+use crate::hypergraph::Hypergraph;
+use crate::{solve, Constraints, Cookbook, Precision, Recipe};
+
+// one thing a surplus can all be turned into
+pub struct Outlet {
+    pub widget: String,
+    // units/second made from the whole surplus
+    pub rate: Rational64,
+    pub machines: u64,
+    // the recipes of the chain, goal first
+    pub recipes: Vec<String>,
+    // units/second taken of the other widgets allowed in
+    pub also: BTreeMap<String, Rational64>,
+    // units/second made beyond the product, sunk or stored
+    pub byproducts: BTreeMap<String, Rational64>
+}
+
+// recipes craftable from `inputs` and whatever they make in turn, none of them making an input back
+fn usable<'c>(recipes: &'c [Recipe], inputs: &BTreeSet<&String>, constraints: &Constraints) -> Vec<&'c Recipe> {
+    let mut available = inputs.clone();
+    let mut usable: Vec<&Recipe> = Vec::new();
+    loop {
+        let found: Vec<&Recipe> = recipes.iter()
+            .filter(| r | !usable.iter().any(| u | u.name == r.name) && constraints.allows(r))
+            .filter(| r | r.reagents.iter().all(| g | available.contains(&g.widget)) && !r.products.iter().any(| p | inputs.contains(&p.widget)))
+            .collect();
+        if found.is_empty() {
+            return usable;
+        }
+        for recipe in found {
+            available.extend(recipe.products.iter().map(| p | &p.widget));
+            usable.push(recipe);
+        }
+    }
+}
+
+/// The end products `rate` of `widget` can be turned into using nothing else but the widgets in
+/// `with`, such as water: a forward solve from a fixed input where a plan is a backward solve from a
+/// target. Every widget the allowed recipes reach from those inputs and don't take in turn is planned
+/// as though the inputs were raw, and scaled to use up exactly the surplus. Sorted by widget.
+pub fn outlets(cookbook: &Cookbook, widget: &String, rate: Rational64, with: &[String], constraints: &Constraints) -> Result<Vec<Outlet>> {
+    if let Some(unknown) = std::iter::once(widget).chain(with).find(| w | !cookbook.widgets.iter().any(| known | known.name() == *w)) {
+        return Err(Error::UnknownWidget(unknown.clone()));
+    }
+    let inputs: BTreeSet<&String> = std::iter::once(widget).chain(with).collect();
+    let resolved = cookbook.resolved();
+    let recipes: Vec<Recipe> = usable(&resolved, &inputs, constraints).into_iter().cloned().collect();
+    let taken: BTreeSet<&String> = recipes.iter().flat_map(| r | r.reagents.iter().map(| g | &g.widget)).collect();
+    let ends: BTreeSet<String> = recipes.iter().flat_map(| r | r.products.iter().map(| p | &p.widget))
+        .filter(| p | !taken.contains(p) && !inputs.contains(p)).cloned().collect();
+    let graph = Hypergraph::from_recipes(&cookbook.widgets, recipes)?;
+    let mut outlets = Vec::new();
+    for end in ends {
+        // what one unit/second draws of the surplus sizes the plan using all of it
+        let unit = solve(&graph, &[], &end, Rational64::from_integer(1), constraints)?;
+        let drawn = match unit.raw_inputs().get(widget).filter(| d | **d > Rational64::from_integer(0)) {
+            Some(drawn) => *drawn,
+            None => continue
+        };
+        let plan = solve(&graph, &[], &end, rate / drawn, constraints)?;
+        let mut recipes = Vec::new();
+        // what the stages are planned to make, surplus of which is only machines rounded up
+        let mut planned = BTreeSet::from([&end]);
+        if let Some(tree) = &plan.tree {
+            for stage in tree.iter_dfs() {
+                if !recipes.contains(&stage.recipe.name) {
+                    recipes.push(stage.recipe.name.clone());
+                }
+                planned.extend(stage.recipe.reagents.iter().map(| r | &r.widget));
+            }
+        }
+        let also = plan.raw_inputs().iter().filter(| (w, _) | *w != widget).map(| (w, r) | (w.clone(), *r)).collect();
+        let byproducts = plan.summary.surplus.iter().filter(| (w, r) | !planned.contains(w) && **r > Rational64::from_integer(0)).map(| (w, r) | (w.clone(), *r)).collect();
+        outlets.push(Outlet { machines: plan.total_machines(), widget: end, rate: plan.rate, recipes, also, byproducts });
+    }
+    Ok(outlets)
+}
+
+pub fn print_outlets(cookbook: &Cookbook, widget: &String, rate: Rational64, outlets: &[Outlet], precision: Precision) {
+    let surplus = cookbook.format_rate(widget, &rate, precision);
+    if outlets.is_empty() {
+        println!("Nothing can be made from {surplus} of {widget} alone", surplus=surplus, widget=widget);
+        return;
+    }
+    println!("{surplus} of {widget} can make, all of it going into each:", surplus=surplus, widget=widget);
+    for outlet in outlets {
+        println!("  {rate} of {product}: {machines} machines ({recipes})", rate=cookbook.format_rate(&outlet.widget, &outlet.rate, precision), product=outlet.widget,
+            machines=outlet.machines, recipes=outlet.recipes.join(", "));
+        let rates = | rates: &BTreeMap<String, Rational64> | rates.iter().map(| (w, r) | format!("{} of {}", cookbook.format_rate(w, r, precision), w)).collect::<Vec<_>>().join(", ");
+        if !outlet.also.is_empty() {
+            println!("    also taking {also}", also=rates(&outlet.also));
+        }
+        if !outlet.byproducts.is_empty() {
+            println!("    with {byproducts} left over", byproducts=rates(&outlet.byproducts));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_recipes;
+
+    #[test]
+    fn outlets_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let outlets = outlets(&cookbook, &"iron-ingot".to_owned(), Rational64::from_integer(1), &[], &Constraints::default()).unwrap();
+        // reinforced plates with screws and rods, all from ingots alone, go on into frames
+        let frame = outlets.iter().find(| o | o.widget == "modular-frame").unwrap();
+        assert!(frame.recipes.contains(&"Screw".to_owned()) && frame.also.is_empty() && frame.byproducts.is_empty());
+        assert!(outlets.iter().all(| o | o.widget != "reinforced-iron-plate" && o.rate > Rational64::from_integer(0)));
+    }
+}
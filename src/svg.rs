@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use crate::{child_flows, decimal, Cookbook, PlanTree, Precision};
+
+// box sizes and spacing in pixels
+const WIDTH: f64 = 230.0;
+const HEIGHT: f64 = 58.0;
+const GAP: f64 = 20.0;
+// from one row of boxes to the next, room for the rate on the link between
+const LEVEL: f64 = 110.0;
+const MARGIN: f64 = 20.0;
+
+// a stage with its place in the drawing, the goal first
+struct Placed {
+    // in columns, a leaf taking up one
+    column: f64,
+    depth: usize,
+    lines: [String; 3],
+    // the stage it feeds and the rate on the link up to it
+    parent: Option<(usize, String)>
+}
+
+// leaves take the next free column from left to right, every other stage centred over its children
+#[allow(clippy::too_many_arguments)]
+fn place(tree: &PlanTree, widget: &String, depth: usize, parent: Option<(usize, String)>, cookbook: &Cookbook, next: &mut f64, placed: &mut Vec<Placed>, precision: Precision) -> f64 {
+    let at = placed.len();
+    let mut machines = format!("{count}x {builder}", count=decimal(&tree.machines, 3), builder=tree.recipe.builder_name());
+    if tree.clock != num::Rational64::from_integer(1) {
+        machines.push_str(&format!(" @ {clock}%", clock=precision.percent(&tree.clock)));
+    }
+    let made = format!("{rate} of {widget}", rate=cookbook.format_rate(widget, &tree.requested_rate, precision), widget=widget);
+    placed.push(Placed { column: 0.0, depth, lines: [tree.recipe.name.clone(), machines, made], parent });
+    let columns: Vec<f64> = tree.children().iter().zip(child_flows(tree)).map(| (child, (reagent, flow)) | {
+        place(child, &reagent.widget, depth + 1, Some((at, cookbook.format_rate(&reagent.widget, &flow, precision))), cookbook, next, placed, precision)
+    }).collect();
+    let column = match (columns.first(), columns.last()) {
+        (Some(first), Some(last)) => (first + last) / 2.0,
+        _ => {
+            *next += 1.0;
+            *next - 1.0
+        }
+    };
+    placed[at].column = column;
+    column
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The plan's stage tree as a standalone SVG, the goal at the top and every stage a box with its
+/// recipe, machines and rate, linked to the stage it feeds by the rate it supplies. Laid out here
+/// rather than by Graphviz, for printing and embedding in pages without anything else installed.
+pub fn render(tree: &PlanTree, widget: &String, cookbook: &Cookbook, precision: Precision) -> String {
+    let mut placed = Vec::new();
+    let mut columns = 0.0;
+    place(tree, widget, 0, None, cookbook, &mut columns, &mut placed, precision);
+    let depth = placed.iter().map(| p | p.depth).max().unwrap_or(0);
+    let (width, height) = (MARGIN * 2.0 + columns * (WIDTH + GAP) - GAP, MARGIN * 2.0 + depth as f64 * LEVEL + HEIGHT);
+    // top left corner of a stage's box
+    let corner = | p: &Placed | (MARGIN + p.column * (WIDTH + GAP), MARGIN + p.depth as f64 * LEVEL);
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#, w=width, h=height).unwrap();
+    writeln!(svg, "  <title>{title}</title>", title=escape(&format!("Plan for {rate} of {widget}", rate=cookbook.format_rate(widget, &tree.requested_rate, precision), widget=widget))).unwrap();
+    writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    // links first, so boxes sit on top of them
+    for p in placed.iter() {
+        if let Some((parent, rate)) = &p.parent {
+            let ((x, y), (px, py)) = (corner(p), corner(&placed[*parent]));
+            let (x1, y1, x2, y2) = (x + WIDTH / 2.0, y, px + WIDTH / 2.0, py + HEIGHT);
+            writeln!(svg, r##"  <line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="#8a94a6" stroke-width="1.5"/>"##, x1=x1, y1=y1, x2=x2, y2=y2).unwrap();
+            writeln!(svg, r##"  <text x="{x}" y="{y}" text-anchor="middle" fill="#3a4252" stroke="white" stroke-width="3" paint-order="stroke">{rate}</text>"##,
+                x=(x1 + x2) / 2.0, y=(y1 + y2) / 2.0 + 4.0, rate=escape(rate)).unwrap();
+        }
+    }
+    for (i, p) in placed.iter().enumerate() {
+        let (x, y) = corner(p);
+        let fill = if i == 0 { "#dce8fb" } else { "#f3f5f9" };
+        writeln!(svg, r##"  <rect x="{x}" y="{y}" width="{w}" height="{h}" rx="6" fill="{fill}" stroke="#56607a"/>"##, x=x, y=y, w=WIDTH, h=HEIGHT, fill=fill).unwrap();
+        for (line, text) in p.lines.iter().enumerate() {
+            let weight = if line == 0 { r#" font-weight="bold""# } else { "" };
+            writeln!(svg, r#"  <text x="{x}" y="{y}" text-anchor="middle"{weight}>{text}</text>"#, x=x + WIDTH / 2.0, y=y + 18.0 + line as f64 * 16.0, weight=weight,
+                text=escape(text)).unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_recipes, solve, Constraints};
+
+    #[test]
+    fn render_test() {
+        let cookbook = load_recipes(&"satisfactory.yaml".into()).unwrap();
+        let graph = cookbook.graph().unwrap();
+        let extraction = cookbook.extraction("normal").unwrap();
+        let widget = "reinforced-iron-plate".to_owned();
+        let plan = solve(&graph, &extraction, &widget, num::Rational64::new(1, 12), &Constraints::default()).unwrap();
+        let tree = plan.tree.as_ref().unwrap();
+        let svg = render(tree, &widget, &cookbook, Precision::default());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // a box per stage, on top of the background, and a link to every stage but the goal
+        let stages = tree.len();
+        assert_eq!(svg.matches("<rect").count(), stages + 1);
+        assert_eq!(svg.matches("<line").count(), stages - 1);
+        assert!(svg.contains(">Reinforced Iron Plate</text>"));
+    }
+}
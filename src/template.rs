@@ -0,0 +1,255 @@
+use num::Rational64;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::{as_f64, child_flows, decimal, describe, tree_text, Cookbook, PlanTree, Precision, Summary};
+
+// a piece of a parsed template
+enum Node {
+    Text(String),
+    // {{path}}
+    Value(String),
+    // {{#each path}} ... {{else}} ... {{/each}}
+    Each(String, Vec<Node>, Vec<Node>),
+    // {{#if path}} ... {{else}} ... {{/if}}
+    If(String, Vec<Node>, Vec<Node>)
+}
+
+/// A user's output template in a small subset of Handlebars: `{{path.to.value}}` substituted as written,
+/// `{{#each list}}` repeating for each item (`this`, `@index`, `@key`, `@first` and `@last` naming it, and
+/// names not found on it looked up outside), `{{#if value}}` for anything but false, null, zero or empty, both
+/// taking an `{{else}}`, `../` stepping out a level and `{{! comments }}`. Nothing is HTML-escaped, and a
+/// block tag alone on its line takes the line with it.
+pub struct Template(Vec<Node>);
+
+// a block being parsed: its kind, what it's about, the line it opened on, its body and whatever follows {{else}}
+struct Open {
+    kind: String,
+    path: String,
+    line: usize,
+    body: Vec<Node>,
+    otherwise: Option<Vec<Node>>
+}
+
+impl Open {
+    fn nodes(&mut self) -> &mut Vec<Node> {
+        self.otherwise.as_mut().unwrap_or(&mut self.body)
+    }
+}
+
+impl Template {
+    pub fn load(path: &PathBuf) -> Result<Template> {
+        let text = std::fs::read_to_string(path).map_err(| source | Error::Io { path: path.clone(), source })?;
+        Template::parse(&text).map_err(| message | Error::Template { path: path.clone(), message })
+    }
+
+    pub fn parse(text: &str) -> std::result::Result<Template, String> {
+        let (mut root, mut open): (Vec<Node>, Vec<Open>) = (Vec::new(), Vec::new());
+        let mut at = 0;
+        while let Some(start) = text[at..].find("{{").map(| i | at + i) {
+            let here = 1 + text[..start].matches('\n').count();
+            let end = text[start..].find("}}").map(| i | start + i).ok_or_else(|| format!("line {}: `{{{{` is never closed", here))?;
+            let tag = text[start + 2..end].trim();
+            let mut before = &text[at..start];
+            at = end + 2;
+            // a block tag with nothing else on its line leaves no blank line behind
+            let block = tag.starts_with(['#', '/', '!']) || tag == "else";
+            let rest_of_line = text[at..].split('\n').next().unwrap_or("");
+            if block && text[..start].rsplit('\n').next().unwrap_or("").trim().is_empty() && rest_of_line.trim().is_empty() {
+                before = before.trim_end_matches([' ', '\t']);
+                at = (at + rest_of_line.len() + 1).min(text.len());
+            }
+            let nodes = match open.last_mut() { Some(block) => block.nodes(), None => &mut root };
+            if !before.is_empty() {
+                nodes.push(Node::Text(before.to_owned()));
+            }
+            match tag.split_once(char::is_whitespace).map_or((tag, ""), | (kind, path) | (kind, path.trim())) {
+                (comment, _) if comment.starts_with('!') => {},
+                ("else", _) => match open.last_mut() {
+                    Some(block) if block.otherwise.is_none() => block.otherwise = Some(Vec::new()),
+                    _ => return Err(format!("line {}: {{{{else}}}} outside an #if or #each", here))
+                },
+                (kind, path) if kind == "#each" || kind == "#if" => {
+                    if path.is_empty() {
+                        return Err(format!("line {}: {{{{{}}}}} needs something to go over", here, kind));
+                    }
+                    open.push(Open { kind: kind[1..].to_owned(), path: path.to_owned(), line: here, body: Vec::new(), otherwise: None });
+                },
+                (kind, _) if kind.starts_with('#') => return Err(format!("line {}: unknown block {{{{{}}}}}, only #each and #if are", here, kind)),
+                (kind, _) if kind.starts_with('/') => {
+                    let block = match open.pop() {
+                        Some(block) if block.kind == kind[1..] => block,
+                        Some(block) => return Err(format!("line {}: {{{{{}}}}} closes the #{} opened on line {}", here, kind, block.kind, block.line)),
+                        None => return Err(format!("line {}: {{{{{}}}}} closes nothing", here, kind))
+                    };
+                    let otherwise = block.otherwise.unwrap_or_default();
+                    let node = if block.kind == "each" { Node::Each(block.path, block.body, otherwise) } else { Node::If(block.path, block.body, otherwise) };
+                    match open.last_mut() { Some(outer) => outer.nodes(), None => &mut root }.push(node);
+                },
+                ("", _) => return Err(format!("line {}: empty {{{{}}}}", here)),
+                _ => match open.last_mut() { Some(block) => block.nodes(), None => &mut root }.push(Node::Value(tag.to_owned()))
+            }
+        }
+        if let Some(block) = open.last() {
+            return Err(format!("line {}: {{{{#{} {}}}}} is never closed", block.line, block.kind, block.path));
+        }
+        if at < text.len() {
+            root.push(Node::Text(text[at..].to_owned()));
+        }
+        Ok(Template(root))
+    }
+
+    /// The template filled in from `context`, names it doesn't have coming out empty.
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render(&self.0, &[Scope { value: context, index: None, key: None, last: false }], &mut out);
+        out
+    }
+}
+
+// what a name is looked up in: the context, then each item being gone over
+#[derive(Clone)]
+struct Scope<'v> {
+    value: &'v Value,
+    index: Option<usize>,
+    key: Option<&'v str>,
+    last: bool
+}
+
+fn lookup(scopes: &[Scope], path: &str) -> Value {
+    let mut path = path;
+    let mut depth = scopes.len();
+    while let Some(rest) = path.strip_prefix("../") {
+        depth = depth.saturating_sub(1).max(1);
+        path = rest;
+    }
+    let scopes = &scopes[..depth];
+    let inner = scopes.last().expect("the context is always in scope");
+    match path {
+        "this" | "." => return inner.value.clone(),
+        "@index" => return inner.index.map_or(Value::Null, | i | json!(i)),
+        "@key" => return inner.key.map_or(Value::Null, | k | json!(k)),
+        "@first" => return json!(inner.index == Some(0)),
+        "@last" => return json!(inner.last),
+        _ => {}
+    }
+    let mut parts = path.strip_prefix("this.").unwrap_or(path).split('.');
+    let first = parts.next().unwrap_or("");
+    let found = scopes.iter().rev().find_map(| scope | scope.value.get(first));
+    parts.fold(found, | value, part | value.and_then(| v | match v {
+        Value::Array(items) => part.parse::<usize>().ok().and_then(| i | items.get(i)),
+        _ => v.get(part)
+    })).cloned().unwrap_or(Value::Null)
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty()
+    }
+}
+
+fn render(nodes: &[Node], scopes: &[Scope], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => match lookup(scopes, path) {
+                Value::Null => {},
+                Value::String(s) => out.push_str(&s),
+                value => out.push_str(&value.to_string())
+            },
+            Node::If(path, then, otherwise) => render(if truthy(&lookup(scopes, path)) { then } else { otherwise }, scopes, out),
+            Node::Each(path, body, otherwise) => {
+                let value = lookup(scopes, path);
+                let items: Vec<(Option<&str>, &Value)> = match &value {
+                    Value::Array(items) => items.iter().map(| item | (None, item)).collect(),
+                    Value::Object(fields) => fields.iter().map(| (key, item) | (Some(key.as_str()), item)).collect(),
+                    _ => Vec::new()
+                };
+                if items.is_empty() {
+                    render(otherwise, scopes, out);
+                }
+                let count = items.len();
+                for (i, (key, item)) in items.into_iter().enumerate() {
+                    let mut inner = scopes.to_vec();
+                    inner.push(Scope { value: item, index: Some(i), key, last: i + 1 == count });
+                    render(body, &inner, out);
+                }
+            }
+        }
+    }
+}
+
+// a rate both for reading, as the plan prints it, and as a plain number of units/second
+fn rate(cookbook: &Cookbook, widget: &String, rate: &Rational64, precision: Precision) -> Value {
+    json!({ "widget": widget, "rate": cookbook.format_rate(widget, rate, precision), "per_second": decimal(rate, precision.places(3)), "per_minute": decimal(&(rate * 60), precision.places(3)) })
+}
+
+fn stages(tree: &PlanTree, widget: &String, depth: usize, cookbook: &Cookbook, out: &mut Vec<Value>, precision: Precision) {
+    let node = &**tree;
+    let mut stage = Map::new();
+    stage.insert("recipe".to_owned(), json!(node.recipe.name));
+    stage.insert("builder".to_owned(), json!(node.recipe.builder_name()));
+    stage.insert("machines".to_owned(), json!(node.machine_count));
+    stage.insert("exact_machines".to_owned(), json!(decimal(&node.machines, 3)));
+    stage.insert("clock".to_owned(), json!(precision.percent(&node.clock)));
+    stage.insert("power".to_owned(), node.recipe.power_at(node.clock).map_or(Value::Null, | p | json!(precision.fixed(as_f64(&(p * node.machines)), 1))));
+    stage.insert("alternate".to_owned(), json!(node.recipe.alternate));
+    stage.insert("makes".to_owned(), rate(cookbook, widget, &node.requested_rate, precision));
+    stage.insert("inputs".to_owned(), Value::Array(node.recipe.reagents.iter()
+        .map(| r | rate(cookbook, &r.widget, &node.draws(r), precision)).collect()));
+    stage.insert("depth".to_owned(), json!(depth));
+    stage.insert("indent".to_owned(), json!("  ".repeat(depth)));
+    stage.insert("line".to_owned(), json!(describe(node, precision)));
+    out.push(Value::Object(stage));
+    for (child, (reagent, _)) in tree.children().iter().zip(child_flows(tree)) {
+        stages(child, &reagent.widget, depth + 1, cookbook, out, precision);
+    }
+}
+
+/// What a template is given of a plan: the goal, every stage in tree order with its depth, the stage tree as
+/// `solve` prints it, the raw resources, machines and power per builder, and the surplus. Rates carry the
+/// widget, the rate as printed and plain numbers per second and minute.
+pub fn context(cookbook: &Cookbook, widget: &String, goal: Rational64, tree: Option<&PlanTree>, summary: &Summary, precision: Precision) -> Result<Value> {
+    let mut all = Vec::new();
+    if let Some(tree) = tree {
+        stages(tree, widget, 0, cookbook, &mut all, precision);
+    }
+    let builders: Vec<Value> = summary.machines.iter().map(| (builder, count) | json!({
+        "builder": builder, "machines": count, "power": summary.power.get(builder).map_or(Value::Null, | p | json!(precision.fixed(as_f64(p), 1)))
+    })).collect();
+    let listed = | rates: &std::collections::BTreeMap<String, Rational64> | rates.iter().filter(| (_, r) | **r > Rational64::from_integer(0))
+        .map(| (w, r) | rate(cookbook, w, r, precision)).collect::<Vec<_>>();
+    Ok(json!({
+        "goal": rate(cookbook, widget, &goal, precision),
+        "raw_resource": tree.is_none(),
+        "stages": all,
+        "tree": tree.map(| tree | tree_text(tree, None, cookbook, precision).0),
+        "raw": listed(&summary.raw),
+        "builders": builders,
+        "machines": summary.machines.values().sum::<u64>(),
+        "power": precision.fixed(as_f64(&summary.total_power()?), 1),
+        "surplus": listed(&summary.surplus),
+        "purchases": listed(&summary.purchases),
+        "crafting_time": summary.crafting_time.map(| t | decimal(&t, precision.places(3)))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let template = Template::parse("Builds:\n{{#each builders}}\n- [ ] {{machines}}x {{builder}}{{#if @last}}.{{else}},{{/if}}\n{{/each}}\n{{! not shown }}\n{{#if missing}}no{{else}}{{goal.widget}} at {{goal.rate}}{{/if}}\n{{#each nothing}}x{{else}}none{{/each}}").unwrap();
+        let context = json!({ "goal": { "widget": "rotor", "rate": "4/min" }, "builders": [{ "builder": "Assembler", "machines": 2 }, { "builder": "Smelter", "machines": 8 }] });
+        assert_eq!(template.render(&context), "Builds:\n- [ ] 2x Assembler,\n- [ ] 8x Smelter.\nrotor at 4/min\nnone");
+        assert!(Template::parse("{{#each a}}{{/if}}").err().unwrap().contains("closes the #each"));
+        assert!(Template::parse("x\n{{#if a}}").err().unwrap().starts_with("line 2"));
+    }
+}
@@ -1,12 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
 use std::ops::Deref;
 
-#[derive(Debug)]
-pub struct NTree<U> {
+// where insert puts a child among its siblings: after every sibling that `compare` doesn't put after it
+pub trait ChildOrder<U> {
+    fn compare(a: &U, b: &U) -> Ordering;
+}
+
+// children in the order they're inserted
+#[derive(Debug, PartialEq)]
+pub struct InsertionOrder;
+
+impl<U> ChildOrder<U> for InsertionOrder {
+    fn compare(_: &U, _: &U) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+// serialized as the node's `data` beside its `children`, nested the same way; `O` keeps the children
+// sorted as they're inserted, e.g. for a layout that doesn't depend on reagent order in the game definition
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(bound(serialize = "U: Serialize", deserialize = "U: Deserialize<'de>"))]
+pub struct NTree<U, O = InsertionOrder> {
     data: U,
-    children: Vec<NTree<U>>
+    #[serde(default)]
+    children: Vec<NTree<U, O>>,
+    #[serde(skip)]
+    order: PhantomData<O>
 }
 
-impl<U> Deref for NTree<U> {
+impl<U, O> Deref for NTree<U, O> {
     type Target = U;
 
     fn deref(&self) -> &Self::Target {
@@ -14,16 +39,674 @@ impl<U> Deref for NTree<U> {
     }
 }
 
-impl<U> NTree<U> {
+// by hand, as deriving would ask for `O: Clone` too
+impl<U: Clone, O> Clone for NTree<U, O> {
+    fn clone(&self) -> Self {
+        NTree { data: self.data.clone(), children: self.children.clone(), order: PhantomData }
+    }
+}
+
+impl<U, O> NTree<U, O> {
     pub fn new(data: U) -> Self {
-        NTree { data, children: vec!() }
+        NTree { data, children: vec!(), order: PhantomData }
     }
 
-    pub fn insert(&mut self, child: Self) {
-        self.children.push(child);
+    pub fn insert(&mut self, child: Self)
+    where O: ChildOrder<U> {
+        let at = self.children.partition_point(| c | O::compare(&c.data, &child.data) != Ordering::Greater);
+        self.children.insert(at, child);
     }
 
-    pub fn children(&self) -> &Vec<NTree<U>> {
+    pub fn children(&self) -> &Vec<NTree<U, O>> {
         &self.children
     }
+
+    // detaches the child at `index`, later children moving up one; None when there's no such child
+    pub fn remove_child(&mut self, index: usize) -> Option<Self> {
+        if index < self.children.len() {
+            Some(self.children.remove(index))
+        } else {
+            None
+        }
+    }
+
+    // puts `child` in place of the one at `index`, returning the old one; None, dropping `child`, when there's no such child
+    pub fn replace_child(&mut self, index: usize, child: Self) -> Option<Self> {
+        self.children.get_mut(index).map(| old | std::mem::replace(old, child))
+    }
+
+    pub fn into_children(self) -> Vec<Self> {
+        self.children
+    }
+
+    pub fn into_parts(self) -> (U, Vec<Self>) {
+        (self.data, self.children)
+    }
+
+    // detaches every child, leaving a leaf
+    pub fn take_children(&mut self) -> Vec<Self> {
+        std::mem::take(&mut self.children)
+    }
+
+    // folds children with equal keys into the first of them, `combine` taking in each later payload and the
+    // merged children's own children joining the survivor's, then merges every level below the same way
+    pub fn merge_by<K, F, G>(&mut self, key: &F, combine: &G)
+    where K: PartialEq, F: Fn(&U) -> K, G: Fn(&mut U, U) {
+        let mut merged: Vec<(K, NTree<U, O>)> = Vec::with_capacity(self.children.len());
+        for child in std::mem::take(&mut self.children) {
+            let child_key = key(&child.data);
+            match merged.iter_mut().find(| (k, _) | *k == child_key) {
+                Some((_, survivor)) => {
+                    combine(&mut survivor.data, child.data);
+                    survivor.children.extend(child.children);
+                },
+                None => merged.push((child_key, child))
+            }
+        }
+        self.children = merged.into_iter().map(| (_, child) | child).collect();
+        for child in self.children.iter_mut() {
+            child.merge_by(key, combine);
+        }
+    }
+
+    // the node reached by following child indices down from this one
+    pub fn at(&self, path: &[usize]) -> Option<&Self> {
+        path.iter().try_fold(self, | node, i | node.children.get(*i))
+    }
+
+    // the same shape with every node's data passed through `f`
+    pub fn map<V, F: Fn(&U) -> V>(&self, f: &F) -> NTree<V> {
+        NTree { data: f(&self.data), children: self.children.iter().map(| c | c.map(f)).collect(), order: PhantomData }
+    }
+
+    // the edits turning this tree into `other`: children pair up with the first unpaired child of the same
+    // `key`, unpaired ones being removed or added whole, and paired ones whose data differs changed;
+    // the roots always pair up
+    pub fn diff<'a, K, F>(&'a self, other: &'a Self, key: F) -> Vec<Edit<'a, U, O>>
+    where U: PartialEq, K: PartialEq, F: Fn(&U) -> K {
+        let mut edits = Vec::new();
+        self.diff_into(other, &key, &mut Vec::new(), &mut edits);
+        edits
+    }
+
+    fn diff_into<'a, K, F>(&'a self, other: &'a Self, key: &F, path: &mut Vec<usize>, edits: &mut Vec<Edit<'a, U, O>>)
+    where U: PartialEq, K: PartialEq, F: Fn(&U) -> K {
+        if self.data != other.data {
+            edits.push(Edit::Changed(path.clone(), &self.data, &other.data));
+        }
+        let mut paired = vec![None; other.children.len()];
+        let mut used = vec![false; self.children.len()];
+        for (j, new) in other.children.iter().enumerate() {
+            let new_key = key(&new.data);
+            paired[j] = (0..self.children.len()).find(| i | !used[*i] && key(&self.children[*i].data) == new_key);
+            if let Some(i) = paired[j] {
+                used[i] = true;
+            }
+        }
+        for (i, old) in self.children.iter().enumerate().filter(| (i, _) | !used[*i]) {
+            let mut old_path = path.clone();
+            old_path.push(i);
+            edits.push(Edit::Removed(old_path, old));
+        }
+        for (j, new) in other.children.iter().enumerate() {
+            path.push(j);
+            match paired[j] {
+                Some(i) => self.children[i].diff_into(new, key, path, edits),
+                None => edits.push(Edit::Added(path.clone(), new))
+            }
+            path.pop();
+        }
+    }
+
+    // path to the first node matching `predicate`, parents before their children and siblings in order
+    pub fn find<F: Fn(&U) -> bool>(&self, predicate: F) -> Option<Vec<usize>> {
+        let mut found = Vec::new();
+        self.collect_matches(&predicate, &mut Vec::new(), &mut found, true);
+        found.pop()
+    }
+
+    // paths to every node matching `predicate`, in the same order
+    pub fn find_all<F: Fn(&U) -> bool>(&self, predicate: F) -> Vec<Vec<usize>> {
+        let mut found = Vec::new();
+        self.collect_matches(&predicate, &mut Vec::new(), &mut found, false);
+        found
+    }
+
+    // true once `first_only` has its match
+    fn collect_matches<F: Fn(&U) -> bool>(&self, predicate: &F, path: &mut Vec<usize>, found: &mut Vec<Vec<usize>>, first_only: bool) -> bool {
+        if predicate(&self.data) {
+            found.push(path.clone());
+            if first_only {
+                return true;
+            }
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            path.push(i);
+            let done = child.collect_matches(predicate, path, found, first_only);
+            path.pop();
+            if done {
+                return true;
+            }
+        }
+        false
+    }
+
+    // one `line` per node drawn as a box-drawing outline, `line` getting each node with its path from this one
+    pub fn render<F: Fn(&Self, &[usize]) -> String>(&self, line: F) -> String {
+        let mut rendered = format!("{}\n", line(self, &[]));
+        self.render_children(&line, &mut Vec::new(), "", &mut rendered);
+        rendered
+    }
+
+    fn render_children<F: Fn(&Self, &[usize]) -> String>(&self, line: &F, path: &mut Vec<usize>, prefix: &str, rendered: &mut String) {
+        for (i, child) in self.children.iter().enumerate() {
+            let is_last = i + 1 == self.children.len();
+            path.push(i);
+            rendered.push_str(&format!("{prefix}{branch}{line}\n", prefix=prefix, branch=if is_last { "└── " } else { "├── " }, line=line(child, path)));
+            child.render_children(line, path, &format!("{prefix}{spacer}", prefix=prefix, spacer=if is_last { "    " } else { "│   " }), rendered);
+            path.pop();
+        }
+    }
+
+    // nodes in the tree, counting this one; a tree always has its root so it is never empty
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        1 + self.children.iter().map(NTree::len).sum::<usize>()
+    }
+
+    // nodes along the longest path down, a lone node being 1 deep
+    pub fn depth(&self) -> usize {
+        1 + self.children.iter().map(NTree::depth).max().unwrap_or(0)
+    }
+
+    // nodes without children
+    pub fn leaves(&self) -> usize {
+        if self.children.is_empty() { 1 } else { self.children.iter().map(NTree::leaves).sum() }
+    }
+
+    // a cursor starting at this node
+    pub fn cursor(&mut self) -> Cursor<'_, U, O> {
+        Cursor { root: self, path: Vec::new() }
+    }
+
+    // every node's data, each parent before its children and siblings in order
+    pub fn iter_dfs(&self) -> Dfs<'_, U, O> {
+        Dfs { stack: vec![self] }
+    }
+
+    // every node's data, level by level
+    pub fn iter_bfs(&self) -> Bfs<'_, U, O> {
+        Bfs { queue: VecDeque::from([self]) }
+    }
+
+    pub fn iter_dfs_mut(&mut self) -> DfsMut<'_, U, O> {
+        DfsMut { stack: vec![self] }
+    }
+
+    pub fn iter_bfs_mut(&mut self) -> BfsMut<'_, U, O> {
+        BfsMut { queue: VecDeque::from([self]) }
+    }
+}
+
+// moves every node's data out, each parent before its children and siblings in order
+impl<U, O> IntoIterator for NTree<U, O> {
+    type Item = U;
+    type IntoIter = IntoIter<U, O>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { stack: vec![self] }
+    }
+}
+
+pub struct IntoIter<U, O = InsertionOrder> {
+    stack: Vec<NTree<U, O>>
+}
+
+impl<U, O> Iterator for IntoIter<U, O> {
+    type Item = U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let NTree { data, children, .. } = self.stack.pop()?;
+        self.stack.extend(children.into_iter().rev());
+        Some(data)
+    }
+}
+
+pub struct Dfs<'a, U, O = InsertionOrder> {
+    stack: Vec<&'a NTree<U, O>>
+}
+
+impl<'a, U, O> Iterator for Dfs<'a, U, O> {
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(&node.data)
+    }
+}
+
+pub struct Bfs<'a, U, O = InsertionOrder> {
+    queue: VecDeque<&'a NTree<U, O>>
+}
+
+impl<'a, U, O> Iterator for Bfs<'a, U, O> {
+    type Item = &'a U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children.iter());
+        Some(&node.data)
+    }
+}
+
+pub struct DfsMut<'a, U, O = InsertionOrder> {
+    stack: Vec<&'a mut NTree<U, O>>
+}
+
+impl<'a, U, O> Iterator for DfsMut<'a, U, O> {
+    type Item = &'a mut U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let NTree { data, children, .. } = self.stack.pop()?;
+        self.stack.extend(children.iter_mut().rev());
+        Some(data)
+    }
+}
+
+pub struct BfsMut<'a, U, O = InsertionOrder> {
+    queue: VecDeque<&'a mut NTree<U, O>>
+}
+
+impl<'a, U, O> Iterator for BfsMut<'a, U, O> {
+    type Item = &'a mut U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let NTree { data, children, .. } = self.queue.pop_front()?;
+        self.queue.extend(children.iter_mut());
+        Some(data)
+    }
+}
+
+// one step of NTree::diff, removals located in the old tree and everything else in the new one
+#[derive(Debug, PartialEq)]
+pub enum Edit<'a, U, O = InsertionOrder> {
+    Added(Vec<usize>, &'a NTree<U, O>),
+    Removed(Vec<usize>, &'a NTree<U, O>),
+    // old data, then new
+    Changed(Vec<usize>, &'a U, &'a U)
+}
+
+// a position in a tree kept as the child indices leading down to it, so it can move in any direction
+// and edit the node it points at in place; moves that would leave the tree do nothing and return false
+pub struct Cursor<'a, U, O = InsertionOrder> {
+    root: &'a mut NTree<U, O>,
+    path: Vec<usize>
+}
+
+impl<'a, U, O> Cursor<'a, U, O> {
+    // child indices from the root down to the current node
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    pub fn node(&self) -> &NTree<U, O> {
+        self.path.iter().fold(&*self.root, | node, i | &node.children[*i])
+    }
+
+    pub fn node_mut(&mut self) -> &mut NTree<U, O> {
+        self.path.iter().fold(&mut *self.root, | node, i | &mut node.children[*i])
+    }
+
+    pub fn data_mut(&mut self) -> &mut U {
+        &mut self.node_mut().data
+    }
+
+    pub fn down(&mut self, index: usize) -> bool {
+        let exists = index < self.node().children.len();
+        if exists {
+            self.path.push(index);
+        }
+        exists
+    }
+
+    pub fn up(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    pub fn next_sibling(&mut self) -> bool {
+        self.sideways(| i, siblings | if i + 1 < siblings { Some(i + 1) } else { None })
+    }
+
+    pub fn previous_sibling(&mut self) -> bool {
+        self.sideways(| i, _ | i.checked_sub(1))
+    }
+
+    fn sideways<F: Fn(usize, usize) -> Option<usize>>(&mut self, step: F) -> bool {
+        let last = match self.path.pop() {
+            Some(last) => last,
+            None => return false
+        };
+        let moved = step(last, self.node().children.len());
+        self.path.push(moved.unwrap_or(last));
+        moved.is_some()
+    }
+}
+
+// the same tree laid out in one vector, each node in depth-first order beside the index just past its subtree;
+// one allocation for the whole tree, so big plans build with less churn and clone cheaply
+#[derive(Clone, Debug)]
+pub struct FlatTree<U> {
+    nodes: Vec<(U, usize)>
+}
+
+// a node of a FlatTree, read like an NTree
+pub struct FlatNode<'a, U> {
+    tree: &'a FlatTree<U>,
+    index: usize
+}
+
+impl<U> Clone for FlatNode<'_, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<U> Copy for FlatNode<'_, U> {}
+
+impl<U> Deref for FlatNode<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree.nodes[self.index].0
+    }
+}
+
+impl<'a, U> FlatNode<'a, U> {
+    pub fn children(&self) -> Vec<FlatNode<'a, U>> {
+        let end = self.tree.nodes[self.index].1;
+        let mut children = Vec::new();
+        let mut child = self.index + 1;
+        while child < end {
+            children.push(FlatNode { tree: self.tree, index: child });
+            child = self.tree.nodes[child].1;
+        }
+        children
+    }
+
+    // the subtree's nodes in depth-first order
+    fn subtree(&self) -> &'a [(U, usize)] {
+        &self.tree.nodes[self.index..self.tree.nodes[self.index].1]
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.subtree().len()
+    }
+
+    pub fn depth(&self) -> usize {
+        1 + self.children().iter().map(FlatNode::depth).max().unwrap_or(0)
+    }
+
+    pub fn leaves(&self) -> usize {
+        let offset = self.index;
+        self.subtree().iter().enumerate().filter(| (i, (_, end)) | *end == offset + i + 1).count()
+    }
+
+    pub fn iter_dfs(&self) -> impl Iterator<Item=&'a U> {
+        self.subtree().iter().map(| (data, _) | data)
+    }
+
+    pub fn iter_bfs(&self) -> impl Iterator<Item=&'a U> {
+        let mut queue = VecDeque::from([*self]);
+        std::iter::from_fn(move || {
+            let node = queue.pop_front()?;
+            queue.extend(node.children());
+            Some(&node.tree.nodes[node.index].0)
+        })
+    }
+}
+
+impl<U> FlatTree<U> {
+    pub fn new(data: U) -> Self {
+        FlatTree { nodes: vec![(data, 1)] }
+    }
+
+    // children only ever join the root, so appending keeps the depth-first layout
+    pub fn insert(&mut self, child: Self) {
+        let offset = self.nodes.len();
+        self.nodes.extend(child.nodes.into_iter().map(| (data, end) | (data, end + offset)));
+        self.nodes[0].1 = self.nodes.len();
+    }
+
+    pub fn root(&self) -> FlatNode<'_, U> {
+        FlatNode { tree: self, index: 0 }
+    }
+
+    pub fn children(&self) -> Vec<FlatNode<'_, U>> {
+        self.root().children()
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.root().depth()
+    }
+
+    pub fn leaves(&self) -> usize {
+        self.root().leaves()
+    }
+
+    pub fn iter_dfs(&self) -> impl Iterator<Item=&U> {
+        self.nodes.iter().map(| (data, _) | data)
+    }
+
+    pub fn iter_bfs(&self) -> impl Iterator<Item=&U> {
+        self.root().iter_bfs()
+    }
+
+    pub fn iter_dfs_mut(&mut self) -> impl Iterator<Item=&mut U> {
+        self.nodes.iter_mut().map(| (data, _) | data)
+    }
+}
+
+impl<U> Deref for FlatTree<U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        &self.nodes[0].0
+    }
+}
+
+impl<U, O> From<NTree<U, O>> for FlatTree<U> {
+    fn from(tree: NTree<U, O>) -> Self {
+        let mut flat = FlatTree::new(tree.data);
+        for child in tree.children {
+            flat.insert(child.into());
+        }
+        flat
+    }
+}
+
+impl<U, O: ChildOrder<U>> From<FlatTree<U>> for NTree<U, O> {
+    fn from(tree: FlatTree<U>) -> Self {
+        // build bottom up, each node gathering the children finished after it
+        let mut finished: Vec<(usize, NTree<U, O>)> = Vec::new();
+        for (i, (data, end)) in tree.nodes.into_iter().enumerate().rev() {
+            let mut node = NTree::new(data);
+            while finished.last().is_some_and(| (start, _) | *start < end) {
+                node.insert(finished.pop().expect("checked above").1);
+            }
+            finished.push((i, node));
+        }
+        finished.pop().expect("a tree has a root").1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //     1
+    //   2   3
+    //  4
+    fn build_tree() -> NTree<u32> {
+        let mut two = NTree::new(2);
+        two.insert(NTree::new(4));
+        let mut tree = NTree::new(1);
+        tree.insert(two);
+        tree.insert(NTree::new(3));
+        tree
+    }
+
+    #[test]
+    fn iter_test() {
+        let mut tree = build_tree();
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![1, 2, 4, 3]);
+        assert_eq!(tree.iter_bfs().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        for (i, data) in tree.iter_bfs_mut().enumerate() {
+            *data += 10 * i as u32;
+        }
+        tree.iter_dfs_mut().for_each(| data | *data += 1);
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![2, 13, 35, 24]);
+    }
+
+    #[test]
+    fn metrics_test() {
+        let tree = build_tree();
+        assert_eq!((tree.len(), tree.depth(), tree.leaves()), (4, 3, 2));
+        let leaf: NTree<u32> = NTree::new(1);
+        assert_eq!((leaf.len(), leaf.depth(), leaf.leaves()), (1, 1, 1));
+    }
+
+    #[test]
+    fn edit_test() {
+        let mut tree = build_tree();
+        assert_eq!(tree.replace_child(1, NTree::new(5)).map(| old | *old), Some(3));
+        assert!(tree.replace_child(2, NTree::new(6)).is_none());
+        let two = tree.remove_child(0).unwrap();
+        assert_eq!(two.iter_dfs().copied().collect::<Vec<_>>(), vec![2, 4]);
+        assert!(tree.remove_child(1).is_none());
+        assert_eq!(tree.take_children().len(), 1);
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn cursor_test() {
+        let mut tree = build_tree();
+        let mut cursor = tree.cursor();
+        assert!(!cursor.up() && !cursor.next_sibling());
+        assert!(cursor.down(0) && cursor.down(0));
+        assert_eq!((cursor.path(), **cursor.node()), (&[0, 0][..], 4));
+        assert!(!cursor.down(0) && !cursor.next_sibling());
+        assert!(cursor.up() && cursor.next_sibling());
+        assert_eq!(**cursor.node(), 3);
+        assert!(!cursor.next_sibling() && cursor.previous_sibling());
+        *cursor.data_mut() = 7;
+        cursor.node_mut().take_children();
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![1, 7, 3]);
+    }
+
+    #[test]
+    fn flat_test() {
+        let flat = FlatTree::from(build_tree());
+        assert_eq!((flat.len(), flat.depth(), flat.leaves()), (4, 3, 2));
+        assert_eq!(flat.iter_dfs().copied().collect::<Vec<_>>(), vec![1, 2, 4, 3]);
+        assert_eq!(flat.iter_bfs().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        let children = flat.children();
+        assert_eq!(children.iter().map(| c | **c).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!((children[0].len(), children[0].leaves()), (2, 1));
+        let mut copy = flat.clone();
+        copy.iter_dfs_mut().for_each(| data | *data *= 10);
+        let tree: NTree<u32> = NTree::from(copy);
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![10, 20, 40, 30]);
+        assert_eq!(*flat, 1);
+    }
+
+    #[test]
+    fn render_test() {
+        let tree = build_tree();
+        assert_eq!(tree.render(| node, path | format!("{} {:?}", **node, path)), "1 []\n├── 2 [0]\n│   └── 4 [0, 0]\n└── 3 [1]\n");
+        assert_eq!(tree.at(&[0, 0]).map(| n | **n), Some(4));
+        assert!(tree.at(&[2]).is_none());
+    }
+
+    #[test]
+    fn merge_test() {
+        // (kind, amount) payloads, two "a" children each with a "c" child
+        let mut tree: NTree<(&str, u32)> = NTree::new(("root", 0));
+        for amount in [1, 2] {
+            let mut a = NTree::new(("a", amount));
+            a.insert(NTree::new(("c", amount * 10)));
+            tree.insert(a);
+            tree.insert(NTree::new(("b", amount)));
+        }
+        tree.merge_by(&| (kind, _) | *kind, &| (_, total), (_, amount) | *total += amount);
+        assert_eq!(tree.iter_dfs().copied().collect::<Vec<_>>(), vec![("root", 0), ("a", 3), ("c", 30), ("b", 3)]);
+    }
+
+    #[test]
+    fn into_iter_test() {
+        assert_eq!(build_tree().into_iter().collect::<Vec<_>>(), vec![1, 2, 4, 3]);
+        let (data, children) = build_tree().into_parts();
+        assert_eq!((data, children.len()), (1, 2));
+        let grandchildren: Vec<u32> = build_tree().into_children().into_iter().flat_map(| c | c.into_children()).flatten().collect();
+        assert_eq!(grandchildren, vec![4]);
+    }
+
+    #[test]
+    fn find_test() {
+        let tree = build_tree();
+        assert_eq!(tree.find(| n | n % 2 == 0), Some(vec![0]));
+        assert_eq!(tree.find_all(| n | *n > 2), vec![vec![0, 0], vec![1]]);
+        assert_eq!(tree.find(| n | *n > 4), None);
+    }
+
+    #[test]
+    fn diff_test() {
+        // (identity, amount) payloads
+        let old = build_tree().map(&| n | (*n, 1));
+        let mut new = build_tree().map(&| n | (*n, 1));
+        new.remove_child(1);
+        new.insert(NTree::new((5, 1)));
+        let mut cursor = new.cursor();
+        cursor.down(0);
+        cursor.down(0);
+        *cursor.data_mut() = (4, 2);
+        assert_eq!(old.diff(&old, | n | n.0), vec![]);
+        assert_eq!(old.diff(&new, | n | n.0), vec![
+            Edit::Removed(vec![1], &old.children()[1]),
+            Edit::Changed(vec![0, 0], &(4, 1), &(4, 2)),
+            Edit::Added(vec![1], &new.children()[1])
+        ]);
+    }
+
+    // by the first field only, so the second shows where equal ones land
+    struct ByFirst;
+
+    impl ChildOrder<(u32, char)> for ByFirst {
+        fn compare(a: &(u32, char), b: &(u32, char)) -> Ordering {
+            a.0.cmp(&b.0)
+        }
+    }
+
+    #[test]
+    fn order_test() {
+        let mut tree = NTree::<_, ByFirst>::new((0, 'r'));
+        for child in [(3, 'a'), (1, 'b'), (3, 'c'), (2, 'd')] {
+            tree.insert(NTree::new(child));
+        }
+        assert_eq!(tree.children().iter().map(| c | c.1).collect::<String>(), "bdac");
+    }
+
+    #[test]
+    fn serde_test() {
+        let json = serde_json::to_string(&build_tree()).unwrap();
+        assert_eq!(json, r#"{"data":1,"children":[{"data":2,"children":[{"data":4,"children":[]}]},{"data":3,"children":[]}]}"#);
+        let restored: NTree<u32> = serde_json::from_str(r#"{"data":1,"children":[{"data":2}]}"#).unwrap();
+        assert_eq!(restored.iter_dfs().copied().collect::<Vec<_>>(), vec![1, 2]);
+    }
 }
@@ -0,0 +1,103 @@
+use num::Rational64;
+
+use crate::error::Result;
+use crate::hypergraph::Hypergraph;
+use crate::{as_f64, solve, solve_within_limits, Constraints, Cookbook, Precision, Recipe};
+
+// raw units/second and machines a plan needs
+#[derive(Clone, Copy)]
+pub struct Footprint {
+    pub raw: Rational64,
+    pub machines: u64
+}
+
+pub struct Candidate {
+    pub recipe: String,
+    pub footprint: Footprint
+}
+
+pub struct Ranking {
+    pub baseline: Footprint,
+    // improving unlocks, best first
+    pub candidates: Vec<Candidate>,
+    // locked alternates that leave the plan as it is or make it worse
+    pub unhelpful: Vec<String>
+}
+
+// None when the builder limits can't fit the plan
+fn footprint(graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, rate: Rational64, constraints: &Constraints) -> Result<Option<Footprint>> {
+    let solution = if constraints.limits.is_empty() {
+        Some(solve(graph, extraction, widget, rate, constraints)?)
+    } else {
+        solve_within_limits(graph, extraction, widget, rate, constraints)?
+    };
+    Ok(solution.map(| plan | Footprint {
+        raw: plan.raw_inputs().values().fold(Rational64::from_integer(0), | acc, r | acc + r),
+        machines: plan.total_machines()
+    }))
+}
+
+// re-solves with each locked alternate recipe unlocked on its own, ranking the ones that cut
+// raw resource usage, or machine count when `by_machines` is set
+pub fn rank(cookbook: &Cookbook, graph: &Hypergraph<String, Recipe>, extraction: &[Recipe], widget: &String, rate: Rational64, constraints: &Constraints, by_machines: bool) -> Result<Ranking> {
+    let baseline = footprint(graph, extraction, widget, rate, constraints)?.ok_or_else(|| crate::error::Error::LimitsExceeded(widget.clone()))?;
+    let key = | f: &Footprint | if by_machines { (Rational64::from_integer(f.machines as i64), f.raw) } else { (f.raw, Rational64::from_integer(f.machines as i64)) };
+    let mut candidates = Vec::new();
+    let mut unhelpful = Vec::new();
+    for recipe in cookbook.recipes.iter().filter(| r | r.alternate && !constraints.unlocked.contains(&r.name)) {
+        let mut unlocked = constraints.clone();
+        unlocked.unlocked.insert(recipe.name.clone());
+        match footprint(graph, extraction, widget, rate, &unlocked)? {
+            Some(footprint) if key(&footprint) < key(&baseline) => candidates.push(Candidate { recipe: recipe.name.clone(), footprint }),
+            _ => unhelpful.push(recipe.name.clone())
+        }
+    }
+    candidates.sort_by(| a, b | key(&a.footprint).cmp(&key(&b.footprint)).then_with(|| a.recipe.cmp(&b.recipe)));
+    Ok(Ranking { baseline, candidates, unhelpful })
+}
+
+pub fn print_ranking(ranking: &Ranking, widget: &String, rate: Rational64, precision: Precision) {
+    let baseline = ranking.baseline;
+    println!("{widget} at {rate}/s needs {raw}/s of raw resources and {machines} machines", widget=widget, rate=precision.fixed(as_f64(&rate), 3),
+        raw=precision.fixed(as_f64(&baseline.raw), 3), machines=baseline.machines);
+    if ranking.candidates.is_empty() {
+        println!("  no single unlock improves the plan");
+    }
+    for (i, candidate) in ranking.candidates.iter().enumerate() {
+        let footprint = candidate.footprint;
+        println!("  {n}. {recipe}: {raw:+.3}/s raw, {machines:+} machines", n=i + 1, recipe=candidate.recipe,
+            raw=as_f64(&(footprint.raw - baseline.raw)), machines=footprint.machines as i64 - baseline.machines as i64);
+    }
+    if !ranking.unhelpful.is_empty() {
+        println!();
+        println!("No improvement from: {recipes}", recipes=ranking.unhelpful.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_test() {
+        let from_ore = | name: &str, ore: i64, seconds: Rational64 | {
+            Recipe::new(name, seconds).made_by("Assembler").reagent("ore", Rational64::from_integer(ore)).product("a", Rational64::from_integer(1))
+        };
+        let one = Rational64::from_integer(1);
+        let cookbook = Cookbook::builder().machine("Assembler", None).item("a").item("ore")
+            .recipe(from_ore("Standard", 4, one))
+            .recipe(from_ore("Lean", 2, one).as_alternate())
+            .recipe(from_ore("Quick", 4, Rational64::new(1, 2)).as_alternate())
+            .recipe(from_ore("Wasteful", 5, one).as_alternate())
+            .build();
+        let graph = cookbook.graph().unwrap();
+        let ranked = | by_machines: bool | {
+            let ranking = rank(&cookbook, &graph, &[], &"a".to_owned(), Rational64::from_integer(2), &Constraints::default(), by_machines).unwrap();
+            (ranking.candidates.iter().map(| c | c.recipe.clone()).collect::<Vec<_>>(), ranking.unhelpful)
+        };
+        // Lean halves the ore on as many machines, Quick halves the machines on the same ore
+        let wasteful = vec!["Wasteful".to_owned()];
+        assert_eq!(ranked(false), (vec!["Lean".to_owned(), "Quick".to_owned()], wasteful.clone()));
+        assert_eq!(ranked(true), (vec!["Quick".to_owned(), "Lean".to_owned()], wasteful));
+    }
+}
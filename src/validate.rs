@@ -0,0 +1,143 @@
+use num::Rational64;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::{Cookbook, Reagent};
+
+// something wrong with the game definition and where it is
+pub struct Problem {
+    pub location: String,
+    pub message: String
+}
+
+fn problem(problems: &mut Vec<Problem>, location: String, message: String) {
+    problems.push(Problem { location, message });
+}
+
+fn check_reagents(reagents: &[Reagent], what: &str, location: &str, widgets: &HashSet<&String>, problems: &mut Vec<Problem>) {
+    for reagent in reagents {
+        if !widgets.contains(&reagent.widget) {
+            problem(problems, location.to_owned(), format!("{what} `{widget}` is not in the widgets section", what=what, widget=reagent.widget));
+        }
+        if reagent.quantity <= Rational64::from_integer(0) {
+            problem(problems, location.to_owned(), format!("{what} `{widget}` has a non-positive quantity {quantity}", what=what, widget=reagent.widget, quantity=reagent.quantity));
+        }
+    }
+}
+
+/// Every problem with the game definition that would otherwise surface one at a time while solving,
+/// or not at all: undeclared widgets, duplicate names, non-positive durations and quantities, unknown
+/// builders and widgets that can never be made.
+pub fn validate(cookbook: &Cookbook) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut seen = BTreeMap::new();
+    for (i, widget) in cookbook.widgets.iter().enumerate() {
+        if let Some(first) = seen.insert(widget.name(), i) {
+            problem(&mut problems, format!("widgets #{n}", n=i + 1), format!("`{name}` is already declared as widgets #{first}", name=widget.name(), first=first + 1));
+        }
+    }
+    let widgets: HashSet<&String> = seen.keys().copied().collect();
+    let builders: HashSet<&String> = cookbook.builders.iter().map(| b | &b.name).collect();
+
+    let mut names = BTreeMap::new();
+    for (i, recipe) in cookbook.recipes.iter().enumerate() {
+        let location = format!("recipes #{n} `{name}`", n=i + 1, name=recipe.name);
+        if let Some(first) = names.insert(&recipe.name, i) {
+            problem(&mut problems, location.clone(), format!("the name is already used by recipes #{first}", first=first + 1));
+        }
+        if recipe.duration <= Rational64::from_integer(0) {
+            problem(&mut problems, location.clone(), format!("non-positive duration {duration}", duration=recipe.duration));
+        }
+        if recipe.products.is_empty() {
+            problem(&mut problems, location.clone(), "makes nothing".to_owned());
+        }
+        check_reagents(&recipe.reagents, "reagent", &location, &widgets, &mut problems);
+        check_reagents(&recipe.products, "product", &location, &widgets, &mut problems);
+        if let Some(builder) = recipe.builder.as_ref().filter(| b | !builders.is_empty() && !builders.contains(b)) {
+            problem(&mut problems, location, format!("builder `{builder}` is not in the builders section", builder=builder));
+        }
+    }
+    for (i, extractor) in cookbook.extractors.iter().enumerate() {
+        let location = format!("extractors #{n} `{name}`", n=i + 1, name=extractor.name);
+        for resource in extractor.resources.iter().filter(| r | !widgets.contains(r)) {
+            problem(&mut problems, location.clone(), format!("resource `{resource}` is not in the widgets section", resource=resource));
+        }
+        if extractor.rate <= Rational64::from_integer(0) {
+            problem(&mut problems, location, format!("non-positive rate {rate}", rate=extractor.rate));
+        }
+    }
+    for (section, tiers) in [("belts", &cookbook.belts), ("pipes", &cookbook.pipes)] {
+        for (i, tier) in tiers.iter().enumerate().filter(| (_, t) | t.rate <= Rational64::from_integer(0)) {
+            problem(&mut problems, format!("{section} #{n} `{name}`", section=section, n=i + 1, name=tier.name), format!("non-positive rate {rate}", rate=tier.rate));
+        }
+    }
+    for (i, generator) in cookbook.generators.iter().enumerate() {
+        check_reagents(&generator.fuel, "fuel", &format!("generators #{n} `{name}`", n=i + 1, name=generator.name), &widgets, &mut problems);
+    }
+
+    // widgets nothing makes are raw and come from extractors, or from outside when there are none;
+    // everything else is makeable once some recipe for it has all of its reagents makeable
+    let extracted: HashSet<&String> = cookbook.extractors.iter().flat_map(| e | e.resources.iter()).collect();
+    let made: HashSet<&String> = cookbook.recipes.iter().flat_map(| r | r.products.iter().map(| p | &p.widget)).collect();
+    let mut makeable: BTreeSet<&String> = widgets.iter().copied().filter(| w | !made.contains(w) && (cookbook.extractors.is_empty() || extracted.contains(w))).collect();
+    makeable.extend(extracted.iter().copied());
+    loop {
+        let before = makeable.len();
+        for recipe in cookbook.recipes.iter() {
+            if recipe.reagents.iter().all(| g | makeable.contains(&g.widget)) {
+                makeable.extend(recipe.products.iter().map(| p | &p.widget));
+            }
+        }
+        if makeable.len() == before {
+            break;
+        }
+    }
+    for (i, widget) in cookbook.widgets.iter().enumerate().filter(| (_, w) | !makeable.contains(w.name())) {
+        let message = if made.contains(widget.name()) {
+            "can never be made, every recipe for it needs a widget that can't be".to_owned()
+        } else {
+            "nothing makes or extracts it".to_owned()
+        };
+        problem(&mut problems, format!("widgets #{n} `{name}`", n=i + 1, name=widget.name()), message);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_test() {
+        let cookbook = Cookbook::from_yaml("
+widgets: [ore, plate, gear, loop]
+belts: [{name: Belt, rate: 0}]
+extractors:
+  - name: Miner
+    resources: [ore, dust]
+    rate: 1
+recipes:
+  - name: Plate
+    duration: 1
+    reagents: [{widget: ore, quantity: 1}]
+    products: [{widget: plate, quantity: 1}]
+  - name: Plate
+    duration: 0
+    reagents: [{widget: plate, quantity: 2}]
+    products: [{widget: gear, quantity: 1}, {widget: cog, quantity: 1}]
+  - name: Loop
+    duration: 1
+    reagents: [{widget: loop, quantity: 1}]
+    products: [{widget: loop, quantity: 2}]
+").unwrap();
+        let problems: Vec<String> = validate(&cookbook).into_iter().map(| p | format!("{}: {}", p.location, p.message)).collect();
+        assert_eq!(problems, vec![
+            "recipes #2 `Plate`: the name is already used by recipes #1",
+            "recipes #2 `Plate`: non-positive duration 0",
+            "recipes #2 `Plate`: product `cog` is not in the widgets section",
+            "extractors #1 `Miner`: resource `dust` is not in the widgets section",
+            "belts #1 `Belt`: non-positive rate 0",
+            "widgets #4 `loop`: can never be made, every recipe for it needs a widget that can't be"
+        ]);
+        assert!(validate(&crate::load_recipes(&"satisfactory.yaml".into()).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A game version such as `1.0` or `0.8.2`, compared part by part with missing parts as zeros, so
+/// `1.0` and `1` are the same version and `1.10` comes after `1.9`.
+#[derive(Clone, Debug)]
+pub struct GameVersion(Vec<u64>);
+
+impl FromStr for GameVersion {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let trimmed = text.trim().trim_start_matches(['v', 'V']);
+        trimmed.split('.').map(| part | part.parse::<u64>()).collect::<Result<Vec<_>, _>>().map(GameVersion)
+            .map_err(| _ | format!("version `{}` is not dot-separated numbers like 1.0 or 0.8.2", text))
+    }
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+impl Ord for GameVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let part = | v: &GameVersion, i: usize | v.0.get(i).copied().unwrap_or(0);
+        (0..self.0.len().max(other.0.len())).map(| i | part(self, i).cmp(&part(other, i))).find(| o | o.is_ne()).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for GameVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for GameVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GameVersion {}
+
+impl Serialize for GameVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_str(self)
+    }
+}
+
+// YAML reads an unquoted 1.0 as a number, which is taken as written; 1.10 has to be quoted to stay itself
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Written {
+    Integer(u64),
+    Decimal(f64),
+    Text(String)
+}
+
+impl<'de> Deserialize<'de> for GameVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let text = match Written::deserialize(deserializer)? {
+            Written::Integer(n) => n.to_string(),
+            Written::Decimal(n) => n.to_string(),
+            Written::Text(text) => text
+        };
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_test() {
+        let v = | text: &str | text.parse::<GameVersion>().unwrap();
+        assert_eq!(v("1.0"), v("1"));
+        assert!(v("1.9") < v("1.10") && v("0.8.2") < v("1.0") && v("v2.1") > v("2.0.9"));
+        assert_eq!(v("1.10.0").to_string(), "1.10.0");
+        assert!("1.x".parse::<GameVersion>().is_err());
+    }
+}